@@ -0,0 +1,344 @@
+//! Oversized tool output is spooled to an artifact file instead of the context.
+//! The model gets a head/tail preview and an id; `read_artifact` pages through
+//! the full text by byte or line range.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::capability::path_policy;
+use crate::config::IrisCfg;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const DEFAULT_THRESHOLD: usize = 16 * 1024;
+const DEFAULT_PREVIEW: usize = 2 * 1024;
+/// Artifacts older than this are removed when the store is configured.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+const DEFAULT_PAGE_LINES: usize = 200;
+const MAX_PAGE_LINES: usize = 5000;
+
+/// Spool directory and size limits for artifacts.
+#[derive(Debug)]
+pub struct ArtifactStore {
+    dir: RwLock<PathBuf>,
+    /// Text longer than this (bytes) becomes an artifact; also the page size cap.
+    threshold: AtomicUsize,
+    /// Bytes of head and of tail kept in the preview.
+    preview: AtomicUsize,
+}
+
+impl Default for ArtifactStore {
+    fn default() -> Self {
+        Self {
+            dir: RwLock::new(path_policy::data_dir("artifacts")),
+            threshold: AtomicUsize::new(DEFAULT_THRESHOLD),
+            preview: AtomicUsize::new(DEFAULT_PREVIEW),
+        }
+    }
+}
+
+impl ArtifactStore {
+    pub fn configure(&self, cfg: &IrisCfg) {
+        let dir = if cfg.artifact_dir.trim().is_empty() {
+            path_policy::data_dir("artifacts")
+        } else {
+            path_policy::expand_home(cfg.artifact_dir.trim())
+        };
+        if let Ok(mut d) = self.dir.write() {
+            *d = dir;
+        }
+        self.threshold.store(cfg.artifact_threshold_bytes.max(1024), Ordering::Relaxed);
+        self.preview.store(cfg.artifact_preview_bytes, Ordering::Relaxed);
+        self.prune();
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.dir.read().map(|d| d.clone()).unwrap_or_default()
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold.load(Ordering::Relaxed)
+    }
+
+    /// Artifact ids are 12 hex characters; anything else cannot name a file.
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        (id.len() == 12 && id.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| self.dir().join(format!("{}.txt", id.to_ascii_lowercase())))
+    }
+
+    /// Return `text` unchanged when small; otherwise spool it and return a summary
+    /// with `artifact_id`, sizes, head and tail.
+    pub fn compact(&self, source: &str, text: String) -> Value {
+        if text.len() <= self.threshold() {
+            return Value::String(text);
+        }
+        let preview = self.preview.load(Ordering::Relaxed);
+        let head = &text[..floor_char(&text, preview)];
+        let tail = &text[ceil_char(&text, text.len().saturating_sub(preview))..];
+        let mut summary = json!({
+            "source": source,
+            "bytes": text.len(),
+            "lines": text.lines().count(),
+            "head": head,
+            "tail": tail,
+        });
+        match self.save(&text) {
+            Ok(id) => {
+                summary["artifact_id"] = json!(id);
+                summary["note"] = json!(format!(
+                    "Output too large for the context; only head and tail are shown. \
+                     Use read_artifact with artifact_id {id} to page through all of it."
+                ));
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, source, "failed to spool artifact");
+                summary["note"] = json!(format!("Output truncated; saving the full text failed: {e}"));
+            }
+        }
+        summary
+    }
+
+    /// `compact` for a whole tool result string: large results become the summary's JSON.
+    pub fn compact_result(&self, source: &str, text: String) -> String {
+        match self.compact(source, text) {
+            Value::String(text) => text,
+            summary => summary.to_string(),
+        }
+    }
+
+    fn save(&self, text: &str) -> std::io::Result<String> {
+        let dir = self.dir();
+        // Tool output can hold file contents and secrets; keep it owner-only
+        path_policy::create_private_dir(&dir)?;
+        let id = Uuid::new_v4().simple().to_string()[..12].to_string();
+        path_policy::write_private(&dir.join(format!("{id}.txt")), text.as_bytes())?;
+        Ok(id)
+    }
+
+    pub fn load(&self, id: &str) -> Result<String, String> {
+        let path = self.path(id).ok_or_else(|| format!("invalid artifact id '{id}'"))?;
+        let bytes = std::fs::read(&path).map_err(|_| format!("no artifact {id} (it may have expired)"))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn prune(&self) {
+        let Ok(entries) = std::fs::read_dir(self.dir()) else { return };
+        let now = SystemTime::now();
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| now.duration_since(t).unwrap_or_default() > MAX_AGE);
+            if expired {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+fn floor_char(s: &str, mut i: usize) -> usize {
+    i = i.min(s.len());
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_char(s: &str, mut i: usize) -> usize {
+    while i < s.len() && !s.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+/// One page of an artifact, by byte or line range.
+fn page(text: &str, params: &Value, max_bytes: usize) -> Value {
+    let total_lines = text.lines().count();
+    if let Some(offset) = params.get("offset").and_then(Value::as_u64) {
+        let start = ceil_char(text, (offset as usize).min(text.len()));
+        let length = params.get("length").and_then(Value::as_u64).map_or(max_bytes, |l| l as usize);
+        let end = floor_char(text, start.saturating_add(length.min(max_bytes)));
+        return json!({
+            "bytes": text.len(),
+            "lines": total_lines,
+            "offset": start,
+            "length": end - start,
+            "content": &text[start..end],
+            "next_offset": (end < text.len()).then_some(end),
+        });
+    }
+
+    let start_line = params.get("start_line").and_then(Value::as_u64).unwrap_or(1).max(1) as usize;
+    let count = params
+        .get("line_count")
+        .and_then(Value::as_u64)
+        .map_or(DEFAULT_PAGE_LINES, |c| c as usize)
+        .min(MAX_PAGE_LINES);
+    let mut content = String::new();
+    let mut shown = 0;
+    let mut cut = false;
+    for line in text.split_inclusive('\n').skip(start_line - 1).take(count) {
+        // Always show at least part of one line so paging makes progress;
+        // the rest of an overlong line is reachable by byte offset.
+        if content.len() + line.len() > max_bytes {
+            if shown == 0 {
+                content.push_str(&line[..floor_char(line, max_bytes)]);
+                shown = 1;
+                cut = true;
+            }
+            break;
+        }
+        content.push_str(line);
+        shown += 1;
+    }
+    let next = start_line + shown;
+    let mut page = json!({
+        "bytes": text.len(),
+        "lines": total_lines,
+        "start_line": start_line,
+        "line_count": shown,
+        "content": content,
+        "next_start_line": (next <= total_lines).then_some(next),
+    });
+    if cut {
+        page["line_truncated"] = json!(true);
+    }
+    page
+}
+
+pub struct ReadArtifact {
+    store: Arc<ArtifactStore>,
+}
+
+impl ReadArtifact {
+    pub fn new(store: Arc<ArtifactStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for ReadArtifact {
+    fn name(&self) -> &str { "read_artifact" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["artifact", "output", "page", "more"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_artifact".into(),
+            description: "Page through a large tool output saved as an artifact (tool results with an artifact_id). \
+                          Give start_line/line_count for lines (default: first 200 lines), or offset/length for bytes. \
+                          Each page is size-capped; continue from next_start_line or next_offset."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "artifact_id": { "type": "string", "minLength": 12, "maxLength": 12 },
+                    "start_line": { "type": "integer", "minimum": 1 },
+                    "line_count": { "type": "integer", "minimum": 1, "maximum": MAX_PAGE_LINES },
+                    "offset": { "type": "integer", "minimum": 0 },
+                    "length": { "type": "integer", "minimum": 1 }
+                },
+                "required": ["artifact_id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let id = request.params.get("artifact_id").and_then(|v| v.as_str()).unwrap_or_default();
+        let (result, error) = match self.store.load(id) {
+            Ok(text) => {
+                let mut page = page(&text, &request.params, self.store.threshold());
+                page["artifact_id"] = json!(id);
+                (Some(page), None)
+            }
+            Err(e) => (None, Some(e)),
+        };
+        CapabilityResponse {
+            id: request.id,
+            result,
+            error,
+            metrics: None,
+            side_effects: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &std::path::Path, threshold: usize) -> ArtifactStore {
+        let store = ArtifactStore::default();
+        *store.dir.write().unwrap() = dir.to_path_buf();
+        store.threshold.store(threshold, Ordering::Relaxed);
+        store.preview.store(10, Ordering::Relaxed);
+        store
+    }
+
+    #[test]
+    fn small_text_passes_through_and_large_text_is_spooled() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path(), 64);
+        assert_eq!(store.compact("t", "short".into()), json!("short"));
+
+        let text: String = (1..=50).map(|i| format!("line {i}\n")).collect();
+        let summary = store.compact("run_bash stdout", text.clone());
+        assert_eq!(summary["lines"], 50);
+        assert_eq!(summary["head"], "line 1\nlin");
+        assert_eq!(summary["tail"], "9\nline 50\n");
+        let id = summary["artifact_id"].as_str().unwrap();
+        assert_eq!(store.load(id).unwrap(), text);
+        assert!(store.load("../../etc/pa").is_err());
+
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &std::path::Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(dir.path()), 0o700);
+        assert_eq!(mode(&dir.path().join(format!("{id}.txt"))), 0o600);
+    }
+
+    #[test]
+    fn pages_by_line_and_byte_range() {
+        let text: String = (1..=10).map(|i| format!("line {i}\n")).collect();
+        let p = page(&text, &json!({ "start_line": 3, "line_count": 2 }), 1024);
+        assert_eq!((p["content"].as_str(), p["next_start_line"].as_u64()), (Some("line 3\nline 4\n"), Some(5)));
+        let last = page(&text, &json!({ "start_line": 10 }), 1024);
+        assert_eq!(last["next_start_line"], Value::Null);
+
+        // The byte cap stops a line page early, at a line boundary.
+        let capped = page(&text, &json!({ "start_line": 1, "line_count": 10 }), 16);
+        assert_eq!((capped["line_count"].as_u64(), capped["next_start_line"].as_u64()), (Some(2), Some(3)));
+
+        let p = page(&text, &json!({ "offset": 7, "length": 7 }), 1024);
+        assert_eq!((p["content"].as_str(), p["next_offset"].as_u64()), (Some("line 2\n"), Some(14)));
+        let p = page("héllo", &json!({ "offset": 2, "length": 2 }), 1024);
+        assert_eq!(p["content"], "ll");
+    }
+
+    #[tokio::test]
+    async fn read_artifact_tool_returns_pages() {
+        use super::super::BuiltinCapability;
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(store(dir.path(), 32));
+        let summary = store.compact("t", "x\n".repeat(100));
+        let tool = ReadArtifact::new(Arc::clone(&store));
+        let request = |params| CapabilityRequest { id: Uuid::new_v4(), method: String::new(), params, version: 1 };
+
+        let resp = tool.execute(request(json!({ "artifact_id": summary["artifact_id"], "line_count": 3 }))).await;
+        assert_eq!(resp.result.unwrap()["content"], "x\nx\nx\n");
+        let resp = tool.execute(request(json!({ "artifact_id": "0123456789ab" }))).await;
+        assert!(resp.error.unwrap().contains("no artifact"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Line-based unified diffs: rendering (for tool results) and strict patch application.
+
+/// Largest middle section (after trimming the common prefix/suffix) diffed with
+/// the LCS table; anything bigger is shown as one replaced block.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Render a unified diff between two texts; empty when they are identical.
+pub fn unified_diff(old: &str, new: &str, path: &str, context: usize) -> String {
+    if old == new {
+        return String::new();
+    }
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&a, &b);
+
+    // Positions (old line, new line) before each op, to number hunks.
+    let mut pos = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0usize, 0usize);
+    for op in &ops {
+        pos.push((i, j));
+        match op {
+            Op::Keep => {
+                i += 1;
+                j += 1;
+            }
+            Op::Remove => i += 1,
+            Op::Add => j += 1,
+        }
+    }
+    pos.push((i, j));
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k] != Op::Keep).collect();
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(context);
+        let mut end = changed[k];
+        // Merge changes whose context windows touch.
+        while k < changed.len() && changed[k] <= end + 2 * context + 1 {
+            end = changed[k];
+            k += 1;
+        }
+        let end = (end + context + 1).min(ops.len());
+
+        let (old_start, new_start) = pos[start];
+        let old_len = ops[start..end].iter().filter(|o| **o != Op::Add).count();
+        let new_len = ops[start..end].iter().filter(|o| **o != Op::Remove).count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        for (op, &(oi, nj)) in ops[start..end].iter().zip(&pos[start..end]) {
+            match op {
+                Op::Keep => out.push_str(&format!(" {}\n", a[oi])),
+                Op::Remove => out.push_str(&format!("-{}\n", a[oi])),
+                Op::Add => out.push_str(&format!("+{}\n", b[nj])),
+            }
+        }
+    }
+    if old.ends_with('\n') != new.ends_with('\n') && !old.is_empty() && !new.is_empty() {
+        out.push_str("\\ trailing newline changed\n");
+    }
+    out
+}
+
+/// Count (added, removed) lines in a rendered unified diff.
+pub fn diff_stats(diff: &str) -> (usize, usize) {
+    let mut added = 0;
+    let mut removed = 0;
+    for line in diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        if line.starts_with('+') {
+            added += 1;
+        } else if line.starts_with('-') {
+            removed += 1;
+        }
+    }
+    (added, removed)
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // Unified format numbers from 1; an empty range points at the line before it.
+    let first = if len == 0 { start } else { start + 1 };
+    if len == 1 { first.to_string() } else { format!("{first},{len}") }
+}
+
+fn diff_ops(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Keep; prefix];
+    if (ma.len() + 1) * (mb.len() + 1) > MAX_LCS_CELLS {
+        ops.extend(std::iter::repeat_n(Op::Remove, ma.len()));
+        ops.extend(std::iter::repeat_n(Op::Add, mb.len()));
+    } else {
+        // lcs[i][j] = LCS length of ma[i..] and mb[j..]
+        let w = mb.len() + 1;
+        let mut lcs = vec![0u32; (ma.len() + 1) * w];
+        for i in (0..ma.len()).rev() {
+            for j in (0..mb.len()).rev() {
+                lcs[i * w + j] = if ma[i] == mb[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < ma.len() && j < mb.len() {
+            if ma[i] == mb[j] {
+                ops.push(Op::Keep);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * w + j] >= lcs[i * w + j + 1] {
+                ops.push(Op::Remove);
+                i += 1;
+            } else {
+                ops.push(Op::Add);
+                j += 1;
+            }
+        }
+        ops.extend(std::iter::repeat_n(Op::Remove, ma.len() - i));
+        ops.extend(std::iter::repeat_n(Op::Add, mb.len() - j));
+    }
+    ops.extend(std::iter::repeat_n(Op::Keep, suffix));
+    ops
+}
+
+#[derive(Debug)]
+struct Hunk {
+    header: String,
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// Apply a unified diff to `original`. Each hunk must match exactly, either at
+/// its stated line or at a single unambiguous location elsewhere in the file.
+pub fn apply_patch(original: &str, patch: &str) -> Result<String, String> {
+    let hunks = parse_hunks(patch)?;
+    if hunks.is_empty() {
+        return Err("patch contains no hunks (expected lines starting with @@)".into());
+    }
+    let trailing_newline = original.ends_with('\n') || original.is_empty();
+    let mut lines: Vec<String> = original.lines().map(String::from).collect();
+
+    // Applied top to bottom; `shift` tracks how earlier hunks moved later lines.
+    let mut shift: isize = 0;
+    let mut floor = 0usize;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + shift).max(0) as usize;
+        let at = locate(&lines, &hunk.old, expected, floor).map_err(|why| {
+            format!(
+                "hunk {} ({}) {why}; re-read the file and regenerate the patch",
+                n + 1,
+                hunk.header
+            )
+        })?;
+        lines.splice(at..at + hunk.old.len(), hunk.new.iter().cloned());
+        shift += hunk.new.len() as isize - hunk.old.len() as isize;
+        floor = at + hunk.new.len();
+    }
+
+    let mut out = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn locate(lines: &[String], old: &[String], expected: usize, floor: usize) -> Result<usize, String> {
+    let fits = |at: usize| at + old.len() <= lines.len() && lines[at..at + old.len()] == *old;
+    if expected >= floor && fits(expected) {
+        return Ok(expected);
+    }
+    if old.is_empty() {
+        return Err("has no context lines and its position is out of range".into());
+    }
+    let found: Vec<usize> = (floor..=lines.len().saturating_sub(old.len()))
+        .filter(|&at| fits(at))
+        .collect();
+    match found.as_slice() {
+        [at] => Ok(*at),
+        [] => Err(format!(
+            "does not match the file near line {} (context or removed lines differ)",
+            expected + 1
+        )),
+        many => Err(format!(
+            "matches {} places (lines {}); add more context lines",
+            many.len(),
+            many.iter().map(|a| (a + 1).to_string()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+fn parse_hunks(patch: &str) -> Result<Vec<Hunk>, String> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in patch.lines() {
+        if let Some(rest) = line.strip_prefix("@@") {
+            let old_start = rest
+                .trim()
+                .strip_prefix('-')
+                .and_then(|r| r.split([',', ' ']).next())
+                .and_then(|n| n.parse::<usize>().ok())
+                .ok_or_else(|| format!("malformed hunk header: {line}"))?;
+            hunks.push(Hunk {
+                header: line.trim_end_matches(|c| c != '@').to_string(),
+                old_start,
+                old: Vec::new(),
+                new: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // File headers (diff --git, ---, +++, index) before the first hunk.
+            continue;
+        };
+        if line.starts_with('\\') {
+            continue;
+        }
+        match line.chars().next() {
+            Some('+') => hunk.new.push(line[1..].to_string()),
+            Some('-') => hunk.old.push(line[1..].to_string()),
+            Some(' ') => {
+                hunk.old.push(line[1..].to_string());
+                hunk.new.push(line[1..].to_string());
+            }
+            // Some tools drop the space on blank context lines.
+            None => {
+                hunk.old.push(String::new());
+                hunk.new.push(String::new());
+            }
+            Some(_) => return Err(format!("unexpected line in hunk {}: {line}", hunk.header)),
+        }
+    }
+    Ok(hunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+
+    #[test]
+    fn diff_renders_hunks() {
+        let new = OLD.replace("c\n", "C\n").replace("i\n", "");
+        let diff = unified_diff(OLD, &new, "x.txt", 1);
+        assert_eq!(
+            diff,
+            "--- a/x.txt\n+++ b/x.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n@@ -8,3 +8,2 @@\n h\n-i\n j\n"
+        );
+        assert_eq!(diff_stats(&diff), (1, 2));
+        assert!(unified_diff(OLD, OLD, "x.txt", 3).is_empty());
+    }
+
+    #[test]
+    fn patch_roundtrips_diff() {
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let diff = unified_diff(OLD, new, "x.txt", 3);
+        assert_eq!(apply_patch(OLD, &diff).unwrap(), new);
+    }
+
+    #[test]
+    fn patch_tolerates_offset_but_not_mismatch() {
+        let patch = "@@ -1,3 +1,3 @@\n e\n-f\n+F\n g\n";
+        assert_eq!(apply_patch(OLD, patch).unwrap(), OLD.replace("f\n", "F\n"));
+
+        let bad = "@@ -1,2 +1,2 @@\n x\n-y\n+z\n";
+        let err = apply_patch(OLD, bad).unwrap_err();
+        assert!(err.contains("hunk 1") && err.contains("does not match"));
+
+        let ambiguous = "@@ -1 +1 @@\n-x\n+y\n";
+        let err = apply_patch("x\nx\nz\n", "@@ -5 +5 @@\n-x\n+y\n").unwrap_err();
+        assert!(err.contains("matches 2 places"), "{err}");
+        assert!(apply_patch("x\n", ambiguous).is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use super::diff;
+use super::undo::UndoJournal;
+use crate::capability::path_policy::FileAccess;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+#[derive(Default)]
+pub struct EditFile {
+    access: Arc<FileAccess>,
+    journal: Arc<UndoJournal>,
+}
+
+impl EditFile {
+    pub fn new(access: Arc<FileAccess>, journal: Arc<UndoJournal>) -> Self {
+        Self { access, journal }
+    }
+}
+
+/// Context lines around each change in the returned diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// The three ways to describe an edit; exactly one per call.
+#[derive(Debug)]
+enum Edit<'a> {
+    Replace { old: &'a str, new: &'a str, all: bool },
+    Lines { start: usize, end: usize, text: &'a str },
+    Patch(&'a str),
+}
+
+impl<'a> Edit<'a> {
+    fn from_params(params: &'a serde_json::Value) -> Result<Self, String> {
+        let str_param = |k: &str| params.get(k).and_then(|v| v.as_str());
+        let line_param = |k: &str| params.get(k).and_then(|v| v.as_u64()).map(|n| n as usize);
+        match (str_param("old_string"), line_param("start_line"), str_param("patch")) {
+            (Some(old), None, None) => Ok(Self::Replace {
+                old,
+                new: str_param("new_string").ok_or("old_string needs new_string")?,
+                all: params.get("replace_all").and_then(|v| v.as_bool()).unwrap_or(false),
+            }),
+            (None, Some(start), None) => Ok(Self::Lines {
+                start,
+                end: line_param("end_line").ok_or("start_line needs end_line")?,
+                text: str_param("new_text").ok_or("start_line needs new_text")?,
+            }),
+            (None, None, Some(patch)) => Ok(Self::Patch(patch)),
+            (None, None, None) => {
+                Err("describe the edit with old_string/new_string, start_line/end_line/new_text, or patch".into())
+            }
+            _ => Err("use exactly one of old_string, start_line or patch per call".into()),
+        }
+    }
+
+    fn apply(&self, content: &str, path: &str) -> Result<String, String> {
+        match *self {
+            Self::Replace { old, new, all } => replace(content, path, old, new, all),
+            Self::Lines { start, end, text } => replace_lines(content, path, start, end, text),
+            Self::Patch(patch) => diff::apply_patch(content, patch).map_err(|e| format!("{path}: {e}")),
+        }
+    }
+}
+
+fn replace(content: &str, path: &str, old: &str, new: &str, all: bool) -> Result<String, String> {
+    if old.is_empty() {
+        return Err("old_string is empty; use start_line/end_line to insert text".into());
+    }
+    if old == new {
+        return Err("old_string and new_string are identical; nothing to change".into());
+    }
+    let starts: Vec<usize> = content.match_indices(old).map(|(i, _)| i).collect();
+    match starts.len() {
+        0 => {
+            let hint = if content.contains(old.trim()) && !old.trim().is_empty() {
+                " It does match with different surrounding whitespace; copy the exact indentation and line breaks."
+            } else {
+                " Re-read the file and copy the exact text, including whitespace."
+            };
+            Err(format!("old_string not found in {path}.{hint}"))
+        }
+        1 => Ok(content.replacen(old, new, 1)),
+        _ if all => Ok(content.replace(old, new)),
+        n => {
+            let lines: Vec<String> = starts
+                .iter()
+                .take(10)
+                .map(|&i| (content[..i].matches('\n').count() + 1).to_string())
+                .collect();
+            Err(format!(
+                "old_string occurs {n} times in {path} (lines {}). Include more surrounding context to make it unique, \
+                 or set replace_all to change every occurrence.",
+                lines.join(", ")
+            ))
+        }
+    }
+}
+
+fn replace_lines(content: &str, path: &str, start: usize, end: usize, text: &str) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    if start == 0 || end < start {
+        return Err(format!(
+            "invalid line range {start}-{end}: lines are 1-based and end_line must be >= start_line"
+        ));
+    }
+    if end > lines.len() {
+        return Err(format!(
+            "line range {start}-{end} is outside {path}, which has {} lines",
+            lines.len()
+        ));
+    }
+    let mut out: Vec<&str> = lines[..start - 1].to_vec();
+    if !text.is_empty() {
+        out.extend(text.strip_suffix('\n').unwrap_or(text).split('\n'));
+    }
+    out.extend(&lines[end..]);
+    let mut result = out.join("\n");
+    if content.ends_with('\n') && !out.is_empty() {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for EditFile {
+    fn name(&self) -> &str { "edit_file" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["edit", "modify", "replace", "change", "patch", "修改", "替换", "编辑"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead, Permission::FileWrite]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "edit_file".into(),
+            description: "Edit an existing file in place and return the unified diff. Use one mode per call: \
+                          old_string/new_string (exact match, must be unique unless replace_all), \
+                          start_line/end_line/new_text (replace an inclusive 1-based line range; empty new_text deletes), \
+                          or patch (a unified diff). Prefer this over write_file for changes to existing files."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "minLength": 1, "description": "File to edit" },
+                    "old_string": { "type": "string", "minLength": 1, "description": "Exact text to replace" },
+                    "new_string": { "type": "string", "description": "Replacement text" },
+                    "replace_all": { "type": "boolean", "description": "Replace every occurrence of old_string" },
+                    "start_line": { "type": "integer", "minimum": 1, "description": "First line to replace (1-based)" },
+                    "end_line": { "type": "integer", "minimum": 1, "description": "Last line to replace (inclusive)" },
+                    "new_text": { "type": "string", "description": "Text that replaces the line range" },
+                    "patch": { "type": "string", "minLength": 1, "description": "Unified diff to apply (hunks starting with @@)" }
+                },
+                "required": ["path"],
+                "oneOf": [
+                    { "required": ["old_string", "new_string"] },
+                    { "required": ["start_line", "end_line", "new_text"] },
+                    { "required": ["patch"] }
+                ],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+            return fail("missing path".into());
+        };
+        let edit = match Edit::from_params(&request.params) {
+            Ok(e) => e,
+            Err(e) => return fail(e),
+        };
+        let policy = self.access.policy();
+        let resolved = match policy.check_read(path) {
+            Ok(p) => p,
+            // Only a path the policy admits is reported as missing
+            Err(_) if policy.check_write(path, 0).is_ok() && !std::path::Path::new(path).exists() => {
+                return fail(format!("{path} does not exist; use write_file to create new files"));
+            }
+            Err(e) => return fail(e),
+        };
+        let original = match tokio::fs::read_to_string(&resolved).await {
+            Ok(c) => c,
+            Err(e) => return fail(format!("failed to read {path}: {e}")),
+        };
+        let updated = match edit.apply(&original, path) {
+            Ok(u) => u,
+            Err(e) => return fail(e),
+        };
+        if updated == original {
+            return fail(format!("the edit leaves {path} unchanged"));
+        }
+        if let Err(e) = policy.check_write(path, updated.len()) {
+            return fail(e);
+        }
+        let entry = match self.journal.write_async(resolved, updated.clone().into_bytes(), "edit_file").await {
+            Ok(entry) => entry,
+            Err(e) => return fail(e),
+        };
+
+        let diff = diff::unified_diff(&original, &updated, path, DIFF_CONTEXT);
+        let (added, removed) = diff::diff_stats(&diff);
+        CapabilityResponse {
+            id: request.id,
+            result: Some(serde_json::json!({
+                "path": path,
+                "diff": diff,
+                "lines_added": added,
+                "lines_removed": removed,
+                "undo_id": entry.short_id(),
+            })),
+            error: None,
+            metrics: None,
+            side_effects: vec![Permission::FileWrite],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    const SRC: &str = "fn main() {\n    let x = 1;\n    let y = 1;\n    println!(\"{x}\");\n}\n";
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    #[test]
+    fn exact_replace_requires_uniqueness() {
+        let err = replace(SRC, "m.rs", "= 1;", "= 2;", false).unwrap_err();
+        assert!(err.contains("occurs 2 times") && err.contains("lines 2, 3"), "{err}");
+        assert_eq!(
+            replace(SRC, "m.rs", "= 1;", "= 2;", true).unwrap().matches("= 2;").count(),
+            2
+        );
+        let out = replace(SRC, "m.rs", "let x = 1;", "let x = 5;", false).unwrap();
+        assert!(out.contains("let x = 5;"));
+
+        let err = replace(SRC, "m.rs", "let z", "let w", false).unwrap_err();
+        assert!(err.contains("not found"));
+        let err = replace(SRC, "m.rs", "  let x = 1;\n  let", "", false).unwrap_err();
+        assert!(err.contains("Re-read"));
+        let err = replace(SRC, "m.rs", "let x = 1; ", "", false).unwrap_err();
+        assert!(err.contains("different surrounding whitespace"));
+    }
+
+    #[test]
+    fn line_range_replace_and_delete() {
+        let out = replace_lines(SRC, "m.rs", 2, 3, "    let x = 2;").unwrap();
+        assert_eq!(out, "fn main() {\n    let x = 2;\n    println!(\"{x}\");\n}\n");
+        let out = replace_lines(SRC, "m.rs", 4, 4, "").unwrap();
+        assert!(!out.contains("println"));
+        assert!(replace_lines(SRC, "m.rs", 4, 9, "").unwrap_err().contains("has 5 lines"));
+        assert!(replace_lines(SRC, "m.rs", 3, 2, "").is_err());
+    }
+
+    #[test]
+    fn modes_are_exclusive() {
+        let both = serde_json::json!({ "path": "a", "old_string": "x", "new_string": "y", "patch": "@@" });
+        assert!(Edit::from_params(&both).unwrap_err().contains("exactly one"));
+        let none = serde_json::json!({ "path": "a" });
+        assert!(Edit::from_params(&none).is_err());
+    }
+
+    #[tokio::test]
+    async fn edits_file_and_returns_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, SRC).unwrap();
+        let path = file.to_str().unwrap();
+
+        let resp = EditFile::default().execute(req(serde_json::json!({
+            "path": path, "old_string": "let x = 1;", "new_string": "let x = 42;"
+        }))).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        let result = resp.result.unwrap();
+        assert!(result["diff"].as_str().unwrap().contains("-    let x = 1;\n+    let x = 42;"));
+        assert_eq!(result["lines_added"], 1);
+
+        let patch = "@@ -3 +3 @@\n-    let y = 1;\n+    let y = 7;\n";
+        let resp = EditFile::default().execute(req(serde_json::json!({ "path": path, "patch": patch }))).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        let content = std::fs::read_to_string(&file).unwrap();
+        assert!(content.contains("let x = 42;") && content.contains("let y = 7;"));
+
+        let resp = EditFile::default().execute(req(serde_json::json!({ "path": path, "patch": patch }))).await;
+        assert!(resp.error.unwrap().contains("regenerate the patch"));
+    }
+
+    #[tokio::test]
+    async fn refuses_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = EditFile::default();
+        let edit = |path: std::path::PathBuf| {
+            tool.execute(req(serde_json::json!({ "path": path, "old_string": "a", "new_string": "b" })))
+        };
+        let resp = edit(dir.path().join("missing.rs")).await;
+        assert!(resp.error.unwrap().contains("use write_file"));
+
+        // The policy is checked first, so refused paths never reveal whether they exist
+        let resp = edit("/no/such/file.rs".into()).await;
+        assert!(resp.error.unwrap().contains("outside the allowed roots"));
+        let resp = edit(dir.path().join(".env")).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+        std::fs::write(dir.path().join(".env"), "a").unwrap();
+        let resp = edit(dir.path().join(".env")).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+    }
+}
@@ -0,0 +1,476 @@
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use crate::capability::net_policy::{HostPolicy, NetContext};
+use crate::environment::hardware::NetworkState;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+use regex::Regex;
+
+/// Fetch a URL over HTTP(S), subject to the host policy in `NetContext`.
+pub struct FetchUrl {
+    net: Arc<NetContext>,
+}
+
+impl FetchUrl {
+    pub fn new(net: Arc<NetContext>) -> Self {
+        Self { net }
+    }
+}
+
+const DEFAULT_TIMEOUT_SECS: u64 = 20;
+const MAX_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_MAX_BYTES: usize = 512 * 1024;
+const MAX_BYTES_CAP: usize = 5 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for FetchUrl {
+    fn name(&self) -> &str { "fetch_url" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["fetch", "url", "http", "download", "website", "web", "网页", "链接", "下载"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::NetworkRead, Permission::NetworkWrite]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "fetch_url".into(),
+            description: "Fetch a URL with GET or POST. HTML pages are returned as extracted text unless raw is true. \
+                          Hosts are subject to an allow/deny list."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "pattern": "^https?://", "description": "http(s) URL to fetch" },
+                    "method": { "type": "string", "enum": ["GET", "POST"], "description": "HTTP method (default GET)" },
+                    "headers": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Extra request headers"
+                    },
+                    "body": { "type": "string", "description": "Request body for POST" },
+                    "timeout_secs": { "type": "integer", "minimum": 1, "maximum": MAX_TIMEOUT_SECS, "description": "Request timeout (default 20)" },
+                    "max_bytes": { "type": "integer", "minimum": 1, "maximum": MAX_BYTES_CAP, "description": "Maximum body bytes to read (default 512 KiB)" },
+                    "raw": { "type": "boolean", "description": "Return HTML as-is instead of extracted text" }
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let Some(url) = params.get("url").and_then(|v| v.as_str()) else {
+            return fail("missing url".into());
+        };
+        let url = match reqwest::Url::parse(url) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+            Ok(u) => return fail(format!("unsupported scheme '{}', use http or https", u.scheme())),
+            Err(e) => return fail(format!("invalid url {url}: {e}")),
+        };
+
+        if self.net.network() == NetworkState::Offline {
+            return fail("network is offline".into());
+        }
+        let policy = self.net.policy();
+
+        let is_post = params.get("method").and_then(|v| v.as_str()) == Some("POST");
+        let timeout = params.get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
+        let max_bytes = params.get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_BYTES_CAP))
+            .unwrap_or(DEFAULT_MAX_BYTES);
+        let raw = params.get("raw").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let headers = params.get("headers").and_then(|v| v.as_object());
+        let body = params.get("body").and_then(|v| v.as_str());
+
+        // Redirects are followed here rather than by reqwest so every hop is
+        // resolved, checked and pinned like the original URL.
+        let mut current = url.clone();
+        let mut post = is_post;
+        let mut hops = 0;
+        let mut resp = loop {
+            let client = match pinned_client(&policy, &current, Duration::from_secs(timeout)).await {
+                Ok(c) => c,
+                Err(e) if hops == 0 => return fail(e),
+                Err(e) => return fail(format!("redirect blocked: {e}")),
+            };
+            let mut builder = if post { client.post(current.clone()) } else { client.get(current.clone()) };
+            // Custom headers (often credentials) are not forwarded to other hosts
+            if let Some(headers) = headers
+                && current.host_str() == url.host_str()
+            {
+                for (name, value) in headers {
+                    if let Some(value) = value.as_str() {
+                        builder = builder.header(name.as_str(), value);
+                    }
+                }
+            }
+            if post && let Some(body) = body {
+                builder = builder.body(body.to_string());
+            }
+
+            let resp = match builder.send().await {
+                Ok(r) => r,
+                Err(e) => return fail(format!("request to {current} failed: {}", error_chain(&e))),
+            };
+            let location = resp.headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .filter(|_| resp.status().is_redirection());
+            let Some(location) = location else { break resp };
+            if hops >= MAX_REDIRECTS {
+                return fail(format!("request to {url} failed: too many redirects"));
+            }
+            current = match current.join(location) {
+                Ok(next) if matches!(next.scheme(), "http" | "https") => next,
+                Ok(next) => return fail(format!("redirect blocked: unsupported scheme '{}'", next.scheme())),
+                Err(e) => return fail(format!("redirect to invalid url {location}: {e}")),
+            };
+            // 301/302/303 continue as GET, like browsers and reqwest
+            if matches!(resp.status().as_u16(), 301..=303) {
+                post = false;
+            }
+            hops += 1;
+        };
+        let status = resp.status();
+        let final_url = resp.url().to_string();
+        let content_type = resp.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+
+        let mut bytes = Vec::new();
+        let mut truncated = false;
+        loop {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => {
+                    let room = max_bytes - bytes.len();
+                    if chunk.len() > room {
+                        bytes.extend_from_slice(&chunk[..room]);
+                        truncated = true;
+                        break;
+                    }
+                    bytes.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => return fail(format!("reading body from {url} failed: {}", error_chain(&e))),
+            }
+        }
+
+        let text = String::from_utf8_lossy(&bytes);
+        let is_html = content_type.contains("html");
+        let (body, title) = if is_html && !raw {
+            (html_to_text(&text), html_title(&text))
+        } else {
+            (text.into_owned(), None)
+        };
+
+        let error = (status.is_client_error() || status.is_server_error())
+            .then(|| format!("HTTP {status} from {final_url}"));
+        let side_effect = if is_post { Permission::NetworkWrite } else { Permission::NetworkRead };
+        CapabilityResponse {
+            id: request.id,
+            result: Some(serde_json::json!({
+                "url": url.as_str(),
+                "final_url": final_url,
+                "status": status.as_u16(),
+                "content_type": content_type,
+                "title": title,
+                "body": body,
+                "bytes": bytes.len(),
+                "truncated": truncated,
+            })),
+            error,
+            metrics: None,
+            side_effects: vec![side_effect],
+        }
+    }
+}
+
+/// A client that connects only to the policy-checked addresses of `url`'s host.
+async fn pinned_client(policy: &HostPolicy, url: &reqwest::Url, timeout: Duration) -> Result<reqwest::Client, String> {
+    let host = url.host_str().unwrap_or("");
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs = policy.resolve(host, port).await?;
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, &addrs)
+        .user_agent(concat!("iris/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {e}"))
+}
+
+/// reqwest hides the useful part (e.g. a blocked redirect) in the source chain.
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut out = e.to_string();
+    let mut source = e.source();
+    while let Some(s) = source {
+        out.push_str(": ");
+        out.push_str(&s.to_string());
+        source = s.source();
+    }
+    out
+}
+
+static RE_INVISIBLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(script|style|noscript|head)\b.*?</(script|style|noscript|head)\s*>|<!--.*?-->").unwrap()
+});
+static RE_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<(br|/p|/div|/li|/h[1-6]|/tr|/section|/article|/pre|/blockquote)\b[^>]*>|<li\b[^>]*>").unwrap()
+});
+static RE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static RE_TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap());
+static RE_ENTITY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").unwrap());
+
+/// Readable text from an HTML page: drops scripts/styles, keeps block breaks, decodes entities.
+fn html_to_text(html: &str) -> String {
+    let visible = RE_INVISIBLE.replace_all(html, " ");
+    let broken = RE_BLOCK.replace_all(&visible, "\n");
+    let stripped = RE_TAG.replace_all(&broken, " ");
+    let decoded = decode_entities(&stripped);
+
+    let mut out = Vec::new();
+    for line in decoded.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            out.push(line);
+        }
+    }
+    out.join("\n")
+}
+
+fn html_title(html: &str) -> Option<String> {
+    RE_TITLE.captures(html).map(|c| {
+        decode_entities(&c[1]).split_whitespace().collect::<Vec<_>>().join(" ")
+    }).filter(|t| !t.is_empty())
+}
+
+fn decode_entities(text: &str) -> String {
+    RE_ENTITY.replace_all(text, |caps: &regex::Captures| {
+        let name = &caps[1];
+        let decoded = if let Some(hex) = name.strip_prefix("#x") {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        } else if let Some(dec) = name.strip_prefix('#') {
+            dec.parse().ok().and_then(char::from_u32)
+        } else {
+            match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => None,
+            }
+        };
+        decoded.map(String::from).unwrap_or_else(|| caps[0].to_string())
+    }).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+    use crate::capability::net_policy::{HostPolicy, HostRule, HostRuleKind};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Minimal HTTP/1.1 server: answers each request with `respond(request_text, port)`.
+    async fn serve(respond: fn(&str, u16) -> String) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let n = sock.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            break;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        let text = String::from_utf8_lossy(&buf);
+                        if let Some(end) = text.find("\r\n\r\n") {
+                            let len = text.lines()
+                                .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                                .and_then(|v| v.parse::<usize>().ok())
+                                .unwrap_or(0);
+                            if buf.len() >= end + 4 + len {
+                                break;
+                            }
+                        }
+                    }
+                    let reply = respond(&String::from_utf8_lossy(&buf), port);
+                    let _ = sock.write_all(reply.as_bytes()).await;
+                    let _ = sock.shutdown().await;
+                });
+            }
+        });
+        port
+    }
+
+    fn http(status: &str, content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn routes(req: &str, port: u16) -> String {
+        let path = req.split_whitespace().nth(1).unwrap_or("/");
+        match path {
+            "/page" => http(
+                "200 OK",
+                "text/html; charset=utf-8",
+                "<html><head><title>Hello &amp; welcome</title><style>p{}</style></head>\
+                 <body><script>alert(1)</script><h1>Title</h1><p>First&nbsp;para</p><p>Second</p></body></html>",
+            ),
+            "/echo" => {
+                let body = req.split("\r\n\r\n").nth(1).unwrap_or("");
+                let token = req.lines()
+                    .find_map(|l| l.strip_prefix("x-token: "))
+                    .unwrap_or("none");
+                http("200 OK", "text/plain", &format!("{token}:{body}"))
+            }
+            "/big" => http("200 OK", "text/plain", &"x".repeat(4096)),
+            "/missing" => http("404 Not Found", "text/plain", "nope"),
+            "/redirect" => format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://localhost:{port}/page\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            ),
+            _ => http("200 OK", "text/plain", "ok"),
+        }
+    }
+
+    fn fetcher(rules: &[(&str, HostRuleKind)]) -> FetchUrl {
+        let net = NetContext::shared();
+        net.set_policy(HostPolicy::new(
+            rules.iter().map(|(h, k)| HostRule { host: h.to_string(), kind: *k }).collect(),
+        ));
+        FetchUrl::new(net)
+    }
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: String::new(),
+            params,
+            version: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn get_html_extracts_text() {
+        let port = serve(routes).await;
+        let f = fetcher(&[("127.0.0.1", HostRuleKind::Allow)]);
+        let resp = f.execute(req(serde_json::json!({ "url": format!("http://127.0.0.1:{port}/page") }))).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        let result = resp.result.unwrap();
+        assert_eq!(result["status"], 200);
+        assert_eq!(result["title"], "Hello & welcome");
+        assert_eq!(result["body"], "Title\nFirst para\nSecond");
+        assert_eq!(resp.side_effects, vec![Permission::NetworkRead]);
+    }
+
+    #[tokio::test]
+    async fn post_sends_body_and_headers() {
+        let port = serve(routes).await;
+        let f = fetcher(&[("127.0.0.1", HostRuleKind::Allow)]);
+        let resp = f.execute(req(serde_json::json!({
+            "url": format!("http://127.0.0.1:{port}/echo"),
+            "method": "POST",
+            "headers": { "x-token": "abc" },
+            "body": "payload",
+        }))).await;
+        assert_eq!(resp.result.unwrap()["body"], "abc:payload");
+        assert_eq!(resp.side_effects, vec![Permission::NetworkWrite]);
+    }
+
+    #[tokio::test]
+    async fn body_is_capped_and_http_errors_reported() {
+        let port = serve(routes).await;
+        let f = fetcher(&[("127.0.0.1", HostRuleKind::Allow)]);
+        let resp = f.execute(req(serde_json::json!({
+            "url": format!("http://127.0.0.1:{port}/big"),
+            "max_bytes": 100,
+        }))).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["bytes"], 100);
+        assert_eq!(result["truncated"], true);
+
+        let resp = f.execute(req(serde_json::json!({ "url": format!("http://127.0.0.1:{port}/missing") }))).await;
+        assert!(resp.error.unwrap().contains("404"));
+        assert_eq!(resp.result.unwrap()["body"], "nope");
+    }
+
+    #[tokio::test]
+    async fn policy_blocks_hosts_and_redirects() {
+        let port = serve(routes).await;
+        let url = format!("http://127.0.0.1:{port}/page");
+
+        // Loopback needs an explicit allow rule.
+        let resp = fetcher(&[]).execute(req(serde_json::json!({ "url": url }))).await;
+        assert!(resp.error.unwrap().contains("local or private"));
+
+        let resp = fetcher(&[("127.0.0.1", HostRuleKind::Deny)])
+            .execute(req(serde_json::json!({ "url": url }))).await;
+        assert!(resp.error.unwrap().contains("denied"));
+
+        // Redirect to a host outside the allowlist is refused.
+        let resp = fetcher(&[("127.0.0.1", HostRuleKind::Allow)])
+            .execute(req(serde_json::json!({ "url": format!("http://127.0.0.1:{port}/redirect") }))).await;
+        assert!(resp.error.unwrap().contains("not in the allowlist"));
+
+        // Each allowed hop is resolved and followed.
+        let resp = fetcher(&[("127.0.0.1", HostRuleKind::Allow), ("localhost", HostRuleKind::Allow)])
+            .execute(req(serde_json::json!({ "url": format!("http://127.0.0.1:{port}/redirect") }))).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        let result = resp.result.unwrap();
+        assert_eq!(result["final_url"], format!("http://localhost:{port}/page"));
+        assert_eq!(result["title"], "Hello & welcome");
+
+        // Mapped IPv6 loopback is still loopback.
+        let resp = fetcher(&[])
+            .execute(req(serde_json::json!({ "url": format!("http://[::ffff:127.0.0.1]:{port}/page") }))).await;
+        assert!(resp.error.unwrap().contains("local or private"));
+    }
+
+    #[tokio::test]
+    async fn offline_short_circuits() {
+        let f = fetcher(&[("127.0.0.1", HostRuleKind::Allow)]);
+        f.net.set_network(NetworkState::Offline);
+        let resp = f.execute(req(serde_json::json!({ "url": "http://127.0.0.1:9/" }))).await;
+        assert_eq!(resp.error.as_deref(), Some("network is offline"));
+    }
+
+    #[tokio::test]
+    async fn rejects_non_http_schemes() {
+        let resp = fetcher(&[]).execute(req(serde_json::json!({ "url": "file:///etc/passwd" }))).await;
+        assert!(resp.error.unwrap().contains("unsupported scheme"));
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(decode_entities("a &lt;b&gt; &#65;&#x42; &bogus;"), "a <b> AB &bogus;");
+    }
+}
@@ -0,0 +1,849 @@
+//! `git` builtin: structured access to repositories.
+//!
+//! Read subcommands (status, diff, log, blame, show, branches) run git with
+//! machine-readable output formats and return parsed JSON. `commit` and
+//! `checkout` change the repository: they need the `git_allow_mutations`
+//! config switch, and the repository must pass the file path policy.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::capability::path_policy::FileAccess;
+use crate::config::IrisCfg;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_LOG_COUNT: u64 = 20;
+const MAX_LOG_COUNT: u64 = 200;
+/// Blame lines returned when no range is given.
+const MAX_BLAME_LINES: usize = 400;
+
+/// Field and record separators for `--format` output.
+const FS: char = '\x1f';
+const RS: char = '\x1e';
+const LOG_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%P%x1f%s%x1f%b%x1e";
+
+/// Whether `commit` and `checkout` are allowed; set from `git_allow_mutations`.
+#[derive(Debug, Default)]
+pub struct GitSettings {
+    allow_mutations: AtomicBool,
+}
+
+impl GitSettings {
+    pub fn configure(&self, cfg: &IrisCfg) {
+        self.allow_mutations.store(cfg.git_allow_mutations, Ordering::Relaxed);
+    }
+
+    pub fn allow_mutations(&self) -> bool {
+        self.allow_mutations.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+pub struct Git {
+    files: Arc<FileAccess>,
+    settings: Arc<GitSettings>,
+}
+
+impl Git {
+    pub fn new(files: Arc<FileAccess>, settings: Arc<GitSettings>) -> Self {
+        Self { files, settings }
+    }
+}
+
+/// Run git in `repo` and return stdout. The repository's own config may name
+/// programs to run (fsmonitor hooks, pagers, external diff and textconv
+/// drivers); those are switched off here or on the subcommands that use them.
+async fn run(repo: &Path, args: &[String]) -> Result<String, String> {
+    let sub = args.first().map(String::as_str).unwrap_or("git");
+    let mut cmd = tokio::process::Command::new("git");
+    cmd.arg("--no-pager")
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "color.ui=never", "-c", "core.quotepath=off"])
+        .args(["-c", "core.fsmonitor=false", "-c", "core.pager=cat"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_PAGER", "cat")
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let out = tokio::time::timeout(GIT_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| format!("git {sub} timed out after {}s", GIT_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        return Err(format!("git {sub} failed: {detail}"));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Revisions and ranges become git arguments; refuse anything that looks like an option.
+fn revision(params: &Value, key: &str) -> Result<Option<String>, String> {
+    match params.get(key).and_then(Value::as_str).map(str::trim) {
+        Some(r) if r.starts_with('-') => Err(format!("{key} must be a revision, not an option: {r}")),
+        Some("") | None => Ok(None),
+        Some(r) => Ok(Some(r.to_string())),
+    }
+}
+
+fn str_param<'a>(params: &'a Value, key: &str) -> Option<&'a str> {
+    params.get(key).and_then(Value::as_str).filter(|s| !s.trim().is_empty())
+}
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+// ---------------------------------------------------------------------------
+// Output parsers
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct Status {
+    branch: Option<String>,
+    commit: Option<String>,
+    upstream: Option<String>,
+    ahead: i64,
+    behind: i64,
+    clean: bool,
+    staged: Vec<Change>,
+    unstaged: Vec<Change>,
+    untracked: Vec<String>,
+    conflicted: Vec<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Change {
+    path: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+}
+
+fn change_kind(c: char) -> Option<&'static str> {
+    match c {
+        'M' => Some("modified"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        'T' => Some("type_changed"),
+        _ => None,
+    }
+}
+
+/// Parse `git status --porcelain=v2 --branch -z`.
+fn parse_status(out: &str) -> Status {
+    let mut status = Status::default();
+    let mut records = out.split('\0').filter(|r| !r.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => status.commit = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for n in value.split_whitespace() {
+                        match n.split_at(1) {
+                            ("+", a) => status.ahead = a.parse().unwrap_or(0),
+                            ("-", b) => status.behind = b.parse().unwrap_or(0),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let kind = record.chars().next().unwrap_or(' ');
+        // Ordinary (1) and rename/copy (2) entries differ only in the extra score field
+        // and the original path that follows as its own record.
+        let fields = match kind {
+            '1' => 8,
+            '2' => 9,
+            'u' => 10,
+            '?' | '!' => 1,
+            _ => continue,
+        };
+        let mut parts = record.splitn(fields + 1, ' ');
+        let _ = parts.next();
+        let xy: Vec<char> = parts.next().unwrap_or("").chars().collect();
+        let path = parts.last().unwrap_or("").to_string();
+        match kind {
+            '?' => status.untracked.push(record[2..].to_string()),
+            '!' => {}
+            'u' => status.conflicted.push(path),
+            _ => {
+                let from = if kind == '2' { records.next().map(String::from) } else { None };
+                if let Some(s) = xy.first().copied().and_then(change_kind) {
+                    status.staged.push(Change { path: path.clone(), status: s, from: from.clone() });
+                }
+                if let Some(s) = xy.get(1).copied().and_then(change_kind) {
+                    status.unstaged.push(Change { path, status: s, from });
+                }
+            }
+        }
+    }
+    status.clean = status.staged.is_empty()
+        && status.unstaged.is_empty()
+        && status.untracked.is_empty()
+        && status.conflicted.is_empty();
+    status
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct FileDiff {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    status: &'static str,
+    added: usize,
+    removed: usize,
+    binary: bool,
+    patch: String,
+}
+
+/// Strip the `a/` or `b/` prefix git puts on diff paths (and quotes, if any).
+fn diff_path(raw: &str) -> Option<String> {
+    let raw = raw.trim_end_matches('\t').trim_matches('"');
+    if raw == "/dev/null" {
+        return None;
+    }
+    Some(raw.get(2..).unwrap_or(raw).to_string())
+}
+
+/// Split a unified diff into per-file entries with line counts.
+fn parse_diff(patch: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut in_hunk = false;
+    for line in patch.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            // `a/x b/x`: both halves are equal unless the file was renamed, and then
+            // the rename lines below carry the real names.
+            let header = header.trim_end();
+            let path = header
+                .rfind(" b/")
+                .map(|i| header[i + 3..].to_string())
+                .unwrap_or_else(|| header.to_string());
+            files.push(FileDiff { path, status: "modified", ..FileDiff::default() });
+            in_hunk = false;
+            files.last_mut().unwrap().patch.push_str(line);
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+        file.patch.push_str(line);
+        let text = line.trim_end_matches('\n');
+        if text.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk {
+            if text.starts_with('+') {
+                file.added += 1;
+            } else if text.starts_with('-') {
+                file.removed += 1;
+            }
+        } else if text.starts_with("new file mode") {
+            file.status = "added";
+        } else if text.starts_with("deleted file mode") {
+            file.status = "deleted";
+        } else if let Some(from) = text.strip_prefix("rename from ") {
+            file.status = "renamed";
+            file.from = Some(from.to_string());
+        } else if let Some(to) = text.strip_prefix("rename to ") {
+            file.path = to.to_string();
+        } else if text.starts_with("Binary files ") || text == "GIT binary patch" {
+            file.binary = true;
+        } else if let Some(p) = text.strip_prefix("+++ ").and_then(diff_path) {
+            file.path = p;
+        }
+    }
+    files
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Commit {
+    hash: String,
+    short: String,
+    author: String,
+    email: String,
+    date: String,
+    parents: Vec<String>,
+    subject: String,
+    body: String,
+}
+
+/// Parse records produced with `LOG_FORMAT`.
+fn parse_log(out: &str) -> Vec<Commit> {
+    out.split(RS)
+        .filter_map(|record| {
+            let record = record.trim_start_matches('\n');
+            if record.is_empty() {
+                return None;
+            }
+            let f: Vec<&str> = record.splitn(8, FS).collect();
+            (f.len() == 8).then(|| Commit {
+                hash: f[0].to_string(),
+                short: f[1].to_string(),
+                author: f[2].to_string(),
+                email: f[3].to_string(),
+                date: f[4].to_string(),
+                parents: f[5].split_whitespace().map(String::from).collect(),
+                subject: f[6].to_string(),
+                body: f[7].trim_end().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct BlameLine {
+    line: usize,
+    commit: String,
+    author: String,
+    date: String,
+    summary: String,
+    text: String,
+}
+
+/// Parse `git blame --porcelain`. Commit details appear only on a commit's first line.
+fn parse_blame(out: &str) -> Vec<BlameLine> {
+    #[derive(Default, Clone)]
+    struct Info {
+        author: String,
+        time: i64,
+        summary: String,
+    }
+    let mut commits: HashMap<String, Info> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, usize)> = None;
+    for row in out.lines() {
+        if let Some(text) = row.strip_prefix('\t') {
+            if let Some((hash, line)) = current.take() {
+                let info = commits.get(&hash).cloned().unwrap_or_default();
+                let date = chrono::DateTime::from_timestamp(info.time, 0)
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_default();
+                lines.push(BlameLine {
+                    line,
+                    commit: hash[..hash.len().min(10)].to_string(),
+                    author: info.author,
+                    date,
+                    summary: info.summary,
+                    text: text.to_string(),
+                });
+            }
+            continue;
+        }
+        let mut parts = row.split(' ');
+        let first = parts.next().unwrap_or("");
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            let line = parts.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            commits.entry(first.to_string()).or_default();
+            current = Some((first.to_string(), line));
+            continue;
+        }
+        let Some((hash, _)) = &current else { continue };
+        let info = commits.entry(hash.clone()).or_default();
+        let value = row.split_once(' ').map(|(_, v)| v).unwrap_or("");
+        match first {
+            "author" => info.author = value.to_string(),
+            "author-time" => info.time = value.parse().unwrap_or(0),
+            "summary" => info.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Branch {
+    name: String,
+    commit: String,
+    current: bool,
+    remote: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    track: Option<String>,
+    date: String,
+    subject: String,
+}
+
+const BRANCH_FORMAT: &str = "--format=%(refname)%1f%(objectname:short)%1f%(HEAD)%1f%(upstream:short)%1f\
+%(upstream:track)%1f%(committerdate:iso-strict)%1f%(contents:subject)";
+
+/// Parse `git for-each-ref` output produced with `BRANCH_FORMAT`.
+fn parse_branches(out: &str) -> Vec<Branch> {
+    out.lines()
+        .filter_map(|line| {
+            let f: Vec<&str> = line.splitn(7, FS).collect();
+            if f.len() != 7 || f[0].ends_with("/HEAD") {
+                return None;
+            }
+            let (name, remote) = match f[0].strip_prefix("refs/heads/") {
+                Some(n) => (n, false),
+                None => (f[0].strip_prefix("refs/remotes/").unwrap_or(f[0]), true),
+            };
+            let some = |s: &str| (!s.is_empty()).then(|| s.to_string());
+            Some(Branch {
+                name: name.to_string(),
+                commit: f[1].to_string(),
+                current: f[2] == "*",
+                remote,
+                upstream: some(f[3]),
+                track: some(f[4].trim_matches(|c| c == '[' || c == ']')),
+                date: f[5].to_string(),
+                subject: f[6].to_string(),
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Subcommands
+// ---------------------------------------------------------------------------
+
+impl Git {
+    async fn status(&self, repo: &Path) -> Result<Value, String> {
+        let out = run(repo, &args(&["status", "--porcelain=v2", "--branch", "-z"])).await?;
+        Ok(json!(parse_status(&out)))
+    }
+
+    async fn diff(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let mut a = args(&["diff", "--no-ext-diff", "--no-textconv", "--find-renames"]);
+        if params.get("staged").and_then(Value::as_bool).unwrap_or(false) {
+            a.push("--cached".into());
+        }
+        if let Some(range) = revision(params, "range")? {
+            a.push(range);
+        }
+        a.push("--".into());
+        a.extend(self.pathspec(repo, params)?);
+        let files = parse_diff(&run(repo, &a).await?);
+        let (added, removed) = files.iter().fold((0, 0), |(a, r), f| (a + f.added, r + f.removed));
+        Ok(json!({ "files_changed": files.len(), "added": added, "removed": removed, "files": files }))
+    }
+
+    async fn log(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let count = params
+            .get("max_count")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_LOG_COUNT)
+            .clamp(1, MAX_LOG_COUNT);
+        let mut a = args(&["log", "--no-ext-diff", "--no-textconv", LOG_FORMAT]);
+        a.push(format!("--max-count={count}"));
+        for (key, flag) in [("author", "--author"), ("grep", "--grep"), ("since", "--since"), ("until", "--until")] {
+            if let Some(v) = str_param(params, key) {
+                a.push(format!("{flag}={v}"));
+            }
+        }
+        if let Some(range) = revision(params, "range")? {
+            a.push(range);
+        }
+        a.push("--".into());
+        a.extend(self.pathspec(repo, params)?);
+        let commits = parse_log(&run(repo, &a).await?);
+        Ok(json!({ "count": commits.len(), "commits": commits }))
+    }
+
+    async fn blame(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let path = self.pathspec(repo, params)?.pop().ok_or("blame needs path")?;
+        let mut a = args(&["blame", "--porcelain", "--no-textconv"]);
+        let start = params.get("start_line").and_then(Value::as_u64);
+        let end = params.get("end_line").and_then(Value::as_u64);
+        match (start, end) {
+            (Some(s), Some(e)) => a.push(format!("-L{s},{e}")),
+            (Some(s), None) => a.push(format!("-L{s},+{MAX_BLAME_LINES}")),
+            (None, Some(e)) => a.push(format!("-L1,{e}")),
+            (None, None) => {}
+        }
+        if let Some(rev) = revision(params, "rev")? {
+            a.push(rev);
+        }
+        a.extend(["--".to_string(), path.clone()]);
+        let mut lines = parse_blame(&run(repo, &a).await?);
+        let truncated = lines.len() > MAX_BLAME_LINES;
+        lines.truncate(MAX_BLAME_LINES);
+        Ok(json!({ "path": path, "lines": lines, "truncated": truncated }))
+    }
+
+    async fn show(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let rev = revision(params, "rev")?.unwrap_or_else(|| "HEAD".into());
+        if let Some(path) = self.pathspec(repo, params)?.pop() {
+            let content = run(repo, &["show".to_string(), "--no-textconv".to_string(), format!("{rev}:{path}")]).await?;
+            return Ok(json!({ "rev": rev, "path": path, "content": content }));
+        }
+        let mut a = args(&["show", "--no-ext-diff", "--no-textconv", "--find-renames", LOG_FORMAT]);
+        a.extend([rev, "--".into()]);
+        let out = run(repo, &a).await?;
+        let (meta, patch) = out.split_once(RS).unwrap_or((&out, ""));
+        let commit = parse_log(&format!("{meta}{RS}")).pop().ok_or("could not parse commit")?;
+        Ok(json!({ "commit": commit, "files": parse_diff(patch) }))
+    }
+
+    async fn branches(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let mut a = args(&["for-each-ref", BRANCH_FORMAT, "--sort=-committerdate", "refs/heads"]);
+        if params.get("remote").and_then(Value::as_bool).unwrap_or(false) {
+            a.push("refs/remotes".into());
+        }
+        let branches = parse_branches(&run(repo, &a).await?);
+        let current = branches.iter().find(|b| b.current).map(|b| b.name.clone());
+        Ok(json!({ "current": current, "branches": branches }))
+    }
+
+    async fn commit(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let message = str_param(params, "message").ok_or("commit needs message")?;
+        let paths = self.pathspec(repo, params)?;
+        if params.get("all").and_then(Value::as_bool).unwrap_or(false) {
+            run(repo, &args(&["add", "--all"])).await?;
+        } else if !paths.is_empty() {
+            let mut a = args(&["add", "--"]);
+            a.extend(paths);
+            run(repo, &a).await?;
+        }
+        run(repo, &["commit".to_string(), "--message".to_string(), message.to_string()]).await?;
+        let out = run(repo, &args(&["log", "-1", LOG_FORMAT])).await?;
+        let commit = parse_log(&out).pop().ok_or("could not read the new commit")?;
+        let stat = run(repo, &args(&["show", "--no-ext-diff", "--no-textconv", "--format=", "HEAD"])).await?;
+        Ok(json!({ "commit": commit, "files": parse_diff(&stat) }))
+    }
+
+    async fn checkout(&self, repo: &Path, params: &Value) -> Result<Value, String> {
+        let target = revision(params, "target")?.ok_or("checkout needs target")?;
+        let mut a = args(&["checkout"]);
+        if params.get("create").and_then(Value::as_bool).unwrap_or(false) {
+            a.push("-b".into());
+        }
+        a.push(target);
+        run(repo, &a).await?;
+        let branch = run(repo, &args(&["rev-parse", "--abbrev-ref", "HEAD"])).await?;
+        let commit = run(repo, &args(&["rev-parse", "--short", "HEAD"])).await?;
+        let branch = branch.trim();
+        Ok(json!({
+            "branch": (branch != "HEAD").then_some(branch),
+            "commit": commit.trim(),
+            "detached": branch == "HEAD",
+        }))
+    }
+
+    /// `path` / `paths` params as repository-relative pathspecs, each checked by the path policy.
+    fn pathspec(&self, repo: &Path, params: &Value) -> Result<Vec<String>, String> {
+        let mut raw: Vec<&str> = params
+            .get("paths")
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        raw.extend(str_param(params, "path"));
+        let policy = self.files.policy();
+        raw.into_iter()
+            .map(|p| {
+                let full = if Path::new(p).is_absolute() { PathBuf::from(p) } else { repo.join(p) };
+                let resolved = policy.check_dir(&full.to_string_lossy())?;
+                let rel = resolved
+                    .strip_prefix(repo)
+                    .map_err(|_| format!("{p} is outside the repository {}", repo.display()))?;
+                Ok(if rel.as_os_str().is_empty() { ".".into() } else { rel.to_string_lossy().into_owned() })
+            })
+            .collect()
+    }
+}
+
+const ACTIONS: [&str; 8] = ["status", "diff", "log", "blame", "show", "branches", "commit", "checkout"];
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Git {
+    fn name(&self) -> &str { "git" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["git", "commit", "branch", "diff", "blame", "repository", "repo", "提交", "分支", "仓库"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead, Permission::FileWrite]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "git".into(),
+            description: "Inspect a git repository and get parsed JSON. Actions: status; diff (working tree, \
+                          staged, or a range like main..HEAD; optional path); log (filters: author, grep, since, \
+                          until, path, range, max_count); blame (path, optional start_line/end_line, rev); \
+                          show (a commit with its per-file diffs, or with path: the file's content at rev); \
+                          branches. commit and checkout change the repository and may be disabled by policy."
+                .into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ACTIONS },
+                    "repo": { "type": "string", "description": "Repository directory (default: current directory)" },
+                    "path": { "type": "string", "description": "File or directory inside the repository" },
+                    "paths": { "type": "array", "items": { "type": "string" }, "description": "commit: files to stage first" },
+                    "range": { "type": "string", "description": "diff/log: revision or range, e.g. HEAD~3..HEAD" },
+                    "rev": { "type": "string", "description": "show/blame: revision (default HEAD)" },
+                    "staged": { "type": "boolean", "description": "diff: staged changes instead of the working tree" },
+                    "author": { "type": "string" },
+                    "grep": { "type": "string", "description": "log: commit message regex" },
+                    "since": { "type": "string", "description": "log: date such as 2024-01-01 or \"2 weeks ago\"" },
+                    "until": { "type": "string" },
+                    "max_count": { "type": "integer", "minimum": 1, "maximum": MAX_LOG_COUNT },
+                    "start_line": { "type": "integer", "minimum": 1 },
+                    "end_line": { "type": "integer", "minimum": 1 },
+                    "remote": { "type": "boolean", "description": "branches: include remote-tracking branches" },
+                    "message": { "type": "string", "minLength": 1, "description": "commit: message" },
+                    "all": { "type": "boolean", "description": "commit: stage all changes first" },
+                    "target": { "type": "string", "description": "checkout: branch or revision" },
+                    "create": { "type": "boolean", "description": "checkout: create the branch" }
+                },
+                "required": ["action"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let Some(action) = str_param(params, "action") else {
+            return fail(format!("missing action (one of {})", ACTIONS.join(", ")));
+        };
+        let mutating = matches!(action, "commit" | "checkout");
+        if mutating && !self.settings.allow_mutations() {
+            return fail(format!("git {action} is disabled by policy (set git_allow_mutations to allow it)"));
+        }
+        let dir = match self.files.policy().check_dir(str_param(params, "repo").unwrap_or(".")) {
+            Ok(d) => d,
+            Err(e) => return fail(e),
+        };
+        let repo = match run(&dir, &args(&["rev-parse", "--show-toplevel"])).await {
+            Ok(top) => PathBuf::from(top.trim()),
+            Err(_) => return fail(format!("{} is not inside a git repository", dir.display())),
+        };
+        if let Err(e) = self.files.policy().check_dir(&repo.to_string_lossy()) {
+            return fail(e);
+        }
+
+        let outcome = match action {
+            "status" => self.status(&repo).await,
+            "diff" => self.diff(&repo, params).await,
+            "log" => self.log(&repo, params).await,
+            "blame" => self.blame(&repo, params).await,
+            "show" => self.show(&repo, params).await,
+            "branches" => self.branches(&repo, params).await,
+            "commit" => self.commit(&repo, params).await,
+            "checkout" => self.checkout(&repo, params).await,
+            other => Err(format!("unknown action {other} (one of {})", ACTIONS.join(", "))),
+        };
+        match outcome {
+            Ok(mut result) => {
+                result["repo"] = json!(repo.display().to_string());
+                CapabilityResponse {
+                    id: request.id,
+                    result: Some(result),
+                    error: None,
+                    metrics: None,
+                    side_effects: vec![if mutating { Permission::FileWrite } else { Permission::FileRead }],
+                }
+            }
+            Err(e) => fail(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    fn sh(dir: &Path, script: &str) {
+        let ok = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(script)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_DATE", "2024-05-01T12:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2024-05-01T12:00:00Z")
+            .status()
+            .unwrap()
+            .success();
+        assert!(ok, "{script}");
+    }
+
+    /// Two commits on main, then a modified, a staged and an untracked file.
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        sh(
+            dir.path(),
+            "git init -q -b main && git config user.name Ada && git config user.email ada@example.com \
+             && printf 'one\\ntwo\\nthree\\n' > a.txt && git add a.txt && git commit -qm 'first commit' \
+             && printf 'one\\n2\\nthree\\nfour\\n' > a.txt && git commit -qam 'second commit' -m 'with a body' \
+             && echo changed >> a.txt && echo new > b.txt && git add b.txt && echo scratch > c.txt",
+        );
+        dir
+    }
+
+    fn git(allow_mutations: bool) -> Git {
+        let settings = Arc::new(GitSettings::default());
+        settings.allow_mutations.store(allow_mutations, Ordering::Relaxed);
+        Git::new(Arc::default(), settings)
+    }
+
+    async fn call(git: &Git, dir: &Path, mut params: Value) -> Result<Value, String> {
+        params["repo"] = json!(dir);
+        let request = CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 };
+        let resp = git.execute(request).await;
+        resp.result.ok_or(resp.error.unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn status_diff_and_log_are_parsed() {
+        let dir = repo();
+        let git = git(false);
+
+        let status = call(&git, dir.path(), json!({ "action": "status" })).await.unwrap();
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["clean"], false);
+        assert_eq!(status["staged"], json!([{ "path": "b.txt", "status": "added" }]));
+        assert_eq!(status["unstaged"], json!([{ "path": "a.txt", "status": "modified" }]));
+        assert_eq!(status["untracked"], json!(["c.txt"]));
+
+        let diff = call(&git, dir.path(), json!({ "action": "diff", "range": "HEAD~1..HEAD" })).await.unwrap();
+        assert_eq!(diff["files"][0]["path"], "a.txt");
+        assert_eq!((diff["added"].as_u64(), diff["removed"].as_u64()), (Some(2), Some(1)));
+        assert!(diff["files"][0]["patch"].as_str().unwrap().contains("+four"));
+        let staged = call(&git, dir.path(), json!({ "action": "diff", "staged": true })).await.unwrap();
+        assert_eq!(staged["files"][0]["status"], "added");
+
+        let log = call(&git, dir.path(), json!({ "action": "log", "grep": "second" })).await.unwrap();
+        assert_eq!(log["count"], 1);
+        assert_eq!(log["commits"][0]["subject"], "second commit");
+        assert_eq!(log["commits"][0]["body"], "with a body");
+        assert_eq!(log["commits"][0]["author"], "Ada");
+        let err = call(&git, dir.path(), json!({ "action": "log", "range": "--output=/tmp/x" })).await.unwrap_err();
+        assert!(err.contains("not an option"));
+    }
+
+    #[tokio::test]
+    async fn repository_config_cannot_run_programs() {
+        let dir = repo();
+        let marker = dir.path().join("ran");
+        sh(
+            dir.path(),
+            &format!(
+                "git config core.fsmonitor 'touch {m}.fsmonitor; false' && git config core.pager 'touch {m}.pager; cat' \
+                 && git config diff.external 'touch {m}.extdiff' && git config diff.evil.textconv 'touch {m}.textconv; cat' \
+                 && echo 'a.txt diff=evil' > .gitattributes",
+                m = marker.display()
+            ),
+        );
+        let git = git(false);
+        for params in [
+            json!({ "action": "status" }),
+            json!({ "action": "diff" }),
+            json!({ "action": "diff", "range": "HEAD~1..HEAD" }),
+            json!({ "action": "log", "max_count": 5 }),
+            json!({ "action": "show", "rev": "HEAD" }),
+            json!({ "action": "show", "rev": "HEAD", "path": "a.txt" }),
+            json!({ "action": "blame", "path": "a.txt", "rev": "HEAD" }),
+        ] {
+            call(&git, dir.path(), params.clone()).await.unwrap_or_else(|e| panic!("{params}: {e}"));
+        }
+        let ran: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|n| n.starts_with("ran."))
+            .collect();
+        assert!(ran.is_empty(), "repository config ran {ran:?}");
+    }
+
+    #[tokio::test]
+    async fn blame_show_and_branches() {
+        let dir = repo();
+        let git = git(false);
+
+        let blame = call(&git, dir.path(), json!({ "action": "blame", "path": "a.txt", "rev": "HEAD" }))
+            .await
+            .unwrap();
+        let lines = blame["lines"].as_array().unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!((lines[0]["summary"].as_str(), lines[0]["text"].as_str()), (Some("first commit"), Some("one")));
+        assert_eq!((lines[1]["summary"].as_str(), lines[1]["line"].as_u64()), (Some("second commit"), Some(2)));
+        assert_eq!(lines[0]["date"], "2024-05-01T12:00:00+00:00");
+
+        let show = call(&git, dir.path(), json!({ "action": "show", "rev": "HEAD~1" })).await.unwrap();
+        assert_eq!(show["commit"]["subject"], "first commit");
+        assert_eq!(show["files"][0]["status"], "added");
+        let old = call(&git, dir.path(), json!({ "action": "show", "rev": "HEAD~1", "path": "a.txt" })).await.unwrap();
+        assert_eq!(old["content"], "one\ntwo\nthree\n");
+
+        sh(dir.path(), "git branch feature HEAD~1");
+        let branches = call(&git, dir.path(), json!({ "action": "branches" })).await.unwrap();
+        assert_eq!(branches["current"], "main");
+        let names: Vec<&str> = branches["branches"].as_array().unwrap().iter().filter_map(|b| b["name"].as_str()).collect();
+        assert!(names.contains(&"feature") && names.contains(&"main"));
+    }
+
+    #[tokio::test]
+    async fn mutations_need_the_policy_switch() {
+        let dir = repo();
+        let err = call(&git(false), dir.path(), json!({ "action": "commit", "message": "x", "all": true }))
+            .await
+            .unwrap_err();
+        assert!(err.contains("disabled by policy"));
+
+        let git = git(true);
+        let commit = call(&git, dir.path(), json!({ "action": "commit", "message": "third", "paths": ["c.txt"] }))
+            .await
+            .unwrap();
+        assert_eq!(commit["commit"]["subject"], "third");
+        let committed: Vec<&str> = commit["files"].as_array().unwrap().iter().filter_map(|f| f["path"].as_str()).collect();
+        assert_eq!(committed, ["b.txt", "c.txt"]);
+
+        let checkout = call(&git, dir.path(), json!({ "action": "checkout", "target": "topic", "create": true }))
+            .await
+            .unwrap();
+        assert_eq!(checkout["branch"], "topic");
+    }
+
+    #[test]
+    fn parses_renames_from_status_and_diff() {
+        let status = parse_status(
+            "# branch.oid abc\0# branch.head dev\0# branch.upstream origin/dev\0# branch.ab +2 -1\0\
+             2 R. N... 100644 100644 100644 aaa bbb R100 new name.rs\0old.rs\0",
+        );
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(status.upstream.as_deref(), Some("origin/dev"));
+        assert_eq!(
+            status.staged,
+            vec![Change { path: "new name.rs".into(), status: "renamed", from: Some("old.rs".into()) }]
+        );
+
+        let files = parse_diff(
+            "diff --git a/old.rs b/new.rs\nsimilarity index 90%\nrename from old.rs\nrename to new.rs\n\
+             --- a/old.rs\n+++ b/new.rs\n@@ -1 +1 @@\n-a\n+b\ndiff --git a/img.png b/img.png\n\
+             Binary files a/img.png and b/img.png differ\n",
+        );
+        assert_eq!((files[0].path.as_str(), files[0].from.as_deref()), ("new.rs", Some("old.rs")));
+        assert_eq!((files[0].added, files[0].removed, files[0].status), (1, 1, "renamed"));
+        assert!(files[1].binary);
+    }
+}
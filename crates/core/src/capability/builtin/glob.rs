@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::walk::{self, EntryKind, WalkOptions};
+use crate::capability::path_policy::FileAccess;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+#[derive(Default)]
+pub struct Glob {
+    access: Arc<FileAccess>,
+}
+
+impl Glob {
+    pub fn new(access: Arc<FileAccess>) -> Self {
+        Self { access }
+    }
+}
+
+const DEFAULT_MAX_RESULTS: usize = 200;
+const MAX_RESULTS_CAP: usize = 2000;
+const MAX_WALK_DEPTH: usize = 64;
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Glob {
+    fn name(&self) -> &str { "glob" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["find", "glob", "files", "matching", "查找", "找文件"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "glob".into(),
+            description: "Find files whose path relative to the search root matches a glob such as \"src/**/*.rs\" or \"*.{toml,md}\". \
+                          Skips .git and gitignored paths by default."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "minLength": 1, "description": "Glob pattern (*, ?, **, [..], {a,b})" },
+                    "path": { "type": "string", "description": "Search root (default: current directory)" },
+                    "include_ignored": { "type": "boolean", "description": "Include gitignored files" },
+                    "max_results": { "type": "integer", "minimum": 1, "maximum": MAX_RESULTS_CAP, "description": "Result cap (default 200)" }
+                },
+                "required": ["pattern"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let Some(pattern) = params.get("pattern").and_then(|v| v.as_str()) else {
+            return fail("missing pattern".into());
+        };
+        let matcher = match walk::glob_to_regex(pattern.trim_start_matches("./")) {
+            Ok(re) => re,
+            Err(e) => return fail(e),
+        };
+        let root = PathBuf::from(params.get("path").and_then(|v| v.as_str()).unwrap_or("."));
+        let opts = WalkOptions {
+            max_depth: MAX_WALK_DEPTH,
+            respect_gitignore: !params.get("include_ignored").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+        let cap = params.get("max_results")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_RESULTS_CAP))
+            .unwrap_or(DEFAULT_MAX_RESULTS);
+
+        let policy = self.access.policy();
+        let walk_root = match policy.check_dir(&root.to_string_lossy()) {
+            Ok(p) => p,
+            Err(e) => return fail(e),
+        };
+        let found = tokio::task::spawn_blocking(move || {
+            let mut matches = Vec::new();
+            let mut truncated = false;
+            walk::walk(&walk_root, &opts, |e| {
+                if e.kind == EntryKind::Dir || !matcher.is_match(&e.rel) || policy.is_denied(&e.path).is_some() {
+                    return true;
+                }
+                if matches.len() >= cap {
+                    truncated = true;
+                    return false;
+                }
+                matches.push(e.rel.clone());
+                true
+            })
+            .map(|()| (matches, truncated))
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("glob task failed: {e}")));
+
+        match found {
+            Ok((matches, truncated)) => CapabilityResponse {
+                id: request.id,
+                result: Some(serde_json::json!({
+                    "root": root.display().to_string(),
+                    "pattern": pattern,
+                    "count": matches.len(),
+                    "matches": matches,
+                    "truncated": truncated,
+                })),
+                error: None,
+                metrics: None,
+                side_effects: vec![Permission::FileRead],
+            },
+            Err(e) => fail(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    #[tokio::test]
+    async fn finds_matching_files_outside_ignored_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/util")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "").unwrap();
+        std::fs::write(root.join("src/util/io.rs"), "").unwrap();
+        std::fs::write(root.join("target/gen.rs"), "").unwrap();
+        std::fs::write(root.join("README.md"), "").unwrap();
+
+        let path = root.to_str().unwrap();
+        let resp = Glob::default().execute(req(serde_json::json!({ "pattern": "**/*.rs", "path": path }))).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["matches"], serde_json::json!(["src/lib.rs", "src/util/io.rs"]));
+
+        let resp = Glob::default().execute(req(serde_json::json!({ "pattern": "**/*.rs", "path": path, "include_ignored": true }))).await;
+        assert_eq!(resp.result.unwrap()["count"], 3);
+
+        let resp = Glob::default().execute(req(serde_json::json!({ "pattern": "**/*.rs", "path": path, "max_results": 1 }))).await;
+        assert_eq!(resp.result.unwrap()["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn bad_pattern_is_an_error() {
+        let resp = Glob::default().execute(req(serde_json::json!({ "pattern": "src/[a" }))).await;
+        assert!(resp.error.unwrap().contains("unclosed"));
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::walk::{self, EntryKind, WalkOptions};
+use crate::capability::path_policy::FileAccess;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+use regex::RegexBuilder;
+
+#[derive(Default)]
+pub struct Grep {
+    access: Arc<FileAccess>,
+}
+
+impl Grep {
+    pub fn new(access: Arc<FileAccess>) -> Self {
+        Self { access }
+    }
+}
+
+const DEFAULT_MAX_MATCHES: usize = 100;
+const MAX_MATCHES_CAP: usize = 1000;
+const MAX_WALK_DEPTH: usize = 64;
+/// Files larger than this are skipped.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+/// Matched lines are cut to this many characters.
+const MAX_LINE_CHARS: usize = 300;
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Grep {
+    fn name(&self) -> &str { "grep" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["grep", "search", "occurrences", "where", "搜索", "查找内容"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "grep".into(),
+            description: "Search file contents for a regex (or literal) and return matching lines with line numbers. \
+                          Searches a file or a directory tree, skipping .git, gitignored and binary files."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "minLength": 1, "description": "Regular expression (Rust regex syntax)" },
+                    "path": { "type": "string", "description": "File or directory to search (default: current directory)" },
+                    "glob": { "type": "string", "description": "Only search files whose relative path matches this glob, e.g. \"**/*.rs\"" },
+                    "fixed_string": { "type": "boolean", "description": "Treat pattern as a literal string" },
+                    "case_insensitive": { "type": "boolean" },
+                    "include_ignored": { "type": "boolean", "description": "Also search gitignored files" },
+                    "max_matches": { "type": "integer", "minimum": 1, "maximum": MAX_MATCHES_CAP, "description": "Match cap (default 100)" }
+                },
+                "required": ["pattern"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let Some(pattern) = params.get("pattern").and_then(|v| v.as_str()) else {
+            return fail("missing pattern".into());
+        };
+        let flag = |key: &str| params.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let source = if flag("fixed_string") { regex::escape(pattern) } else { pattern.to_string() };
+        let regex = match RegexBuilder::new(&source).case_insensitive(flag("case_insensitive")).build() {
+            Ok(re) => re,
+            Err(e) => return fail(format!("invalid pattern: {e}")),
+        };
+        let file_filter = match params.get("glob").and_then(|v| v.as_str()) {
+            Some(g) => match walk::glob_to_regex(g.trim_start_matches("./")) {
+                Ok(re) => Some(re),
+                Err(e) => return fail(e),
+            },
+            None => None,
+        };
+        let root = PathBuf::from(params.get("path").and_then(|v| v.as_str()).unwrap_or("."));
+        let opts = WalkOptions { max_depth: MAX_WALK_DEPTH, respect_gitignore: !flag("include_ignored") };
+        let cap = params.get("max_matches")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_MATCHES_CAP))
+            .unwrap_or(DEFAULT_MAX_MATCHES);
+
+        let policy = self.access.policy();
+        let requested = root.to_string_lossy().into_owned();
+        let search_root = if root.is_file() { policy.check_read(&requested) } else { policy.check_dir(&requested) };
+        let search_root = match search_root {
+            Ok(p) => p,
+            Err(e) => return fail(e),
+        };
+        let searched = tokio::task::spawn_blocking(move || {
+            let mut search = Search { regex, cap, matches: Vec::new(), files_searched: 0, truncated: false };
+            if search_root.is_file() {
+                search.file(&search_root, &requested);
+                return Ok(search);
+            }
+            walk::walk(&search_root, &opts, |e| {
+                if e.kind != EntryKind::File || e.size > MAX_FILE_BYTES {
+                    return true;
+                }
+                if file_filter.as_ref().is_some_and(|f| !f.is_match(&e.rel)) {
+                    return true;
+                }
+                // Every file is checked before it is opened, so deny globs hold inside the tree
+                match policy.check_read(&e.path.to_string_lossy()) {
+                    Ok(path) => search.file(&path, &e.rel),
+                    Err(_) => true,
+                }
+            })
+            .map(|()| search)
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("grep task failed: {e}")));
+
+        match searched {
+            Ok(search) => CapabilityResponse {
+                id: request.id,
+                result: Some(serde_json::json!({
+                    "pattern": pattern,
+                    "root": root.display().to_string(),
+                    "count": search.matches.len(),
+                    "files_searched": search.files_searched,
+                    "matches": search.matches,
+                    "truncated": search.truncated,
+                })),
+                error: None,
+                metrics: None,
+                side_effects: vec![Permission::FileRead],
+            },
+            Err(e) => fail(e),
+        }
+    }
+}
+
+struct Search {
+    regex: regex::Regex,
+    cap: usize,
+    matches: Vec<serde_json::Value>,
+    files_searched: usize,
+    truncated: bool,
+}
+
+impl Search {
+    /// Search one file; returns `false` once the match cap is hit.
+    fn file(&mut self, path: &std::path::Path, display: &str) -> bool {
+        let Ok(mut file) = std::fs::File::open(path) else {
+            return true;
+        };
+        let mut bytes = Vec::new();
+        if file.by_ref().take(MAX_FILE_BYTES).read_to_end(&mut bytes).is_err() {
+            return true;
+        }
+        // Treat a NUL byte in the first 8 KiB as binary, like git does.
+        if bytes.iter().take(8192).any(|&b| b == 0) {
+            return true;
+        }
+        self.files_searched += 1;
+        let text = String::from_utf8_lossy(&bytes);
+        for (idx, line) in text.lines().enumerate() {
+            if !self.regex.is_match(line) {
+                continue;
+            }
+            if self.matches.len() >= self.cap {
+                self.truncated = true;
+                return false;
+            }
+            let mut shown: String = line.chars().take(MAX_LINE_CHARS).collect();
+            if line.chars().count() > MAX_LINE_CHARS {
+                shown.push_str("...");
+            }
+            self.matches.push(serde_json::json!({
+                "path": display,
+                "line": idx + 1,
+                "text": shown,
+            }));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn alpha() {}\n// TODO: beta\nfn Gamma() {}\n").unwrap();
+        std::fs::write(root.join("notes.md"), "todo: write docs\n").unwrap();
+        std::fs::write(root.join("target/gen.rs"), "// TODO generated\n").unwrap();
+        std::fs::write(root.join("blob.bin"), b"TODO\0binary").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn finds_lines_with_numbers() {
+        let dir = fixture();
+        let path = dir.path().to_str().unwrap();
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "TODO", "path": path }))).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["count"], 1);
+        assert_eq!(result["matches"][0]["path"], "src/lib.rs");
+        assert_eq!(result["matches"][0]["line"], 2);
+        assert_eq!(result["matches"][0]["text"], "// TODO: beta");
+    }
+
+    #[tokio::test]
+    async fn options_filter_and_widen_search() {
+        let dir = fixture();
+        let path = dir.path().to_str().unwrap();
+        let resp = Grep::default().execute(req(serde_json::json!({
+            "pattern": "todo", "path": path, "case_insensitive": true, "glob": "*.md"
+        }))).await;
+        assert_eq!(resp.result.unwrap()["matches"][0]["path"], "notes.md");
+
+        let resp = Grep::default().execute(req(serde_json::json!({
+            "pattern": "TODO", "path": path, "include_ignored": true
+        }))).await;
+        assert_eq!(resp.result.unwrap()["count"], 2);
+
+        let resp = Grep::default().execute(req(serde_json::json!({
+            "pattern": "fn", "path": path, "max_matches": 1
+        }))).await;
+        assert_eq!(resp.result.unwrap()["truncated"], true);
+
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "alpha()", "path": path, "fixed_string": true }))).await;
+        assert_eq!(resp.result.unwrap()["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn searches_single_file_and_rejects_bad_regex() {
+        let dir = fixture();
+        let file = dir.path().join("src/lib.rs");
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "^fn", "path": file.to_str().unwrap() }))).await;
+        assert_eq!(resp.result.unwrap()["count"], 2);
+
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "(" }))).await;
+        assert!(resp.error.unwrap().contains("invalid pattern"));
+    }
+
+    #[tokio::test]
+    async fn denied_files_are_never_read() {
+        let dir = fixture();
+        std::fs::create_dir_all(dir.path().join(".ssh")).unwrap();
+        std::fs::write(dir.path().join(".ssh/id_rsa"), "TODO secret key\n").unwrap();
+        std::fs::write(dir.path().join(".env"), "TODO=secret\n").unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "secret", "path": path }))).await;
+        assert_eq!(resp.result.unwrap()["count"], 0);
+
+        let env = dir.path().join(".env");
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "secret", "path": env.to_str().unwrap() }))).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+        let ssh = dir.path().join(".ssh");
+        let resp = Grep::default().execute(req(serde_json::json!({ "pattern": "secret", "path": ssh.to_str().unwrap() }))).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+    }
+}
@@ -0,0 +1,547 @@
+//! Background jobs: `job_start` runs a command detached (no `run_bash` timeout,
+//! and `job_cpu_secs` instead of the bash CPU limit), `job_poll` reports status
+//! and buffered output, `job_kill` stops it.
+//!
+//! Output from stdout and stderr goes into one ring buffer per job. When a job
+//! ends, an internal `SensoryEvent` is sent so iris can tell the user.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::sandbox::SandboxState;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission, SensoryEvent};
+use llm::provider::ToolDefinition;
+
+/// Bytes of combined output kept per job; older output is dropped.
+const RING_BYTES: usize = 256 * 1024;
+const MAX_RUNNING: usize = 8;
+/// Finished jobs kept for polling before the oldest are forgotten.
+const MAX_FINISHED: usize = 32;
+const DEFAULT_TAIL_BYTES: usize = 4096;
+const MAX_TAIL_BYTES: usize = 64 * 1024;
+/// Output tail included in the completion event.
+const EVENT_TAIL_CHARS: usize = 800;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Exited(i32),
+    Killed,
+    Failed(String),
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Exited(_) => "exited",
+            Self::Killed => "killed",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Fixed-size output buffer that remembers how many bytes were ever written.
+#[derive(Debug, Default)]
+struct RingBuffer {
+    bytes: VecDeque<u8>,
+    total: u64,
+}
+
+impl RingBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        self.total += chunk.len() as u64;
+        self.bytes.extend(chunk);
+        let excess = self.bytes.len().saturating_sub(RING_BYTES);
+        self.bytes.drain(..excess);
+    }
+
+    /// Output from absolute offset `since` (clamped to what is still buffered),
+    /// at most `max` bytes from the end. Returns (text, start offset).
+    fn read(&self, since: Option<u64>, max: usize) -> (String, u64) {
+        let first = self.total - self.bytes.len() as u64;
+        let from = since.unwrap_or(0).clamp(first, self.total);
+        let from = from.max(self.total.saturating_sub(max as u64));
+        let skip = (from - first) as usize;
+        let tail: Vec<u8> = self.bytes.iter().skip(skip).copied().collect();
+        (String::from_utf8_lossy(&tail).into_owned(), from)
+    }
+}
+
+struct Job {
+    id: u64,
+    command: String,
+    started: Instant,
+    output: Mutex<RingBuffer>,
+    status: Mutex<JobStatus>,
+    finished: Mutex<Option<Instant>>,
+    cancel: CancellationToken,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        self.status.lock().map(|s| s.clone()).unwrap_or(JobStatus::Running)
+    }
+
+    fn runtime(&self) -> Duration {
+        let end = self.finished.lock().ok().and_then(|f| *f).unwrap_or_else(Instant::now);
+        end - self.started
+    }
+
+    fn summary(&self) -> serde_json::Value {
+        let status = self.status();
+        serde_json::json!({
+            "job_id": self.id,
+            "command": self.command,
+            "status": status.as_str(),
+            "exit_code": match status { JobStatus::Exited(code) => Some(code), _ => None },
+            "runtime_secs": self.runtime().as_secs(),
+        })
+    }
+
+    fn finish(&self, status: JobStatus) {
+        if let Ok(mut f) = self.finished.lock() {
+            *f = Some(Instant::now());
+        }
+        if let Ok(mut s) = self.status.lock() {
+            *s = status;
+        }
+    }
+
+    /// Text of the internal event announcing that the job ended.
+    fn completion_message(&self) -> String {
+        let outcome = match self.status() {
+            JobStatus::Exited(code) => format!("exited with code {code}"),
+            JobStatus::Killed => "was killed".to_string(),
+            JobStatus::Failed(e) => format!("failed: {e}"),
+            JobStatus::Running => "is still running".to_string(),
+        };
+        let (tail, _) = self
+            .output
+            .lock()
+            .map(|o| o.read(None, EVENT_TAIL_CHARS * 4))
+            .unwrap_or_default();
+        let tail: String = {
+            let chars: Vec<char> = tail.trim_end().chars().collect();
+            chars[chars.len().saturating_sub(EVENT_TAIL_CHARS)..].iter().collect()
+        };
+        format!(
+            "[background job {}] `{}` {outcome} after {}s. Output tail:\n{}",
+            self.id,
+            self.command,
+            self.runtime().as_secs(),
+            if tail.is_empty() { "(no output)" } else { &tail }
+        )
+    }
+}
+
+/// Shared table of background jobs, owned by the registry.
+#[derive(Default)]
+pub struct JobManager {
+    sandbox: Arc<SandboxState>,
+    jobs: Mutex<BTreeMap<u64, Arc<Job>>>,
+    next_id: AtomicU64,
+    notify: Mutex<Option<mpsc::Sender<SensoryEvent>>>,
+}
+
+impl JobManager {
+    pub fn new(sandbox: Arc<SandboxState>) -> Self {
+        Self { sandbox, ..Self::default() }
+    }
+
+    /// Where completion events go; normally the scheduler's event channel.
+    pub fn set_notifier(&self, tx: mpsc::Sender<SensoryEvent>) {
+        if let Ok(mut n) = self.notify.lock() {
+            *n = Some(tx);
+        }
+    }
+
+    fn get(&self, id: u64) -> Option<Arc<Job>> {
+        self.jobs.lock().ok().and_then(|j| j.get(&id).cloned())
+    }
+
+    fn list(&self) -> Vec<Arc<Job>> {
+        self.jobs.lock().map(|j| j.values().cloned().collect()).unwrap_or_default()
+    }
+
+    fn start(&self, command: &str, cwd: Option<&str>) -> Result<Arc<Job>, String> {
+        let mut jobs = self.jobs.lock().map_err(|_| "job table poisoned")?;
+        let running = jobs.values().filter(|j| j.status() == JobStatus::Running).count();
+        if running >= MAX_RUNNING {
+            return Err(format!("{running} background jobs are already running; kill or wait for one first"));
+        }
+
+        let policy = self.sandbox.job_policy();
+        let cwd = policy.resolve_cwd(cwd)?;
+        let mut cmd = policy.command(command, &cwd);
+        cmd.stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        // Own process group, so kill reaches pipelines and grandchildren too.
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let mut child = cmd.spawn().map_err(|e| format!("failed to start job: {e}"))?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Arc::new(Job {
+            id,
+            command: command.to_string(),
+            started: Instant::now(),
+            output: Mutex::default(),
+            status: Mutex::new(JobStatus::Running),
+            finished: Mutex::new(None),
+            cancel: CancellationToken::new(),
+        });
+
+        let readers: Vec<_> = [
+            child.stdout.take().map(|s| Box::new(s) as Box<dyn tokio::io::AsyncRead + Send + Unpin>),
+            child.stderr.take().map(|s| Box::new(s) as Box<dyn tokio::io::AsyncRead + Send + Unpin>),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|mut pipe| {
+            let job = Arc::clone(&job);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 8192];
+                while let Ok(n) = pipe.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    if let Ok(mut out) = job.output.lock() {
+                        out.push(&buf[..n]);
+                    }
+                }
+            })
+        })
+        .collect();
+
+        let notify = self.notify.lock().ok().and_then(|n| n.clone());
+        let waiter = Arc::clone(&job);
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                res = child.wait() => match res {
+                    Ok(s) => JobStatus::Exited(s.code().unwrap_or(-1)),
+                    Err(e) => JobStatus::Failed(e.to_string()),
+                },
+                () = waiter.cancel.cancelled() => {
+                    #[cfg(unix)]
+                    if let Some(pid) = child.id() {
+                        // SAFETY: plain syscall; negative pid targets the job's process group.
+                        unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
+                    }
+                    let _ = child.kill().await;
+                    JobStatus::Killed
+                }
+            };
+            // Drain what the pipes still hold (bounded: orphans may keep them open).
+            for reader in readers {
+                let _ = tokio::time::timeout(Duration::from_secs(1), reader).await;
+            }
+            waiter.finish(status);
+            tracing::info!(job = waiter.id, status = waiter.status().as_str(), "background job ended");
+            if let Some(tx) = notify {
+                let _ = tx.send(SensoryEvent::internal(waiter.completion_message())).await;
+            }
+        });
+
+        jobs.insert(id, Arc::clone(&job));
+        let finished: Vec<u64> = jobs
+            .values()
+            .filter(|j| j.status() != JobStatus::Running)
+            .map(|j| j.id)
+            .collect();
+        for old in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED)) {
+            jobs.remove(old);
+        }
+        Ok(job)
+    }
+}
+
+fn job_id(params: &serde_json::Value) -> Option<u64> {
+    params.get("job_id").and_then(|v| v.as_u64())
+}
+
+fn error_response(request: &CapabilityRequest, error: String) -> CapabilityResponse {
+    CapabilityResponse { id: request.id, result: None, error: Some(error), metrics: None, side_effects: vec![] }
+}
+
+pub struct JobStart {
+    jobs: Arc<JobManager>,
+}
+
+impl JobStart {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for JobStart {
+    fn name(&self) -> &str { "job_start" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["background", "job", "build", "long-running", "后台", "任务"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::ProcessSpawn]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "job_start".into(),
+            description: "Start a long-running bash command (build, test suite, download) in the background and \
+                          return a job id immediately. Use job_poll to check output and job_kill to stop it; \
+                          you are notified when it finishes. Prefer run_bash for commands under 30 seconds."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "minLength": 1, "description": "The bash command to run" },
+                    "cwd": { "type": "string", "description": "Working directory, relative to the sandbox working directory" }
+                },
+                "required": ["command"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let Some(command) = request.params.get("command").and_then(|v| v.as_str()) else {
+            return error_response(&request, "missing command".into());
+        };
+        let cwd = request.params.get("cwd").and_then(|v| v.as_str());
+        match self.jobs.start(command, cwd) {
+            Ok(job) => CapabilityResponse {
+                id: request.id,
+                result: Some(job.summary()),
+                error: None,
+                metrics: None,
+                side_effects: vec![Permission::ProcessSpawn],
+            },
+            Err(e) => error_response(&request, e),
+        }
+    }
+}
+
+pub struct JobPoll {
+    jobs: Arc<JobManager>,
+}
+
+impl JobPoll {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for JobPoll {
+    fn name(&self) -> &str { "job_poll" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["job", "status", "progress", "jobs", "任务状态", "进度"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::ProcessSpawn]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "job_poll".into(),
+            description: "Report a background job's status, exit code and recent output (stdout and stderr combined). \
+                          Pass `since` (the previous next_offset) to get only new output. Without job_id, lists all jobs."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer", "minimum": 1 },
+                    "since": { "type": "integer", "minimum": 0, "description": "Output offset to read from" },
+                    "tail_bytes": { "type": "integer", "minimum": 1, "maximum": MAX_TAIL_BYTES, "description": "Max output bytes (default 4096)" }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let Some(id) = job_id(&request.params) else {
+            let jobs: Vec<serde_json::Value> = self.jobs.list().iter().map(|j| j.summary()).collect();
+            return CapabilityResponse {
+                id: request.id,
+                result: Some(serde_json::json!({ "jobs": jobs })),
+                error: None,
+                metrics: None,
+                side_effects: vec![],
+            };
+        };
+        let Some(job) = self.jobs.get(id) else {
+            return error_response(&request, format!("no background job {id}"));
+        };
+        let since = request.params.get("since").and_then(|v| v.as_u64());
+        let max = request.params.get("tail_bytes")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_TAIL_BYTES))
+            .unwrap_or(DEFAULT_TAIL_BYTES);
+        let (output, from, total) = match job.output.lock() {
+            Ok(out) => {
+                let (text, from) = out.read(since, max);
+                (text, from, out.total)
+            }
+            Err(_) => (String::new(), 0, 0),
+        };
+        let mut result = job.summary();
+        result["output"] = output.into();
+        result["output_offset"] = from.into();
+        result["next_offset"] = total.into();
+        result["output_skipped"] = (from > since.unwrap_or(0)).into();
+        CapabilityResponse { id: request.id, result: Some(result), error: None, metrics: None, side_effects: vec![] }
+    }
+}
+
+pub struct JobKill {
+    jobs: Arc<JobManager>,
+}
+
+impl JobKill {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for JobKill {
+    fn name(&self) -> &str { "job_kill" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["kill", "stop", "cancel", "job", "终止", "停止"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::ProcessSpawn]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "job_kill".into(),
+            description: "Kill a running background job and its child processes.".into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer", "minimum": 1 }
+                },
+                "required": ["job_id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let Some(id) = job_id(&request.params) else {
+            return error_response(&request, "missing job_id".into());
+        };
+        let Some(job) = self.jobs.get(id) else {
+            return error_response(&request, format!("no background job {id}"));
+        };
+        if job.status() != JobStatus::Running {
+            return error_response(&request, format!("job {id} already ended ({})", job.status().as_str()));
+        }
+        job.cancel.cancel();
+        // Give the waiter a moment to reap the process so the reply shows the final state.
+        for _ in 0..20 {
+            if job.status() != JobStatus::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        CapabilityResponse {
+            id: request.id,
+            result: Some(job.summary()),
+            error: None,
+            metrics: None,
+            side_effects: vec![Permission::ProcessSpawn],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    #[test]
+    fn ring_buffer_keeps_tail_and_offsets() {
+        let mut ring = RingBuffer::default();
+        ring.push(&vec![b'a'; RING_BYTES]);
+        ring.push(b"xyz");
+        assert_eq!(ring.total, RING_BYTES as u64 + 3);
+        assert_eq!(ring.read(None, 3), ("xyz".into(), RING_BYTES as u64));
+        // Offsets that were already dropped are clamped to the oldest buffered byte.
+        let (text, from) = ring.read(Some(0), usize::MAX);
+        assert_eq!((text.len(), from), (RING_BYTES, 3));
+        assert_eq!(ring.read(Some(ring.total), 10).0, "");
+    }
+
+    #[tokio::test]
+    async fn job_runs_in_background_and_notifies() {
+        let jobs = Arc::new(JobManager::default());
+        let (tx, mut rx) = mpsc::channel(4);
+        jobs.set_notifier(tx);
+
+        let resp = JobStart::new(Arc::clone(&jobs))
+            .execute(req(serde_json::json!({ "command": "echo one; echo two >&2; sleep 0.2; exit 3" })))
+            .await;
+        let started = resp.result.unwrap();
+        assert_eq!(started["status"], "running");
+        let id = started["job_id"].as_u64().unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(event.content.contains("exited with code 3"), "{}", event.content);
+        assert!(event.content.contains("one"));
+
+        let poll = JobPoll::new(Arc::clone(&jobs));
+        let result = poll.execute(req(serde_json::json!({ "job_id": id }))).await.result.unwrap();
+        assert_eq!(result["status"], "exited");
+        assert_eq!(result["exit_code"], 3);
+        let output = result["output"].as_str().unwrap();
+        assert!(output.contains("one\n") && output.contains("two\n"));
+
+        let since = result["next_offset"].clone();
+        let result = poll.execute(req(serde_json::json!({ "job_id": id, "since": since }))).await.result.unwrap();
+        assert_eq!(result["output"], "");
+        let listed = poll.execute(req(serde_json::json!({}))).await.result.unwrap();
+        assert_eq!(listed["jobs"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn kill_stops_process_group() {
+        let jobs = Arc::new(JobManager::default());
+        let started = JobStart::new(Arc::clone(&jobs))
+            .execute(req(serde_json::json!({ "command": "sleep 30 | cat" })))
+            .await
+            .result
+            .unwrap();
+        let id = started["job_id"].as_u64().unwrap();
+
+        let kill = JobKill::new(Arc::clone(&jobs));
+        let result = kill.execute(req(serde_json::json!({ "job_id": id }))).await.result.unwrap();
+        assert_eq!(result["status"], "killed");
+        let resp = kill.execute(req(serde_json::json!({ "job_id": id }))).await;
+        assert!(resp.error.unwrap().contains("already ended"));
+        let resp = kill.execute(req(serde_json::json!({ "job_id": 999 }))).await;
+        assert!(resp.error.unwrap().contains("no background job"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::walk::{self, WalkOptions};
+use crate::capability::path_policy::FileAccess;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+#[derive(Default)]
+pub struct ListDir {
+    access: Arc<FileAccess>,
+}
+
+impl ListDir {
+    pub fn new(access: Arc<FileAccess>) -> Self {
+        Self { access }
+    }
+}
+
+const DEFAULT_MAX_ENTRIES: usize = 200;
+const MAX_ENTRIES_CAP: usize = 1000;
+const MAX_DEPTH: usize = 5;
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for ListDir {
+    fn name(&self) -> &str { "list_dir" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["ls", "list", "directory", "folder", "tree", "目录", "文件夹", "列出"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_dir".into(),
+            description: "List a directory's entries (name, kind, size), optionally recursing. Skips .git and gitignored paths by default."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Directory to list (default: current directory)" },
+                    "depth": { "type": "integer", "minimum": 1, "maximum": MAX_DEPTH, "description": "Levels to descend (default 1)" },
+                    "include_ignored": { "type": "boolean", "description": "Include gitignored entries" },
+                    "max_entries": { "type": "integer", "minimum": 1, "maximum": MAX_ENTRIES_CAP, "description": "Result cap (default 200)" }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let root = PathBuf::from(params.get("path").and_then(|v| v.as_str()).unwrap_or("."));
+        let opts = WalkOptions {
+            max_depth: params.get("depth").and_then(|v| v.as_u64()).unwrap_or(1).clamp(1, MAX_DEPTH as u64) as usize,
+            respect_gitignore: !params.get("include_ignored").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+        let cap = params.get("max_entries")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_ENTRIES_CAP))
+            .unwrap_or(DEFAULT_MAX_ENTRIES);
+
+        let policy = self.access.policy();
+        let walk_root = match policy.check_dir(&root.to_string_lossy()) {
+            Ok(p) => p,
+            Err(e) => {
+                return CapabilityResponse { id: request.id, result: None, error: Some(e), metrics: None, side_effects: vec![] };
+            }
+        };
+        let listed = tokio::task::spawn_blocking(move || {
+            let mut entries = Vec::new();
+            let mut truncated = false;
+            walk::walk(&walk_root, &opts, |e| {
+                if policy.is_denied(&e.path).is_some() {
+                    return true;
+                }
+                if entries.len() >= cap {
+                    truncated = true;
+                    return false;
+                }
+                entries.push(serde_json::json!({
+                    "path": e.rel,
+                    "kind": e.kind.as_str(),
+                    "size": e.size,
+                }));
+                true
+            })
+            .map(|()| (entries, truncated))
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("list_dir task failed: {e}")));
+
+        match listed {
+            Ok((entries, truncated)) => CapabilityResponse {
+                id: request.id,
+                result: Some(serde_json::json!({
+                    "path": root.display().to_string(),
+                    "count": entries.len(),
+                    "entries": entries,
+                    "truncated": truncated,
+                })),
+                error: None,
+                metrics: None,
+                side_effects: vec![Permission::FileRead],
+            },
+            Err(e) => CapabilityResponse {
+                id: request.id,
+                result: None,
+                error: Some(e),
+                metrics: None,
+                side_effects: vec![],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    fn req(params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    #[tokio::test]
+    async fn lists_with_depth_and_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "abc").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), "").unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        let resp = ListDir::default().execute(req(serde_json::json!({ "path": path }))).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["entries"][0]["path"], "a.txt");
+        assert_eq!(result["entries"][0]["size"], 3);
+        assert_eq!(result["entries"][1]["kind"], "dir");
+
+        let resp = ListDir::default().execute(req(serde_json::json!({ "path": path, "depth": 2, "max_entries": 2 }))).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn missing_dir_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not/here");
+        let resp = ListDir::default().execute(req(serde_json::json!({ "path": missing.to_str().unwrap() }))).await;
+        assert!(resp.error.unwrap().contains("cannot access"));
+
+        let resp = ListDir::default().execute(req(serde_json::json!({ "path": "/definitely/not/here" }))).await;
+        assert!(resp.error.unwrap().contains("outside the allowed roots"));
+    }
+}
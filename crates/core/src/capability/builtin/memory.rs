@@ -0,0 +1,392 @@
+//! Memory tools: `remember` saves a pinned knowledge entry, `recall` searches
+//! knowledge and episodes, `forget` deletes an entry by id.
+//!
+//! Builtins cannot reach the scheduler's `WorkingMemory`, so pins and removals
+//! are queued here and applied by the scheduler at the start of the next tick.
+
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+use super::DbHandle;
+
+use crate::memory::{embedding, episodic, semantic};
+use crate::types::{CapabilityRequest, CapabilityResponse, Knowledge, Permission};
+use llm::provider::ToolDefinition;
+
+const DEFAULT_RECALL_LIMIT: usize = 10;
+const MAX_RECALL_LIMIT: usize = 50;
+const MAX_MEMORY_CHARS: usize = 2000;
+/// Search terms taken from one query.
+const MAX_TERMS: usize = 8;
+
+/// Working-memory change requested by a memory tool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PinChange {
+    Pin { id: Uuid, content: String },
+    Remove(Uuid),
+}
+
+/// Database handle and pending working-memory changes shared by the memory tools.
+#[derive(Debug, Default)]
+pub struct MemoryAccess {
+    db: Arc<DbHandle>,
+    pending: Mutex<Vec<PinChange>>,
+}
+
+impl MemoryAccess {
+    pub fn new(db: Arc<DbHandle>) -> Self {
+        Self { db, pending: Mutex::default() }
+    }
+
+    fn pool(&self) -> Option<sqlx::PgPool> {
+        self.db.pool()
+    }
+
+    fn queue(&self, change: PinChange) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(change);
+        }
+    }
+
+    /// Drain queued changes; the scheduler applies them to working memory.
+    pub fn take_pin_changes(&self) -> Vec<PinChange> {
+        self.pending.lock().map(|mut p| std::mem::take(&mut *p)).unwrap_or_default()
+    }
+}
+
+/// ILIKE patterns for a query: the whole phrase plus its longer words.
+fn like_patterns(query: &str) -> Vec<String> {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    let mut terms = vec![query.trim().to_string()];
+    for word in query.split(|c: char| c.is_whitespace() || ",.;:!?\"'()，。；：！？".contains(c)) {
+        // CJK words carry meaning at two characters; Latin ones need three.
+        let long_enough = word.chars().count() >= 3 || (word.chars().count() == 2 && !word.is_ascii());
+        if long_enough && !terms.iter().any(|t| t.eq_ignore_ascii_case(word)) {
+            terms.push(word.to_string());
+        }
+    }
+    terms.truncate(MAX_TERMS);
+    terms.into_iter().filter(|t| !t.is_empty()).map(|t| format!("%{}%", escape(&t))).collect()
+}
+
+/// How many of the query's patterns `text` contains (case-insensitive).
+fn match_score(text: &str, patterns: &[String]) -> usize {
+    let text = text.to_lowercase();
+    patterns
+        .iter()
+        .filter(|p| {
+            let term = p.trim_matches('%').replace("\\%", "%").replace("\\_", "_").replace("\\\\", "\\");
+            text.contains(&term.to_lowercase())
+        })
+        .count()
+}
+
+fn respond(request: &CapabilityRequest, result: Result<serde_json::Value, String>) -> CapabilityResponse {
+    match result {
+        Ok(value) => CapabilityResponse { id: request.id, result: Some(value), error: None, metrics: None, side_effects: vec![] },
+        Err(error) => CapabilityResponse { id: request.id, result: None, error: Some(error), metrics: None, side_effects: vec![] },
+    }
+}
+
+const NO_DB: &str = "long-term memory needs a database (DATABASE_URL is not connected)";
+
+pub struct Remember {
+    memory: Arc<MemoryAccess>,
+}
+
+impl Remember {
+    pub fn new(memory: Arc<MemoryAccess>) -> Self {
+        Self { memory }
+    }
+
+    async fn run(&self, content: &str) -> Result<serde_json::Value, String> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err("content is empty".into());
+        }
+        if content.chars().count() > MAX_MEMORY_CHARS {
+            return Err(format!("memory is too long (max {MAX_MEMORY_CHARS} characters); save a summary"));
+        }
+        let Some(pool) = self.memory.pool() else {
+            // Still useful for the rest of this session.
+            let id = Uuid::new_v4();
+            self.memory.queue(PinChange::Pin { id, content: content.to_string() });
+            return Ok(serde_json::json!({ "id": id, "content": content, "durable": false, "note": NO_DB }));
+        };
+
+        let existing = semantic::search_any(&pool, &like_patterns(content)[..1], 5)
+            .await
+            .map_err(|e| format!("memory lookup failed: {e}"))?;
+        if let Some(k) = existing.iter().find(|k| k.pinned && k.summary.trim() == content) {
+            return Ok(serde_json::json!({ "id": k.id, "content": k.summary, "durable": true, "already_remembered": true }));
+        }
+
+        let knowledge = Knowledge {
+            id: Uuid::new_v4(),
+            summary: content.to_string(),
+            embedding: Some(embedding::generate(content)),
+            source_episode_ids: vec![],
+            created_at: chrono::Utc::now(),
+            pinned: true,
+        };
+        episodic::write_knowledge(&pool, &knowledge)
+            .await
+            .map_err(|e| format!("failed to save memory: {e}"))?;
+        self.memory.queue(PinChange::Pin { id: knowledge.id, content: knowledge.summary.clone() });
+        Ok(serde_json::json!({ "id": knowledge.id, "content": knowledge.summary, "durable": true }))
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Remember {
+    fn name(&self) -> &str { "remember" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["remember", "note", "memorize", "记住", "记下"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "remember".into(),
+            description: "Save a fact to long-term memory as a pinned entry, e.g. \"The staging DB listens on port 5433\". \
+                          Write it as a self-contained statement; it can be found later with recall."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string", "minLength": 1, "maxLength": MAX_MEMORY_CHARS, "description": "The fact to remember" }
+                },
+                "required": ["content"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let result = match request.params.get("content").and_then(|v| v.as_str()) {
+            Some(content) => self.run(content).await,
+            None => Err("missing content".into()),
+        };
+        respond(&request, result)
+    }
+}
+
+pub struct Recall {
+    memory: Arc<MemoryAccess>,
+}
+
+impl Recall {
+    pub fn new(memory: Arc<MemoryAccess>) -> Self {
+        Self { memory }
+    }
+
+    async fn run(&self, query: &str, limit: usize, include_episodes: bool) -> Result<serde_json::Value, String> {
+        let pool = self.memory.pool().ok_or(NO_DB)?;
+        let patterns = like_patterns(query);
+        if patterns.is_empty() {
+            return Err("query is empty".into());
+        }
+        // Over-fetch, then rank by how many terms each entry matches.
+        let fetch = (limit * 3) as i64;
+        let mut knowledge = semantic::search_any(&pool, &patterns, fetch)
+            .await
+            .map_err(|e| format!("memory search failed: {e}"))?;
+        knowledge.sort_by_key(|k| std::cmp::Reverse((k.pinned, match_score(&k.summary, &patterns))));
+        knowledge.truncate(limit);
+        let knowledge: Vec<serde_json::Value> = knowledge
+            .iter()
+            .map(|k| serde_json::json!({
+                "id": k.id,
+                "content": k.summary,
+                "pinned": k.pinned,
+                "created_at": k.created_at.to_rfc3339(),
+            }))
+            .collect();
+
+        let episodes: Vec<serde_json::Value> = if include_episodes {
+            let mut found = episodic::search_any(&pool, &patterns, fetch)
+                .await
+                .map_err(|e| format!("memory search failed: {e}"))?;
+            found.sort_by_key(|e| std::cmp::Reverse(match_score(&e.content, &patterns)));
+            found.truncate(limit);
+            found
+                .iter()
+                .map(|e| serde_json::json!({
+                    "id": e.id,
+                    "content": e.content,
+                    "created_at": e.created_at.to_rfc3339(),
+                }))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(serde_json::json!({
+            "query": query,
+            "knowledge": knowledge,
+            "episodes": episodes,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Recall {
+    fn name(&self) -> &str { "recall" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["recall", "remember when", "what did", "回忆", "记得"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "recall".into(),
+            description: "Search long-term memory: saved facts (pinned first) and, optionally, past conversation episodes. \
+                          Matches the phrase or any of its words. Returned ids can be passed to forget."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "minLength": 1, "description": "Words to look for, e.g. \"staging DB port\"" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_RECALL_LIMIT, "description": "Max results per section (default 10)" },
+                    "include_episodes": { "type": "boolean", "description": "Also search past conversation (default true)" }
+                },
+                "required": ["query"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let limit = params.get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_RECALL_LIMIT))
+            .unwrap_or(DEFAULT_RECALL_LIMIT);
+        let include_episodes = params.get("include_episodes").and_then(|v| v.as_bool()).unwrap_or(true);
+        let result = match params.get("query").and_then(|v| v.as_str()) {
+            Some(query) => self.run(query, limit, include_episodes).await,
+            None => Err("missing query".into()),
+        };
+        respond(&request, result)
+    }
+}
+
+pub struct Forget {
+    memory: Arc<MemoryAccess>,
+}
+
+impl Forget {
+    pub fn new(memory: Arc<MemoryAccess>) -> Self {
+        Self { memory }
+    }
+
+    async fn run(&self, id: Uuid) -> Result<serde_json::Value, String> {
+        self.memory.queue(PinChange::Remove(id));
+        let Some(pool) = self.memory.pool() else {
+            return Ok(serde_json::json!({ "id": id, "forgotten": "session", "note": NO_DB }));
+        };
+        let deleted = if semantic::delete(&pool, id).await.map_err(|e| format!("failed to forget: {e}"))? {
+            "knowledge"
+        } else if episodic::delete(&pool, id).await.map_err(|e| format!("failed to forget: {e}"))? {
+            "episode"
+        } else {
+            return Err(format!("no memory with id {id}; use recall to find it"));
+        };
+        Ok(serde_json::json!({ "id": id, "forgotten": deleted }))
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for Forget {
+    fn name(&self) -> &str { "forget" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["forget", "delete memory", "忘记", "忘掉"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "forget".into(),
+            description: "Delete a memory (saved fact or past episode) by the id returned from recall or remember.".into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "minLength": 36, "maxLength": 36, "description": "Memory id (UUID)" }
+                },
+                "required": ["id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let result = match request.params.get("id").and_then(|v| v.as_str()).map(Uuid::parse_str) {
+            Some(Ok(id)) => self.run(id).await,
+            Some(Err(e)) => Err(format!("invalid id: {e}")),
+            None => Err("missing id".into()),
+        };
+        respond(&request, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    #[test]
+    fn patterns_cover_phrase_and_words() {
+        let patterns = like_patterns("staging DB port");
+        assert_eq!(patterns, vec!["%staging DB port%", "%staging%", "%port%"]);
+        assert_eq!(like_patterns("100%_done")[0], "%100\\%\\_done%");
+        assert!(like_patterns("预发 数据库").contains(&"%数据库%".to_string()));
+        assert!(like_patterns("   ").is_empty());
+    }
+
+    #[test]
+    fn score_counts_matched_terms() {
+        let patterns = like_patterns("staging DB port");
+        assert_eq!(match_score("Our staging DB is on port 5433", &patterns), 2);
+        assert_eq!(match_score("notes: Staging DB port 5433", &patterns), 3);
+        assert_eq!(match_score("prod port is 5432", &patterns), 1);
+    }
+
+    #[tokio::test]
+    async fn without_db_remember_pins_for_session_only() {
+        let memory = Arc::new(MemoryAccess::default());
+        let req = |params| CapabilityRequest { id: Uuid::new_v4(), method: String::new(), params, version: 1 };
+
+        let resp = Remember::new(Arc::clone(&memory))
+            .execute(req(serde_json::json!({ "content": "staging DB is on port 5433" })))
+            .await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["durable"], false);
+        let id = Uuid::parse_str(result["id"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            memory.take_pin_changes(),
+            vec![PinChange::Pin { id, content: "staging DB is on port 5433".into() }]
+        );
+
+        let resp = Recall::new(Arc::clone(&memory)).execute(req(serde_json::json!({ "query": "staging" }))).await;
+        assert!(resp.error.unwrap().contains("database"));
+
+        Forget::new(Arc::clone(&memory)).execute(req(serde_json::json!({ "id": id.to_string() }))).await;
+        assert_eq!(memory.take_pin_changes(), vec![PinChange::Remove(id)]);
+    }
+}
@@ -1,10 +1,38 @@
 pub mod read_file;
 pub mod write_file;
 pub mod run_bash;
+pub mod fetch_url;
+pub mod list_dir;
+pub mod glob;
+pub mod grep;
+pub mod walk;
+pub mod edit_file;
+pub mod diff;
+pub mod sandbox;
+pub mod shell_session;
+pub mod jobs;
+pub mod memory;
+pub mod schedule_reminder;
+pub mod artifact;
+pub mod undo;
+pub mod git;
+pub mod rust_symbols;
 
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
+use crate::capability::mcp::client::McpTool;
+use crate::capability::net_policy::{self, HostPolicy, NetContext};
+use crate::capability::path_policy::{FileAccess, PathPolicy};
+use crate::config::IrisCfg;
+use artifact::ArtifactStore;
+use git::GitSettings;
+use jobs::JobManager;
+use memory::MemoryAccess;
+use sandbox::SandboxState;
+use undo::UndoJournal;
+
 use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
 use llm::provider::ToolDefinition;
 
@@ -16,6 +44,24 @@ const BUILTIN_NS: Uuid = Uuid::from_bytes([
     0x84, 0xf1, 0xfc, 0x91, 0xff, 0x98, 0x14, 0xfd,
 ]);
 
+/// Database pool shared with builtins that need it; empty until boot connects.
+#[derive(Debug, Default)]
+pub struct DbHandle {
+    pool: RwLock<Option<sqlx::PgPool>>,
+}
+
+impl DbHandle {
+    pub fn set(&self, pool: sqlx::PgPool) {
+        if let Ok(mut p) = self.pool.write() {
+            *p = Some(pool);
+        }
+    }
+
+    pub fn pool(&self) -> Option<sqlx::PgPool> {
+        self.pool.read().ok().and_then(|p| p.clone())
+    }
+}
+
 #[async_trait::async_trait]
 pub trait BuiltinCapability: Send + Sync {
     fn name(&self) -> &str;
@@ -27,6 +73,26 @@ pub trait BuiltinCapability: Send + Sync {
 
 pub struct BuiltinRegistry {
     caps: HashMap<Uuid, Box<dyn BuiltinCapability>>,
+    /// Host policy and network state shared with network builtins.
+    net: Arc<NetContext>,
+    /// Sandbox profile and limits for `run_bash`.
+    sandbox: Arc<SandboxState>,
+    /// Path access policy for file builtins.
+    files: Arc<FileAccess>,
+    /// Background jobs started by `job_start`.
+    jobs: Arc<JobManager>,
+    /// Database pool for memory and schedule builtins.
+    db: Arc<DbHandle>,
+    /// Working-memory pins queued by the memory tools.
+    memory: Arc<MemoryAccess>,
+    /// Spool for oversized tool output, read back with `read_artifact`.
+    artifacts: Arc<ArtifactStore>,
+    /// Journal of file writes, restored by `undo_write` and `/undo`.
+    journal: Arc<UndoJournal>,
+    /// Whether the `git` tool may commit and check out.
+    git: Arc<GitSettings>,
+    /// Tools mounted from each MCP server, by server name.
+    mcp_mounts: HashMap<String, Vec<Uuid>>,
 }
 
 impl Default for BuiltinRegistry {
@@ -37,18 +103,141 @@ impl Default for BuiltinRegistry {
 
 impl BuiltinRegistry {
     pub fn new() -> Self {
-        let mut reg = Self { caps: HashMap::new() };
-        reg.register(Box::new(read_file::ReadFile));
-        reg.register(Box::new(write_file::WriteFile));
-        reg.register(Box::new(run_bash::RunBash));
+        let net = NetContext::shared();
+        let sandbox = Arc::new(SandboxState::default());
+        let files = FileAccess::shared();
+        let jobs = Arc::new(JobManager::new(Arc::clone(&sandbox)));
+        let db = Arc::new(DbHandle::default());
+        let memory = Arc::new(MemoryAccess::new(Arc::clone(&db)));
+        let artifacts = Arc::new(ArtifactStore::default());
+        let journal = Arc::new(UndoJournal::default());
+        let git = Arc::new(GitSettings::default());
+        let mut reg = Self {
+            caps: HashMap::new(),
+            net: Arc::clone(&net),
+            sandbox: Arc::clone(&sandbox),
+            files: Arc::clone(&files),
+            jobs: Arc::clone(&jobs),
+            db: Arc::clone(&db),
+            memory: Arc::clone(&memory),
+            artifacts: Arc::clone(&artifacts),
+            journal: Arc::clone(&journal),
+            git: Arc::clone(&git),
+            mcp_mounts: HashMap::new(),
+        };
+        reg.register(Box::new(read_file::ReadFile::new(Arc::clone(&files), Arc::clone(&artifacts))));
+        reg.register(Box::new(write_file::WriteFile::new(Arc::clone(&files), Arc::clone(&journal))));
+        reg.register(Box::new(run_bash::RunBash::new(Arc::clone(&sandbox), Arc::clone(&artifacts))));
+        reg.register(Box::new(shell_session::ShellSession::new(sandbox)));
+        reg.register(Box::new(fetch_url::FetchUrl::new(net)));
+        reg.register(Box::new(list_dir::ListDir::new(Arc::clone(&files))));
+        reg.register(Box::new(glob::Glob::new(Arc::clone(&files))));
+        reg.register(Box::new(grep::Grep::new(Arc::clone(&files))));
+        reg.register(Box::new(edit_file::EditFile::new(Arc::clone(&files), Arc::clone(&journal))));
+        reg.register(Box::new(jobs::JobStart::new(Arc::clone(&jobs))));
+        reg.register(Box::new(jobs::JobPoll::new(Arc::clone(&jobs))));
+        reg.register(Box::new(jobs::JobKill::new(jobs)));
+        reg.register(Box::new(memory::Remember::new(Arc::clone(&memory))));
+        reg.register(Box::new(memory::Recall::new(Arc::clone(&memory))));
+        reg.register(Box::new(memory::Forget::new(memory)));
+        reg.register(Box::new(schedule_reminder::ScheduleReminder::new(db)));
+        reg.register(Box::new(artifact::ReadArtifact::new(artifacts)));
+        reg.register(Box::new(undo::UndoWrite::new(journal, Arc::clone(&files))));
+        reg.register(Box::new(rust_symbols::RustSymbols::new(Arc::clone(&files))));
+        reg.register(Box::new(git::Git::new(files, git)));
         reg
     }
 
+    /// Shared network context (host policy, network state) for network builtins.
+    pub fn net(&self) -> &Arc<NetContext> {
+        &self.net
+    }
+
+    /// Sandbox settings for `run_bash`, configured from `IrisCfg` and safe mode.
+    pub fn sandbox(&self) -> &Arc<SandboxState> {
+        &self.sandbox
+    }
+
+    /// Path policy for the file builtins (read, write, edit, list, glob, grep, git,
+    /// rust_symbols and undo), loaded from `IrisCfg`.
+    pub fn files(&self) -> &Arc<FileAccess> {
+        &self.files
+    }
+
+    /// Background job table; the scheduler sets its completion notifier.
+    pub fn jobs(&self) -> &Arc<JobManager> {
+        &self.jobs
+    }
+
+    /// Apply the configured permission policy: sandbox profile, file path policy,
+    /// and (with a database) host rules and the pool for memory and schedule tools.
+    pub async fn apply_config(&self, cfg: &IrisCfg, pool: Option<&sqlx::PgPool>) {
+        self.sandbox.configure(cfg);
+        self.files.set_policy(PathPolicy::from_cfg(cfg));
+        self.artifacts.configure(cfg);
+        self.journal.configure(cfg);
+        self.git.configure(cfg);
+        let Some(pool) = pool else { return };
+        self.set_pool(pool.clone());
+        match net_policy::load_rules(pool).await {
+            Ok(rules) => {
+                tracing::info!(count = rules.len(), "host rules loaded");
+                self.net.set_policy(HostPolicy::new(rules));
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to load host rules"),
+        }
+    }
+
+    /// Spool for oversized tool output.
+    pub fn artifacts(&self) -> &Arc<ArtifactStore> {
+        &self.artifacts
+    }
+
+    /// Journal of file writes made by `write_file` and `edit_file`.
+    pub fn undo(&self) -> &Arc<UndoJournal> {
+        &self.journal
+    }
+
+    /// Hand the database pool to builtins that use it.
+    pub fn set_pool(&self, pool: sqlx::PgPool) {
+        self.db.set(pool);
+    }
+
+    /// Shared state for `remember`, `recall` and `forget`.
+    pub fn memory(&self) -> &Arc<MemoryAccess> {
+        &self.memory
+    }
+
     fn register(&mut self, cap: Box<dyn BuiltinCapability>) {
         let id = Uuid::new_v5(&BUILTIN_NS, cap.name().as_bytes());
         self.caps.insert(id, cap);
     }
 
+    /// Add a server's MCP tools, replacing any mounted earlier. Tools whose
+    /// name is already taken are skipped. Returns the number mounted.
+    pub fn mount_mcp(&mut self, server: &str, tools: Vec<McpTool>) -> usize {
+        self.unmount_mcp(server);
+        let mut ids = Vec::new();
+        for tool in tools {
+            if self.get_by_name(tool.name()).is_some() {
+                tracing::warn!(server, tool = tool.name(), "MCP tool name already registered, skipping");
+                continue;
+            }
+            ids.push(Uuid::new_v5(&BUILTIN_NS, tool.name().as_bytes()));
+            self.register(Box::new(tool));
+        }
+        let count = ids.len();
+        self.mcp_mounts.insert(server.to_string(), ids);
+        count
+    }
+
+    /// Remove every tool mounted from `server`.
+    pub fn unmount_mcp(&mut self, server: &str) {
+        for id in self.mcp_mounts.remove(server).unwrap_or_default() {
+            self.caps.remove(&id);
+        }
+    }
+
     /// Returns (id, keywords) pairs for FastPath registration.
     pub fn entries(&self) -> Vec<(Uuid, Vec<String>)> {
         self.caps.iter().map(|(id, cap)| (*id, cap.keywords())).collect()
@@ -74,14 +263,7 @@ impl BuiltinRegistry {
     /// Human-readable description of all registered builtins, for LLM self-context injection.
     pub fn describe(&self) -> String {
         let mut lines: Vec<String> = self.caps.values().map(|cap| {
-            let perms: Vec<&str> = cap.permissions().iter().map(|p| match p {
-                Permission::FileRead => "FileRead",
-                Permission::FileWrite => "FileWrite",
-                Permission::ProcessSpawn => "ProcessSpawn",
-                Permission::NetworkRead => "NetworkRead",
-                Permission::NetworkWrite => "NetworkWrite",
-                Permission::SystemInfo => "SystemInfo",
-            }).collect();
+            let perms: Vec<&str> = cap.permissions().iter().map(Permission::as_str).collect();
             format!("- {} (permissions: {})", cap.name(), perms.join(", "))
         }).collect();
         lines.sort(); // deterministic order
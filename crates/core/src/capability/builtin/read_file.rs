@@ -1,7 +1,21 @@
+use std::sync::Arc;
+
+use super::artifact::ArtifactStore;
+use crate::capability::path_policy::{self, FileAccess};
 use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
 use llm::provider::ToolDefinition;
 
-pub struct ReadFile;
+#[derive(Default)]
+pub struct ReadFile {
+    access: Arc<FileAccess>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl ReadFile {
+    pub fn new(access: Arc<FileAccess>, artifacts: Arc<ArtifactStore>) -> Self {
+        Self { access, artifacts }
+    }
+}
 
 /// Extract a file path from user input.
 /// Priority: quoted string > token containing `/` or `.`
@@ -40,7 +54,8 @@ impl super::BuiltinCapability for ReadFile {
     fn tool_definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "read_file".into(),
-            description: "Read the contents of a file at the given path".into(),
+            description: "Read the contents of a file at the given path. Large files come back as a head/tail \
+                          preview with an artifact_id; page through them with read_artifact.".into(),
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -71,28 +86,37 @@ impl super::BuiltinCapability for ReadFile {
             }
         };
 
-        match tokio::fs::read_to_string(&path).await {
-            Ok(content) => {
-                let size = content.len();
-                CapabilityResponse {
-                    id: request.id,
-                    result: Some(serde_json::json!({
-                        "path": path,
-                        "content": content,
-                        "size_bytes": size,
-                    })),
-                    error: None,
-                    metrics: None,
-                    side_effects: vec![Permission::FileRead],
-                }
-            }
-            Err(e) => CapabilityResponse {
-                id: request.id,
-                result: None,
-                error: Some(format!("failed to read {path}: {e}")),
-                metrics: None,
-                side_effects: vec![],
-            },
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+        // Checked after extraction so free-text requests get the same policy.
+        let resolved = match self.access.policy().check_read(&path) {
+            Ok(p) => p,
+            Err(e) => return fail(e),
+        };
+        let bytes = match tokio::fs::read(&resolved).await {
+            Ok(b) => b,
+            Err(e) => return fail(format!("failed to read {path}: {e}")),
+        };
+        if path_policy::looks_binary(&bytes) {
+            return fail(format!("{path} looks like a binary file ({} bytes); read_file only returns text", bytes.len()));
+        }
+        let content = String::from_utf8_lossy(&bytes).into_owned();
+        let size = bytes.len();
+        CapabilityResponse {
+            id: request.id,
+            result: Some(serde_json::json!({
+                "path": path,
+                "content": self.artifacts.compact(&format!("read_file {path}"), content),
+                "size_bytes": size,
+            })),
+            error: None,
+            metrics: None,
+            side_effects: vec![Permission::FileRead],
         }
     }
 }
@@ -117,4 +141,24 @@ mod tests {
     fn no_path_found() {
         assert_eq!(extract_path("hello world"), None);
     }
+
+    fn req(method: &str, params: serde_json::Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: method.into(), params, version: 1 }
+    }
+
+    #[tokio::test]
+    async fn policy_applies_to_structured_and_free_text_paths() {
+        use crate::capability::builtin::BuiltinCapability;
+        let read = ReadFile::default();
+        let resp = read.execute(req("", serde_json::json!({ "path": "/etc/shadow" }))).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+        let resp = read.execute(req("show me ~/.ssh/id_rsa", serde_json::json!({}))).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let blob = dir.path().join("blob.bin");
+        std::fs::write(&blob, b"\x7fELF\0\0\0").unwrap();
+        let resp = read.execute(req("", serde_json::json!({ "path": blob.to_str().unwrap() }))).await;
+        assert!(resp.error.unwrap().contains("binary"));
+    }
 }
@@ -1,12 +1,25 @@
+use super::artifact::ArtifactStore;
+use super::sandbox::SandboxState;
 use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
 use llm::provider::ToolDefinition;
+use std::sync::Arc;
 use std::time::Duration;
 
-pub struct RunBash;
+#[derive(Default)]
+pub struct RunBash {
+    sandbox: Arc<SandboxState>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl RunBash {
+    pub fn new(sandbox: Arc<SandboxState>, artifacts: Arc<ArtifactStore>) -> Self {
+        Self { sandbox, artifacts }
+    }
+}
 
 /// Extract command string from user input.
 /// Priority: fenced code block > backtick > quoted string > text after trigger word.
-fn extract_command(input: &str) -> Option<String> {
+pub(super) fn extract_command(input: &str) -> Option<String> {
     // Fenced code block
     if let Some(start) = input.find("```") {
         let after = &input[start + 3..];
@@ -84,11 +97,16 @@ impl super::BuiltinCapability for RunBash {
     fn tool_definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "run_bash".into(),
-            description: "Execute a bash command and return stdout, stderr, and exit code".into(),
+            description: "Execute a bash command and return stdout, stderr, and exit code. \
+                          Depending on configuration it runs sandboxed: jailed working directory, resource limits, \
+                          scrubbed environment and possibly no network. Large output comes back as a head/tail \
+                          preview with an artifact_id for read_artifact."
+                .into(),
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
-                    "command": { "type": "string", "description": "The bash command to execute" }
+                    "command": { "type": "string", "description": "The bash command to execute" },
+                    "cwd": { "type": "string", "description": "Working directory, relative to the sandbox working directory" }
                 },
                 "required": ["command"]
             }),
@@ -115,30 +133,30 @@ impl super::BuiltinCapability for RunBash {
             }
         };
 
+        let policy = self.sandbox.policy();
+        let cwd = match policy.resolve_cwd(request.params.get("cwd").and_then(|v| v.as_str())) {
+            Ok(dir) => dir,
+            Err(e) => {
+                return CapabilityResponse {
+                    id: request.id,
+                    result: None,
+                    error: Some(e),
+                    metrics: None,
+                    side_effects: vec![],
+                };
+            }
+        };
+
         let result = tokio::time::timeout(
             Duration::from_secs(TIMEOUT_SECS),
-            tokio::process::Command::new("bash")
-                .arg("-c")
-                .arg(&cmd)
-                .output(),
+            policy.command(&cmd, &cwd).output(),
         ).await;
 
         match result {
             Ok(Ok(output)) => {
                 let code = output.status.code().unwrap_or(-1);
-                const MAX_OUTPUT: usize = 64 * 1024;
-                let stdout_raw = String::from_utf8_lossy(&output.stdout);
-                let stderr_raw = String::from_utf8_lossy(&output.stderr);
-                let stdout = if stdout_raw.len() > MAX_OUTPUT {
-                    format!("{}... [truncated, {} bytes total]", &stdout_raw[..MAX_OUTPUT], stdout_raw.len())
-                } else {
-                    stdout_raw.to_string()
-                };
-                let stderr = if stderr_raw.len() > MAX_OUTPUT {
-                    format!("{}... [truncated, {} bytes total]", &stderr_raw[..MAX_OUTPUT], stderr_raw.len())
-                } else {
-                    stderr_raw.to_string()
-                };
+                let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+                let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
                 let error = if code == 0 {
                     None
                 } else {
@@ -155,9 +173,12 @@ impl super::BuiltinCapability for RunBash {
                     id: request.id,
                     result: Some(serde_json::json!({
                         "command": cmd,
-                        "stdout": stdout,
-                        "stderr": stderr,
+                        "stdout": self.artifacts.compact("run_bash stdout", stdout),
+                        "stderr": self.artifacts.compact("run_bash stderr", stderr),
                         "exit_code": code,
+                        "cwd": cwd.display().to_string(),
+                        "sandbox": policy.profile.as_str(),
+                        "isolated": policy.isolate,
                     })),
                     error,
                     metrics: None,
@@ -211,7 +232,7 @@ mod tests {
 
     #[tokio::test]
     async fn non_zero_exit_sets_error() {
-        let cap = RunBash;
+        let cap = RunBash::default();
         let req = CapabilityRequest {
             id: uuid::Uuid::new_v4(),
             method: "run false".into(),
@@ -222,4 +243,79 @@ mod tests {
         assert!(resp.error.is_some());
         assert!(resp.result.is_some());
     }
+
+    #[tokio::test]
+    async fn large_output_becomes_an_artifact() {
+        let cap = RunBash::default();
+        let req = CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: String::new(),
+            params: serde_json::json!({"command":"seq 1 20000"}),
+            version: 1,
+        };
+        let result = cap.execute(req).await.result.unwrap();
+        let stdout = &result["stdout"];
+        assert_eq!(stdout["lines"], 20000);
+        assert!(stdout["tail"].as_str().unwrap().ends_with("19999\n20000\n"));
+        let id = stdout["artifact_id"].as_str().unwrap();
+        let full = cap.artifacts.load(id).unwrap();
+        assert_eq!(full.lines().count(), 20000);
+    }
+
+    fn sandboxed(profile: &str, workdir: &std::path::Path) -> RunBash {
+        let state = SandboxState::default();
+        state.configure(&crate::config::IrisCfg {
+            bash_sandbox: profile.into(),
+            bash_sandbox_workdir: workdir.to_string_lossy().into_owned(),
+            bash_file_size_mb: 1,
+            ..crate::config::IrisCfg::default()
+        });
+        RunBash::new(Arc::new(state), Arc::default())
+    }
+
+    async fn run(cap: &RunBash, params: serde_json::Value) -> CapabilityResponse {
+        cap.execute(CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: String::new(),
+            params,
+            version: 1,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn standard_sandbox_jails_cwd_and_limits_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let cap = sandboxed("standard", dir.path());
+
+        let resp = run(&cap, serde_json::json!({"command": "pwd; echo $IRIS_SANDBOX", "cwd": "sub"})).await;
+        let result = resp.result.unwrap();
+        let stdout = result["stdout"].as_str().unwrap();
+        assert!(stdout.starts_with(dir.path().canonicalize().unwrap().join("sub").to_str().unwrap()));
+        assert!(stdout.contains("standard"));
+        assert_eq!(result["sandbox"], "standard");
+
+        let resp = run(&cap, serde_json::json!({"command": "ls", "cwd": "/etc"})).await;
+        assert!(resp.error.unwrap().contains("outside the sandbox"));
+
+        // RLIMIT_FSIZE (1 MB here) stops oversized writes.
+        let resp = run(&cap, serde_json::json!({"command": "head -c 2000000 /dev/zero > big"})).await;
+        assert!(resp.error.is_some());
+        assert!(std::fs::metadata(dir.path().join("big")).unwrap().len() <= 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn strict_sandbox_isolates_network_when_available() {
+        if !super::super::sandbox::namespaces_available() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let cap = sandboxed("strict", dir.path());
+        let resp = run(&cap, serde_json::json!({"command": "grep -c : /proc/net/dev"})).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["isolated"], true);
+        // Only the loopback interface exists in the new network namespace.
+        assert_eq!(result["stdout"].as_str().unwrap().trim(), "1");
+    }
 }
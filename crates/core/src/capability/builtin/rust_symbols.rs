@@ -0,0 +1,573 @@
+//! `rust_symbols`: a syn-based index of Rust items for navigating crates.
+//!
+//! Every `.rs` file under the requested directory is parsed with `syn`; items,
+//! impl blocks, trait impls, methods and their doc comments are recorded with
+//! `file:line` locations. Parsed files are cached by modification time, so
+//! repeated queries only reparse what changed.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use syn::{Attribute, Expr, ExprLit, ImplItem, Item, Lit, Meta, TraitItem, Type};
+
+use super::walk::{self, EntryKind, WalkOptions};
+use crate::capability::path_policy::FileAccess;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const MAX_WALK_DEPTH: usize = 32;
+const MAX_FILES: usize = 5000;
+/// Larger files (usually generated code) are skipped.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+/// Lines scanned for a signature that spans several lines.
+const MAX_SIGNATURE_LINES: usize = 8;
+const MAX_SIGNATURE_CHARS: usize = 240;
+const MAX_DOC_CHARS: usize = 300;
+/// Cached files across all indexed roots before the cache is reset.
+const MAX_CACHED_FILES: usize = 50_000;
+
+#[derive(Debug, Clone, Serialize)]
+struct Symbol {
+    name: String,
+    kind: &'static str,
+    /// Module path, plus the impl type or trait for associated functions.
+    path: String,
+    location: String,
+    signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc: Option<String>,
+    /// Trait implemented by an impl block or one of its methods.
+    #[serde(rename = "trait", skip_serializing_if = "Option::is_none")]
+    trait_name: Option<String>,
+    /// Type an impl block or method belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    self_type: Option<String>,
+    #[serde(skip)]
+    file: String,
+    #[serde(skip)]
+    line: usize,
+}
+
+/// Module path for a file: `src/capability/mod.rs` → `capability`.
+fn module_path(rel: &str) -> Vec<String> {
+    let parts: Vec<&str> = rel.trim_end_matches(".rs").split('/').collect();
+    let start = parts.iter().rposition(|p| *p == "src").map(|i| i + 1).unwrap_or(0);
+    let mut module: Vec<String> = parts[start..].iter().map(|p| p.to_string()).collect();
+    if matches!(module.last().map(String::as_str), Some("mod" | "lib" | "main")) {
+        module.pop();
+    }
+    module
+}
+
+/// First paragraph of the doc comments, on one line.
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .skip_while(|l| l.is_empty())
+        .take_while(|l| !l.is_empty())
+        .collect();
+    let text = lines.join(" ");
+    (!text.is_empty()).then(|| text.chars().take(MAX_DOC_CHARS).collect())
+}
+
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()).unwrap_or_default(),
+        Type::Reference(r) => type_name(&r.elem),
+        Type::Paren(p) => type_name(&p.elem),
+        Type::Group(g) => type_name(&g.elem),
+        _ => "_".into(),
+    }
+}
+
+/// Declaration text from `line` up to the body or the terminating `;`.
+fn signature(lines: &[&str], line: usize) -> String {
+    let mut sig = String::new();
+    for text in lines.iter().skip(line.saturating_sub(1)).take(MAX_SIGNATURE_LINES) {
+        if let Some(end) = text.find(['{', ';']) {
+            sig.push_str(&text[..end]);
+            break;
+        }
+        sig.push_str(text);
+        sig.push(' ');
+    }
+    let sig = sig.split_whitespace().collect::<Vec<_>>().join(" ");
+    sig.chars().take(MAX_SIGNATURE_CHARS).collect()
+}
+
+/// Enclosing trait or impl block of an associated function.
+#[derive(Default)]
+struct Parent {
+    owner: Option<String>,
+    trait_name: Option<String>,
+    self_type: Option<String>,
+}
+
+struct Collector<'a> {
+    file: &'a str,
+    lines: Vec<&'a str>,
+    module: Vec<String>,
+    out: Vec<Symbol>,
+}
+
+impl Collector<'_> {
+    fn push(&mut self, kind: &'static str, name: String, line: usize, attrs: &[Attribute], parent: &Parent) {
+        let path = self
+            .module
+            .iter()
+            .map(String::as_str)
+            .chain(parent.owner.as_deref())
+            .chain(std::iter::once(name.as_str()))
+            .collect::<Vec<_>>()
+            .join("::");
+        self.out.push(Symbol {
+            location: format!("{}:{line}", self.file),
+            signature: signature(&self.lines, line),
+            doc: doc(attrs),
+            name,
+            kind,
+            path,
+            trait_name: parent.trait_name.clone(),
+            self_type: parent.self_type.clone(),
+            file: self.file.to_string(),
+            line,
+        });
+    }
+
+    fn item(&mut self, kind: &'static str, ident: &syn::Ident, attrs: &[Attribute]) {
+        self.push(kind, ident.to_string(), ident.span().start().line, attrs, &Parent::default());
+    }
+
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Fn(f) => self.item("fn", &f.sig.ident, &f.attrs),
+                Item::Struct(s) => self.item("struct", &s.ident, &s.attrs),
+                Item::Enum(e) => self.item("enum", &e.ident, &e.attrs),
+                Item::Union(u) => self.item("union", &u.ident, &u.attrs),
+                Item::Type(t) => self.item("type", &t.ident, &t.attrs),
+                Item::Const(c) => self.item("const", &c.ident, &c.attrs),
+                Item::Static(s) => self.item("static", &s.ident, &s.attrs),
+                Item::TraitAlias(t) => self.item("trait", &t.ident, &t.attrs),
+                Item::Macro(m) => {
+                    if let Some(ident) = &m.ident {
+                        self.item("macro", ident, &m.attrs);
+                    }
+                }
+                Item::Trait(t) => {
+                    self.item("trait", &t.ident, &t.attrs);
+                    let parent = Parent { owner: Some(t.ident.to_string()), ..Parent::default() };
+                    for ti in &t.items {
+                        if let TraitItem::Fn(f) = ti {
+                            let ident = &f.sig.ident;
+                            self.push("trait_method", ident.to_string(), ident.span().start().line, &f.attrs, &parent);
+                        }
+                    }
+                }
+                Item::Mod(m) => {
+                    self.item("mod", &m.ident, &m.attrs);
+                    if let Some((_, inner)) = &m.content {
+                        self.module.push(m.ident.to_string());
+                        self.items(inner);
+                        self.module.pop();
+                    }
+                }
+                Item::Impl(i) => self.impl_block(i),
+                _ => {}
+            }
+        }
+    }
+
+    fn impl_block(&mut self, block: &syn::ItemImpl) {
+        let self_type = type_name(&block.self_ty);
+        let trait_name = block
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|s| s.ident.to_string());
+        let name = match &trait_name {
+            Some(t) => format!("{t} for {self_type}"),
+            None => self_type.clone(),
+        };
+        let line = block.impl_token.span.start().line;
+        let mut parent = Parent { owner: None, trait_name, self_type: Some(self_type.clone()) };
+        self.push("impl", name, line, &block.attrs, &parent);
+        parent.owner = Some(self_type);
+        for item in &block.items {
+            if let ImplItem::Fn(f) = item {
+                let ident = &f.sig.ident;
+                self.push("method", ident.to_string(), ident.span().start().line, &f.attrs, &parent);
+            }
+        }
+    }
+}
+
+/// Parse one file into symbols; `rel` is its path relative to the indexed root.
+fn index_source(rel: &str, source: &str) -> Result<Vec<Symbol>, String> {
+    let result = match syn::parse_file(source) {
+        Ok(file) => {
+            let mut collector = Collector { file: rel, lines: source.lines().collect(), module: module_path(rel), out: Vec::new() };
+            collector.items(&file.items);
+            Ok(collector.out)
+        }
+        Err(e) => Err(format!("{rel}:{}: {e}", e.span().start().line)),
+    };
+    // Line information is kept per thread for every parsed file; release it.
+    proc_macro2::extra::invalidate_current_thread_spans();
+    result
+}
+
+struct CachedFile {
+    modified: Option<SystemTime>,
+    symbols: Result<Vec<Symbol>, String>,
+}
+
+type Cache = HashMap<(PathBuf, String), CachedFile>;
+
+struct Index {
+    symbols: Vec<Symbol>,
+    files: usize,
+    errors: Vec<String>,
+    truncated: bool,
+}
+
+/// Walk `root` and collect symbols, reparsing only files changed since the last call.
+fn build_index(root: &Path, cache: &Mutex<Cache>) -> Result<Index, String> {
+    let mut sources = Vec::new();
+    let opts = WalkOptions { max_depth: MAX_WALK_DEPTH, respect_gitignore: true };
+    walk::walk(root, &opts, |e| {
+        if e.kind == EntryKind::File
+            && e.rel.ends_with(".rs")
+            && e.size <= MAX_FILE_BYTES
+            && !e.rel.split('/').any(|c| c == "target")
+        {
+            sources.push((e.path.clone(), e.rel.clone()));
+        }
+        sources.len() <= MAX_FILES
+    })?;
+    let truncated = sources.len() > MAX_FILES;
+    sources.truncate(MAX_FILES);
+
+    let mut cache = cache.lock().map_err(|e| e.to_string())?;
+    if cache.len() > MAX_CACHED_FILES {
+        cache.clear();
+    }
+    let mut index = Index { symbols: Vec::new(), files: sources.len(), errors: Vec::new(), truncated };
+    for (path, rel) in sources {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let key = (root.to_path_buf(), rel);
+        let stale = cache.get(&key).is_none_or(|c| c.modified.is_none() || c.modified != modified);
+        if stale {
+            let symbols = match std::fs::read_to_string(&path) {
+                Ok(source) => index_source(&key.1, &source),
+                Err(e) => Err(format!("{}: {e}", key.1)),
+            };
+            cache.insert(key.clone(), CachedFile { modified, symbols });
+        }
+        match &cache[&key].symbols {
+            Ok(symbols) => index.symbols.extend(symbols.iter().cloned()),
+            Err(e) => index.errors.push(e.clone()),
+        }
+    }
+    Ok(index)
+}
+
+/// Last path segment without generics: `crate::a::Trait<T>` → `Trait`.
+fn bare_name(name: &str) -> &str {
+    let name = name.split('<').next().unwrap_or(name).trim();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Symbols answering one query, in file and line order.
+fn query(symbols: Vec<Symbol>, root: &Path, params: &Value) -> Result<Vec<Symbol>, String> {
+    let text = |key: &str| params.get(key).and_then(Value::as_str).map(str::trim).filter(|s| !s.is_empty());
+    let action = text("action").unwrap_or("find");
+    let mut found: Vec<Symbol> = match action {
+        "find" => {
+            let name = text("name").ok_or("find needs name")?;
+            let kind = text("kind");
+            let last = bare_name(name);
+            let qualified = name.contains("::").then(|| name.trim_start_matches("crate::"));
+            let of_kind = |s: &Symbol| kind.is_none_or(|k| s.kind == k);
+            let exact: Vec<Symbol> = symbols
+                .iter()
+                .filter(|s| s.name == last && qualified.is_none_or(|q| s.path.ends_with(q)) && of_kind(s))
+                .cloned()
+                .collect();
+            if exact.is_empty() {
+                let needle = last.to_lowercase();
+                symbols
+                    .into_iter()
+                    .filter(|s| s.kind != "impl" && s.name.to_lowercase().contains(&needle) && of_kind(s))
+                    .collect()
+            } else {
+                exact
+            }
+        }
+        "implementors" => {
+            let name = text("trait").ok_or("implementors needs trait")?;
+            let name = bare_name(name);
+            symbols
+                .into_iter()
+                .filter(|s| s.kind == "impl" && s.trait_name.as_deref() == Some(name))
+                .collect()
+        }
+        "impls" => {
+            let name = text("type").ok_or("impls needs type")?;
+            let name = bare_name(name);
+            symbols
+                .into_iter()
+                .filter(|s| matches!(s.kind, "impl" | "method") && s.self_type.as_deref() == Some(name))
+                .collect()
+        }
+        "outline" => {
+            let file = text("file").ok_or("outline needs file")?;
+            let rel = Path::new(file).strip_prefix(root).unwrap_or(Path::new(file));
+            let rel = rel.to_string_lossy();
+            let rel = rel.trim_start_matches("./");
+            symbols.into_iter().filter(|s| s.file == rel).collect()
+        }
+        other => return Err(format!("unknown action {other} (find, implementors, impls, outline)")),
+    };
+    found.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    Ok(found)
+}
+
+#[derive(Default)]
+pub struct RustSymbols {
+    files: Arc<FileAccess>,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl RustSymbols {
+    pub fn new(files: Arc<FileAccess>) -> Self {
+        Self { files, cache: Arc::default() }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for RustSymbols {
+    fn name(&self) -> &str { "rust_symbols" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["rust", "symbol", "defined", "definition", "implements", "impl", "trait", "定义", "实现"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileRead]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "rust_symbols".into(),
+            description: "Navigate Rust code without grepping: parses every .rs file under path and answers with \
+                          file:line, signature and doc summary. Actions: find (where is name defined; accepts \
+                          Type::method, falls back to substring matches), implementors (impls of a trait), \
+                          impls (impl blocks and methods of a type), outline (items in one file)."
+                .into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["find", "implementors", "impls", "outline"], "description": "Default: find" },
+                    "path": { "type": "string", "description": "Crate or workspace directory (default: current directory)" },
+                    "name": { "type": "string", "description": "find: item name, e.g. Scheduler or Scheduler::run" },
+                    "kind": {
+                        "type": "string",
+                        "enum": ["fn", "method", "trait_method", "struct", "enum", "union", "trait", "type",
+                                 "const", "static", "mod", "macro", "impl"],
+                        "description": "find: only this kind of item"
+                    },
+                    "trait": { "type": "string", "description": "implementors: trait name" },
+                    "type": { "type": "string", "description": "impls: type name" },
+                    "file": { "type": "string", "description": "outline: file path relative to path" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT, "description": "Result cap (default 50)" }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = request.params.clone();
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+
+        let dir = params.get("path").and_then(Value::as_str).unwrap_or(".");
+        let root = match self.files.policy().check_dir(dir) {
+            Ok(r) => r,
+            Err(e) => return fail(e),
+        };
+        let limit = params
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map(|n| (n as usize).clamp(1, MAX_LIMIT))
+            .unwrap_or(DEFAULT_LIMIT);
+
+        let cache = Arc::clone(&self.cache);
+        let answered = tokio::task::spawn_blocking(move || {
+            let index = build_index(&root, &cache)?;
+            let found = query(index.symbols, &root, &params)?;
+            Ok::<_, String>((root, index.files, index.errors, index.truncated, found))
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("rust_symbols task failed: {e}")));
+
+        match answered {
+            Ok((root, files, errors, files_truncated, found)) => CapabilityResponse {
+                id: request.id,
+                result: Some(json!({
+                    "root": root.display().to_string(),
+                    "files_indexed": files,
+                    "files_truncated": files_truncated,
+                    "parse_errors": errors,
+                    "count": found.len(),
+                    "truncated": found.len() > limit,
+                    "results": &found[..found.len().min(limit)],
+                })),
+                error: None,
+                metrics: None,
+                side_effects: vec![Permission::FileRead],
+            },
+            Err(e) => fail(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    const LIB: &str = r#"//! Shapes.
+pub mod shapes;
+
+/// Something with an area.
+///
+/// Longer explanation.
+pub trait Area {
+    fn area(&self) -> f64;
+}
+
+mod inner {
+    pub fn helper() {}
+}
+"#;
+
+    const SHAPES: &str = r#"use crate::Area;
+
+/// A square.
+pub struct Square(pub f64);
+
+impl Square {
+    pub fn new(side: f64)
+        -> Self {
+        Self(side)
+    }
+}
+
+impl Area for Square {
+    fn area(&self) -> f64 { self.0 * self.0 }
+}
+
+impl<T: Area> Area for Box<T> {
+    fn area(&self) -> f64 { (**self).area() }
+}
+"#;
+
+    fn krate() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), LIB).unwrap();
+        std::fs::write(dir.path().join("src/shapes.rs"), SHAPES).unwrap();
+        std::fs::write(dir.path().join("src/broken.rs"), "fn oops( {").unwrap();
+        dir
+    }
+
+    async fn call(tool: &RustSymbols, dir: &Path, mut params: Value) -> Value {
+        params["path"] = json!(dir);
+        let request = CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 };
+        let resp = tool.execute(request).await;
+        resp.result.unwrap_or_else(|| panic!("{:?}", resp.error))
+    }
+
+    #[tokio::test]
+    async fn finds_definitions_with_locations_signatures_and_docs() {
+        let dir = krate();
+        let tool = RustSymbols::default();
+
+        let trait_def = call(&tool, dir.path(), json!({ "name": "Area", "kind": "trait" })).await;
+        assert_eq!(trait_def["results"][0]["location"], "src/lib.rs:7");
+        assert_eq!(trait_def["results"][0]["doc"], "Something with an area.");
+        assert_eq!(trait_def["results"][0]["signature"], "pub trait Area");
+        assert!(trait_def["parse_errors"][0].as_str().unwrap().starts_with("src/broken.rs:1"));
+
+        let new = call(&tool, dir.path(), json!({ "name": "Square::new" })).await;
+        assert_eq!(new["count"], 1);
+        assert_eq!(new["results"][0]["path"], "shapes::Square::new");
+        assert_eq!(new["results"][0]["signature"], "pub fn new(side: f64) -> Self");
+
+        let helper = call(&tool, dir.path(), json!({ "name": "help" })).await;
+        assert_eq!(helper["results"][0]["path"], "inner::helper");
+    }
+
+    #[tokio::test]
+    async fn answers_trait_and_type_queries() {
+        let dir = krate();
+        let tool = RustSymbols::default();
+
+        let impls = call(&tool, dir.path(), json!({ "action": "implementors", "trait": "crate::Area" })).await;
+        let names: Vec<&str> = impls["results"].as_array().unwrap().iter().filter_map(|r| r["name"].as_str()).collect();
+        assert_eq!(names, ["Area for Square", "Area for Box"]);
+        assert_eq!(impls["results"][0]["location"], "src/shapes.rs:13");
+
+        let square = call(&tool, dir.path(), json!({ "action": "impls", "type": "Square" })).await;
+        assert_eq!(square["count"], 4);
+
+        let outline = call(&tool, dir.path(), json!({ "action": "outline", "file": "src/lib.rs" })).await;
+        let kinds: Vec<&str> = outline["results"].as_array().unwrap().iter().filter_map(|r| r["kind"].as_str()).collect();
+        assert_eq!(kinds, ["mod", "trait", "trait_method", "mod", "fn"]);
+    }
+
+    #[tokio::test]
+    async fn changed_files_are_reindexed() {
+        let dir = krate();
+        let tool = RustSymbols::default();
+        assert_eq!(call(&tool, dir.path(), json!({ "name": "circle" })).await["count"], 0);
+
+        let file = dir.path().join("src/shapes.rs");
+        std::fs::write(&file, format!("{SHAPES}\npub struct Circle;\n")).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(later).unwrap();
+        assert_eq!(call(&tool, dir.path(), json!({ "name": "circle" })).await["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn indexes_this_crate() {
+        let tool = RustSymbols::default();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let found = call(&tool, dir, json!({ "action": "implementors", "trait": "BuiltinCapability", "limit": 500 })).await;
+        let locations: Vec<&str> = found["results"].as_array().unwrap().iter().filter_map(|r| r["location"].as_str()).collect();
+        assert!(locations.iter().any(|l| l.starts_with("src/capability/builtin/rust_symbols.rs:")));
+        assert_eq!(found["parse_errors"], json!([]));
+    }
+}
@@ -0,0 +1,337 @@
+//! Sandbox profiles for `run_bash`.
+//!
+//! - `off`: the command runs with iris's own privileges (legacy behaviour).
+//! - `standard`: working-directory jail, rlimits and a scrubbed environment.
+//! - `strict`: standard plus network and mount namespaces via `unshare`, when available.
+//!
+//! Safe mode always escalates to `strict`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+use crate::config::IrisCfg;
+
+/// Environment variables passed through a scrubbed environment.
+const ENV_ALLOWLIST: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_ALL", "LC_CTYPE", "TERM", "TZ",
+];
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxProfile {
+    Off,
+    Standard,
+    Strict,
+}
+
+impl SandboxProfile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Standard => "standard",
+            Self::Strict => "strict",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "off" => Some(Self::Off),
+            "standard" => Some(Self::Standard),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// Resource limits applied with `setrlimit` in the child before exec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// 0 leaves CPU time unlimited.
+    pub cpu_secs: u64,
+    pub memory_mb: u64,
+    pub file_size_mb: u64,
+    pub max_procs: u64,
+}
+
+/// Sandbox settings from config; shared by the registry and `RunBash`.
+#[derive(Debug)]
+pub struct SandboxState {
+    configured: RwLock<SandboxProfile>,
+    limits: RwLock<Limits>,
+    workdir: RwLock<Option<PathBuf>>,
+    /// CPU limit for background jobs, which are expected to outlive `bash_cpu_secs`.
+    job_cpu_secs: AtomicU64,
+    safe_mode: AtomicBool,
+}
+
+impl Default for SandboxState {
+    fn default() -> Self {
+        Self {
+            configured: RwLock::new(SandboxProfile::Off),
+            limits: RwLock::new(Limits::from_cfg(&IrisCfg::default())),
+            workdir: RwLock::new(None),
+            job_cpu_secs: AtomicU64::new(IrisCfg::default().job_cpu_secs),
+            safe_mode: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Limits {
+    pub fn from_cfg(cfg: &IrisCfg) -> Self {
+        Self {
+            cpu_secs: cfg.bash_cpu_secs,
+            memory_mb: cfg.bash_memory_mb,
+            file_size_mb: cfg.bash_file_size_mb,
+            max_procs: cfg.bash_max_procs,
+        }
+    }
+}
+
+impl SandboxState {
+    /// Apply the `bash_sandbox*` config keys. Unknown profile names fall back to `standard`.
+    pub fn configure(&self, cfg: &IrisCfg) {
+        let profile = SandboxProfile::parse(&cfg.bash_sandbox).unwrap_or_else(|| {
+            tracing::warn!(value = %cfg.bash_sandbox, "unknown bash_sandbox profile, using standard");
+            SandboxProfile::Standard
+        });
+        let workdir = (!cfg.bash_sandbox_workdir.is_empty())
+            .then(|| PathBuf::from(&cfg.bash_sandbox_workdir));
+        if let Ok(mut p) = self.configured.write() {
+            *p = profile;
+        }
+        if let Ok(mut l) = self.limits.write() {
+            *l = Limits::from_cfg(cfg);
+        }
+        if let Ok(mut w) = self.workdir.write() {
+            *w = workdir;
+        }
+        self.job_cpu_secs.store(cfg.job_cpu_secs, Ordering::Relaxed);
+    }
+
+    pub fn set_safe_mode(&self, active: bool) {
+        self.safe_mode.store(active, Ordering::Relaxed);
+    }
+
+    /// Profile in force: the configured one, or `strict` while in safe mode.
+    pub fn profile(&self) -> SandboxProfile {
+        if self.safe_mode.load(Ordering::Relaxed) {
+            return SandboxProfile::Strict;
+        }
+        self.configured
+            .read()
+            .map(|p| *p)
+            .unwrap_or(SandboxProfile::Standard)
+    }
+
+    /// Resolve the effective settings for one command.
+    pub fn policy(&self) -> SandboxPolicy {
+        let profile = self.profile();
+        let workdir = self
+            .workdir
+            .read()
+            .ok()
+            .and_then(|w| w.clone())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("/"));
+        SandboxPolicy {
+            profile,
+            workdir,
+            limits: self
+                .limits
+                .read()
+                .map(|l| *l)
+                .unwrap_or(Limits::from_cfg(&IrisCfg::default())),
+            isolate: profile == SandboxProfile::Strict && namespaces_available(),
+        }
+    }
+
+    /// Settings for a background job: like [`Self::policy`] but with the job CPU limit.
+    pub fn job_policy(&self) -> SandboxPolicy {
+        let mut policy = self.policy();
+        policy.limits.cpu_secs = self.job_cpu_secs.load(Ordering::Relaxed);
+        policy
+    }
+}
+
+/// Effective sandbox for a single `run_bash` invocation.
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    pub profile: SandboxProfile,
+    pub workdir: PathBuf,
+    pub limits: Limits,
+    /// Run inside fresh network and mount namespaces.
+    pub isolate: bool,
+}
+
+impl SandboxPolicy {
+    /// Resolve a requested working directory; it must stay inside the jail.
+    pub fn resolve_cwd(&self, requested: Option<&str>) -> Result<PathBuf, String> {
+        let jail = self
+            .workdir
+            .canonicalize()
+            .unwrap_or_else(|_| self.workdir.clone());
+        let Some(requested) = requested else {
+            return Ok(jail);
+        };
+        let candidate = jail.join(requested);
+        let resolved = candidate
+            .canonicalize()
+            .map_err(|e| format!("cwd {requested} is not accessible: {e}"))?;
+        if self.profile != SandboxProfile::Off && !resolved.starts_with(&jail) {
+            return Err(format!(
+                "cwd {} is outside the sandbox working directory {}",
+                resolved.display(),
+                jail.display()
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Build the command for `script`, applying this profile.
+    pub fn command(&self, script: &str, cwd: &Path) -> tokio::process::Command {
+        let mut cmd = if self.isolate {
+            // Private /tmp inside the new mount namespace; the network namespace has only lo.
+            let mut c = tokio::process::Command::new("unshare");
+            c.args(["--user", "--map-root-user", "--net", "--mount", "--fork", "--", "bash", "-c"])
+                .arg("mount -t tmpfs tmpfs /tmp 2>/dev/null; ip link set lo up 2>/dev/null; exec bash -c \"$0\"")
+                .arg(script);
+            c
+        } else {
+            let mut c = tokio::process::Command::new("bash");
+            c.arg("-c").arg(script);
+            c
+        };
+        cmd.current_dir(cwd).kill_on_drop(true);
+        if self.profile == SandboxProfile::Off {
+            return cmd;
+        }
+
+        cmd.env_clear();
+        for key in ENV_ALLOWLIST {
+            if let Ok(value) = std::env::var(key) {
+                cmd.env(key, value);
+            }
+        }
+        if std::env::var_os("PATH").is_none() {
+            cmd.env("PATH", DEFAULT_PATH);
+        }
+        cmd.env("IRIS_SANDBOX", self.profile.as_str());
+
+        #[cfg(unix)]
+        {
+            let limits = self.limits;
+            // SAFETY: only async-signal-safe setrlimit calls between fork and exec.
+            unsafe {
+                cmd.pre_exec(move || {
+                    let mb = 1024 * 1024;
+                    if limits.cpu_secs > 0 {
+                        set_rlimit(libc::RLIMIT_CPU, limits.cpu_secs)?;
+                    }
+                    set_rlimit(libc::RLIMIT_AS, limits.memory_mb * mb)?;
+                    set_rlimit(libc::RLIMIT_FSIZE, limits.file_size_mb * mb)?;
+                    set_rlimit(libc::RLIMIT_NPROC, limits.max_procs)?;
+                    Ok(())
+                });
+            }
+        }
+        cmd
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, value: u64) -> std::io::Result<()> {
+    let rlim = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    // SAFETY: plain syscall with a valid pointer.
+    if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Whether unprivileged user/network/mount namespaces work here (probed once).
+pub fn namespaces_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        std::process::Command::new("unshare")
+            .args([
+                "--user",
+                "--map-root-user",
+                "--net",
+                "--mount",
+                "--fork",
+                "true",
+            ])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(profile: &str, workdir: &Path) -> IrisCfg {
+        IrisCfg {
+            bash_sandbox: profile.into(),
+            bash_sandbox_workdir: workdir.to_string_lossy().into_owned(),
+            ..IrisCfg::default()
+        }
+    }
+
+    #[test]
+    fn safe_mode_escalates_to_strict() {
+        let state = SandboxState::default();
+        state.configure(&cfg("standard", Path::new("/tmp")));
+        assert_eq!(state.profile(), SandboxProfile::Standard);
+        state.set_safe_mode(true);
+        assert_eq!(state.profile(), SandboxProfile::Strict);
+        state.set_safe_mode(false);
+        state.configure(&cfg("bogus", Path::new("/tmp")));
+        assert_eq!(state.profile(), SandboxProfile::Standard);
+    }
+
+    #[test]
+    fn jobs_get_their_own_cpu_limit() {
+        let state = SandboxState::default();
+        state.configure(&IrisCfg { job_cpu_secs: 3600, ..cfg("standard", Path::new("/tmp")) });
+        let bash = state.policy();
+        let job = state.job_policy();
+        assert_eq!(bash.limits.cpu_secs, 30);
+        assert_eq!(job.limits.cpu_secs, 3600);
+        assert_eq!(Limits { cpu_secs: 30, ..job.limits }, bash.limits);
+        assert_eq!(job.profile, bash.profile);
+
+        state.configure(&cfg("standard", Path::new("/tmp")));
+        assert_eq!(state.job_policy().limits.cpu_secs, 0);
+    }
+
+    #[test]
+    fn cwd_must_stay_inside_jail() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let state = SandboxState::default();
+        state.configure(&cfg("standard", dir.path()));
+        let policy = state.policy();
+        let jail = dir.path().canonicalize().unwrap();
+        assert_eq!(policy.resolve_cwd(None).unwrap(), jail);
+        assert_eq!(policy.resolve_cwd(Some("sub")).unwrap(), jail.join("sub"));
+        assert!(
+            policy
+                .resolve_cwd(Some(".."))
+                .unwrap_err()
+                .contains("outside")
+        );
+        assert!(policy.resolve_cwd(Some("/")).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use uuid::Uuid;
+
+use super::DbHandle;
+use crate::schedule::cron::Recurrence;
+use crate::schedule::store::{self, Schedule, ScheduleKind};
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const MAX_MESSAGE_CHARS: usize = 1000;
+/// Furthest ahead `in_minutes` may point (one year).
+const MAX_IN_MINUTES: u64 = 525_600;
+
+pub struct ScheduleReminder {
+    db: Arc<DbHandle>,
+}
+
+impl ScheduleReminder {
+    pub fn new(db: Arc<DbHandle>) -> Self {
+        Self { db }
+    }
+}
+
+/// Parse an absolute time: RFC 3339, `YYYY-MM-DD HH:MM` (local) or `HH:MM`
+/// (the next such local time, today or tomorrow).
+fn parse_at(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let text = text.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+            .ok_or_else(|| format!("{text} does not exist in local time"))
+    };
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return local(naive);
+        }
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+        let today = now.with_timezone(&Local).date_naive();
+        let candidate = local(today.and_time(time))?;
+        return if candidate > now { Ok(candidate) } else { local((today + Duration::days(1)).and_time(time)) };
+    }
+    Err(format!(
+        "cannot parse time '{text}'; use RFC 3339, YYYY-MM-DD HH:MM or HH:MM (local time)"
+    ))
+}
+
+/// Build the schedule described by the tool parameters.
+fn plan(params: &serde_json::Value, now: DateTime<Utc>) -> Result<Schedule, String> {
+    let message = params.get("message").and_then(|v| v.as_str()).map(str::trim).unwrap_or_default();
+    if message.is_empty() {
+        return Err("missing message".into());
+    }
+    if message.chars().count() > MAX_MESSAGE_CHARS {
+        return Err(format!("message is too long (max {MAX_MESSAGE_CHARS} characters)"));
+    }
+    let kind = match params.get("kind").and_then(|v| v.as_str()).unwrap_or("reminder") {
+        "task" => ScheduleKind::Task,
+        _ => ScheduleKind::Reminder,
+    };
+    let recurrence = params.get("recurrence").and_then(|v| v.as_str()).map(str::trim);
+    let rule = recurrence.map(Recurrence::parse).transpose()?;
+
+    let explicit = match (params.get("at").and_then(|v| v.as_str()), params.get("in_minutes").and_then(|v| v.as_u64())) {
+        (Some(_), Some(_)) => return Err("use either at or in_minutes, not both".into()),
+        (Some(at), None) => Some(parse_at(at, now)?),
+        (None, Some(mins)) => Some(now + Duration::minutes(mins.clamp(1, MAX_IN_MINUTES) as i64)),
+        (None, None) => None,
+    };
+    let next_run = match (explicit, &rule) {
+        (Some(t), _) if t <= now => {
+            return Err(format!("{} is in the past", t.with_timezone(&Local).format("%Y-%m-%d %H:%M")));
+        }
+        (Some(t), _) => t,
+        (None, Some(rule)) => rule.next_after(now).ok_or("recurrence never matches")?,
+        (None, None) => return Err("say when: at, in_minutes or recurrence".into()),
+    };
+    Ok(Schedule {
+        id: Uuid::new_v4(),
+        kind,
+        message: message.to_string(),
+        next_run,
+        recurrence: recurrence.map(String::from),
+        last_run: None,
+        created_at: now,
+    })
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for ScheduleReminder {
+    fn name(&self) -> &str { "schedule_reminder" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["remind", "reminder", "timer", "schedule", "later", "every", "提醒", "定时", "闹钟"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "schedule_reminder".into(),
+            description: "Schedule a reminder or a future task. kind=reminder tells the user the message when due; \
+                          kind=task hands the message back to you as an instruction to act on. Give the time as \
+                          at (RFC 3339, \"YYYY-MM-DD HH:MM\" or \"HH:MM\", local time) or in_minutes, and/or a \
+                          recurrence (5-field cron like \"0 9 * * 1-5\", @hourly, @daily, @weekly, or \"@every 30m\"). \
+                          The user can list and cancel schedules with /schedules."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string", "minLength": 1, "maxLength": MAX_MESSAGE_CHARS, "description": "What to remind about or do" },
+                    "at": { "type": "string", "minLength": 4, "description": "When, in local time" },
+                    "in_minutes": { "type": "integer", "minimum": 1, "maximum": MAX_IN_MINUTES },
+                    "recurrence": { "type": "string", "minLength": 1, "description": "Cron expression or @hourly/@daily/@weekly/@monthly/@every <n><s|m|h|d>" },
+                    "kind": { "type": "string", "enum": ["reminder", "task"], "description": "Default: reminder" }
+                },
+                "required": ["message"],
+                "anyOf": [
+                    { "required": ["at"] },
+                    { "required": ["in_minutes"] },
+                    { "required": ["recurrence"] }
+                ],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+        let schedule = match plan(&request.params, Utc::now()) {
+            Ok(s) => s,
+            Err(e) => return fail(e),
+        };
+        let Some(pool) = self.db.pool() else {
+            return fail("schedules need a database (DATABASE_URL is not connected)".into());
+        };
+        if let Err(e) = store::create(&pool, &schedule).await {
+            return fail(format!("failed to save schedule: {e}"));
+        }
+        CapabilityResponse {
+            id: request.id,
+            result: Some(serde_json::json!({
+                "id": schedule.short_id(),
+                "kind": schedule.kind.as_db_str(),
+                "message": schedule.message,
+                "next_run": schedule.next_run.with_timezone(&Local).to_rfc3339(),
+                "recurrence": schedule.recurrence,
+            })),
+            error: None,
+            metrics: None,
+            side_effects: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).earliest().unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_absolute_and_clock_times() {
+        let now = local(2026, 10, 18, 15, 0);
+        assert_eq!(parse_at("2026-10-20 09:30", now).unwrap(), local(2026, 10, 20, 9, 30));
+        assert_eq!(parse_at("16:00", now).unwrap(), local(2026, 10, 18, 16, 0));
+        // A clock time already past today means tomorrow.
+        assert_eq!(parse_at("09:00", now).unwrap(), local(2026, 10, 19, 9, 0));
+        assert_eq!(parse_at("2026-10-18T12:00:00Z", now).unwrap().to_rfc3339(), "2026-10-18T12:00:00+00:00");
+        assert!(parse_at("tomorrow-ish", now).is_err());
+    }
+
+    #[test]
+    fn plans_one_shot_and_recurring_schedules() {
+        let now = local(2026, 10, 18, 15, 0);
+        let s = plan(&serde_json::json!({ "message": "tea", "in_minutes": 5 }), now).unwrap();
+        assert_eq!((s.kind, s.next_run, s.recurrence), (ScheduleKind::Reminder, now + Duration::minutes(5), None));
+
+        let s = plan(&serde_json::json!({ "message": "check CI", "kind": "task", "recurrence": "0 9 * * *" }), now).unwrap();
+        assert_eq!((s.kind, s.next_run), (ScheduleKind::Task, local(2026, 10, 19, 9, 0)));
+
+        let err = plan(&serde_json::json!({ "message": "x", "at": "2020-01-01 00:00" }), now).unwrap_err();
+        assert!(err.contains("in the past"));
+        assert!(plan(&serde_json::json!({ "message": "x", "recurrence": "bogus" }), now).is_err());
+        assert!(plan(&serde_json::json!({ "message": "x" }), now).unwrap_err().contains("say when"));
+    }
+}
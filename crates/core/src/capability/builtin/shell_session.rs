@@ -0,0 +1,461 @@
+//! Long-lived bash sessions for `shell_session`: `cd`, exported variables and
+//! activated virtualenvs carry over between calls, unlike `run_bash`.
+//!
+//! Each session is one `bash` process with piped stdin/stdout (stderr is merged
+//! into stdout). A command is written followed by a unique sentinel line that
+//! reports the exit status and the new working directory; output is captured up
+//! to that line. A command that times out kills its session, since the shell
+//! state is unknown afterwards.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+use super::sandbox::SandboxState;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const DEFAULT_SESSION: &str = "default";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_TIMEOUT_SECS: u64 = 600;
+const MAX_SESSIONS: usize = 8;
+const MAX_OUTPUT: usize = 64 * 1024;
+const SENTINEL_PREFIX: &str = "__IRIS_SHELL_DONE_";
+
+/// One live bash process.
+struct Shell {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    cwd: String,
+    sandbox: &'static str,
+}
+
+/// Outcome of one command in a session.
+#[derive(Debug)]
+struct RunOutput {
+    output: String,
+    exit_code: i32,
+    truncated: bool,
+}
+
+impl Shell {
+    async fn spawn(sandbox: &SandboxState) -> Result<Self, String> {
+        let policy = sandbox.policy();
+        let cwd = policy.resolve_cwd(None)?;
+        let mut cmd = policy.command("exec bash --noprofile --norc", &cwd);
+        cmd.stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null());
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("failed to start shell: {e}"))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err("failed to open shell pipes".into());
+        };
+        let mut shell = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            cwd: cwd.display().to_string(),
+            sandbox: policy.profile.as_str(),
+        };
+        shell.write("exec 2>&1\n").await?;
+        Ok(shell)
+    }
+
+    async fn write(&mut self, text: &str) -> Result<(), String> {
+        self.stdin
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|e| format!("shell is gone: {e}"))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| format!("shell is gone: {e}"))
+    }
+
+    /// Run `command` in the shell's current state and capture output up to the sentinel.
+    async fn run(&mut self, command: &str) -> Result<RunOutput, String> {
+        let sentinel = format!("{SENTINEL_PREFIX}{}__", uuid::Uuid::new_v4().simple());
+        // Braces keep `cd`/`export` in this shell; stdin is detached so commands
+        // cannot swallow the sentinel line.
+        let script = format!(
+            "{{\n{command}\n}} < /dev/null\nprintf '\\n{sentinel} %d %s\\n' \"$?\" \"$PWD\"\n"
+        );
+        self.write(&script).await?;
+
+        let mut output = String::new();
+        let mut truncated = false;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = self
+                .stdout
+                .read_line(&mut line)
+                .await
+                .map_err(|e| format!("failed to read shell output: {e}"))?;
+            if n == 0 {
+                return Err("shell exited".into());
+            }
+            if let Some(rest) = line.strip_prefix(&sentinel) {
+                let mut parts = rest.trim().splitn(2, ' ');
+                let exit_code = parts.next().and_then(|c| c.parse().ok()).unwrap_or(-1);
+                if let Some(cwd) = parts.next() {
+                    self.cwd = cwd.to_string();
+                }
+                // The sentinel printf starts with a newline of its own.
+                if output.ends_with('\n') {
+                    output.pop();
+                }
+                return Ok(RunOutput {
+                    output,
+                    exit_code,
+                    truncated,
+                });
+            }
+            if output.len() + line.len() <= MAX_OUTPUT {
+                output.push_str(&line);
+            } else {
+                truncated = true;
+            }
+        }
+    }
+
+    /// Exported environment of the shell, as `NAME=value` pairs.
+    async fn env(&mut self) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let out = self.run("env").await?;
+        Ok(out
+            .output
+            .lines()
+            .filter_map(|l| l.split_once('='))
+            .filter(|(k, _)| {
+                !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+            .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string())))
+            .collect())
+    }
+}
+
+impl Drop for Shell {
+    fn drop(&mut self) {
+        let _ = self.child.start_kill();
+    }
+}
+
+#[derive(Default)]
+pub struct ShellSession {
+    sandbox: Arc<SandboxState>,
+    sessions: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Shell>>>>,
+}
+
+impl ShellSession {
+    pub fn new(sandbox: Arc<SandboxState>) -> Self {
+        Self {
+            sandbox,
+            sessions: Mutex::default(),
+        }
+    }
+
+    /// Existing session, or a fresh one when `name` is not open yet.
+    async fn session(&self, name: &str) -> Result<(Arc<tokio::sync::Mutex<Shell>>, bool), String> {
+        if let Some(shell) = self
+            .sessions
+            .lock()
+            .map_err(|_| "session table poisoned")?
+            .get(name)
+        {
+            return Ok((Arc::clone(shell), false));
+        }
+        let shell = Arc::new(tokio::sync::Mutex::new(Shell::spawn(&self.sandbox).await?));
+        let mut sessions = self.sessions.lock().map_err(|_| "session table poisoned")?;
+        if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(name) {
+            return Err(format!(
+                "too many open shell sessions ({MAX_SESSIONS}); close one first"
+            ));
+        }
+        let entry = sessions.entry(name.to_string()).or_insert(shell);
+        Ok((Arc::clone(entry), true))
+    }
+
+    fn remove(&self, name: &str) -> bool {
+        self.sessions
+            .lock()
+            .map(|mut s| s.remove(name).is_some())
+            .unwrap_or(false)
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for ShellSession {
+    fn name(&self) -> &str { "shell_session" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["shell", "session", "cd", "export", "venv", "activate", "终端", "会话"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::ProcessSpawn]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "shell_session".into(),
+            description: "Run a command in a persistent bash session where cd, exported variables and \
+                          activated virtualenvs carry over between calls. Output is stdout and stderr combined. \
+                          action: run (default), status (cwd and environment), reset (fresh shell) or close. \
+                          A timed-out command kills its session."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["run", "status", "reset", "close"] },
+                    "command": { "type": "string", "minLength": 1, "description": "Command to run (action run)" },
+                    "session": { "type": "string", "minLength": 1, "description": "Session name (default: \"default\")" },
+                    "timeout_secs": { "type": "integer", "minimum": 1, "maximum": MAX_TIMEOUT_SECS, "description": "Per-command timeout (default 30)" }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let fail = |error: String| CapabilityResponse {
+            id: request.id,
+            result: None,
+            error: Some(error),
+            metrics: None,
+            side_effects: vec![],
+        };
+        let ok = |result: serde_json::Value, error: Option<String>| CapabilityResponse {
+            id: request.id,
+            result: Some(result),
+            error,
+            metrics: None,
+            side_effects: vec![Permission::ProcessSpawn],
+        };
+
+        let name = params
+            .get("session")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_SESSION);
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("run");
+        match action {
+            "close" => {
+                let closed = self.remove(name);
+                return ok(
+                    serde_json::json!({ "session": name, "closed": closed }),
+                    None,
+                );
+            }
+            "reset" => {
+                self.remove(name);
+                return match self.session(name).await {
+                    Ok((shell, _)) => {
+                        let shell = shell.lock().await;
+                        ok(
+                            serde_json::json!({ "session": name, "reset": true, "cwd": shell.cwd, "sandbox": shell.sandbox }),
+                            None,
+                        )
+                    }
+                    Err(e) => fail(e),
+                };
+            }
+            "status" => {
+                let (shell, _) = match self.session(name).await {
+                    Ok(s) => s,
+                    Err(e) => return fail(e),
+                };
+                let mut shell = shell.lock().await;
+                let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
+                return match tokio::time::timeout(timeout, shell.env()).await {
+                    Ok(Ok(env)) => ok(
+                        serde_json::json!({
+                            "session": name,
+                            "cwd": shell.cwd,
+                            "sandbox": shell.sandbox,
+                            "env": env,
+                        }),
+                        None,
+                    ),
+                    Ok(Err(e)) => {
+                        drop(shell);
+                        self.remove(name);
+                        fail(format!("session {name} failed: {e}"))
+                    }
+                    Err(_) => {
+                        drop(shell);
+                        self.remove(name);
+                        fail(format!("session {name} stopped responding and was closed"))
+                    }
+                };
+            }
+            "run" => {}
+            other => {
+                return fail(format!(
+                    "unknown action {other}; use run, status, reset or close"
+                ));
+            }
+        }
+
+        let command = params
+            .get("command")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or_else(|| super::run_bash::extract_command(&request.method));
+        let Some(command) = command else {
+            return fail("could not extract command from input".into());
+        };
+        let timeout_secs = params
+            .get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
+
+        let (shell, started) = match self.session(name).await {
+            Ok(s) => s,
+            Err(e) => return fail(e),
+        };
+        let mut shell = shell.lock().await;
+        match tokio::time::timeout(Duration::from_secs(timeout_secs), shell.run(&command)).await {
+            Ok(Ok(out)) => {
+                let error = (out.exit_code != 0).then(|| {
+                    let preview = out
+                        .output
+                        .lines()
+                        .map(str::trim)
+                        .find(|l| !l.is_empty())
+                        .unwrap_or("no output");
+                    format!("command exited with code {}: {preview}", out.exit_code)
+                });
+                ok(
+                    serde_json::json!({
+                        "session": name,
+                        "command": command,
+                        "output": out.output,
+                        "exit_code": out.exit_code,
+                        "truncated": out.truncated,
+                        "cwd": shell.cwd,
+                        "sandbox": shell.sandbox,
+                        "new_session": started,
+                    }),
+                    error,
+                )
+            }
+            Ok(Err(e)) => {
+                drop(shell);
+                self.remove(name);
+                fail(format!(
+                    "session {name} ended: {e}; the next command starts a fresh shell"
+                ))
+            }
+            Err(_) => {
+                drop(shell);
+                self.remove(name);
+                fail(format!(
+                    "command timed out after {timeout_secs}s; session {name} was killed and the next command starts a fresh shell"
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+
+    async fn call(cap: &ShellSession, params: serde_json::Value) -> CapabilityResponse {
+        cap.execute(CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: String::new(),
+            params,
+            version: 1,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn state_persists_between_commands() {
+        let cap = ShellSession::default();
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().canonicalize().unwrap();
+
+        let resp = call(&cap, serde_json::json!({ "command": format!("cd {} && export GREETING=hi", dir_path.display()) })).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        assert_eq!(resp.result.unwrap()["new_session"], true);
+
+        let resp = call(
+            &cap,
+            serde_json::json!({ "command": "pwd; echo $GREETING; printf no-newline" }),
+        )
+        .await;
+        let result = resp.result.unwrap();
+        assert_eq!(
+            result["output"],
+            format!("{}\nhi\nno-newline", dir_path.display())
+        );
+        assert_eq!(result["cwd"], dir_path.display().to_string());
+        assert_eq!(result["new_session"], false);
+
+        let resp = call(&cap, serde_json::json!({ "action": "status" })).await;
+        assert_eq!(resp.result.unwrap()["env"]["GREETING"], "hi");
+
+        let resp = call(
+            &cap,
+            serde_json::json!({ "command": "echo oops >&2; false" }),
+        )
+        .await;
+        assert!(resp.error.unwrap().contains("exited with code 1: oops"));
+    }
+
+    #[tokio::test]
+    async fn reset_close_and_timeout_start_fresh() {
+        let cap = ShellSession::default();
+        call(
+            &cap,
+            serde_json::json!({ "command": "export X=1", "session": "a" }),
+        )
+        .await;
+        call(
+            &cap,
+            serde_json::json!({ "action": "reset", "session": "a" }),
+        )
+        .await;
+        let resp = call(
+            &cap,
+            serde_json::json!({ "command": "echo \"[$X]\"", "session": "a" }),
+        )
+        .await;
+        assert_eq!(resp.result.unwrap()["output"], "[]\n");
+
+        let resp = call(
+            &cap,
+            serde_json::json!({ "action": "close", "session": "a" }),
+        )
+        .await;
+        assert_eq!(resp.result.unwrap()["closed"], true);
+
+        let resp = call(
+            &cap,
+            serde_json::json!({ "command": "sleep 5", "timeout_secs": 1 }),
+        )
+        .await;
+        assert!(resp.error.unwrap().contains("timed out"));
+        let resp = call(&cap, serde_json::json!({ "command": "echo back" })).await;
+        let result = resp.result.unwrap();
+        assert_eq!(result["output"], "back\n");
+        assert_eq!(result["new_session"], true);
+
+        let resp = call(&cap, serde_json::json!({ "command": "exit 3" })).await;
+        assert!(resp.error.unwrap().contains("ended"));
+    }
+}
@@ -0,0 +1,491 @@
+//! Undo journal for file changes made by builtins.
+//!
+//! `write_file` and `edit_file` go through `UndoJournal::write`: the previous
+//! content is kept as a blob named by its SHA-256, the new content is written
+//! atomically (temp file plus rename), and an entry records path, hashes, time,
+//! tool and originating event. `undo_write` and `/undo` restore the previous
+//! version, or remove the file if the write created it.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::capability::path_policy::{self, FileAccess, PathPolicy};
+use crate::config::IrisCfg;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+const DEFAULT_MAX_ENTRIES: usize = 500;
+const INDEX_FILE: &str = "journal.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub path: PathBuf,
+    pub tool: String,
+    /// Event being processed when the write happened.
+    pub event_id: Option<Uuid>,
+    pub at: DateTime<Utc>,
+    /// Hash of the content before the write; `None` if the file did not exist.
+    pub previous: Option<String>,
+    pub current: String,
+    pub undone: bool,
+}
+
+impl JournalEntry {
+    /// Short id shown in `/undo list` and accepted by `/undo <id>`.
+    pub fn short_id(&self) -> String {
+        self.id.simple().to_string()[..8].to_string()
+    }
+}
+
+#[derive(Debug)]
+struct Settings {
+    dir: PathBuf,
+    retention: Duration,
+    max_entries: usize,
+}
+
+/// Journal of file writes, stored under a directory as an index plus content blobs.
+#[derive(Debug)]
+pub struct UndoJournal {
+    settings: RwLock<Settings>,
+    entries: Mutex<Vec<JournalEntry>>,
+    origin: RwLock<Option<Uuid>>,
+}
+
+impl Default for UndoJournal {
+    fn default() -> Self {
+        Self {
+            settings: RwLock::new(Settings {
+                dir: path_policy::data_dir("undo"),
+                retention: Duration::days(DEFAULT_RETENTION_DAYS),
+                max_entries: DEFAULT_MAX_ENTRIES,
+            }),
+            entries: Mutex::new(Vec::new()),
+            origin: RwLock::new(None),
+        }
+    }
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Write `bytes` to `path` via a temp file in the same directory and a rename,
+/// keeping the permissions of an existing file.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    write_atomic_with(path, bytes, false)
+}
+
+/// `write_atomic` for the journal's own files, which are readable only by the owner.
+fn write_atomic_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    write_atomic_with(path, bytes, true)
+}
+
+fn write_atomic_with(path: &Path, bytes: &[u8], private: bool) -> std::io::Result<()> {
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = dir.join(format!(".{name}.iris-tmp-{}", Uuid::new_v4().simple()));
+    let result = (|| {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        if !private && let Ok(meta) = std::fs::metadata(path) {
+            std::fs::set_permissions(&tmp, meta.permissions())?;
+        }
+        std::fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+impl UndoJournal {
+    /// Apply the journal directory and retention settings and load the index.
+    pub fn configure(&self, cfg: &IrisCfg) {
+        let dir = if cfg.undo_journal_dir.trim().is_empty() {
+            path_policy::data_dir("undo")
+        } else {
+            path_policy::expand_home(cfg.undo_journal_dir.trim())
+        };
+        let loaded: Vec<JournalEntry> = std::fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        if let Ok(mut s) = self.settings.write() {
+            *s = Settings {
+                dir,
+                retention: Duration::days(cfg.undo_retention_days as i64),
+                max_entries: cfg.undo_max_entries.max(1),
+            };
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            *entries = loaded;
+            self.prune(&mut entries);
+        }
+    }
+
+    /// Tag subsequent writes with the event being processed.
+    pub fn set_origin(&self, event_id: Option<Uuid>) {
+        if let Ok(mut o) = self.origin.write() {
+            *o = event_id;
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.settings.read().map(|s| s.dir.clone()).unwrap_or_default()
+    }
+
+    fn blob(&self, hash: &str) -> PathBuf {
+        self.dir().join("blobs").join(hash)
+    }
+
+    /// Journal and atomically write `bytes` to the already policy-checked `path`.
+    pub fn write(&self, path: &Path, bytes: &[u8], tool: &str) -> Result<JournalEntry, String> {
+        let path = std::path::absolute(path).map_err(|e| e.to_string())?;
+        let previous = match std::fs::read(&path) {
+            Ok(old) => {
+                let h = hash(&old);
+                let blob = self.blob(&h);
+                if !blob.exists() {
+                    path_policy::create_private_dir(&self.dir().join("blobs"))
+                        .and_then(|()| write_atomic_private(&blob, &old))
+                        .map_err(|e| format!("failed to save undo copy: {e}"))?;
+                }
+                Some(h)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        write_atomic(&path, bytes).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+
+        let entry = JournalEntry {
+            id: Uuid::new_v4(),
+            path,
+            tool: tool.to_string(),
+            event_id: self.origin.read().ok().and_then(|o| *o),
+            at: Utc::now(),
+            previous,
+            current: hash(bytes),
+            undone: false,
+        };
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.push(entry.clone());
+        self.prune(&mut entries);
+        self.save(&entries);
+        Ok(entry)
+    }
+
+    /// [`write`](Self::write) on the blocking pool, so large files and the fsync
+    /// do not stall the tick loop.
+    pub async fn write_async(
+        self: &Arc<Self>,
+        path: PathBuf,
+        bytes: Vec<u8>,
+        tool: &'static str,
+    ) -> Result<JournalEntry, String> {
+        let journal = Arc::clone(self);
+        tokio::task::spawn_blocking(move || journal.write(&path, &bytes, tool))
+            .await
+            .map_err(|e| format!("undo journal write failed: {e}"))?
+    }
+
+    /// Most recent entries first, including undone ones.
+    pub fn recent(&self, limit: usize) -> Vec<JournalEntry> {
+        self.entries
+            .lock()
+            .map(|e| e.iter().rev().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Undo one write: by id prefix, or the latest not yet undone. The entry's path
+    /// is re-checked against the current `policy`. Refuses when the file changed
+    /// since that write, unless `force`.
+    pub fn undo(&self, id: Option<&str>, force: bool, policy: &PathPolicy) -> Result<JournalEntry, String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        let index = match id.map(|p| p.trim().replace('-', "").to_lowercase()) {
+            Some(prefix) => {
+                let matches: Vec<usize> = (0..entries.len())
+                    .filter(|&i| !prefix.is_empty() && entries[i].id.simple().to_string().starts_with(&prefix))
+                    .collect();
+                match matches.as_slice() {
+                    [i] => *i,
+                    [] => return Err(format!("no journal entry {prefix}")),
+                    _ => return Err(format!("id prefix {prefix} matches {} entries", matches.len())),
+                }
+            }
+            None => entries.iter().rposition(|e| !e.undone).ok_or("nothing to undo")?,
+        };
+        let entry = entries[index].clone();
+        if entry.undone {
+            return Err(format!("{} was already undone", entry.short_id()));
+        }
+        policy.check_write(&entry.path.to_string_lossy(), 0)?;
+
+        let now = std::fs::read(&entry.path).ok().map(|b| hash(&b));
+        if !force && now.as_deref() != Some(entry.current.as_str()) {
+            return Err(format!(
+                "{} changed after this write; undo with force to overwrite it anyway",
+                entry.path.display()
+            ));
+        }
+        match &entry.previous {
+            Some(h) => {
+                let old = std::fs::read(self.blob(h)).map_err(|_| "the saved copy has expired".to_string())?;
+                write_atomic(&entry.path, &old).map_err(|e| format!("failed to restore {}: {e}", entry.path.display()))?;
+            }
+            None if now.is_some() => std::fs::remove_file(&entry.path)
+                .map_err(|e| format!("failed to remove {}: {e}", entry.path.display()))?,
+            None => {}
+        }
+        entries[index].undone = true;
+        self.save(&entries);
+        Ok(entries[index].clone())
+    }
+
+    /// Drop entries past retention, then blobs no entry refers to.
+    fn prune(&self, entries: &mut Vec<JournalEntry>) {
+        let Ok(settings) = self.settings.read() else { return };
+        let cutoff = Utc::now() - settings.retention;
+        entries.retain(|e| e.at >= cutoff);
+        let excess = entries.len().saturating_sub(settings.max_entries);
+        entries.drain(..excess);
+
+        let Ok(blobs) = std::fs::read_dir(settings.dir.join("blobs")) else { return };
+        for blob in blobs.flatten() {
+            let name = blob.file_name().to_string_lossy().into_owned();
+            if !entries.iter().any(|e| e.previous.as_deref() == Some(name.as_str())) {
+                let _ = std::fs::remove_file(blob.path());
+            }
+        }
+    }
+
+    fn save(&self, entries: &[JournalEntry]) {
+        let dir = self.dir();
+        let result = path_policy::create_private_dir(&dir).and_then(|()| {
+            let json = serde_json::to_vec_pretty(entries).unwrap_or_default();
+            write_atomic_private(&dir.join(INDEX_FILE), &json)
+        });
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "failed to save undo journal");
+        }
+    }
+}
+
+pub struct UndoWrite {
+    journal: Arc<UndoJournal>,
+    access: Arc<FileAccess>,
+}
+
+impl UndoWrite {
+    pub fn new(journal: Arc<UndoJournal>, access: Arc<FileAccess>) -> Self {
+        Self { journal, access }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::BuiltinCapability for UndoWrite {
+    fn name(&self) -> &str { "undo_write" }
+
+    fn keywords(&self) -> Vec<String> {
+        ["undo", "revert", "restore", "rollback", "撤销", "恢复", "还原"]
+            .iter().map(|s| s.to_string()).collect()
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![Permission::FileWrite]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "undo_write".into(),
+            description: "Undo a file change made by write_file or edit_file, restoring the previous version \
+                          (or removing a newly created file). Without undo_id, undoes the latest change. \
+                          action=list shows recent changes. Refuses if the file changed since, unless force is true."
+                .into(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["undo", "list"], "description": "Default: undo" },
+                    "undo_id": { "type": "string", "minLength": 4, "description": "Id (or prefix) from a write result or list" },
+                    "force": { "type": "boolean" }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let params = &request.params;
+        let outcome = if params.get("action").and_then(|v| v.as_str()) == Some("list") {
+            let items: Vec<serde_json::Value> = self
+                .journal
+                .recent(20)
+                .iter()
+                .map(|e| serde_json::json!({
+                    "undo_id": e.short_id(),
+                    "path": e.path.display().to_string(),
+                    "tool": e.tool,
+                    "at": e.at.to_rfc3339(),
+                    "created_file": e.previous.is_none(),
+                    "undone": e.undone,
+                }))
+                .collect();
+            Ok(serde_json::json!({ "changes": items }))
+        } else {
+            let id = params.get("undo_id").and_then(|v| v.as_str());
+            let force = params.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+            self.journal.undo(id, force, &self.access.policy()).map(|e| serde_json::json!({
+                "undone": e.short_id(),
+                "path": e.path.display().to_string(),
+                "restored": if e.previous.is_some() { "previous version" } else { "removed (file was created by the write)" },
+            }))
+        };
+        let (result, error, side_effects) = match outcome {
+            Ok(v) => (Some(v), None, vec![Permission::FileWrite]),
+            Err(e) => (None, Some(e), vec![]),
+        };
+        CapabilityResponse {
+            id: request.id,
+            result,
+            error,
+            metrics: None,
+            side_effects,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(dir: &Path, max_entries: usize) -> UndoJournal {
+        let journal = UndoJournal::default();
+        journal.configure(&IrisCfg {
+            undo_journal_dir: dir.join("journal").display().to_string(),
+            undo_max_entries: max_entries,
+            ..IrisCfg::default()
+        });
+        journal
+    }
+
+    #[test]
+    fn undo_restores_previous_versions_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        let journal = journal(dir.path(), 100);
+        let origin = Uuid::new_v4();
+        journal.set_origin(Some(origin));
+
+        let created = journal.write(&file, b"one", "write_file").unwrap();
+        assert_eq!((created.previous.as_deref(), created.event_id), (None, Some(origin)));
+        journal.write(&file, b"two", "edit_file").unwrap();
+
+        journal.undo(None, false, &PathPolicy::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one");
+        journal.undo(None, false, &PathPolicy::default()).unwrap();
+        assert!(!file.exists(), "undoing the creating write removes the file");
+        assert_eq!(journal.undo(None, false, &PathPolicy::default()).unwrap_err(), "nothing to undo");
+
+        // The index survives a restart.
+        let reloaded = self::journal(dir.path(), 100);
+        assert_eq!(reloaded.recent(10).len(), 2);
+        assert!(reloaded.recent(10).iter().all(|e| e.undone));
+    }
+
+    #[test]
+    fn undo_refuses_files_changed_since_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("b.txt");
+        std::fs::write(&file, "orig").unwrap();
+        let journal = journal(dir.path(), 100);
+        let entry = journal.write(&file, b"new", "write_file").unwrap();
+        std::fs::write(&file, "edited by hand").unwrap();
+
+        assert!(journal.undo(Some(&entry.short_id()), false, &PathPolicy::default()).unwrap_err().contains("changed after"));
+        journal.undo(Some(&entry.short_id()), true, &PathPolicy::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "orig");
+    }
+
+    #[tokio::test]
+    async fn undo_rechecks_the_policy_for_any_id_form() {
+        use crate::capability::builtin::BuiltinCapability;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("d.txt");
+        let journal = Arc::new(journal(dir.path(), 100));
+        let entry = journal.write(&file, b"secret", "write_file").unwrap();
+
+        // The path has since been denied
+        let access = FileAccess::shared();
+        access.set_policy(PathPolicy::from_cfg(&IrisCfg {
+            file_denied_globs: format!("{}/*.txt", dir.path().display()),
+            ..IrisCfg::default()
+        }));
+        let undo = UndoWrite::new(Arc::clone(&journal), access);
+        let full = entry.id.to_string();
+        let long_prefix = entry.id.simple().to_string()[..12].to_string();
+        for id in [full.as_str(), long_prefix.as_str(), &entry.short_id()] {
+            let resp = undo
+                .execute(CapabilityRequest {
+                    id: Uuid::new_v4(),
+                    method: String::new(),
+                    params: serde_json::json!({ "undo_id": id }),
+                    version: 1,
+                })
+                .await;
+            assert!(resp.error.unwrap().contains("denied"), "{id}");
+        }
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "secret");
+        assert!(!journal.recent(1)[0].undone);
+    }
+
+    #[test]
+    fn retention_drops_old_entries_and_their_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("c.txt");
+        let journal = journal(dir.path(), 2);
+        for content in ["1", "2", "3", "4"] {
+            journal.write(&file, content.as_bytes(), "write_file").unwrap();
+        }
+        let kept = journal.recent(10);
+        assert_eq!(kept.len(), 2);
+        let blobs = std::fs::read_dir(dir.path().join("journal/blobs")).unwrap().count();
+        assert_eq!(blobs, 2);
+
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.path().join("journal")), 0o700);
+        assert_eq!(mode(&dir.path().join("journal/blobs")), 0o700);
+        assert_eq!(mode(&dir.path().join("journal/blobs").join(hash(b"3"))), 0o600);
+        assert_eq!(mode(&dir.path().join("journal").join(INDEX_FILE)), 0o600);
+        assert_eq!(kept[1].previous.as_deref(), Some(hash(b"2").as_str()));
+    }
+
+    #[test]
+    fn atomic_write_keeps_permissions_and_leaves_no_temp_files() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("run.sh");
+        std::fs::write(&file, "old").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o755)).unwrap();
+        write_atomic(&file, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
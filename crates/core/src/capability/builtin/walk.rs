@@ -0,0 +1,286 @@
+//! Directory walking shared by `list_dir`, `glob` and `grep`: glob patterns
+//! and `.gitignore` rules (nested files, `!` negation, `/` anchoring, dir-only).
+
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// Compile a glob into an anchored regex over `/`-separated relative paths.
+/// Supports `*`, `?`, `**`, `[...]` (with `!` negation) and `{a,b}`.
+pub fn glob_to_regex(pattern: &str) -> Result<Regex, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut re = String::from("^");
+    let mut i = 0;
+    let mut brace_depth = 0usize;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_segment_start = i == 0 || chars[i - 1] == '/';
+                if at_segment_start && chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 3;
+                    continue;
+                }
+                re.push_str(".*");
+                i += 2;
+                continue;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                let Some(end) = chars[i + 1..].iter().position(|&c| c == ']').map(|p| p + i + 1) else {
+                    return Err(format!("unclosed '[' in glob {pattern}"));
+                };
+                let mut class: String = chars[i + 1..end].iter().collect();
+                if let Some(rest) = class.strip_prefix('!') {
+                    class = format!("^{rest}");
+                }
+                re.push('[');
+                re.push_str(&class.replace('\\', "\\\\"));
+                re.push(']');
+                i = end;
+            }
+            '{' => {
+                brace_depth += 1;
+                re.push_str("(?:");
+            }
+            '}' if brace_depth > 0 => {
+                brace_depth -= 1;
+                re.push(')');
+            }
+            ',' if brace_depth > 0 => re.push('|'),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    if brace_depth > 0 {
+        return Err(format!("unclosed '{{' in glob {pattern}"));
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| format!("invalid glob {pattern}: {e}"))
+}
+
+#[derive(Debug)]
+struct IgnoreRule {
+    /// Directory holding the `.gitignore`, relative to the walk root ("" for the root).
+    base: String,
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// `.gitignore` rules collected while walking; later rules override earlier ones.
+#[derive(Debug, Default)]
+pub struct Gitignore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl Gitignore {
+    /// Add the rules of a `.gitignore` located in `base` (relative to the walk root).
+    pub fn add(&mut self, base: &str, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            // A slash anywhere but the end anchors the pattern to its .gitignore.
+            let pattern = match line.strip_prefix('/') {
+                Some(rest) => rest.to_string(),
+                None if line.contains('/') => line.to_string(),
+                None => format!("**/{line}"),
+            };
+            if let Ok(regex) = glob_to_regex(&pattern) {
+                self.rules.push(IgnoreRule {
+                    base: base.to_string(),
+                    regex,
+                    negated,
+                    dir_only,
+                });
+            }
+        }
+    }
+
+    /// Whether a root-relative path is ignored (last matching rule wins).
+    pub fn is_ignored(&self, rel: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let local = if rule.base.is_empty() {
+                Some(rel)
+            } else {
+                rel.strip_prefix(&rule.base).and_then(|r| r.strip_prefix('/'))
+            };
+            if local.is_some_and(|p| rule.regex.is_match(p)) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Dir => "dir",
+            Self::Symlink => "symlink",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Path relative to the walk root, `/`-separated.
+    pub rel: String,
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Deepest level to return; direct children are depth 1.
+    pub max_depth: usize,
+    pub respect_gitignore: bool,
+}
+
+/// Walk `root` depth-first in name order, skipping `.git` and (optionally) ignored paths.
+/// `visit` returns `false` to stop early. Symlinks are reported but not followed.
+pub fn walk(root: &Path, opts: &WalkOptions, mut visit: impl FnMut(&Entry) -> bool) -> Result<(), String> {
+    let meta = std::fs::metadata(root).map_err(|e| format!("cannot access {}: {e}", root.display()))?;
+    if !meta.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let mut ignore = Gitignore::default();
+    let mut stack: Vec<(PathBuf, String, usize)> = vec![(root.to_path_buf(), String::new(), 0)];
+    while let Some((dir, rel_dir, depth)) = stack.pop() {
+        if opts.respect_gitignore
+            && let Ok(contents) = std::fs::read_to_string(dir.join(".gitignore"))
+        {
+            ignore.add(&rel_dir, &contents);
+        }
+        let Ok(read) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut children: Vec<_> = read.flatten().collect();
+        children.sort_by_key(|e| e.file_name());
+
+        let mut subdirs = Vec::new();
+        for child in children {
+            let name = child.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                continue;
+            }
+            let Ok(file_type) = child.file_type() else {
+                continue;
+            };
+            let kind = if file_type.is_symlink() {
+                EntryKind::Symlink
+            } else if file_type.is_dir() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            let rel = if rel_dir.is_empty() { name } else { format!("{rel_dir}/{name}") };
+            if opts.respect_gitignore && ignore.is_ignored(&rel, kind == EntryKind::Dir) {
+                continue;
+            }
+            let entry = Entry {
+                size: child.metadata().map(|m| m.len()).unwrap_or(0),
+                path: child.path(),
+                rel,
+                kind,
+                depth: depth + 1,
+            };
+            if !visit(&entry) {
+                return Ok(());
+            }
+            if kind == EntryKind::Dir && entry.depth < opts.max_depth {
+                subdirs.push((entry.path, entry.rel, entry.depth));
+            }
+        }
+        // Reverse so the stack pops subdirectories in name order.
+        stack.extend(subdirs.into_iter().rev());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        let re = glob_to_regex("src/**/*.rs").unwrap();
+        assert!(re.is_match("src/main.rs"));
+        assert!(re.is_match("src/a/b/lib.rs"));
+        assert!(!re.is_match("tests/main.rs"));
+        let re = glob_to_regex("*.{toml,lock}").unwrap();
+        assert!(re.is_match("Cargo.toml"));
+        assert!(!re.is_match("crates/Cargo.toml"));
+        assert!(glob_to_regex("file[!0-9].txt").unwrap().is_match("fileA.txt"));
+        assert!(glob_to_regex("a[b").is_err());
+    }
+
+    #[test]
+    fn gitignore_rules() {
+        let mut ig = Gitignore::default();
+        ig.add("", "target/\n*.log\n!keep.log\n/build\n");
+        ig.add("web", "dist\n");
+        assert!(ig.is_ignored("target", true));
+        assert!(!ig.is_ignored("target", false));
+        assert!(ig.is_ignored("a/b/debug.log", false));
+        assert!(!ig.is_ignored("a/keep.log", false));
+        assert!(ig.is_ignored("build", true));
+        assert!(!ig.is_ignored("src/build", true));
+        assert!(ig.is_ignored("web/dist", true));
+        assert!(!ig.is_ignored("dist", true));
+    }
+
+    #[test]
+    fn walk_respects_gitignore_and_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("src/nested/deep.rs"), "").unwrap();
+        std::fs::write(root.join("target/out.bin"), "").unwrap();
+
+        let collect = |opts: WalkOptions| {
+            let mut seen = Vec::new();
+            walk(root, &opts, |e| {
+                seen.push(e.rel.clone());
+                true
+            })
+            .unwrap();
+            seen
+        };
+        let all = collect(WalkOptions { max_depth: 10, respect_gitignore: true });
+        assert_eq!(all, vec![".gitignore", "src", "src/main.rs", "src/nested", "src/nested/deep.rs"]);
+
+        let shallow = collect(WalkOptions { max_depth: 1, respect_gitignore: false });
+        assert_eq!(shallow, vec![".gitignore", "src", "target"]);
+    }
+}
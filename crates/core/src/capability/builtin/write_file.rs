@@ -1,7 +1,21 @@
+use std::sync::Arc;
+
+use super::undo::UndoJournal;
+use crate::capability::path_policy::FileAccess;
 use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
 use llm::provider::ToolDefinition;
 
-pub struct WriteFile;
+#[derive(Default)]
+pub struct WriteFile {
+    access: Arc<FileAccess>,
+    journal: Arc<UndoJournal>,
+}
+
+impl WriteFile {
+    pub fn new(access: Arc<FileAccess>, journal: Arc<UndoJournal>) -> Self {
+        Self { access, journal }
+    }
+}
 
 /// Extract file path from input (same heuristic as read_file).
 fn extract_path(input: &str) -> Option<String> {
@@ -94,7 +108,9 @@ impl super::BuiltinCapability for WriteFile {
     fn tool_definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "write_file".into(),
-            description: "Write content to a file at the given path, creating or overwriting it".into(),
+            description: "Write content to a file at the given path, creating or overwriting it. \
+                          The previous version is kept; undo_write restores it."
+                .into(),
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -143,12 +159,26 @@ impl super::BuiltinCapability for WriteFile {
         };
 
         let bytes = content.len();
-        match tokio::fs::write(&path, &content).await {
-            Ok(()) => CapabilityResponse {
+        // Checked after extraction so free-text requests get the same policy.
+        let resolved = match self.access.policy().check_write(&path, bytes) {
+            Ok(p) => p,
+            Err(e) => {
+                return CapabilityResponse {
+                    id: request.id,
+                    result: None,
+                    error: Some(e),
+                    metrics: None,
+                    side_effects: vec![],
+                };
+            }
+        };
+        match self.journal.write_async(resolved, content.into_bytes(), "write_file").await {
+            Ok(entry) => CapabilityResponse {
                 id: request.id,
                 result: Some(serde_json::json!({
                     "path": path,
                     "bytes_written": bytes,
+                    "undo_id": entry.short_id(),
                 })),
                 error: None,
                 metrics: None,
@@ -157,7 +187,7 @@ impl super::BuiltinCapability for WriteFile {
             Err(e) => CapabilityResponse {
                 id: request.id,
                 result: None,
-                error: Some(format!("failed to write {path}: {e}")),
+                error: Some(e),
                 metrics: None,
                 side_effects: vec![],
             },
@@ -185,4 +215,17 @@ mod tests {
     fn extracts_path_from_input() {
         assert_eq!(extract_path("写入 /tmp/test.txt"), Some("/tmp/test.txt".into()));
     }
+
+    #[tokio::test]
+    async fn free_text_writes_are_policy_checked() {
+        use crate::capability::builtin::BuiltinCapability;
+        let request = CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: r#"write "/etc/sudoers" "ALL ALL=(ALL) NOPASSWD: ALL""#.into(),
+            params: serde_json::json!({}),
+            version: 1,
+        };
+        let resp = WriteFile::default().execute(request).await;
+        assert!(resp.error.unwrap().contains("denied by policy"));
+    }
 }
@@ -14,6 +14,7 @@ struct CapabilityRow {
     manifest: serde_json::Value,
     state: String,
     lkg_version: Option<Uuid>,
+    current_version: Option<Uuid>,
     quarantine_count: i32,
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
@@ -28,6 +29,7 @@ impl From<CapabilityRow> for CapabilityRecord {
                 permissions: vec![],
                 resource_limits: serde_json::Value::Null,
                 keywords: vec![],
+                self_tests: vec![],
             });
         Self {
             id: row.id,
@@ -36,6 +38,7 @@ impl From<CapabilityRow> for CapabilityRecord {
             manifest,
             state: CapabilityState::from_db(&row.state).unwrap_or(CapabilityState::Quarantined),
             lkg_version: row.lkg_version,
+            current_version: row.current_version,
             quarantine_count: row.quarantine_count,
             created_at: row.created_at,
             updated_at: row.updated_at,
@@ -67,7 +70,7 @@ pub async fn insert(pool: &PgPool, record: &CapabilityRecord) -> Result<(), sqlx
 /// Fetch a capability by ID.
 pub async fn fetch_by_id(pool: &PgPool, id: Uuid) -> Result<Option<CapabilityRecord>, sqlx::Error> {
     let row: Option<CapabilityRow> = sqlx::query_as(
-        "SELECT id, name, binary_path, manifest, state, lkg_version, quarantine_count, created_at, updated_at
+        "SELECT id, name, binary_path, manifest, state, lkg_version, current_version, quarantine_count, created_at, updated_at
          FROM capability WHERE id = $1"
     )
     .bind(id)
@@ -78,7 +81,7 @@ pub async fn fetch_by_id(pool: &PgPool, id: Uuid) -> Result<Option<CapabilityRec
 /// Fetch a capability by name.
 pub async fn fetch_by_name(pool: &PgPool, name: &str) -> Result<Option<CapabilityRecord>, sqlx::Error> {
     let row: Option<CapabilityRow> = sqlx::query_as(
-        "SELECT id, name, binary_path, manifest, state, lkg_version, quarantine_count, created_at, updated_at
+        "SELECT id, name, binary_path, manifest, state, lkg_version, current_version, quarantine_count, created_at, updated_at
          FROM capability WHERE name = $1"
     )
     .bind(name)
@@ -90,7 +93,7 @@ pub async fn fetch_by_name(pool: &PgPool, name: &str) -> Result<Option<Capabilit
 /// Fetch all capabilities in a given state.
 pub async fn fetch_by_state(pool: &PgPool, state: CapabilityState) -> Result<Vec<CapabilityRecord>, sqlx::Error> {
     let rows: Vec<CapabilityRow> = sqlx::query_as(
-        "SELECT id, name, binary_path, manifest, state, lkg_version, quarantine_count, created_at, updated_at
+        "SELECT id, name, binary_path, manifest, state, lkg_version, current_version, quarantine_count, created_at, updated_at
          FROM capability WHERE state = $1 ORDER BY updated_at DESC"
     )
     .bind(state.as_db_str())
@@ -111,7 +114,7 @@ pub async fn update_state(pool: &PgPool, id: Uuid, new_state: CapabilityState) -
     Ok(())
 }
 
-/// Update LKG version pointer (called when active_candidate → confirmed).
+/// Update the LKG pointer to a `capability_version` row (called when active_candidate → confirmed).
 pub async fn update_lkg(pool: &PgPool, id: Uuid, lkg_version: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query(
         "UPDATE capability SET lkg_version = $1, updated_at = now() WHERE id = $2"
@@ -147,25 +150,25 @@ pub async fn init_score(pool: &PgPool, capability_id: Uuid) -> Result<(), sqlx::
     Ok(())
 }
 
-/// Record a usage outcome (success or failure).
+/// Record a usage outcome (success or failure) for the capability and its installed version.
 pub async fn record_outcome(pool: &PgPool, capability_id: Uuid, success: bool) -> Result<(), sqlx::Error> {
-    if success {
-        sqlx::query(
+    let (score_sql, version_sql) = if success {
+        (
             "UPDATE capability_score SET usage_count = usage_count + 1, success_count = success_count + 1, updated_at = now()
-             WHERE capability_id = $1"
+             WHERE capability_id = $1",
+            "UPDATE capability_version SET usage_count = usage_count + 1, success_count = success_count + 1
+             WHERE id = (SELECT current_version FROM capability WHERE id = $1)",
         )
-        .bind(capability_id)
-        .execute(pool)
-        .await?;
     } else {
-        sqlx::query(
+        (
             "UPDATE capability_score SET usage_count = usage_count + 1, fail_count = fail_count + 1, updated_at = now()
-             WHERE capability_id = $1"
+             WHERE capability_id = $1",
+            "UPDATE capability_version SET usage_count = usage_count + 1, fail_count = fail_count + 1
+             WHERE id = (SELECT current_version FROM capability WHERE id = $1)",
         )
-        .bind(capability_id)
-        .execute(pool)
-        .await?;
-    }
+    };
+    sqlx::query(score_sql).bind(capability_id).execute(pool).await?;
+    sqlx::query(version_sql).bind(capability_id).execute(pool).await?;
     Ok(())
 }
 
@@ -201,3 +204,17 @@ impl From<ScoreRow> for CapabilityScore {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Migrated pool for tests that need Postgres. `None` (the test is skipped)
+    /// unless `IRIS_TEST_DATABASE_URL` is set.
+    pub(crate) async fn test_pool() -> Option<PgPool> {
+        let url = std::env::var("IRIS_TEST_DATABASE_URL").ok()?;
+        let pool = PgPool::connect(&url).await.expect("IRIS_TEST_DATABASE_URL is not reachable");
+        sqlx::migrate!("../../migrations").run(&pool).await.expect("failed to migrate the test database");
+        Some(pool)
+    }
+}
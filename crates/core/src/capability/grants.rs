@@ -0,0 +1,173 @@
+//! Permission enforcement for process capabilities.
+//!
+//! A capability may cause the side effects its manifest declares, adjusted by
+//! user grants in the `capability_grant` table: `allow` adds a permission,
+//! `deny` revokes one (deny wins). Side effects reported outside that set are
+//! violations, and the runtime quarantines the capability.
+
+use sqlx::PgPool;
+
+use crate::types::Permission;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantKind {
+    Allow,
+    Deny,
+}
+
+impl GrantKind {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+
+    pub fn from_db(s: &str) -> Option<Self> {
+        match s {
+            "allow" => Some(Self::Allow),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionGrant {
+    pub capability: String,
+    pub permission: Permission,
+    pub kind: GrantKind,
+}
+
+/// Permissions a capability may exercise: declared or allowed, minus denied.
+pub fn effective(declared: &[Permission], grants: &[PermissionGrant]) -> Vec<Permission> {
+    let has = |p: Permission, kind: GrantKind| grants.iter().any(|g| g.permission == p && g.kind == kind);
+    Permission::ALL
+        .into_iter()
+        .filter(|&p| (declared.contains(&p) || has(p, GrantKind::Allow)) && !has(p, GrantKind::Deny))
+        .collect()
+}
+
+/// Declared permissions the user has revoked; the capability is not invoked while any remain.
+pub fn revoked(declared: &[Permission], grants: &[PermissionGrant]) -> Vec<Permission> {
+    let allowed = effective(declared, grants);
+    declared.iter().copied().filter(|p| !allowed.contains(p)).collect()
+}
+
+/// Reported side effects outside the effective permission set, without duplicates.
+pub fn violations(declared: &[Permission], grants: &[PermissionGrant], side_effects: &[Permission]) -> Vec<Permission> {
+    let allowed = effective(declared, grants);
+    let mut out: Vec<Permission> = Vec::new();
+    for p in side_effects {
+        if !allowed.contains(p) && !out.contains(p) {
+            out.push(*p);
+        }
+    }
+    out
+}
+
+pub fn join(permissions: &[Permission]) -> String {
+    permissions.iter().map(Permission::as_str).collect::<Vec<_>>().join(", ")
+}
+
+fn from_rows(rows: Vec<(String, String, String)>) -> Vec<PermissionGrant> {
+    rows.into_iter()
+        .filter_map(|(capability, permission, kind)| {
+            Some(PermissionGrant {
+                capability,
+                permission: Permission::parse(&permission)?,
+                kind: GrantKind::from_db(&kind)?,
+            })
+        })
+        .collect()
+}
+
+/// Grants for one capability.
+pub async fn load_for(pool: &PgPool, capability: &str) -> Result<Vec<PermissionGrant>, sqlx::Error> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT capability, permission, grant_kind FROM capability_grant WHERE capability = $1 ORDER BY permission",
+    )
+    .bind(capability)
+    .fetch_all(pool)
+    .await?;
+    Ok(from_rows(rows))
+}
+
+/// All grants, ordered by capability.
+pub async fn load_all(pool: &PgPool) -> Result<Vec<PermissionGrant>, sqlx::Error> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT capability, permission, grant_kind FROM capability_grant ORDER BY capability, permission",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(from_rows(rows))
+}
+
+/// Insert or replace the grant for a capability's permission.
+pub async fn set_grant(
+    pool: &PgPool,
+    capability: &str,
+    permission: Permission,
+    kind: GrantKind,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO capability_grant (capability, permission, grant_kind) VALUES ($1, $2, $3) \
+         ON CONFLICT (capability, permission) DO UPDATE SET grant_kind = $3, created_at = now()",
+    )
+    .bind(capability)
+    .bind(permission.as_str())
+    .bind(kind.as_db_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remove a grant. Returns whether one existed.
+pub async fn remove_grant(pool: &PgPool, capability: &str, permission: Permission) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM capability_grant WHERE capability = $1 AND permission = $2")
+        .bind(capability)
+        .bind(permission.as_str())
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(permission: Permission, kind: GrantKind) -> PermissionGrant {
+        PermissionGrant { capability: "weather".into(), permission, kind }
+    }
+
+    #[test]
+    fn undeclared_side_effects_are_violations() {
+        let declared = [Permission::NetworkRead];
+        let reported = [Permission::NetworkRead, Permission::NetworkWrite, Permission::NetworkWrite];
+        assert_eq!(violations(&declared, &[], &reported), vec![Permission::NetworkWrite]);
+        assert!(violations(&declared, &[], &[Permission::NetworkRead]).is_empty());
+    }
+
+    #[test]
+    fn grants_extend_and_revoke_the_manifest() {
+        let declared = [Permission::NetworkRead, Permission::FileRead];
+        let grants = [
+            grant(Permission::NetworkWrite, GrantKind::Allow),
+            grant(Permission::FileRead, GrantKind::Deny),
+        ];
+        assert_eq!(effective(&declared, &grants), vec![Permission::NetworkRead, Permission::NetworkWrite]);
+        assert_eq!(revoked(&declared, &grants), vec![Permission::FileRead]);
+        assert_eq!(
+            violations(&declared, &grants, &[Permission::NetworkWrite, Permission::FileRead]),
+            vec![Permission::FileRead]
+        );
+    }
+
+    #[test]
+    fn permission_names_parse_loosely() {
+        assert_eq!(Permission::parse("network_write"), Some(Permission::NetworkWrite));
+        assert_eq!(Permission::parse("FileRead"), Some(Permission::FileRead));
+        assert_eq!(Permission::parse("process-spawn"), Some(Permission::ProcessSpawn));
+        assert_eq!(Permission::parse("root"), None);
+    }
+}
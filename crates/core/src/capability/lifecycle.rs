@@ -22,6 +22,7 @@ impl std::error::Error for InvalidTransition {}
 ///   staged → quarantined (self-test failed)
 ///   active_candidate → confirmed (10 min stable run)
 ///   active_candidate → quarantined (crash after restart failure)
+///   confirmed → active_candidate (rebuilt version passed self-test)
 ///   confirmed → quarantined (regression failure)
 ///   confirmed → retired (user-confirmed retirement)
 ///   quarantined → staged (new version fix)
@@ -36,6 +37,7 @@ pub fn validate_transition(
             | (CapabilityState::Staged, CapabilityState::Quarantined)
             | (CapabilityState::ActiveCandidate, CapabilityState::Confirmed)
             | (CapabilityState::ActiveCandidate, CapabilityState::Quarantined)
+            | (CapabilityState::Confirmed, CapabilityState::ActiveCandidate)
             | (CapabilityState::Confirmed, CapabilityState::Quarantined)
             | (CapabilityState::Confirmed, CapabilityState::Retired)
             | (CapabilityState::Quarantined, CapabilityState::Staged)
@@ -67,6 +69,7 @@ mod tests {
             (CapabilityState::Staged, CapabilityState::Quarantined),
             (CapabilityState::ActiveCandidate, CapabilityState::Confirmed),
             (CapabilityState::ActiveCandidate, CapabilityState::Quarantined),
+            (CapabilityState::Confirmed, CapabilityState::ActiveCandidate),
             (CapabilityState::Confirmed, CapabilityState::Quarantined),
             (CapabilityState::Confirmed, CapabilityState::Retired),
             (CapabilityState::Quarantined, CapabilityState::Staged),
@@ -90,7 +93,6 @@ mod tests {
             (CapabilityState::ActiveCandidate, CapabilityState::Staged),
             (CapabilityState::ActiveCandidate, CapabilityState::Retired),
             (CapabilityState::Confirmed, CapabilityState::Staged),
-            (CapabilityState::Confirmed, CapabilityState::ActiveCandidate),
             (CapabilityState::Retired, CapabilityState::Staged),
             (CapabilityState::Quarantined, CapabilityState::Confirmed),
         ];
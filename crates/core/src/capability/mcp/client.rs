@@ -0,0 +1,429 @@
+//! Stdio MCP client: launches a server, runs the `initialize` / `tools/list`
+//! handshake and proxies `tools/call` over JSON-RPC. Each remote tool is
+//! wrapped in an `McpTool` so it sits in the `BuiltinRegistry` next to the builtins.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::oneshot;
+
+use super::config::McpServerConfig;
+use crate::types::{CapabilityRequest, CapabilityResponse, Permission};
+use llm::provider::ToolDefinition;
+
+/// Longest tool name accepted by LLM tool-use APIs.
+const MAX_TOOL_NAME: usize = 64;
+/// `tools/list` pages followed before giving up on pagination.
+const MAX_LIST_PAGES: usize = 20;
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// A tool advertised by a server in `tools/list`.
+#[derive(Debug, Clone)]
+pub struct RemoteTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// A running MCP server process.
+pub struct McpServer {
+    name: String,
+    child: Mutex<Child>,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: Pending,
+    next_id: AtomicU64,
+    timeout: Duration,
+}
+
+impl McpServer {
+    /// Launch the server and complete the handshake. `timeout` bounds every request.
+    pub async fn start(
+        cfg: &McpServerConfig,
+        timeout: Duration,
+    ) -> Result<(Arc<Self>, Vec<RemoteTool>), String> {
+        let mut child = tokio::process::Command::new(&cfg.command)
+            .args(&cfg.args)
+            .envs(&cfg.env)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("{}: {e}", cfg.command))?;
+        let stdin = child.stdin.take().ok_or("failed to capture stdin")?;
+        let stdout = child.stdout.take().ok_or("failed to capture stdout")?;
+
+        let server = Arc::new(Self {
+            name: cfg.name.clone(),
+            child: Mutex::new(child),
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending: Arc::default(),
+            next_id: AtomicU64::new(1),
+            timeout,
+        });
+        tokio::spawn(read_loop(stdout, Arc::clone(&server.pending), Arc::downgrade(&server)));
+
+        match server.handshake().await {
+            Ok(tools) => Ok((server, tools)),
+            Err(e) => {
+                server.kill();
+                Err(e)
+            }
+        }
+    }
+
+    async fn handshake(&self) -> Result<Vec<RemoteTool>, String> {
+        let init = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": super::PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "iris", "version": env!("CARGO_PKG_VERSION") }
+                }),
+            )
+            .await?;
+        let protocol = init.get("protocolVersion").and_then(Value::as_str).unwrap_or("?");
+        tracing::debug!(server = %self.name, protocol, "MCP server initialized");
+        self.send(&super::notification("notifications/initialized", json!({}))).await?;
+
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let params = match &cursor {
+                Some(c) => json!({ "cursor": c }),
+                None => json!({}),
+            };
+            let page = self.request("tools/list", params).await?;
+            for tool in page.get("tools").and_then(Value::as_array).into_iter().flatten() {
+                let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                tools.push(RemoteTool {
+                    name: name.to_string(),
+                    description: tool.get("description").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    input_schema: tool
+                        .get("inputSchema")
+                        .filter(|s| s.is_object())
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object" })),
+                });
+            }
+            cursor = page.get("nextCursor").and_then(Value::as_str).map(String::from);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(tools)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Call a remote tool. A result flagged `isError` comes back as `Err`.
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<String, String> {
+        let result = self
+            .request("tools/call", json!({ "name": tool, "arguments": arguments }))
+            .await?;
+        let text = result_text(&result);
+        if result.get("isError").and_then(Value::as_bool) == Some(true) {
+            Err(text)
+        } else {
+            Ok(text)
+        }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, tx);
+        }
+        let forget = || {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(&id);
+            }
+        };
+        if let Err(e) = self.send(&super::request(id, method, params)).await {
+            forget();
+            return Err(e);
+        }
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("MCP server {} exited", self.name)),
+            Err(_) => {
+                forget();
+                Err(format!(
+                    "MCP server {} did not answer {method} within {}s",
+                    self.name,
+                    self.timeout.as_secs()
+                ))
+            }
+        }
+    }
+
+    async fn send(&self, msg: &Value) -> Result<(), String> {
+        let mut line = msg.to_string();
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(line.as_bytes()).await.map_err(|e| format!("MCP server {}: {e}", self.name))?;
+        stdin.flush().await.map_err(|e| format!("MCP server {}: {e}", self.name))
+    }
+
+    /// `Some(exit code)` once the process has exited.
+    pub fn exit_status(&self) -> Option<Option<i32>> {
+        let mut child = self.child.lock().ok()?;
+        match child.try_wait() {
+            Ok(Some(status)) => Some(status.code()),
+            _ => None,
+        }
+    }
+
+    pub fn kill(&self) {
+        if let Ok(mut child) = self.child.lock() {
+            let _ = child.start_kill();
+        }
+    }
+}
+
+/// Route responses to waiting requests and answer server-initiated requests.
+async fn read_loop(stdout: ChildStdout, pending: Pending, server: Weak<McpServer>) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(msg) = serde_json::from_str::<Value>(&line) else {
+            tracing::debug!("MCP: ignoring non-JSON line from server");
+            continue;
+        };
+        match (msg.get("id"), msg.get("method").and_then(Value::as_str)) {
+            (Some(id), Some(method)) => {
+                let reply = if method == "ping" {
+                    super::response(id.clone(), json!({}))
+                } else {
+                    super::error_response(id.clone(), super::METHOD_NOT_FOUND, "not supported by this client")
+                };
+                let Some(server) = server.upgrade() else { break };
+                let _ = server.send(&reply).await;
+            }
+            (Some(id), None) => {
+                let Some(id) = id.as_u64() else { continue };
+                let Some(tx) = pending.lock().ok().and_then(|mut p| p.remove(&id)) else {
+                    continue;
+                };
+                let result = match msg.get("error") {
+                    Some(err) => Err(err
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .map(String::from)
+                        .unwrap_or_else(|| err.to_string())),
+                    None => Ok(msg.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let _ = tx.send(result);
+            }
+            // Notifications (progress, logging, list changes) are not used yet.
+            _ => {}
+        }
+    }
+    // Stdout closed: dropping the senders fails every request still in flight.
+    if let Ok(mut p) = pending.lock() {
+        p.clear();
+    }
+}
+
+/// Flatten a `tools/call` result's content blocks into text.
+fn result_text(result: &Value) -> String {
+    let parts: Vec<String> = result
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|block| {
+            let text = |v: &Value| v.get("text").and_then(Value::as_str).map(String::from);
+            match block.get("type").and_then(Value::as_str) {
+                Some("text") => text(block).unwrap_or_default(),
+                Some("resource") => {
+                    let resource = block.get("resource").unwrap_or(&Value::Null);
+                    text(resource).unwrap_or_else(|| {
+                        format!("[resource {}]", resource.get("uri").and_then(Value::as_str).unwrap_or("?"))
+                    })
+                }
+                Some(kind) => match block.get("mimeType").and_then(Value::as_str) {
+                    Some(mime) => format!("[{kind} content: {mime}]"),
+                    None => format!("[{kind} content]"),
+                },
+                None => block.to_string(),
+            }
+        })
+        .collect();
+    if parts.is_empty() {
+        result.get("structuredContent").map(Value::to_string).unwrap_or_default()
+    } else {
+        parts.join("\n")
+    }
+}
+
+/// Registry name for a remote tool: `<server>__<tool>`, limited to the
+/// characters and length tool-use APIs accept.
+pub fn tool_name(server: &str, tool: &str) -> String {
+    format!("{server}__{tool}")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .take(MAX_TOOL_NAME)
+        .collect()
+}
+
+/// One remote tool, proxied through its server.
+pub struct McpTool {
+    server: Arc<McpServer>,
+    remote: String,
+    definition: ToolDefinition,
+}
+
+impl McpTool {
+    pub fn new(server: Arc<McpServer>, tool: RemoteTool) -> Self {
+        let description = if tool.description.is_empty() {
+            format!("Tool '{}' from MCP server {}", tool.name, server.name)
+        } else {
+            format!("[MCP {}] {}", server.name, tool.description)
+        };
+        let definition = ToolDefinition {
+            name: tool_name(&server.name, &tool.name),
+            description,
+            input_schema: tool.input_schema,
+        };
+        Self { server, remote: tool.name, definition }
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::capability::builtin::BuiltinCapability for McpTool {
+    fn name(&self) -> &str { &self.definition.name }
+
+    fn keywords(&self) -> Vec<String> {
+        let mut words: Vec<String> = self
+            .remote
+            .split(['_', '-', '.'])
+            .filter(|w| w.len() >= 3)
+            .map(str::to_lowercase)
+            .collect();
+        words.push(self.server.name.to_lowercase());
+        words
+    }
+
+    fn permissions(&self) -> Vec<Permission> {
+        vec![]
+    }
+
+    fn tool_definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, request: CapabilityRequest) -> CapabilityResponse {
+        let (result, error) = match self.server.call_tool(&self.remote, request.params).await {
+            Ok(text) => (Some(json!({ "content": text })), None),
+            Err(e) => (None, Some(e)),
+        };
+        CapabilityResponse {
+            id: request.id,
+            result,
+            error,
+            metrics: None,
+            side_effects: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::capability::builtin::BuiltinCapability;
+    use std::io::Write;
+
+    /// Minimal MCP server in bash: answers initialize, tools/list and an `echo` tool.
+    pub(crate) const FAKE_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"fake","version":"0"}}}\n' "$id" ;;
+    *'"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echo text","inputSchema":{"type":"object","properties":{"text":{"type":"string"}},"required":["text"]}}]}}\n' "$id" ;;
+    *'"tools/call"'*'"crash"'*)
+      exit 3 ;;
+    *'"tools/call"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      printf '{"jsonrpc":"2.0","method":"notifications/message","params":{}}\n'
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echo: %s"}]}}\n' "$id" "$text" ;;
+  esac
+done
+"#;
+
+    pub(crate) fn fake_config(script: &tempfile::NamedTempFile) -> McpServerConfig {
+        McpServerConfig {
+            name: "fake".into(),
+            command: "bash".into(),
+            args: vec![script.path().display().to_string()],
+            env: HashMap::new(),
+            disabled: false,
+        }
+    }
+
+    fn request(params: Value) -> CapabilityRequest {
+        CapabilityRequest { id: uuid::Uuid::new_v4(), method: String::new(), params, version: 1 }
+    }
+
+    #[tokio::test]
+    async fn handshake_lists_tools_and_proxies_calls() {
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        script.write_all(FAKE_SERVER.as_bytes()).unwrap();
+        let (server, tools) = McpServer::start(&fake_config(&script), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].input_schema["required"], json!(["text"]));
+
+        let tool = McpTool::new(Arc::clone(&server), tools[0].clone());
+        assert_eq!(tool.name(), "fake__echo");
+        let resp = tool.execute(request(json!({ "text": "hi" }))).await;
+        assert_eq!(resp.result, Some(json!({ "content": "echo: hi" })));
+
+        let resp = tool.execute(request(json!({ "text": "crash" }))).await;
+        assert!(resp.error.unwrap().contains("exited"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.exit_status(), Some(Some(3)));
+    }
+
+    #[tokio::test]
+    async fn start_fails_for_missing_command_or_silent_server() {
+        let mut cfg = fake_config(&tempfile::NamedTempFile::new().unwrap());
+        cfg.command = "/nonexistent/mcp-server".into();
+        assert!(McpServer::start(&cfg, Duration::from_secs(1)).await.is_err());
+
+        cfg.command = "sleep".into();
+        cfg.args = vec!["30".into()];
+        let err = McpServer::start(&cfg, Duration::from_millis(200)).await.err().unwrap();
+        assert!(err.contains("did not answer initialize"), "{err}");
+    }
+
+    #[test]
+    fn tool_names_are_prefixed_and_sanitized() {
+        assert_eq!(tool_name("github", "create_issue"), "github__create_issue");
+        assert_eq!(tool_name("my server", "fs.read"), "my_server__fs_read");
+        assert_eq!(tool_name("s", &"x".repeat(100)).len(), MAX_TOOL_NAME);
+    }
+
+    #[test]
+    fn result_content_is_flattened() {
+        let result = json!({ "content": [
+            { "type": "text", "text": "line one" },
+            { "type": "image", "data": "...", "mimeType": "image/png" },
+            { "type": "resource", "resource": { "uri": "file:///a", "text": "inline" } }
+        ] });
+        assert_eq!(result_text(&result), "line one\n[image content: image/png]\ninline");
+        assert_eq!(result_text(&json!({ "content": [], "structuredContent": { "n": 1 } })), "{\"n\":1}");
+    }
+}
@@ -0,0 +1,81 @@
+//! MCP server list, read from the `mcpServers` JSON file other agents use:
+//!
+//! ```json
+//! { "mcpServers": { "github": { "command": "github-mcp", "args": ["stdio"], "env": {} } } }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct McpServerConfig {
+    #[serde(skip)]
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment on top of iris's own.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Deserialize)]
+struct McpFile {
+    #[serde(rename = "mcpServers", default)]
+    servers: BTreeMap<String, McpServerConfig>,
+}
+
+/// Enabled servers from the file contents, sorted by name.
+pub fn parse(text: &str) -> Result<Vec<McpServerConfig>, String> {
+    let file: McpFile = serde_json::from_str(text).map_err(|e| format!("invalid MCP config: {e}"))?;
+    Ok(file
+        .servers
+        .into_iter()
+        .filter(|(_, cfg)| !cfg.disabled)
+        .map(|(name, cfg)| McpServerConfig { name, ..cfg })
+        .collect())
+}
+
+/// Load the server list; a missing file means no servers.
+pub fn load(path: &str) -> Result<Vec<McpServerConfig>, String> {
+    if path.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let path = crate::capability::path_policy::expand_home(path.trim());
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_enabled_servers_in_name_order() {
+        let servers = parse(
+            r#"{ "mcpServers": {
+                "search": { "command": "search-mcp", "args": ["--stdio"], "env": { "TOKEN": "x" } },
+                "old": { "command": "old-mcp", "disabled": true },
+                "db": { "command": "db-mcp" }
+            } }"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["db", "search"]);
+        assert_eq!(servers[1].args, ["--stdio"]);
+        assert_eq!(servers[1].env.get("TOKEN").map(String::as_str), Some("x"));
+        assert!(parse(r#"{ "mcpServers": { "x": { "args": [] } } }"#).is_err());
+    }
+
+    #[test]
+    fn missing_file_means_no_servers() {
+        assert_eq!(load("/nonexistent/iris/mcp.json").unwrap(), vec![]);
+        assert_eq!(load("").unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Configured MCP servers: which are running, how often each has crashed, and
+//! which are waiting to be restarted.
+//!
+//! A crashed server stays tracked until it is retired: `schedule_restart` puts
+//! it on a backoff timer and `poll_restarts` launches due restarts in the
+//! background, so a slow or failing handshake never blocks the tick.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+
+use super::client::{McpServer, McpTool, RemoteTool};
+use super::config::McpServerConfig;
+
+/// Delay before the first restart of a crashed server; doubles per crash.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+/// Upper bound for the restart delay.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+type Launch = JoinHandle<Result<(Arc<McpServer>, Vec<RemoteTool>), String>>;
+
+#[derive(Default)]
+pub struct McpManager {
+    configs: Vec<McpServerConfig>,
+    running: HashMap<String, Arc<McpServer>>,
+    crashes: HashMap<String, i32>,
+    /// Crashed servers and when their restart is due.
+    pending: HashMap<String, Instant>,
+    /// Restarts launched by `poll_restarts` that have not finished yet.
+    restarting: HashMap<String, Launch>,
+    timeout: Duration,
+    backoff: Duration,
+}
+
+impl McpManager {
+    pub fn new(configs: Vec<McpServerConfig>, timeout: Duration) -> Self {
+        Self { configs, timeout, backoff: RESTART_BACKOFF, ..Self::default() }
+    }
+
+    /// Names of all configured servers.
+    pub fn names(&self) -> Vec<String> {
+        self.configs.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Launch a configured server and wrap its tools for the registry.
+    pub async fn start(&mut self, name: &str) -> Result<Vec<McpTool>, String> {
+        let cfg = self.config(name)?;
+        let (server, tools) = McpServer::start(cfg, self.timeout).await?;
+        Ok(self.started(name, server, tools))
+    }
+
+    fn config(&self, name: &str) -> Result<&McpServerConfig, String> {
+        self.configs
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| format!("unknown MCP server {name}"))
+    }
+
+    fn started(&mut self, name: &str, server: Arc<McpServer>, tools: Vec<RemoteTool>) -> Vec<McpTool> {
+        tracing::info!(server = name, tools = tools.len(), "MCP server started");
+        if let Some(old) = self.running.insert(name.to_string(), Arc::clone(&server)) {
+            old.kill();
+        }
+        tools.into_iter().map(|t| McpTool::new(Arc::clone(&server), t)).collect()
+    }
+
+    /// Servers whose process exited since the last check, with exit codes.
+    /// They leave the running set; the caller decides whether to restart them.
+    pub fn health_check(&mut self) -> Vec<(String, Option<i32>)> {
+        let exited: Vec<(String, Option<i32>)> = self
+            .running
+            .iter()
+            .filter_map(|(name, server)| server.exit_status().map(|code| (name.clone(), code)))
+            .collect();
+        for (name, _) in &exited {
+            self.running.remove(name);
+        }
+        exited
+    }
+
+    /// Count a crash; returns the total for this server.
+    pub fn record_crash(&mut self, name: &str) -> i32 {
+        let count = self.crashes.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    /// Queue a restart after a delay that doubles with each recorded crash.
+    pub fn schedule_restart(&mut self, name: &str) {
+        let crashes = self.crashes.get(name).copied().unwrap_or(0).clamp(1, 16) as u32;
+        let delay = self.backoff.saturating_mul(1 << (crashes - 1)).min(MAX_RESTART_BACKOFF);
+        tracing::info!(server = name, delay_secs = delay.as_secs_f32(), "MCP server restart scheduled");
+        self.pending.insert(name.to_string(), Instant::now() + delay);
+    }
+
+    /// Whether a server is waiting for or in the middle of a restart.
+    pub fn is_restarting(&self, name: &str) -> bool {
+        self.pending.contains_key(name) || self.restarting.contains_key(name)
+    }
+
+    /// Launch restarts that are due and collect the ones that finished. A failed
+    /// restart is returned as an error; reschedule it or give up.
+    pub async fn poll_restarts(&mut self) -> Vec<(String, Result<Vec<McpTool>, String>)> {
+        let now = Instant::now();
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in due {
+            self.pending.remove(&name);
+            let cfg = match self.config(&name) {
+                Ok(cfg) => cfg.clone(),
+                Err(e) => {
+                    tracing::warn!(server = %name, error = %e, "dropping restart");
+                    continue;
+                }
+            };
+            let timeout = self.timeout;
+            self.restarting
+                .insert(name, tokio::spawn(async move { McpServer::start(&cfg, timeout).await }));
+        }
+
+        let finished: Vec<String> = self
+            .restarting
+            .iter()
+            .filter(|(_, launch)| launch.is_finished())
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut results = Vec::with_capacity(finished.len());
+        for name in finished {
+            let Some(launch) = self.restarting.remove(&name) else { continue };
+            let result = match launch.await {
+                Ok(Ok((server, tools))) => Ok(self.started(&name, server, tools)),
+                Ok(Err(e)) => Err(e),
+                Err(e) => Err(format!("restart task failed: {e}")),
+            };
+            results.push((name, result));
+        }
+        results
+    }
+
+    pub fn shutdown_all(&mut self) {
+        self.pending.clear();
+        for (_, launch) in self.restarting.drain() {
+            launch.abort();
+        }
+        for (name, server) in self.running.drain() {
+            server.kill();
+            tracing::info!(server = %name, "MCP server stopped");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::mcp::client::tests::{FAKE_SERVER, fake_config};
+    use std::io::Write;
+
+    async fn poll_until_done(mgr: &mut McpManager) -> Vec<(String, Result<Vec<McpTool>, String>)> {
+        for _ in 0..200 {
+            let results = mgr.poll_restarts().await;
+            if !results.is_empty() {
+                return results;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("restart never finished");
+    }
+
+    #[tokio::test]
+    async fn crashed_servers_are_detected_and_restarted_with_backoff() {
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        script.write_all(FAKE_SERVER.as_bytes()).unwrap();
+        let mut mgr = McpManager::new(vec![fake_config(&script)], Duration::from_secs(5));
+        mgr.backoff = Duration::from_millis(20);
+        assert_eq!(mgr.start("fake").await.unwrap().len(), 1);
+        assert!(mgr.health_check().is_empty());
+
+        mgr.running["fake"].kill();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let crashed = mgr.health_check();
+        assert_eq!(crashed.len(), 1);
+        assert_eq!(crashed[0].0, "fake");
+        assert!(!mgr.running.contains_key("fake"));
+
+        // Backoff doubles per crash
+        mgr.record_crash("fake");
+        mgr.record_crash("fake");
+        mgr.schedule_restart("fake");
+        assert!(mgr.is_restarting("fake"));
+        let due = mgr.pending["fake"].saturating_duration_since(Instant::now());
+        assert!(due > Duration::from_millis(20) && due <= Duration::from_millis(40), "{due:?}");
+        assert!(mgr.poll_restarts().await.is_empty(), "not due yet");
+
+        let results = poll_until_done(&mut mgr).await;
+        let (name, tools) = &results[0];
+        assert_eq!(name, "fake");
+        assert_eq!(tools.as_ref().unwrap().len(), 1, "tools are remounted");
+        assert!(mgr.running.contains_key("fake"));
+        assert!(!mgr.is_restarting("fake"));
+        mgr.shutdown_all();
+    }
+
+    #[tokio::test]
+    async fn failed_restarts_are_reported_not_lost() {
+        let mut cfg = fake_config(&tempfile::NamedTempFile::new().unwrap());
+        cfg.command = "/nonexistent/mcp-server".into();
+        let mut mgr = McpManager::new(vec![cfg], Duration::from_secs(1));
+        mgr.backoff = Duration::ZERO;
+        mgr.record_crash("fake");
+        mgr.schedule_restart("fake");
+
+        let results = poll_until_done(&mut mgr).await;
+        assert!(results[0].1.as_ref().is_err());
+        assert!(!mgr.running.contains_key("fake"));
+
+        // The caller counts the failure and tries again later
+        assert_eq!(mgr.record_crash("fake"), 2);
+        mgr.schedule_restart("fake");
+        assert!(mgr.is_restarting("fake"));
+        mgr.shutdown_all();
+        assert!(!mgr.is_restarting("fake"));
+    }
+}
@@ -0,0 +1,37 @@
+//! Model Context Protocol (MCP) over stdio: newline-delimited JSON-RPC 2.0.
+//!
+//! `client` launches external MCP servers and proxies their tools into the
+//! `BuiltinRegistry`; `manager` tracks the running servers and their crashes;
+//! `server` serves iris itself to other agents (`iris --mcp-server`).
+
+pub mod client;
+pub mod config;
+pub mod manager;
+pub mod server;
+
+use serde_json::{Value, json};
+
+/// Protocol revision sent in `initialize`.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes.
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+pub fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+pub fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
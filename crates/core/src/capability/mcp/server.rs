@@ -0,0 +1,296 @@
+//! `iris --mcp-server`: serve iris over stdio MCP so other agents can use its
+//! builtins and long-term memory. Tools are the `BuiltinRegistry` builtins
+//! (under the same sandbox, path and host policies as the runtime) plus
+//! `narrative` and `self_model`; resources expose recent knowledge, the
+//! narrative and the self-model.
+
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::capability::builtin::BuiltinRegistry;
+use crate::cognition::tool_call;
+use crate::config::IrisCfg;
+use crate::identity::{narrative, self_model};
+use crate::memory;
+use crate::types::NarrativeEventType;
+use llm::provider::ToolDefinition;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+/// Entries returned by the knowledge and narrative resources.
+const RESOURCE_LIMIT: i64 = 50;
+
+const RESOURCES: [(&str, &str, &str); 3] = [
+    ("iris://knowledge", "knowledge", "Recent long-term knowledge, pinned facts included"),
+    ("iris://narrative", "narrative", "Recent narrative events (milestones, capability changes)"),
+    ("iris://self-model", "self-model", "Self-model key/value entries"),
+];
+
+/// Run the MCP server on stdin/stdout until stdin closes.
+pub async fn serve(cfg: &IrisCfg, pool: Option<PgPool>) -> std::io::Result<()> {
+    let registry = BuiltinRegistry::new();
+    registry.apply_config(cfg, pool.as_ref()).await;
+    let server = IrisMcp { registry, pool };
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(reply) = server.handle_line(&line).await {
+            let mut out = reply.to_string();
+            out.push('\n');
+            stdout.write_all(out.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+struct IrisMcp {
+    registry: BuiltinRegistry,
+    pool: Option<PgPool>,
+}
+
+impl IrisMcp {
+    /// Answer one JSON-RPC line; `None` for notifications and stray responses.
+    async fn handle_line(&self, line: &str) -> Option<Value> {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            return Some(super::error_response(Value::Null, super::PARSE_ERROR, "invalid JSON"));
+        };
+        let method = msg.get("method").and_then(Value::as_str)?;
+        let id = msg.get("id")?.clone();
+        let params = msg.get("params").cloned().unwrap_or_else(|| json!({}));
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": super::PROTOCOL_VERSION,
+                "capabilities": { "tools": {}, "resources": {} },
+                "serverInfo": { "name": "iris", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.tools() })),
+            "tools/call" => self.call_tool(&params).await,
+            "resources/list" => Ok(json!({
+                "resources": RESOURCES
+                    .iter()
+                    .map(|(uri, name, description)| json!({
+                        "uri": uri, "name": name, "description": description, "mimeType": "application/json"
+                    }))
+                    .collect::<Vec<_>>()
+            })),
+            "resources/read" => self.read_resource(&params).await,
+            _ => Err((super::METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        };
+        Some(match result {
+            Ok(result) => super::response(id, result),
+            Err((code, message)) => super::error_response(id, code, &message),
+        })
+    }
+
+    fn tools(&self) -> Vec<Value> {
+        let mut defs = self.registry.tool_definitions();
+        if self.pool.is_some() {
+            defs.extend(memory_tools());
+        }
+        defs.into_iter()
+            .map(|d| json!({ "name": d.name, "description": d.description, "inputSchema": d.input_schema }))
+            .collect()
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((super::INVALID_PARAMS, "missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        let outcome = match (name, &self.pool) {
+            ("narrative", Some(pool)) => narrative_tool(pool, &arguments).await,
+            ("self_model", Some(pool)) => self_model_tool(pool, &arguments).await,
+            _ => tool_call::execute_named_tool(&self.registry, name, &arguments).await,
+        };
+        // `remember` queues working-memory pins for the runtime; there is none here.
+        self.registry.memory().take_pin_changes();
+        let (text, is_error) = match outcome {
+            Ok(text) => (text, false),
+            Err(e) => (e, true),
+        };
+        Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+    }
+
+    async fn read_resource(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or((super::INVALID_PARAMS, "missing uri".to_string()))?;
+        if !RESOURCES.iter().any(|(u, _, _)| *u == uri) {
+            return Err((super::INVALID_PARAMS, format!("unknown resource {uri}")));
+        }
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or((super::INTERNAL_ERROR, "iris memory needs a database (DATABASE_URL)".to_string()))?;
+        let body = match uri {
+            "iris://knowledge" => memory::semantic::recent(pool, RESOURCE_LIMIT).await.map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|k| json!({
+                        "id": k.id, "summary": k.summary, "pinned": k.pinned, "created_at": k.created_at
+                    }))
+                    .collect::<Vec<_>>()
+            }),
+            "iris://narrative" => narrative::fetch_recent(pool, RESOURCE_LIMIT).await.map(narrative_json),
+            _ => self_model::list_all(pool).await.map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|e| json!({ "key": e.key, "value": e.value, "updated_at": e.updated_at }))
+                    .collect()
+            }),
+        }
+        .map_err(|e| (super::INTERNAL_ERROR, e.to_string()))?;
+        Ok(json!({ "contents": [{
+            "uri": uri,
+            "mimeType": "application/json",
+            "text": serde_json::to_string_pretty(&body).unwrap_or_default()
+        }] }))
+    }
+}
+
+/// Tools backed directly by iris's identity tables.
+fn memory_tools() -> Vec<ToolDefinition> {
+    vec![
+        ToolDefinition {
+            name: "narrative".into(),
+            description: "Recent events from iris's narrative (milestones, capability changes, recoveries), newest first."
+                .into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT },
+                    "event_type": {
+                        "type": "string",
+                        "enum": ["capability_gained", "capability_lost", "capability_quarantined",
+                                 "goal_achieved", "milestone_reached", "error_recovery", "other"]
+                    }
+                },
+                "additionalProperties": false
+            }),
+        },
+        ToolDefinition {
+            name: "self_model".into(),
+            description: "Read iris's self-model: one entry by key, or all entries when key is omitted.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": { "key": { "type": "string", "minLength": 1 } },
+                "additionalProperties": false
+            }),
+        },
+    ]
+}
+
+fn narrative_json(events: Vec<crate::types::NarrativeEvent>) -> Vec<Value> {
+    events
+        .into_iter()
+        .map(|e| json!({
+            "occurred_at": e.occurred_at,
+            "event_type": e.event_type.as_str(),
+            "description": e.description,
+            "significance": e.significance
+        }))
+        .collect()
+}
+
+async fn narrative_tool(pool: &PgPool, args: &Value) -> Result<String, String> {
+    let limit = args.get("limit").and_then(Value::as_i64).unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let events = match args.get("event_type").and_then(Value::as_str) {
+        Some(kind) => narrative::fetch_by_type(pool, NarrativeEventType::parse(kind), limit).await,
+        None => narrative::fetch_recent(pool, limit).await,
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(Value::Array(narrative_json(events)).to_string())
+}
+
+async fn self_model_tool(pool: &PgPool, args: &Value) -> Result<String, String> {
+    match args.get("key").and_then(Value::as_str) {
+        Some(key) => match self_model::get(pool, key).await.map_err(|e| e.to_string())? {
+            Some(entry) => Ok(json!({ "key": entry.key, "value": entry.value }).to_string()),
+            None => Err(format!("no self-model entry {key}")),
+        },
+        None => {
+            let entries = self_model::list_all(pool).await.map_err(|e| e.to_string())?;
+            let map: serde_json::Map<String, Value> = entries.into_iter().map(|e| (e.key, e.value)).collect();
+            Ok(Value::Object(map).to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn server() -> IrisMcp {
+        let registry = BuiltinRegistry::new();
+        registry.apply_config(&IrisCfg::default(), None).await;
+        IrisMcp { registry, pool: None }
+    }
+
+    async fn call(server: &IrisMcp, method: &str, params: Value) -> Value {
+        let line = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }).to_string();
+        server.handle_line(&line).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn initialize_and_list_builtin_tools() {
+        let server = server().await;
+        let init = call(&server, "initialize", json!({})).await;
+        assert_eq!(init["id"], 7);
+        assert_eq!(init["result"]["serverInfo"]["name"], "iris");
+
+        let tools = call(&server, "tools/list", json!({})).await;
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|t| t["name"].as_str())
+            .collect();
+        assert!(names.contains(&"read_file") && names.contains(&"recall"));
+        // Identity tools need the database.
+        assert!(!names.contains(&"narrative"));
+        assert!(tools["result"]["tools"][0]["inputSchema"].is_object());
+    }
+
+    #[tokio::test]
+    async fn tool_calls_go_through_schema_and_path_policy() {
+        let server = server().await;
+        let dir = tempfile::tempdir_in("/tmp").unwrap();
+        let file = dir.path().join("note.txt");
+        std::fs::write(&file, "hello from iris").unwrap();
+
+        let ok = call(&server, "tools/call", json!({ "name": "read_file", "arguments": { "path": file } })).await;
+        assert_eq!(ok["result"]["isError"], false);
+        assert!(ok["result"]["content"][0]["text"].as_str().unwrap().contains("hello from iris"));
+
+        let bad = call(&server, "tools/call", json!({ "name": "read_file", "arguments": {} })).await;
+        assert_eq!(bad["result"]["isError"], true);
+
+        let denied = call(&server, "tools/call", json!({ "name": "read_file", "arguments": { "path": "/etc/shadow" } })).await;
+        assert_eq!(denied["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn notifications_are_silent_and_unknown_methods_fail() {
+        let server = server().await;
+        let note = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }).to_string();
+        assert!(server.handle_line(&note).await.is_none());
+
+        let unknown = call(&server, "prompts/list", json!({})).await;
+        assert_eq!(unknown["error"]["code"], super::super::METHOD_NOT_FOUND);
+
+        let resource = call(&server, "resources/read", json!({ "uri": "iris://narrative" })).await;
+        assert!(resource["error"]["message"].as_str().unwrap().contains("database"));
+
+        let garbage = server.handle_line("{not json").await.unwrap();
+        assert_eq!(garbage["error"]["code"], super::super::PARSE_ERROR);
+    }
+}
@@ -2,3 +2,9 @@ pub mod lifecycle;
 pub mod db;
 pub mod process_manager;
 pub mod builtin;
+pub mod net_policy;
+pub mod path_policy;
+pub mod mcp;
+pub mod grants;
+pub mod self_test;
+pub mod versions;
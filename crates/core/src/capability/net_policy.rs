@@ -0,0 +1,345 @@
+//! Host allow/deny rules and network state shared by network builtins.
+//!
+//! Rules live in the `host_rule` table and are loaded into a `NetContext` at boot;
+//! the tick loop keeps its network state current so fetches fail fast when offline.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+
+use sqlx::PgPool;
+
+use crate::environment::hardware::NetworkState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostRuleKind {
+    Allow,
+    Deny,
+}
+
+impl HostRuleKind {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+
+    pub fn from_db(s: &str) -> Option<Self> {
+        match s {
+            "allow" => Some(Self::Allow),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// A rule for a host and all of its subdomains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostRule {
+    pub host: String,
+    pub kind: HostRuleKind,
+}
+
+/// Host policy: deny rules always win; a non-empty allowlist admits only its hosts.
+/// Loopback and private addresses, and names resolving to them, are refused
+/// unless explicitly allowed.
+#[derive(Debug, Clone, Default)]
+pub struct HostPolicy {
+    rules: Vec<HostRule>,
+}
+
+impl HostPolicy {
+    pub fn new(rules: Vec<HostRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[HostRule] {
+        &self.rules
+    }
+
+    /// Check a URL host; the error explains why it was refused.
+    pub fn check(&self, host: &str) -> Result<(), String> {
+        let host = normalize_host(host);
+        if host.is_empty() {
+            return Err("URL has no host".into());
+        }
+        if self.matching(&host, HostRuleKind::Deny) {
+            return Err(format!("host {host} is denied"));
+        }
+        let allowed = self.matching(&host, HostRuleKind::Allow);
+        let has_allowlist = self.rules.iter().any(|r| r.kind == HostRuleKind::Allow);
+        if has_allowlist && !allowed {
+            return Err(format!("host {host} is not in the allowlist"));
+        }
+        if !allowed && is_internal_host(&host) {
+            return Err(format!(
+                "host {host} is a local or private address; allow it explicitly first"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the addresses a host resolved to: unless the host is explicitly
+    /// allowed, none of them may be local or private.
+    pub fn check_addrs(&self, host: &str, addrs: &[IpAddr]) -> Result<(), String> {
+        let host = normalize_host(host);
+        if self.matching(&host, HostRuleKind::Allow) {
+            return Ok(());
+        }
+        match addrs.iter().find(|ip| is_internal_ip(**ip)) {
+            Some(ip) => Err(format!(
+                "host {host} resolves to a local or private address ({ip}); allow it explicitly first"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Check a host, resolve it and check every address it resolved to. Callers
+    /// should connect only to the returned addresses so a second lookup cannot
+    /// swap in an address that was never checked.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+        self.check(host)?;
+        let host = normalize_host(host);
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| format!("failed to resolve {host}: {e}"))?
+                .collect(),
+        };
+        if addrs.is_empty() {
+            return Err(format!("host {host} did not resolve to any address"));
+        }
+        let ips: Vec<IpAddr> = addrs.iter().map(|a| a.ip()).collect();
+        self.check_addrs(&host, &ips)?;
+        Ok(addrs)
+    }
+
+    fn matching(&self, host: &str, kind: HostRuleKind) -> bool {
+        self.rules
+            .iter()
+            .any(|r| r.kind == kind && host_matches(host, &r.host))
+    }
+}
+
+/// Shared state for network builtins: host policy plus last known network state.
+#[derive(Debug)]
+pub struct NetContext {
+    policy: RwLock<HostPolicy>,
+    network: RwLock<NetworkState>,
+}
+
+impl Default for NetContext {
+    fn default() -> Self {
+        Self {
+            policy: RwLock::new(HostPolicy::default()),
+            network: RwLock::new(NetworkState::Unknown),
+        }
+    }
+}
+
+impl NetContext {
+    pub fn shared() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn policy(&self) -> HostPolicy {
+        self.policy.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn set_policy(&self, policy: HostPolicy) {
+        if let Ok(mut p) = self.policy.write() {
+            *p = policy;
+        }
+    }
+
+    pub fn network(&self) -> NetworkState {
+        self.network
+            .read()
+            .map(|n| *n)
+            .unwrap_or(NetworkState::Unknown)
+    }
+
+    pub fn set_network(&self, state: NetworkState) {
+        if let Ok(mut n) = self.network.write() {
+            *n = state;
+        }
+    }
+}
+
+/// Load all host rules.
+pub async fn load_rules(pool: &PgPool) -> Result<Vec<HostRule>, sqlx::Error> {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT host, rule FROM host_rule ORDER BY host")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(host, rule)| HostRuleKind::from_db(&rule).map(|kind| HostRule { host, kind }))
+        .collect())
+}
+
+/// Insert or replace the rule for a host.
+pub async fn set_rule(pool: &PgPool, host: &str, kind: HostRuleKind) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO host_rule (host, rule) VALUES ($1, $2) \
+         ON CONFLICT (host) DO UPDATE SET rule = $2, created_at = now()",
+    )
+    .bind(normalize_host(host))
+    .bind(kind.as_db_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remove the rule for a host. Returns whether one existed.
+pub async fn remove_rule(pool: &PgPool, host: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM host_rule WHERE host = $1")
+        .bind(normalize_host(host))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Lowercase, strip brackets around IPv6 literals and a trailing dot.
+pub fn normalize_host(host: &str) -> String {
+    host.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_lowercase()
+}
+
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let pattern = normalize_host(pattern);
+    let pattern = pattern.trim_start_matches("*.");
+    host == pattern
+        || host
+            .strip_suffix(pattern)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_internal_host(host: &str) -> bool {
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") {
+        return true;
+    }
+    host.parse::<IpAddr>().is_ok_and(is_internal_ip)
+}
+
+/// Loopback, private, link-local, CGNAT and other non-public addresses,
+/// including IPv4 addresses embedded in IPv6.
+fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_internal_v4(v4);
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // unique local
+                || (first & 0xffc0) == 0xfe80 // link local
+        }
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0 // "this" network
+        || (a == 100 && (b & 0xc0) == 64) // CGNAT 100.64.0.0/10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(host: &str, kind: HostRuleKind) -> HostRule {
+        HostRule {
+            host: host.into(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn empty_policy_allows_public_hosts_only() {
+        let policy = HostPolicy::default();
+        assert!(policy.check("example.com").is_ok());
+        assert!(policy.check("127.0.0.1").is_err());
+        assert!(policy.check("localhost").is_err());
+        assert!(policy.check("192.168.1.10").is_err());
+        assert!(policy.check("[::1]").is_err());
+        assert!(policy.check("[::ffff:127.0.0.1]").is_err());
+        assert!(policy.check("[::ffff:10.1.2.3]").is_err());
+        assert!(policy.check("100.64.0.1").is_err());
+        assert!(policy.check("100.127.255.255").is_err());
+        assert!(policy.check("100.128.0.1").is_ok());
+        assert!(policy.check("[2606:4700::1111]").is_ok());
+    }
+
+    #[test]
+    fn resolved_addresses_are_checked() {
+        let public: IpAddr = "93.184.216.34".parse().unwrap();
+        let private: IpAddr = "10.0.0.7".parse().unwrap();
+        let mapped: IpAddr = "::ffff:192.168.0.1".parse().unwrap();
+
+        let policy = HostPolicy::default();
+        assert!(policy.check_addrs("example.com", &[public]).is_ok());
+        let err = policy.check_addrs("rebind.example", &[public, private]).unwrap_err();
+        assert!(err.contains("resolves to a local or private address (10.0.0.7)"), "{err}");
+        assert!(policy.check_addrs("rebind.example", &[mapped]).is_err());
+
+        // An explicit allow rule admits internal addresses
+        let policy = HostPolicy::new(vec![rule("nas.example", HostRuleKind::Allow)]);
+        assert!(policy.check_addrs("nas.example", &[private]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn resolve_checks_literals_before_connecting() {
+        let policy = HostPolicy::default();
+        assert!(policy.resolve("127.0.0.1", 80).await.unwrap_err().contains("local or private"));
+        assert!(policy.resolve("[::ffff:7f00:1]", 80).await.is_err());
+
+        let policy = HostPolicy::new(vec![rule("127.0.0.1", HostRuleKind::Allow)]);
+        let addrs = policy.resolve("127.0.0.1", 8080).await.unwrap();
+        assert_eq!(addrs, vec!["127.0.0.1:8080".parse().unwrap()]);
+    }
+
+    #[test]
+    fn deny_covers_subdomains_and_wins() {
+        let policy = HostPolicy::new(vec![
+            rule("example.com", HostRuleKind::Deny),
+            rule("api.example.com", HostRuleKind::Allow),
+        ]);
+        assert!(policy.check("example.com").is_err());
+        assert!(policy.check("api.example.com").is_err());
+        assert!(policy.check("notexample.com").is_err()); // allowlist non-empty
+    }
+
+    #[test]
+    fn allowlist_restricts_and_admits_local() {
+        let policy = HostPolicy::new(vec![
+            rule("docs.rs", HostRuleKind::Allow),
+            rule("127.0.0.1", HostRuleKind::Allow),
+        ]);
+        assert!(policy.check("docs.rs").is_ok());
+        assert!(policy.check("Static.Docs.RS.").is_ok());
+        assert!(policy.check("127.0.0.1").is_ok());
+        assert!(policy.check("crates.io").is_err());
+    }
+
+    #[test]
+    fn context_tracks_network_state() {
+        let ctx = NetContext::shared();
+        assert_eq!(ctx.network(), NetworkState::Unknown);
+        ctx.set_network(NetworkState::Offline);
+        assert_eq!(ctx.network(), NetworkState::Offline);
+    }
+}
@@ -0,0 +1,372 @@
+//! Path access policy for file builtins: allowed roots, denied globs for
+//! secrets, a size cap, binary detection and symlink-escape checks.
+//!
+//! Configured from `iris_config` (`file_allowed_roots`, `file_denied_globs`,
+//! `file_max_bytes`). Paths are checked after symlinks are resolved, and deny
+//! globs are matched against both the requested and the resolved path.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use regex::Regex;
+
+use crate::capability::builtin::walk;
+use crate::config::IrisCfg;
+
+/// Default secret globs; a match on a directory covers everything inside it.
+pub const DEFAULT_DENIED_GLOBS: &str = "**/.ssh,**/.gnupg,**/.aws,**/.kube,**/.docker/config.json,\
+**/.netrc,**/.pgpass,**/.git-credentials,**/.env,**/.env.*,**/*.pem,**/*.key,**/id_rsa*,**/id_ed25519*,\
+**/id_ecdsa*,/etc/shadow,/etc/gshadow,/etc/sudoers,/etc/sudoers.d,/proc/*/environ";
+
+/// Bytes inspected for NUL when deciding whether a file is binary.
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+#[derive(Debug, Clone)]
+pub struct PathPolicy {
+    /// Canonical allowed roots; empty means any path (deny globs still apply).
+    roots: Vec<PathBuf>,
+    denied: Vec<(String, Regex)>,
+    max_bytes: u64,
+}
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        Self::from_cfg(&IrisCfg::default())
+    }
+}
+
+impl PathPolicy {
+    /// Build from config. `~` expands to `$HOME`; relative roots resolve against the working directory.
+    pub fn from_cfg(cfg: &IrisCfg) -> Self {
+        let roots = split_list(&cfg.file_allowed_roots)
+            .map(|r| {
+                let path = expand_home(r);
+                path.canonicalize().unwrap_or(path)
+            })
+            .collect();
+        let denied = split_list(&cfg.file_denied_globs)
+            .filter_map(|g| {
+                let pattern = expand_home(g).to_string_lossy().into_owned();
+                match walk::glob_to_regex(&pattern) {
+                    Ok(re) => Some((g.to_string(), re)),
+                    Err(e) => {
+                        tracing::warn!(glob = g, error = %e, "ignoring invalid file_denied_globs entry");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self {
+            roots,
+            denied,
+            max_bytes: cfg.file_max_bytes,
+        }
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Check an existing file for reading; returns its resolved path.
+    pub fn check_read(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = self.resolve(path)?;
+        let meta =
+            std::fs::metadata(&resolved).map_err(|e| format!("cannot access {path}: {e}"))?;
+        if meta.is_dir() {
+            return Err(format!("{path} is a directory; use list_dir"));
+        }
+        if meta.len() > self.max_bytes {
+            return Err(format!(
+                "{path} is {} bytes, over the {} byte limit for file tools",
+                meta.len(),
+                self.max_bytes
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Check a file that is about to be written with `len` bytes; the file may not exist yet.
+    pub fn check_write(&self, path: &str, len: usize) -> Result<PathBuf, String> {
+        if len as u64 > self.max_bytes {
+            return Err(format!(
+                "refusing to write {len} bytes to {path}: over the {} byte limit",
+                self.max_bytes
+            ));
+        }
+        let resolved = self.resolve(path)?;
+        if resolved.is_dir() {
+            return Err(format!("{path} is a directory"));
+        }
+        Ok(resolved)
+    }
+
+    /// Check a directory to list or search; returns its resolved path.
+    pub fn check_dir(&self, path: &str) -> Result<PathBuf, String> {
+        self.resolve(path)
+    }
+
+    /// Whether a path (or one of its ancestors) matches a deny glob.
+    pub fn is_denied(&self, path: &Path) -> Option<&str> {
+        let mut current = Some(path);
+        while let Some(p) = current {
+            let text = p.to_string_lossy();
+            if let Some((glob, _)) = self.denied.iter().find(|(_, re)| re.is_match(&text)) {
+                return Some(glob);
+            }
+            current = p.parent();
+        }
+        None
+    }
+
+    /// Resolve symlinks (for files that do not exist yet, of the nearest existing
+    /// ancestor) and apply the root and deny checks to the result.
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        if path.trim().is_empty() {
+            return Err("empty path".into());
+        }
+        let requested = absolute(&expand_home(path));
+        if let Some(glob) = self.is_denied(&requested) {
+            return Err(format!("access to {path} is denied by policy ({glob})"));
+        }
+
+        let mut existing = requested.as_path();
+        let mut rest = Vec::new();
+        let resolved = loop {
+            match existing.canonicalize() {
+                Ok(real) => break rest.iter().rev().fold(real, |acc: PathBuf, c| acc.join(c)),
+                Err(_) => {
+                    let (Some(parent), Some(name)) = (existing.parent(), existing.file_name())
+                    else {
+                        return Err(format!("cannot resolve {path}"));
+                    };
+                    rest.push(name.to_os_string());
+                    existing = parent;
+                }
+            }
+        };
+
+        if let Some(glob) = self.is_denied(&resolved) {
+            return Err(format!(
+                "access to {path} is denied by policy ({glob}, resolved to {})",
+                resolved.display()
+            ));
+        }
+        if !self.roots.is_empty() && !self.roots.iter().any(|r| resolved.starts_with(r)) {
+            let escaped =
+                resolved != requested && self.roots.iter().any(|r| requested.starts_with(r));
+            let roots: Vec<String> = self.roots.iter().map(|r| r.display().to_string()).collect();
+            return Err(if escaped {
+                format!(
+                    "{path} resolves to {} through a symlink, outside the allowed roots ({})",
+                    resolved.display(),
+                    roots.join(", ")
+                )
+            } else {
+                format!("{path} is outside the allowed roots ({})", roots.join(", "))
+            });
+        }
+        Ok(resolved)
+    }
+}
+
+/// Shared, reconfigurable policy handed to file builtins.
+#[derive(Debug, Default)]
+pub struct FileAccess {
+    policy: RwLock<PathPolicy>,
+}
+
+impl FileAccess {
+    pub fn shared() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn policy(&self) -> PathPolicy {
+        self.policy.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn set_policy(&self, policy: PathPolicy) {
+        if let Ok(mut p) = self.policy.write() {
+            *p = policy;
+        }
+    }
+}
+
+/// Heuristic binary check: a NUL byte near the start, as git does.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_SNIFF_BYTES).any(|&b| b == 0)
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Per-user data directory for `name`: `~/.iris/<name>`, or a directory under
+/// the system temp dir keyed by user id when `$HOME` is unset.
+pub(crate) fn data_dir(name: &str) -> PathBuf {
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".iris").join(name),
+        // SAFETY: getuid has no preconditions and cannot fail
+        None => std::env::temp_dir().join(format!("iris-{}", unsafe { libc::getuid() })).join(name),
+    }
+}
+
+/// Create `dir` (and missing parents) accessible only to the owner (0700),
+/// tightening it if it already exists.
+pub(crate) fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700).create(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    builder.create(dir)
+}
+
+/// Write a file readable only by the owner (0600).
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(bytes)
+}
+
+/// Absolute, lexically normalized path (`.` and `..` removed) without touching the filesystem.
+fn absolute(path: &Path) -> PathBuf {
+    let base = if path.is_absolute() {
+        PathBuf::new()
+    } else {
+        std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))
+    };
+    let mut out = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(roots: &Path) -> PathPolicy {
+        PathPolicy::from_cfg(&IrisCfg {
+            file_allowed_roots: roots.to_string_lossy().into_owned(),
+            file_max_bytes: 16,
+            ..IrisCfg::default()
+        })
+    }
+
+    #[test]
+    fn denies_secrets_anywhere() {
+        let p = PathPolicy::from_cfg(&IrisCfg {
+            file_allowed_roots: String::new(),
+            ..IrisCfg::default()
+        });
+        assert!(
+            p.check_read("/etc/shadow")
+                .unwrap_err()
+                .contains("denied by policy")
+        );
+        assert!(p.is_denied(Path::new("/home/u/.ssh/id_rsa")).is_some());
+        assert!(p.is_denied(Path::new("/srv/app/.env")).is_some());
+        assert!(p.is_denied(Path::new("/srv/app/main.rs")).is_none());
+    }
+
+    #[test]
+    fn roots_size_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let p = policy(dir.path());
+        let small = dir.path().join("small.txt");
+        std::fs::write(&small, "hi").unwrap();
+        std::fs::write(dir.path().join("big.txt"), "x".repeat(32)).unwrap();
+
+        assert!(p.check_read(small.to_str().unwrap()).is_ok());
+        assert!(
+            p.check_read(dir.path().join("big.txt").to_str().unwrap())
+                .unwrap_err()
+                .contains("byte limit")
+        );
+        assert!(
+            p.check_read("/etc/hostname")
+                .unwrap_err()
+                .contains("outside the allowed roots")
+        );
+        // New files under a root are fine; `..` cannot climb out.
+        assert!(
+            p.check_write(dir.path().join("new/file.txt").to_str().unwrap(), 4)
+                .is_ok()
+        );
+        let climb = format!("{}/../escape.txt", dir.path().display());
+        assert!(p.check_write(&climb, 4).is_err());
+        assert!(
+            p.check_write(small.to_str().unwrap(), 64)
+                .unwrap_err()
+                .contains("byte limit")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escape_is_refused() {
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("secret.txt");
+        std::fs::write(&target, "s").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let err = policy(dir.path())
+            .check_read(link.to_str().unwrap())
+            .unwrap_err();
+        assert!(err.contains("through a symlink"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_dirs_and_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("a/b");
+        create_private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        create_private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700, "an existing directory is tightened");
+
+        let file = dir.join("blob");
+        write_private(&file, b"secret").unwrap();
+        assert_eq!(mode(&file), 0o600);
+        assert_eq!(std::fs::read(&file).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn binary_sniffing() {
+        assert!(looks_binary(b"\x7fELF\0\0"));
+        assert!(!looks_binary("plain text".as_bytes()));
+    }
+}
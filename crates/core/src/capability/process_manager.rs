@@ -279,6 +279,18 @@ impl ProcessManager {
         self.children.contains_key(&cap_id)
     }
 
+    /// Version the running process was spawned from, if it is running.
+    pub fn running_version(&self, cap_id: Uuid) -> Option<Option<Uuid>> {
+        self.children.get(&cap_id).map(|h| h.record.current_version)
+    }
+
+    /// Update the lifecycle state kept for a running capability, e.g. once confirmed.
+    pub fn set_state(&mut self, cap_id: Uuid, state: CapabilityState) {
+        if let Some(handle) = self.children.get_mut(&cap_id) {
+            handle.record.state = state;
+        }
+    }
+
     /// Check if a capability has been running for at least `duration`.
     pub fn has_been_running_for(&self, cap_id: Uuid, duration: Duration) -> bool {
         self.children
@@ -310,9 +322,11 @@ mod tests {
                 permissions: vec![],
                 resource_limits: serde_json::json!({"memory_mb": 128}),
                 keywords: vec![],
+                self_tests: vec![],
             },
             state: CapabilityState::Confirmed,
             lkg_version: None,
+            current_version: None,
             quarantine_count: 0,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
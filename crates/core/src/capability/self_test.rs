@@ -0,0 +1,392 @@
+//! Self-test harness run before a staged capability is promoted.
+//!
+//! Each manifest case is sent over NDJSON through the `ProcessManager` with the
+//! manifest's invocation timeout. A reply passes when it echoes the request id,
+//! reports only declared side effects and matches the case's expected shape or
+//! JSON Schema. Results are recorded in `capability_self_test`, and the outcome
+//! drives staged → active_candidate (all passed) or staged → quarantined.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::process_manager::ProcessManager;
+use super::{db as capability_db, grants, lifecycle};
+use crate::cognition::schema;
+use crate::identity::narrative;
+use crate::types::{
+    CapabilityRecord, CapabilityRequest, CapabilityResponse, CapabilityState, NarrativeEventType, SelfTestCase,
+};
+
+/// Invocation timeout when the manifest declares none (ms).
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// Outcome of one self-test case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    pub name: String,
+    pub passed: bool,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Outcome of a full self-test run.
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub run_id: Uuid,
+    pub capability_id: Uuid,
+    pub cases: Vec<CaseResult>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        !self.cases.is_empty() && self.cases.iter().all(|c| c.passed)
+    }
+
+    /// First failure as `case: error`, for logs and narrative.
+    pub fn first_failure(&self) -> Option<String> {
+        self.cases
+            .iter()
+            .find(|c| !c.passed)
+            .map(|c| format!("{}: {}", c.name, c.error.as_deref().unwrap_or("failed")))
+    }
+}
+
+/// Liveness case used when a manifest declares no self-tests.
+pub fn ping_case() -> SelfTestCase {
+    SelfTestCase {
+        name: "ping".into(),
+        method: "self_test".into(),
+        params: serde_json::json!({}),
+        expect_shape: None,
+        expect_schema: None,
+        expect_error: false,
+    }
+}
+
+/// Check that `actual` has the shape of `expected`: every object key present,
+/// array items shaped like the first expected item, scalars of the same JSON
+/// type. `null` in `expected` accepts anything.
+pub fn shape_matches(expected: &Value, actual: &Value, path: &str) -> Result<(), String> {
+    match (expected, actual) {
+        (Value::Null, _) => Ok(()),
+        (Value::Object(exp), Value::Object(act)) => {
+            for (key, exp_value) in exp {
+                let child = format!("{path}.{key}");
+                let act_value = act.get(key).ok_or_else(|| format!("{child}: missing"))?;
+                shape_matches(exp_value, act_value, &child)?;
+            }
+            Ok(())
+        }
+        (Value::Array(exp), Value::Array(act)) => match exp.first() {
+            Some(item) => act
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, v)| shape_matches(item, v, &format!("{path}[{i}]"))),
+            None => Ok(()),
+        },
+        (Value::Bool(_), Value::Bool(_)) | (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_)) => {
+            Ok(())
+        }
+        _ => Err(format!("{path}: expected {}, got {}", type_name(expected), type_name(actual))),
+    }
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Check one reply against its case and the capability's declared permissions.
+pub fn check_response(
+    case: &SelfTestCase,
+    request_id: Uuid,
+    resp: &CapabilityResponse,
+    record: &CapabilityRecord,
+) -> Result<(), String> {
+    if resp.id != request_id {
+        return Err(format!("response id {} does not match request {request_id}", resp.id));
+    }
+    let violations = grants::violations(&record.manifest.permissions, &[], &resp.side_effects);
+    if !violations.is_empty() {
+        return Err(format!("undeclared side effects: {}", grants::join(&violations)));
+    }
+    match (&resp.error, case.expect_error) {
+        (Some(e), false) => return Err(format!("capability error: {e}")),
+        (None, true) => return Err("expected an error response".into()),
+        (Some(_), true) => return Ok(()),
+        (None, false) => {}
+    }
+    let result = resp.result.clone().unwrap_or(Value::Null);
+    if let Some(shape) = &case.expect_shape {
+        shape_matches(shape, &result, "$")?;
+    }
+    if let Some(schema_value) = &case.expect_schema {
+        schema::validate(&result, schema_value).map_err(|violations| {
+            violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+        })?;
+    }
+    Ok(())
+}
+
+fn invocation_timeout(record: &CapabilityRecord) -> Duration {
+    let ms = record
+        .manifest
+        .resource_limits
+        .get("timeout_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_TIMEOUT_MS);
+    Duration::from_millis(ms)
+}
+
+/// Spawn the capability through `pm`, run every case and stop the process.
+pub async fn run(pm: &mut ProcessManager, record: &CapabilityRecord) -> SelfTestReport {
+    let cases = if record.manifest.self_tests.is_empty() {
+        vec![ping_case()]
+    } else {
+        record.manifest.self_tests.clone()
+    };
+    let mut report = SelfTestReport {
+        run_id: Uuid::new_v4(),
+        capability_id: record.id,
+        cases: Vec::with_capacity(cases.len()),
+    };
+    let fail = |name: &str, error: String, elapsed_ms: u64| CaseResult {
+        name: name.to_string(),
+        passed: false,
+        error: Some(error),
+        elapsed_ms,
+    };
+
+    if let Err(e) = pm.spawn(record) {
+        report.cases = cases.iter().map(|c| fail(&c.name, e.to_string(), 0)).collect();
+        return report;
+    }
+
+    let timeout = invocation_timeout(record);
+    let mut dead: Option<String> = None;
+    for case in &cases {
+        if let Some(reason) = &dead {
+            report.cases.push(fail(&case.name, format!("not run: {reason}"), 0));
+            continue;
+        }
+        let request = CapabilityRequest {
+            id: Uuid::new_v4(),
+            method: case.method.clone(),
+            params: case.params.clone(),
+            version: 1,
+        };
+        let request_id = request.id;
+        let started = Instant::now();
+        let outcome = pm.invoke(record.id, request, timeout).await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let checked = match outcome {
+            Ok(resp) => check_response(case, request_id, &resp, record),
+            Err(e) => {
+                // The process is gone or out of sync; later cases cannot run
+                dead = Some(e.to_string());
+                Err(e.to_string())
+            }
+        };
+        report.cases.push(match checked {
+            Ok(()) => CaseResult { name: case.name.clone(), passed: true, error: None, elapsed_ms },
+            Err(e) => fail(&case.name, e, elapsed_ms),
+        });
+    }
+
+    pm.kill(record.id);
+    report
+}
+
+/// Store one row per case.
+pub async fn record_results(pool: &PgPool, report: &SelfTestReport) -> Result<(), sqlx::Error> {
+    for case in &report.cases {
+        sqlx::query(
+            "INSERT INTO capability_self_test (id, run_id, capability_id, case_name, passed, error, elapsed_ms) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(report.run_id)
+        .bind(report.capability_id)
+        .bind(&case.name)
+        .bind(case.passed)
+        .bind(&case.error)
+        .bind(case.elapsed_ms as i64)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Self-test a staged capability, record the results and promote or quarantine it.
+/// Returns the new state.
+pub async fn run_and_promote(
+    pool: &PgPool,
+    pm: &mut ProcessManager,
+    record: &CapabilityRecord,
+) -> Result<(CapabilityState, SelfTestReport), String> {
+    let report = run(pm, record).await;
+    if let Err(e) = record_results(pool, &report).await {
+        tracing::warn!(error = %e, "failed to record self-test results");
+    }
+
+    let state = if report.passed() {
+        CapabilityState::ActiveCandidate
+    } else {
+        CapabilityState::Quarantined
+    };
+    lifecycle::validate_transition(record.state, state).map_err(|e| e.to_string())?;
+    capability_db::update_state(pool, record.id, state)
+        .await
+        .map_err(|e| format!("failed to update capability state: {e}"))?;
+
+    let name = &record.name;
+    let evt = match report.first_failure() {
+        None => {
+            tracing::info!(capability = %name, cases = report.cases.len(), "self-test passed, promoted to active candidate");
+            narrative::new_event(
+                NarrativeEventType::CapabilityGained,
+                format!("capability {name} passed {} self-test case(s)", report.cases.len()),
+                0.7,
+            )
+        }
+        Some(failure) => {
+            tracing::warn!(capability = %name, failure = %failure, "self-test failed, quarantined");
+            if let Err(e) = capability_db::increment_quarantine(pool, record.id).await {
+                tracing::warn!(error = %e, "failed to increment quarantine count");
+            }
+            narrative::new_event(
+                NarrativeEventType::CapabilityQuarantined,
+                format!("capability {name} quarantined (self-test failed: {failure})"),
+                0.5,
+            )
+        }
+    };
+    let _ = narrative::record(pool, &evt).await;
+    Ok((state, report))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::{CapabilityManifest, Permission};
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+    use tokio_util::sync::CancellationToken;
+
+    pub(crate) fn record_for(script: &Path, permissions: Vec<Permission>, self_tests: Vec<SelfTestCase>) -> CapabilityRecord {
+        let now = chrono::Utc::now();
+        CapabilityRecord {
+            id: Uuid::new_v4(),
+            name: "test_cap".into(),
+            binary_path: script.display().to_string(),
+            manifest: CapabilityManifest {
+                name: "test_cap".into(),
+                binary_path: script.display().to_string(),
+                permissions,
+                resource_limits: json!({"memory_mb": 512, "timeout_ms": 2000}),
+                keywords: Vec::new(),
+                self_tests,
+            },
+            state: CapabilityState::Staged,
+            lkg_version: None,
+            current_version: None,
+            quarantine_count: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub(crate) fn script(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("cap.sh");
+        std::fs::write(&path, format!("#!/bin/bash\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// Fake capability answering every request with `result` and `side_effects`.
+    pub(crate) fn replying(result: &str, side_effects: &str) -> String {
+        format!(
+            r#"while read -r line; do
+id=$(printf '%s' "$line" | sed 's/.*"id":"\([^"]*\)".*/\1/')
+printf '{{"id":"%s","result":{result},"error":null,"metrics":null,"side_effects":{side_effects}}}\n' "$id"
+done"#
+        )
+    }
+
+    fn case(name: &str, shape: Option<Value>, schema: Option<Value>) -> SelfTestCase {
+        SelfTestCase {
+            name: name.into(),
+            method: "lookup".into(),
+            params: json!({"q": "x"}),
+            expect_shape: shape,
+            expect_schema: schema,
+            expect_error: false,
+        }
+    }
+
+    #[test]
+    fn shapes_compare_keys_and_types() {
+        let expected = json!({"temp": 1.5, "tags": ["a"], "meta": null});
+        assert!(shape_matches(&expected, &json!({"temp": 20, "tags": ["x", "y"], "meta": {}, "extra": 1}), "$").is_ok());
+        assert_eq!(shape_matches(&expected, &json!({"temp": 20, "tags": []}), "$").unwrap_err(), "$.meta: missing");
+        assert_eq!(
+            shape_matches(&expected, &json!({"temp": "hot", "tags": [], "meta": 1}), "$").unwrap_err(),
+            "$.temp: expected number, got string"
+        );
+        assert!(shape_matches(&expected, &json!({"temp": 1, "tags": [3], "meta": 1}), "$").unwrap_err().contains("tags[0]"));
+    }
+
+    #[tokio::test]
+    async fn declared_cases_pass_against_matching_replies() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = vec![
+            case("shape", Some(json!({"temp": 0, "unit": "C"})), None),
+            case("schema", None, Some(json!({"type": "object", "required": ["temp"], "properties": {"temp": {"type": "number"}}}))),
+        ];
+        let bin = script(dir.path(), &replying(r#"{"temp":21.5,"unit":"C"}"#, r#"["NetworkRead"]"#));
+        let record = record_for(&bin, vec![Permission::NetworkRead], cases);
+        let mut pm = ProcessManager::new(CancellationToken::new());
+        let report = run(&mut pm, &record).await;
+        assert!(report.passed(), "{:?}", report.cases);
+        assert_eq!(report.cases.len(), 2);
+        assert!(!pm.is_running(record.id));
+    }
+
+    #[tokio::test]
+    async fn mismatches_and_dead_processes_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = vec![
+            case("schema", None, Some(json!({"type": "object", "required": ["temp"]}))),
+            case("side effects", None, None),
+        ];
+        let bin = script(dir.path(), &replying(r#"{"unit":"C"}"#, r#"["FileWrite"]"#));
+        let report = run(&mut ProcessManager::new(CancellationToken::new()), &record_for(&bin, vec![], cases)).await;
+        assert!(!report.passed());
+        assert!(report.cases[0].error.as_deref().unwrap().contains("FileWrite"));
+
+        let bin = script(dir.path(), "exit 1");
+        let cases = vec![case("first", None, None), case("second", None, None)];
+        let report = run(&mut ProcessManager::new(CancellationToken::new()), &record_for(&bin, vec![], cases)).await;
+        assert!(report.cases.iter().all(|c| !c.passed));
+        assert!(report.cases[1].error.as_deref().unwrap().starts_with("not run"));
+    }
+
+    #[tokio::test]
+    async fn empty_manifest_runs_the_ping_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = script(dir.path(), &replying("null", "[]"));
+        let report = run(&mut ProcessManager::new(CancellationToken::new()), &record_for(&bin, vec![], vec![])).await;
+        assert!(report.passed());
+        assert_eq!(report.cases[0].name, "ping");
+    }
+}
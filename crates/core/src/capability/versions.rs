@@ -0,0 +1,270 @@
+//! Versioned capability builds.
+//!
+//! Every build of a capability gets a `capability_version` row holding its
+//! binary path, source, manifest and binary hash. `capability.current_version`
+//! names the installed build and `capability.lkg_version` the last confirmed
+//! one, so a crash or regression can restore an earlier confirmed binary.
+//! A rebuild is recorded first and only activated once it passes self-test.
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::types::{CapabilityManifest, CapabilityRecord, CapabilityVersion};
+
+#[derive(sqlx::FromRow)]
+struct VersionRow {
+    id: Uuid,
+    capability_id: Uuid,
+    version: i32,
+    binary_path: String,
+    source: String,
+    manifest: serde_json::Value,
+    binary_hash: String,
+    confirmed_at: Option<chrono::DateTime<chrono::Utc>>,
+    usage_count: i64,
+    success_count: i64,
+    fail_count: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<VersionRow> for CapabilityVersion {
+    fn from(row: VersionRow) -> Self {
+        let manifest = serde_json::from_value(row.manifest).unwrap_or_else(|_| CapabilityManifest {
+            name: String::new(),
+            binary_path: row.binary_path.clone(),
+            permissions: vec![],
+            resource_limits: serde_json::Value::Null,
+            keywords: vec![],
+            self_tests: vec![],
+        });
+        Self {
+            id: row.id,
+            capability_id: row.capability_id,
+            version: row.version,
+            binary_path: row.binary_path,
+            source: row.source,
+            manifest,
+            binary_hash: row.binary_hash,
+            confirmed_at: row.confirmed_at,
+            usage_count: row.usage_count,
+            success_count: row.success_count,
+            fail_count: row.fail_count,
+            created_at: row.created_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "id, capability_id, version, binary_path, source, manifest, binary_hash, confirmed_at, \
+                       usage_count, success_count, fail_count, created_at";
+
+/// SHA-256 of a binary, hex.
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// Minimum invocations of a version before its fail rate is compared.
+pub const MIN_REGRESSION_SAMPLES: i64 = 10;
+
+/// How far (absolute) a version's fail rate may exceed its predecessor's.
+pub const REGRESSION_MARGIN: f64 = 0.25;
+
+/// Record a new build as the next version. It is not installed until [`activate`].
+pub async fn insert(
+    pool: &PgPool,
+    capability_id: Uuid,
+    binary_path: &str,
+    source: &str,
+    manifest: &CapabilityManifest,
+    binary_hash: &str,
+) -> Result<CapabilityVersion, sqlx::Error> {
+    let manifest_json = serde_json::to_value(manifest).unwrap_or_default();
+    let row: VersionRow = sqlx::query_as(&format!(
+        "INSERT INTO capability_version (id, capability_id, version, binary_path, source, manifest, binary_hash)
+         SELECT $1, $2, COALESCE(MAX(version), 0) + 1, $3, $4, $5, $6 FROM capability_version WHERE capability_id = $2
+         RETURNING {COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(capability_id)
+    .bind(binary_path)
+    .bind(source)
+    .bind(&manifest_json)
+    .bind(binary_hash)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.into())
+}
+
+/// Make a version the installed one: binary, manifest and current pointer.
+pub async fn activate(pool: &PgPool, version: &CapabilityVersion) -> Result<(), sqlx::Error> {
+    let manifest_json = serde_json::to_value(&version.manifest).unwrap_or_default();
+    sqlx::query(
+        "UPDATE capability SET current_version = $1, binary_path = $2, manifest = $3, updated_at = now() WHERE id = $4",
+    )
+    .bind(version.id)
+    .bind(&version.binary_path)
+    .bind(&manifest_json)
+    .bind(version.capability_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// All versions of a capability, newest first.
+pub async fn list(pool: &PgPool, capability_id: Uuid) -> Result<Vec<CapabilityVersion>, sqlx::Error> {
+    let rows: Vec<VersionRow> = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM capability_version WHERE capability_id = $1 ORDER BY version DESC"
+    ))
+    .bind(capability_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// The installed version, recording the current binary as version 1 for
+/// capabilities registered before versions existed.
+pub async fn ensure_current(pool: &PgPool, record: &CapabilityRecord) -> Result<Uuid, String> {
+    if let Some(id) = record.current_version {
+        return Ok(id);
+    }
+    let hash = hash_file(Path::new(&record.binary_path))?;
+    let version = insert(pool, record.id, &record.binary_path, "", &record.manifest, &hash)
+        .await
+        .map_err(|e| e.to_string())?;
+    activate(pool, &version).await.map_err(|e| e.to_string())?;
+    Ok(version.id)
+}
+
+/// Mark a version confirmed and point the capability's LKG at it.
+pub async fn confirm(pool: &PgPool, capability_id: Uuid, version_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE capability_version SET confirmed_at = COALESCE(confirmed_at, now()) WHERE id = $1")
+        .bind(version_id)
+        .execute(pool)
+        .await?;
+    super::db::update_lkg(pool, capability_id, version_id).await
+}
+
+/// Install an earlier confirmed version and point the LKG back at it.
+pub async fn restore(pool: &PgPool, version: &CapabilityVersion) -> Result<(), sqlx::Error> {
+    activate(pool, version).await?;
+    super::db::update_lkg(pool, version.capability_id, version.id).await
+}
+
+/// The version to fall back to when `current` fails: the newest confirmed
+/// version older than it whose binary is still on disk.
+pub fn rollback_target(versions: &[CapabilityVersion], current: Option<Uuid>) -> Option<&CapabilityVersion> {
+    let current_number = current.and_then(|id| versions.iter().find(|v| v.id == id)).map(|v| v.version);
+    versions
+        .iter()
+        .filter(|v| Some(v.id) != current && v.confirmed_at.is_some())
+        .filter(|v| current_number.is_none_or(|n| v.version < n))
+        .filter(|v| Path::new(&v.binary_path).is_file())
+        .max_by_key(|v| v.version)
+}
+
+/// Whether `current` fails noticeably more often than `previous`, the version a
+/// rollback would restore. Needs [`MIN_REGRESSION_SAMPLES`] invocations of `current`.
+pub fn regressed(current: &CapabilityVersion, previous: &CapabilityVersion) -> bool {
+    fn fail_rate(v: &CapabilityVersion) -> f64 {
+        if v.usage_count == 0 { 0.0 } else { v.fail_count as f64 / v.usage_count as f64 }
+    }
+    current.id != previous.id
+        && current.usage_count >= MIN_REGRESSION_SAMPLES
+        && fail_rate(current) >= fail_rate(previous) + REGRESSION_MARGIN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(number: i32, confirmed: bool, binary_path: &Path) -> CapabilityVersion {
+        CapabilityVersion {
+            id: Uuid::new_v4(),
+            capability_id: Uuid::nil(),
+            version: number,
+            binary_path: binary_path.display().to_string(),
+            source: String::new(),
+            manifest: CapabilityManifest {
+                name: "weather".into(),
+                binary_path: binary_path.display().to_string(),
+                permissions: vec![],
+                resource_limits: serde_json::Value::Null,
+                keywords: vec![],
+                self_tests: vec![],
+            },
+            binary_hash: String::new(),
+            confirmed_at: confirmed.then(chrono::Utc::now),
+            usage_count: 0,
+            success_count: 0,
+            fail_count: 0,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn rollback_picks_the_newest_older_confirmed_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::write(&bin, b"binary").unwrap();
+        let missing = dir.path().join("gone");
+        let versions = vec![
+            version(4, false, &bin),
+            version(3, true, &missing),
+            version(2, true, &bin),
+            version(1, true, &bin),
+        ];
+
+        // A failing candidate falls back to v2 (v3's binary is gone)
+        assert_eq!(rollback_target(&versions, Some(versions[0].id)).map(|v| v.version), Some(2));
+        // A regressing confirmed version falls back to the one before it
+        assert_eq!(rollback_target(&versions, Some(versions[2].id)).map(|v| v.version), Some(1));
+        assert!(rollback_target(&versions, Some(versions[3].id)).is_none());
+        assert!(rollback_target(&versions[..1], Some(versions[0].id)).is_none());
+    }
+
+    fn used(mut v: CapabilityVersion, success: i64, fail: i64) -> CapabilityVersion {
+        v.usage_count = success + fail;
+        v.success_count = success;
+        v.fail_count = fail;
+        v
+    }
+
+    #[test]
+    fn regressing_v2_rolls_back_to_v1() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::write(&bin, b"binary").unwrap();
+        let v1 = used(version(1, true, &bin), 18, 2);
+        let v2 = version(2, false, &bin);
+        let history = vec![v2.clone(), v1.clone()];
+
+        // Too few samples of v2 to judge
+        let early = used(v2.clone(), 1, 4);
+        assert!(!regressed(&early, &v1));
+        // v2 fails about as often as v1 did
+        assert!(!regressed(&used(v2.clone(), 16, 4), &v1));
+
+        let failing = used(v2.clone(), 4, 8);
+        let target = rollback_target(&history, Some(failing.id)).unwrap();
+        assert_eq!(target.version, 1);
+        assert!(regressed(&failing, target));
+        // Once v1 is restored there is nothing older to compare against
+        assert!(rollback_target(&history, Some(v1.id)).is_none());
+        assert!(!regressed(&v1, &v1));
+    }
+
+    #[test]
+    fn hash_file_is_hex_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bin");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(hash_file(&dir.path().join("missing")).is_err());
+    }
+}
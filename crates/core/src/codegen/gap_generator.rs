@@ -1,24 +1,79 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use sqlx::PgPool;
 use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+use crate::config::IrisCfg;
 use crate::types::{CodegenHistory, GapDescriptor};
 use llm::provider::LlmProvider;
 
+use super::install::{self, InstallSettings};
 use super::{crate_permit, db, prompt, repair_loop};
 
+/// Result of one codegen run.
+pub type CodegenOutcome = Result<repair_loop::RepairResult, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Codegen runs in flight, capped by `codegen_max_concurrent` and
+/// `codegen_max_per_hour`.
+pub struct CodegenQueue {
+    running: Vec<oneshot::Receiver<CodegenOutcome>>,
+    started: VecDeque<Instant>,
+    max_concurrent: usize,
+    max_per_hour: usize,
+}
+
+impl CodegenQueue {
+    pub fn new(max_concurrent: usize, max_per_hour: usize) -> Self {
+        Self { running: Vec::new(), started: VecDeque::new(), max_concurrent, max_per_hour }
+    }
+
+    pub fn from_cfg(cfg: &IrisCfg) -> Self {
+        Self::new(cfg.codegen_max_concurrent, cfg.codegen_max_per_hour)
+    }
+
+    /// Whether another run may start now.
+    pub fn has_capacity(&mut self) -> bool {
+        let hour = Duration::from_secs(3600);
+        while self.started.front().is_some_and(|at| at.elapsed() >= hour) {
+            self.started.pop_front();
+        }
+        self.running.len() < self.max_concurrent && self.started.len() < self.max_per_hour
+    }
+
+    /// Track a run started with [`submit_async`].
+    pub fn push(&mut self, run: oneshot::Receiver<CodegenOutcome>) {
+        self.started.push_back(Instant::now());
+        self.running.push(run);
+    }
+
+    /// Outcomes of the runs that finished since the last call.
+    pub fn poll(&mut self) -> Vec<CodegenOutcome> {
+        let mut finished = Vec::new();
+        self.running.retain_mut(|run| match run.try_recv() {
+            Ok(outcome) => {
+                finished.push(outcome);
+                false
+            }
+            Err(oneshot::error::TryRecvError::Empty) => true,
+            Err(oneshot::error::TryRecvError::Closed) => false,
+        });
+        finished
+    }
+}
+
 /// Submit a gap for async code generation.
 /// Returns a oneshot receiver that will contain the result.
 pub fn submit_async(
     gap: GapDescriptor,
     pool: PgPool,
     llm: Arc<dyn LlmProvider>,
+    settings: InstallSettings,
     cancel: CancellationToken,
-) -> oneshot::Receiver<Result<repair_loop::RepairResult, Box<dyn std::error::Error + Send + Sync>>>
-{
+) -> oneshot::Receiver<CodegenOutcome> {
     let (tx, rx) = oneshot::channel();
 
     tokio::spawn(async move {
@@ -26,7 +81,7 @@ pub fn submit_async(
             _ = cancel.cancelled() => {
                 Err("codegen cancelled".into())
             }
-            result = generate_inner(&gap, &pool, &*llm) => result,
+            result = generate_inner(&gap, &pool, &*llm, &settings) => result,
         };
         // oneshot send fails only if receiver was dropped (fire-and-forget) — benign
         let _ = tx.send(result);
@@ -40,13 +95,15 @@ pub async fn generate(
     gap: &GapDescriptor,
     pool: &PgPool,
     llm: &dyn LlmProvider,
-) -> Result<repair_loop::RepairResult, Box<dyn std::error::Error + Send + Sync>> {
-    generate_inner(gap, pool, llm).await
+    settings: &InstallSettings,
+) -> CodegenOutcome {
+    generate_inner(gap, pool, llm, settings).await
 }
 async fn generate_inner(
     gap: &GapDescriptor,
     pool: &PgPool,
     llm: &dyn LlmProvider,
+    settings: &InstallSettings,
 ) -> Result<repair_loop::RepairResult, Box<dyn std::error::Error + Send + Sync>> {
     // Check which suggested crates are approved
     let approved: Vec<String> = {
@@ -66,7 +123,21 @@ async fn generate_inner(
     let codegen_prompt = prompt::build_codegen_prompt(gap, &approved, &failures);
 
     // Run repair loop
-    let result = repair_loop::run(llm, &codegen_prompt).await?;
+    let mut result = repair_loop::run(llm, &codegen_prompt).await?;
+
+    // Build, register and self-test the capability; an install failure counts as a failed attempt
+    if result.success {
+        match install::install(pool, settings, gap, &result.source_code).await {
+            Ok(installed) => {
+                tracing::info!(capability = %installed.name, version = installed.version, state = ?installed.state, "generated capability installed");
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to install generated capability");
+                result.success = false;
+                result.last_error = Some(e);
+            }
+        }
+    }
 
     // Record history
     let history = CodegenHistory {
@@ -84,3 +155,38 @@ async fn generate_inner(
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(success: bool) -> oneshot::Receiver<CodegenOutcome> {
+        let (tx, rx) = oneshot::channel();
+        let _ = tx.send(Ok(repair_loop::RepairResult {
+            source_code: String::new(),
+            success,
+            iterations: 1,
+            last_error: None,
+        }));
+        rx
+    }
+
+    #[test]
+    fn queue_caps_concurrent_and_hourly_runs() {
+        let mut queue = CodegenQueue::new(1, 2);
+        assert!(queue.has_capacity());
+        let (pending_tx, pending) = oneshot::channel();
+        queue.push(pending);
+        assert!(!queue.has_capacity(), "one run at a time");
+        assert!(queue.poll().is_empty());
+
+        drop(pending_tx);
+        assert!(queue.poll().is_empty(), "an abandoned run frees its slot without an outcome");
+        assert!(queue.has_capacity());
+        queue.push(finished(true));
+        let outcomes = queue.poll();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].as_ref().unwrap().success);
+        assert!(!queue.has_capacity(), "two runs started this hour");
+    }
+}
@@ -0,0 +1,428 @@
+//! Install pipeline for generated capabilities.
+//!
+//! Successful repair-loop source is built as a release binary under the
+//! managed capability directory, given a manifest derived from the gap,
+//! inserted as `Staged`, and handed to the self-test harness, which moves it to
+//! `ActiveCandidate` or `Quarantined`. A gap that maps onto an existing
+//! capability is rebuilt into it as the next version instead.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::capability::process_manager::ProcessManager;
+use crate::capability::{db as capability_db, lifecycle, path_policy, self_test, versions};
+use crate::config::IrisCfg;
+use crate::identity::narrative;
+use crate::types::{
+    CapabilityManifest, CapabilityRecord, CapabilityState, GapDescriptor, GapType, NarrativeEventType, Permission,
+};
+
+/// Default memory budget for generated capabilities (MB).
+pub const DEFAULT_MEMORY_MB: u64 = 256;
+
+/// Maximum keywords taken from the trigger description.
+const MAX_KEYWORDS: usize = 8;
+
+/// Keywords used in a capability's name.
+const NAME_KEYWORDS: usize = 2;
+
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "into", "what", "when", "where", "which", "how",
+    "can", "you", "your", "please", "could", "would", "should", "about", "there", "their", "have", "has",
+    "need", "want", "some", "any", "all", "are", "was", "were", "will", "not", "but", "its", "get",
+];
+
+/// Where and how generated capabilities are built.
+#[derive(Debug, Clone)]
+pub struct InstallSettings {
+    pub dir: PathBuf,
+    pub build_timeout: Duration,
+}
+
+impl InstallSettings {
+    pub fn from_cfg(cfg: &IrisCfg) -> Self {
+        let dir = if cfg.capability_dir.trim().is_empty() {
+            path_policy::data_dir("capabilities")
+        } else {
+            path_policy::expand_home(cfg.capability_dir.trim())
+        };
+        Self {
+            dir,
+            build_timeout: Duration::from_secs(cfg.codegen_compile_timeout_secs.max(1)),
+        }
+    }
+
+    fn source_dir(&self, name: &str) -> PathBuf {
+        self.dir.join("src").join(name)
+    }
+
+    /// Binaries are named by content hash so earlier versions stay available for rollback.
+    fn binary_path(&self, name: &str, hash: &str) -> PathBuf {
+        self.dir.join("bin").join(format!("{name}-{}", &hash[..12]))
+    }
+}
+
+/// An installed capability and where its lifecycle ended up.
+#[derive(Debug, Clone)]
+pub struct Installed {
+    pub id: Uuid,
+    pub name: String,
+    pub version: i32,
+    pub state: CapabilityState,
+    pub self_test_error: Option<String>,
+}
+
+/// Cargo manifest for a generated capability binary. The dependency set matches
+/// the IPC types the codegen prompt asks for.
+pub(crate) fn cargo_manifest(package: &str) -> String {
+    format!(
+        r#"[package]
+name = "{package}"
+version = "0.1.0"
+edition = "2021"
+
+[[bin]]
+name = "{package}"
+path = "src/main.rs"
+
+[dependencies]
+serde = {{ version = "1", features = ["derive"] }}
+serde_json = "1"
+uuid = {{ version = "1", features = ["serde", "v4"] }}
+
+[profile.release]
+strip = true
+"#
+    )
+}
+
+/// Capability name for a gap: gap type plus its first ASCII keywords, so the same
+/// request maps onto the same capability. Falls back to the first 8 hex digits of
+/// the gap id when the trigger has no usable keywords.
+pub fn capability_name(gap: &GapDescriptor) -> String {
+    let words: Vec<String> = keywords_for(&gap.trigger_description)
+        .into_iter()
+        .filter(|w| w.chars().all(|c| c.is_ascii_alphanumeric()))
+        .take(NAME_KEYWORDS)
+        .collect();
+    if words.is_empty() {
+        let id = gap.id.simple().to_string();
+        format!("{}_{}", gap.gap_type.as_str(), &id[..8])
+    } else {
+        format!("{}_{}", gap.gap_type.as_str(), words.join("_"))
+    }
+}
+
+/// Permissions a generated capability may need for its gap type.
+pub fn permissions_for(gap_type: GapType) -> Vec<Permission> {
+    match gap_type {
+        GapType::FileSystem => vec![Permission::FileRead],
+        GapType::Network => vec![Permission::NetworkRead],
+        GapType::ExternalAPI => vec![Permission::NetworkRead, Permission::NetworkWrite],
+        GapType::SystemInfo => vec![Permission::SystemInfo],
+        GapType::DataProcessing | GapType::Compute | GapType::Unknown => Vec::new(),
+    }
+}
+
+/// Routing keywords: distinct lowercase words of the trigger description, minus stop words.
+pub fn keywords_for(trigger: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for word in trigger.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let word = word.to_lowercase();
+        if word.chars().count() < 3 || word.chars().all(|c| c.is_ascii_digit()) || STOP_WORDS.contains(&word.as_str())
+        {
+            continue;
+        }
+        if !out.contains(&word) {
+            out.push(word);
+        }
+        if out.len() == MAX_KEYWORDS {
+            break;
+        }
+    }
+    out
+}
+
+/// Manifest for a generated capability installed at `binary_path`.
+pub fn build_manifest(gap: &GapDescriptor, binary_path: &Path) -> CapabilityManifest {
+    CapabilityManifest {
+        name: capability_name(gap),
+        binary_path: binary_path.display().to_string(),
+        permissions: permissions_for(gap.gap_type),
+        resource_limits: serde_json::json!({
+            "memory_mb": DEFAULT_MEMORY_MB,
+            "timeout_ms": self_test::DEFAULT_TIMEOUT_MS,
+        }),
+        keywords: keywords_for(&gap.trigger_description),
+        self_tests: vec![self_test::ping_case()],
+    }
+}
+
+/// Build `source` as a release binary and copy it into the managed `bin/` directory.
+/// Returns the installed path and the binary's hash.
+pub async fn build_release(settings: &InstallSettings, name: &str, source: &str) -> Result<(PathBuf, String), String> {
+    // Binaries built here are executed, so nobody else may write into the tree
+    let dir = settings.dir.clone();
+    tokio::task::spawn_blocking(move || path_policy::create_private_dir(&dir))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+        .map_err(|e| format!("failed to create {}: {e}", settings.dir.display()))?;
+    let src_dir = settings.source_dir(name);
+    tokio::fs::create_dir_all(src_dir.join("src"))
+        .await
+        .map_err(|e| format!("failed to create {}: {e}", src_dir.display()))?;
+    tokio::fs::write(src_dir.join("Cargo.toml"), cargo_manifest(name))
+        .await
+        .map_err(|e| format!("failed to write Cargo.toml: {e}"))?;
+    tokio::fs::write(src_dir.join("src").join("main.rs"), source)
+        .await
+        .map_err(|e| format!("failed to write main.rs: {e}"))?;
+
+    // Shared target dir so dependencies are compiled once across capabilities
+    let target_dir = settings.dir.join("target");
+    let mut cmd = tokio::process::Command::new("cargo");
+    cmd.args(["build", "--release", "--quiet"])
+        .current_dir(&src_dir)
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(settings.build_timeout, cmd.output())
+        .await
+        .map_err(|_| format!("release build timed out after {}s", settings.build_timeout.as_secs()))?
+        .map_err(|e| format!("failed to spawn cargo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("release build failed: {}", stderr.chars().take(2000).collect::<String>()));
+    }
+
+    let built = target_dir.join("release").join(name);
+    let hash_path = built.clone();
+    let hash = tokio::task::spawn_blocking(move || versions::hash_file(&hash_path))
+        .await
+        .map_err(|e| format!("failed to hash {}: {e}", built.display()))??;
+    let dest = settings.binary_path(name, &hash);
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    tokio::fs::copy(&built, &dest)
+        .await
+        .map_err(|e| format!("failed to copy {}: {e}", built.display()))?;
+    Ok((dest, hash))
+}
+
+/// States a capability moves through once a rebuilt version passes self-test.
+fn promotion_path(from: CapabilityState) -> &'static [CapabilityState] {
+    match from {
+        CapabilityState::Quarantined => &[CapabilityState::Staged, CapabilityState::ActiveCandidate],
+        CapabilityState::Staged | CapabilityState::Confirmed => &[CapabilityState::ActiveCandidate],
+        CapabilityState::ActiveCandidate | CapabilityState::Retired => &[],
+    }
+}
+
+/// Build, register and self-test a generated capability. A gap whose capability
+/// already exists is rebuilt into it instead.
+pub async fn install(
+    pool: &PgPool,
+    settings: &InstallSettings,
+    gap: &GapDescriptor,
+    source: &str,
+) -> Result<Installed, String> {
+    let name = capability_name(gap);
+    if let Some(existing) = capability_db::fetch_by_name(pool, &name)
+        .await
+        .map_err(|e| format!("failed to look up capability: {e}"))?
+    {
+        return rebuild(pool, settings, &existing, gap, source).await;
+    }
+
+    let (binary, hash) = build_release(settings, &name, source).await?;
+    let manifest = build_manifest(gap, &binary);
+    let now = chrono::Utc::now();
+    let record = CapabilityRecord {
+        id: Uuid::new_v4(),
+        name: name.clone(),
+        binary_path: manifest.binary_path.clone(),
+        manifest,
+        state: CapabilityState::Staged,
+        lkg_version: None,
+        current_version: None,
+        quarantine_count: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    capability_db::insert(pool, &record)
+        .await
+        .map_err(|e| format!("failed to insert capability: {e}"))?;
+    if let Err(e) = capability_db::init_score(pool, record.id).await {
+        tracing::warn!(error = %e, "failed to init capability score");
+    }
+    let version = versions::insert(pool, record.id, &record.binary_path, source, &record.manifest, &hash)
+        .await
+        .map_err(|e| format!("failed to record capability version: {e}"))?;
+    versions::activate(pool, &version)
+        .await
+        .map_err(|e| format!("failed to activate capability version: {e}"))?;
+    let record = CapabilityRecord { current_version: Some(version.id), ..record };
+
+    let mut pm = ProcessManager::new(CancellationToken::new());
+    let (state, report) = self_test::run_and_promote(pool, &mut pm, &record).await?;
+    Ok(Installed {
+        id: record.id,
+        name,
+        version: version.version,
+        state,
+        self_test_error: report.first_failure(),
+    })
+}
+
+/// Build the next version of an existing capability. The new build is
+/// self-tested on its own and only installed when it passes, as an active
+/// candidate; the last confirmed version stays the rollback target until the
+/// new one is confirmed. A failing build leaves the installed version running.
+pub async fn rebuild(
+    pool: &PgPool,
+    settings: &InstallSettings,
+    record: &CapabilityRecord,
+    gap: &GapDescriptor,
+    source: &str,
+) -> Result<Installed, String> {
+    if record.state == CapabilityState::Retired {
+        return Err(format!("capability {} is retired", record.name));
+    }
+    // Capabilities registered before versions existed get their binary recorded as v1
+    if let Err(e) = versions::ensure_current(pool, record).await {
+        tracing::warn!(capability = %record.name, error = %e, "failed to record installed version");
+    }
+
+    let (binary, hash) = build_release(settings, &record.name, source).await?;
+    let manifest = CapabilityManifest { name: record.name.clone(), ..build_manifest(gap, &binary) };
+    let version = versions::insert(pool, record.id, &manifest.binary_path, source, &manifest, &hash)
+        .await
+        .map_err(|e| format!("failed to record capability version: {e}"))?;
+    let candidate = CapabilityRecord {
+        binary_path: version.binary_path.clone(),
+        manifest: version.manifest.clone(),
+        current_version: Some(version.id),
+        ..record.clone()
+    };
+
+    let mut pm = ProcessManager::new(CancellationToken::new());
+    let report = self_test::run(&mut pm, &candidate).await;
+    if let Err(e) = self_test::record_results(pool, &report).await {
+        tracing::warn!(error = %e, "failed to record self-test results");
+    }
+    let name = &record.name;
+    if let Some(failure) = report.first_failure() {
+        tracing::warn!(capability = %name, version = version.version, failure = %failure, "rebuilt version failed self-test, keeping installed version");
+        return Ok(Installed {
+            id: record.id,
+            name: name.clone(),
+            version: version.version,
+            state: record.state,
+            self_test_error: Some(failure),
+        });
+    }
+
+    versions::activate(pool, &version)
+        .await
+        .map_err(|e| format!("failed to activate capability version: {e}"))?;
+    let mut state = record.state;
+    for &next in promotion_path(record.state) {
+        lifecycle::validate_transition(state, next).map_err(|e| e.to_string())?;
+        capability_db::update_state(pool, record.id, next)
+            .await
+            .map_err(|e| format!("failed to update capability state: {e}"))?;
+        state = next;
+    }
+
+    tracing::info!(capability = %name, version = version.version, "rebuilt version passed self-test, installed as active candidate");
+    let evt = narrative::new_event(
+        NarrativeEventType::CapabilityGained,
+        format!("capability {name} v{} passed {} self-test case(s)", version.version, report.cases.len()),
+        0.7,
+    );
+    let _ = narrative::record(pool, &evt).await;
+    Ok(Installed {
+        id: record.id,
+        name: name.clone(),
+        version: version.version,
+        state,
+        self_test_error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::EventSource;
+
+    fn gap(gap_type: GapType, trigger: &str) -> GapDescriptor {
+        GapDescriptor {
+            id: Uuid::parse_str("0123abcd-0000-0000-0000-000000000000").unwrap(),
+            gap_type,
+            trigger_description: trigger.into(),
+            source: EventSource::External,
+            suggested_crates: Vec::new(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn manifest_derives_name_permissions_and_keywords() {
+        let g = gap(GapType::ExternalAPI, "Please fetch the weather forecast for Berlin, the weather API");
+        let manifest = build_manifest(&g, Path::new("/caps/bin/x"));
+        assert_eq!(manifest.name, "external_api_fetch_weather");
+        assert_eq!(manifest.permissions, vec![Permission::NetworkRead, Permission::NetworkWrite]);
+        assert_eq!(manifest.keywords, vec!["fetch", "weather", "forecast", "berlin", "api"]);
+        assert_eq!(manifest.resource_limits["memory_mb"], DEFAULT_MEMORY_MB);
+        assert_eq!(manifest.self_tests, vec![self_test::ping_case()]);
+        assert!(permissions_for(GapType::Compute).is_empty());
+    }
+
+    #[test]
+    fn repeated_requests_share_a_capability_name() {
+        let first = gap(GapType::Network, "ping example.com and report latency");
+        let again = GapDescriptor { id: Uuid::new_v4(), ..gap(GapType::Network, "Ping example.com, report the latency") };
+        assert_eq!(capability_name(&first), "network_ping_example");
+        assert_eq!(capability_name(&first), capability_name(&again));
+        // No ASCII keywords: fall back to the gap id
+        assert_eq!(capability_name(&gap(GapType::Compute, "算 一下")), "compute_0123abcd");
+    }
+
+    #[test]
+    fn rebuilt_versions_are_promoted_through_valid_transitions() {
+        for from in [
+            CapabilityState::Staged,
+            CapabilityState::ActiveCandidate,
+            CapabilityState::Confirmed,
+            CapabilityState::Quarantined,
+        ] {
+            let mut state = from;
+            for &next in promotion_path(from) {
+                assert!(lifecycle::validate_transition(state, next).is_ok(), "{state:?} → {next:?}");
+                state = next;
+            }
+            assert_eq!(state, CapabilityState::ActiveCandidate);
+        }
+        assert!(promotion_path(CapabilityState::Retired).is_empty());
+    }
+
+    #[test]
+    fn empty_capability_dir_falls_back_to_the_user_data_dir() {
+        let settings = InstallSettings::from_cfg(&IrisCfg { capability_dir: " ".into(), ..IrisCfg::default() });
+        assert_eq!(settings.dir, path_policy::data_dir("capabilities"));
+    }
+
+    #[test]
+    fn cargo_manifest_builds_a_named_binary() {
+        let toml = cargo_manifest("network_0123abcd");
+        assert!(toml.contains("[[bin]]\nname = \"network_0123abcd\""));
+        assert!(toml.contains("serde_json"));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod crate_permit;
 pub mod db;
 pub mod gap_generator;
+pub mod install;
 pub mod prompt;
 pub mod repair_loop;
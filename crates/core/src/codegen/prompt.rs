@@ -43,7 +43,9 @@ pub fn build_codegen_prompt(
     prompt.push_str("- No `unsafe` code\n");
     prompt.push_str("- Must read CapabilityRequest from stdin (NDJSON) and write CapabilityResponse to stdout\n");
     prompt.push_str("- Compile timeout: 120s, memory budget: 512MB\n");
-    prompt.push_str("- Handle errors gracefully, return error in CapabilityResponse\n\n");
+    prompt.push_str("- Handle errors gracefully, return error in CapabilityResponse\n");
+    prompt.push_str("- Report side_effects as permission names: FileRead, FileWrite, NetworkRead, NetworkWrite, ProcessSpawn, SystemInfo\n");
+    prompt.push_str("- Answer a `self_test` method with a response echoing the request id\n\n");
 
     // IPC Protocol Types
     prompt.push_str("## IPC Protocol Types (use these exact definitions):\n");
@@ -121,27 +121,20 @@ fn compile_in_temp_dir(source_code: &str) -> Result<(), String> {
     let src_dir = tmp.path().join("src");
     std::fs::create_dir_all(&src_dir).map_err(|e| format!("failed to create src dir: {e}"))?;
 
-    // Write Cargo.toml
-    let cargo_toml = r#"[package]
-name = "iris-codegen-check"
-version = "0.1.0"
-edition = "2021"
-
-[lib]
-path = "src/lib.rs"
-"#;
+    // Same manifest the installer uses, so code that checks here also builds there
+    let cargo_toml = super::install::cargo_manifest("iris-codegen-check");
     let mut f = std::fs::File::create(tmp.path().join("Cargo.toml"))
         .map_err(|e| format!("failed to write Cargo.toml: {e}"))?;
     f.write_all(cargo_toml.as_bytes())
         .map_err(|e| format!("failed to write Cargo.toml: {e}"))?;
 
     // Write source
-    std::fs::write(src_dir.join("lib.rs"), source_code)
-        .map_err(|e| format!("failed to write lib.rs: {e}"))?;
+    std::fs::write(src_dir.join("main.rs"), source_code)
+        .map_err(|e| format!("failed to write main.rs: {e}"))?;
 
     // Run cargo build with timeout
     let output = std::process::Command::new("cargo")
-        .args(["build", "--lib"])
+        .args(["build"])
         .current_dir(tmp.path())
         .env("CARGO_TARGET_DIR", tmp.path().join("target"))
         .output()
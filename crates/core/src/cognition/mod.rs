@@ -2,5 +2,7 @@ pub mod arbitration;
 pub mod fast_path;
 pub mod perception;
 pub mod response;
+pub mod schema;
+pub mod self_critic;
 pub mod slow_path;
 pub mod tool_call;
@@ -1,21 +1,44 @@
-//! Perception module — rule-based PerceptFeature extraction.
+//! Perception module — PerceptFeature extraction.
 //!
-//! Extracts threat level, complexity, intent tag, and intent confidence
-//! from a GatedEvent without any LLM calls (< 1ms target).
+//! The rule-based extractor needs no LLM calls (< 1ms target). When a lite
+//! model is configured, `perceive` asks it for intent, urgency, threat and
+//! language in one structured call and falls back to the rules on timeout.
 
-use crate::types::{GatedEvent, PerceptFeature};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::i18n;
+use crate::types::{GatedEvent, PerceptFeature, PerceptSource, SensoryEvent};
+use llm::provider::{ChatMessage, CompletionRequest, LlmProvider, Role};
+use uuid::Uuid;
 
 /// Threat keywords and their implicit severity.
 const THREAT_KEYWORDS: &[&str] = &[
     "error", "crash", "panic", "fail", "critical", "emergency", "attack",
 ];
 
+/// Urgency markers for the rule-based extractor.
+const URGENCY_KEYWORDS: &[&str] = &[
+    "urgent", "asap", "immediately", "now", "emergency", "critical", "紧急", "马上", "立刻",
+];
+
+/// Intent tags shared by the rule-based and LLM extractors.
+pub const INTENT_TAGS: &[&str] = &["question", "command", "request", "feedback", "statement"];
+
+/// Percepts kept per event id; older entries are evicted first.
+const PERCEPT_CACHE_CAP: usize = 256;
+
 /// Extract perceptual features from a gated event.
 pub fn extract(event: &GatedEvent) -> PerceptFeature {
-    let lower = event.event.content.to_lowercase();
+    extract_rules(&event.event.content)
+}
+
+/// Rule-based extraction from raw content.
+pub fn extract_rules(content: &str) -> PerceptFeature {
+    let lower = content.to_lowercase();
 
     let threat = compute_threat(&lower);
-    let complexity_raw = compute_complexity(&event.event.content);
+    let complexity_raw = compute_complexity(content);
     let (intent_tag, intent_confidence) = classify_intent(&lower);
 
     PerceptFeature {
@@ -23,6 +46,150 @@ pub fn extract(event: &GatedEvent) -> PerceptFeature {
         complexity_raw,
         intent_tag,
         intent_confidence,
+        urgency: compute_urgency(&lower),
+        language: detect_language(content).into(),
+        source: PerceptSource::Rules,
+    }
+}
+
+/// Perceive an event with the lite model, falling back to rules on timeout or bad output.
+pub async fn perceive(
+    provider: &dyn LlmProvider,
+    event: &SensoryEvent,
+    timeout: Duration,
+) -> PerceptFeature {
+    let request = CompletionRequest {
+        messages: vec![
+            ChatMessage {
+                role: Role::System,
+                content: "You are a perception classifier. Output ONLY valid JSON. No markdown, no explanation.".into(),
+                content_blocks: vec![],
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Classify the message below. Return exactly one JSON object with keys:\n\
+                     - intent: one of {}\n\
+                     - intent_confidence: number in [0,1]\n\
+                     - urgency: number in [0,1] (how soon a reply or action is needed)\n\
+                     - threat: number in [0,1] (errors, failures, danger, attacks)\n\
+                     - language: ISO 639-1 code of the message\n\n\
+                     Message:\n{}",
+                    INTENT_TAGS.join(", "),
+                    event.content
+                ),
+                content_blocks: vec![],
+            },
+        ],
+        max_tokens: 120,
+        temperature: 0.0,
+        tools: vec![],
+    };
+
+    match tokio::time::timeout(timeout, provider.complete(request)).await {
+        Ok(Ok(resp)) => match parse_llm_percept(&resp.content, &event.content) {
+            Some(features) => return features,
+            None => tracing::debug!(
+                event_id = %event.id,
+                raw_len = resp.content.len(),
+                "perception output unparseable, using rules"
+            ),
+        },
+        Ok(Err(e)) => {
+            tracing::debug!(event_id = %event.id, error = %e, "perception LLM call failed, using rules")
+        }
+        Err(_) => tracing::debug!(
+            event_id = %event.id,
+            timeout_ms = timeout.as_millis() as u64,
+            "perception LLM timed out, using rules"
+        ),
+    }
+    extract_rules(&event.content)
+}
+
+/// Parse the lite model's JSON; missing or malformed fields make the whole result invalid.
+fn parse_llm_percept(raw: &str, content: &str) -> Option<PerceptFeature> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    let parsed: serde_json::Value = serde_json::from_str(raw.get(start..=end)?).ok()?;
+
+    let intent = parsed.get("intent")?.as_str()?.trim().to_lowercase();
+    if !INTENT_TAGS.contains(&intent.as_str()) {
+        return None;
+    }
+    let unit = |key: &str| {
+        parsed
+            .get(key)
+            .and_then(|v| v.as_f64())
+            .map(|n| (n as f32).clamp(0.0, 1.0))
+    };
+    let language = parsed
+        .get("language")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| detect_language(content).into());
+
+    Some(PerceptFeature {
+        threat: unit("threat")?,
+        complexity_raw: compute_complexity(content),
+        intent_tag: intent,
+        intent_confidence: unit("intent_confidence")?,
+        urgency: unit("urgency")?,
+        language,
+        source: PerceptSource::Llm,
+    })
+}
+
+/// Intent confidence above which a conversational LLM percept skips tool routing.
+const CONVERSATIONAL_SKIP_CONFIDENCE: f32 = 0.85;
+
+/// True when the lite model is confident the event is plain conversation
+/// (feedback or a statement, no threat) so tool routing can be skipped.
+pub fn is_conversational(features: &PerceptFeature) -> bool {
+    features.source == PerceptSource::Llm
+        && matches!(features.intent_tag.as_str(), "feedback" | "statement")
+        && features.intent_confidence >= CONVERSATIONAL_SKIP_CONFIDENCE
+        && features.threat < 0.5
+}
+
+/// Per-event percept cache so gating and routing share one perception result.
+#[derive(Debug, Default)]
+pub struct PerceptCache {
+    entries: HashMap<Uuid, PerceptFeature>,
+    order: VecDeque<Uuid>,
+}
+
+impl PerceptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, event_id: &Uuid) -> Option<&PerceptFeature> {
+        self.entries.get(event_id)
+    }
+
+    pub fn contains(&self, event_id: &Uuid) -> bool {
+        self.entries.contains_key(event_id)
+    }
+
+    pub fn insert(&mut self, event_id: Uuid, features: PerceptFeature) {
+        if self.entries.insert(event_id, features).is_none() {
+            self.order.push_back(event_id);
+        }
+        while self.order.len() > PERCEPT_CACHE_CAP {
+            if let Some(old) = self.order.pop_front() {
+                self.entries.remove(&old);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 }
 
@@ -35,11 +202,37 @@ fn compute_threat(lower: &str) -> f32 {
     (count as f32 * 0.25).min(1.0)
 }
 
+/// Compute urgency from urgency markers plus half the threat signal (0.0–1.0).
+fn compute_urgency(lower: &str) -> f32 {
+    let count = URGENCY_KEYWORDS
+        .iter()
+        .filter(|k| has_marker(lower, k))
+        .count();
+    (count as f32 * 0.3 + compute_threat(lower) * 0.5).min(1.0)
+}
+
+/// Latin markers match whole words only ("now" is not in "know"); CJK markers
+/// match anywhere since the text has no word separators.
+fn has_marker(lower: &str, marker: &str) -> bool {
+    if marker.is_ascii() {
+        lower
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == marker)
+    } else {
+        lower.contains(marker)
+    }
+}
+
 /// Compute raw complexity from content length (0.0–1.0).
 fn compute_complexity(content: &str) -> f32 {
     (content.len() as f32 / 200.0).min(1.0)
 }
 
+/// Language code for a percept: "zh", "en", or "und" when too short to tell.
+pub fn detect_language(content: &str) -> &'static str {
+    i18n::detect(content).map_or("und", |lang| lang.code())
+}
+
 /// Rule-based intent classification.
 /// Returns (intent_tag, confidence).
 pub fn classify_intent(text: &str) -> (String, f32) {
@@ -134,4 +327,108 @@ mod tests {
     fn intent_statement() {
         assert_eq!(classify_intent("the sky is blue").0, "statement");
     }
+
+    #[test]
+    fn rules_detect_language_and_urgency() {
+        let zh = extract_rules("帮我看一下这个文件");
+        assert_eq!(zh.language, "zh");
+        assert_eq!(zh.source, PerceptSource::Rules);
+        assert_eq!(extract_rules("hello there").language, "en");
+        assert_eq!(extract_rules("123 !!").language, "und");
+
+        let urgent = extract_rules("urgent: the server crashed, fix it now");
+        assert!(urgent.urgency > extract_rules("hello there").urgency);
+        assert!(extract_rules("do it now!").urgency > 0.0);
+    }
+
+    #[test]
+    fn urgency_markers_match_whole_words() {
+        assert_eq!(extract_rules("I know").urgency, 0.0);
+        assert_eq!(extract_rules("snow is known to fall nowhere here").urgency, 0.0);
+        assert!(extract_rules("马上处理").urgency > 0.0);
+    }
+
+    #[tokio::test]
+    async fn perceive_uses_llm_json() {
+        let provider = llm::provider::MockProvider::new(
+            r#"{"intent":"command","intent_confidence":0.9,"urgency":0.8,"threat":0.1,"language":"de"}"#,
+        );
+        let event = SensoryEvent::external("starte den build neu");
+        let features = perceive(&provider, &event, Duration::from_secs(1)).await;
+        assert_eq!(features.source, PerceptSource::Llm);
+        assert_eq!(features.intent_tag, "command");
+        assert_eq!(features.language, "de");
+        assert!((features.urgency - 0.8).abs() < f32::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn perceive_falls_back_on_bad_output() {
+        let provider = llm::provider::MockProvider::new(r#"{"intent":"dance","urgency":2}"#);
+        let event = SensoryEvent::external("what is this?");
+        let features = perceive(&provider, &event, Duration::from_secs(1)).await;
+        assert_eq!(features.source, PerceptSource::Rules);
+        assert_eq!(features.intent_tag, "question");
+    }
+
+    #[tokio::test]
+    async fn perceive_falls_back_on_timeout() {
+        struct SlowProvider;
+
+        impl LlmProvider for SlowProvider {
+            fn name(&self) -> &str {
+                "slow"
+            }
+
+            fn complete(
+                &self,
+                _request: CompletionRequest,
+            ) -> std::pin::Pin<
+                Box<
+                    dyn std::future::Future<
+                            Output = Result<
+                                llm::provider::CompletionResponse,
+                                llm::provider::LlmError,
+                            >,
+                        > + Send
+                        + '_,
+                >,
+            > {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Err(llm::provider::LlmError::RequestFailed("late".into()))
+                })
+            }
+        }
+
+        let event = SensoryEvent::external("run the tests");
+        let features = perceive(&SlowProvider, &event, Duration::from_millis(20)).await;
+        assert_eq!(features.source, PerceptSource::Rules);
+        assert_eq!(features.intent_tag, "command");
+    }
+
+    #[test]
+    fn conversational_requires_confident_llm_percept() {
+        let mut features = parse_llm_percept(
+            r#"{"intent":"feedback","intent_confidence":0.95,"urgency":0.1,"threat":0.0,"language":"en"}"#,
+            "thanks!",
+        )
+        .unwrap();
+        assert!(is_conversational(&features));
+
+        features.intent_confidence = 0.5;
+        assert!(!is_conversational(&features));
+        assert!(!is_conversational(&extract_rules("thanks, great work")));
+    }
+
+    #[test]
+    fn cache_evicts_oldest() {
+        let mut cache = PerceptCache::new();
+        let first = Uuid::new_v4();
+        cache.insert(first, extract_rules("a"));
+        for _ in 0..PERCEPT_CACHE_CAP {
+            cache.insert(Uuid::new_v4(), extract_rules("b"));
+        }
+        assert_eq!(cache.len(), PERCEPT_CACHE_CAP);
+        assert!(!cache.contains(&first));
+    }
 }
@@ -0,0 +1,531 @@
+//! JSON Schema validation for tool inputs.
+//!
+//! Covers the subset of draft 2020-12 that tool definitions actually use:
+//! `type`, `properties`, `required`, `additionalProperties`, `items`/`prefixItems`,
+//! `enum`, `const`, numeric and length bounds, `pattern`, `anyOf`/`oneOf`/`allOf`.
+//! Unknown keywords are ignored, as the spec requires.
+
+use serde_json::Value;
+
+/// A single schema violation with the JSON path of the offending value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Location in the instance, e.g. `$.files[2].path`.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl SchemaViolation {
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({ "path": self.path, "message": self.message })
+    }
+}
+
+/// Validate `instance` against `schema`. Returns every violation found.
+pub fn validate(instance: &Value, schema: &Value) -> Result<(), Vec<SchemaViolation>> {
+    let mut errors = Vec::new();
+    check(instance, schema, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Validate a tool call's input. Tool inputs must always be JSON objects.
+pub fn validate_tool_input(input: &Value, schema: &Value) -> Result<(), Vec<SchemaViolation>> {
+    if !input.is_object() {
+        return Err(vec![SchemaViolation {
+            path: "$".into(),
+            message: format!("tool input must be an object, got {}", type_name(input)),
+        }]);
+    }
+    validate(input, schema)
+}
+
+/// Structured tool error payload returned to the model for invalid input.
+pub fn invalid_input_error(tool_name: &str, violations: &[SchemaViolation]) -> String {
+    serde_json::json!({
+        "error": "invalid_input",
+        "tool": tool_name,
+        "violations": violations.iter().map(SchemaViolation::to_json).collect::<Vec<_>>(),
+        "hint": "Fix the listed fields to match the tool's input_schema and call the tool again.",
+    })
+    .to_string()
+}
+
+fn push(errors: &mut Vec<SchemaViolation>, path: &str, message: impl Into<String>) {
+    errors.push(SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    });
+}
+
+fn check(value: &Value, schema: &Value, path: &str, errors: &mut Vec<SchemaViolation>) {
+    let schema = match schema {
+        Value::Object(map) => map,
+        Value::Bool(false) => {
+            push(errors, path, "no value is allowed here");
+            return;
+        }
+        // `true` and malformed schemas accept everything.
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !names.is_empty() && !names.iter().any(|t| matches_json_type(value, t)) {
+            push(
+                errors,
+                path,
+                format!("expected {}, got {}", names.join(" or "), type_name(value)),
+            );
+            // Further keyword checks would only repeat the type mismatch.
+            return;
+        }
+    }
+
+    if let Some(expected) = schema.get("const")
+        && !json_eq(value, expected)
+    {
+        push(errors, path, format!("must equal {expected}"));
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.iter().any(|a| json_eq(value, a))
+    {
+        let list: Vec<String> = allowed.iter().map(Value::to_string).collect();
+        push(
+            errors,
+            path,
+            format!("must be one of [{}]", list.join(", ")),
+        );
+    }
+
+    match value {
+        Value::String(s) => check_string(s, schema, path, errors),
+        Value::Number(_) => check_number(value, schema, path, errors),
+        Value::Array(items) => check_array(items, schema, path, errors),
+        Value::Object(map) => check_object(map, schema, path, errors),
+        _ => {}
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for sub in all {
+            check(value, sub, path, errors);
+        }
+    }
+
+    if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+        let branches: Vec<Vec<SchemaViolation>> = any
+            .iter()
+            .map(|sub| branch_errors(value, sub, path))
+            .collect();
+        if !branches.iter().any(Vec::is_empty) {
+            push(
+                errors,
+                path,
+                format!(
+                    "does not match any allowed schema ({})",
+                    summarize(&branches)
+                ),
+            );
+        }
+    }
+
+    if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+        let branches: Vec<Vec<SchemaViolation>> = one
+            .iter()
+            .map(|sub| branch_errors(value, sub, path))
+            .collect();
+        let matched = branches.iter().filter(|b| b.is_empty()).count();
+        if matched == 0 {
+            push(
+                errors,
+                path,
+                format!("does not match any oneOf schema ({})", summarize(&branches)),
+            );
+        } else if matched > 1 {
+            push(
+                errors,
+                path,
+                format!("matches {matched} oneOf schemas, expected exactly one"),
+            );
+        }
+    }
+
+    if let Some(not) = schema.get("not")
+        && branch_errors(value, not, path).is_empty()
+    {
+        push(errors, path, "must not match the excluded schema");
+    }
+}
+
+fn branch_errors(value: &Value, schema: &Value, path: &str) -> Vec<SchemaViolation> {
+    let mut errors = Vec::new();
+    check(value, schema, path, &mut errors);
+    errors
+}
+
+/// First violation of each failed branch, for a compact composite message.
+fn summarize(branches: &[Vec<SchemaViolation>]) -> String {
+    branches
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.first().map(|v| format!("#{i}: {v}")))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn check_string(
+    s: &str,
+    schema: &serde_json::Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    let len = s.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+        && len < min
+    {
+        push(
+            errors,
+            path,
+            format!("must be at least {min} characters, got {len}"),
+        );
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+        && len > max
+    {
+        push(
+            errors,
+            path,
+            format!("must be at most {max} characters, got {len}"),
+        );
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        match regex::Regex::new(pattern) {
+            Ok(re) if !re.is_match(s) => {
+                push(errors, path, format!("must match pattern {pattern:?}"));
+            }
+            Ok(_) => {}
+            Err(e) => push(
+                errors,
+                path,
+                format!("schema pattern {pattern:?} is invalid: {e}"),
+            ),
+        }
+    }
+}
+
+fn check_number(
+    value: &Value,
+    schema: &serde_json::Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    let Some(n) = value.as_f64() else { return };
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+        && n < min
+    {
+        push(errors, path, format!("must be >= {min}, got {value}"));
+    }
+    if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+        && n > max
+    {
+        push(errors, path, format!("must be <= {max}, got {value}"));
+    }
+    if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64)
+        && n <= min
+    {
+        push(errors, path, format!("must be > {min}, got {value}"));
+    }
+    if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64)
+        && n >= max
+    {
+        push(errors, path, format!("must be < {max}, got {value}"));
+    }
+}
+
+fn check_array(
+    items: &[Value],
+    schema: &serde_json::Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    let len = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+        && len < min
+    {
+        push(
+            errors,
+            path,
+            format!("must have at least {min} items, got {len}"),
+        );
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+        && len > max
+    {
+        push(
+            errors,
+            path,
+            format!("must have at most {max} items, got {len}"),
+        );
+    }
+    if schema.get("uniqueItems").and_then(Value::as_bool) == Some(true) {
+        for (i, item) in items.iter().enumerate() {
+            if items[..i].iter().any(|prev| json_eq(prev, item)) {
+                push(errors, &format!("{path}[{i}]"), "duplicate item");
+            }
+        }
+    }
+
+    let prefix = schema
+        .get("prefixItems")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    for (i, (item, sub)) in items.iter().zip(prefix).enumerate() {
+        check(item, sub, &format!("{path}[{i}]"), errors);
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate().skip(prefix.len()) {
+            check(item, item_schema, &format!("{path}[{i}]"), errors);
+        }
+    }
+}
+
+fn check_object(
+    map: &serde_json::Map<String, Value>,
+    schema: &serde_json::Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaViolation>,
+) {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(key) {
+                push(
+                    errors,
+                    &child_path(path, key),
+                    "required property is missing",
+                );
+            }
+        }
+    }
+
+    let props = schema.get("properties").and_then(Value::as_object);
+    for (key, value) in map {
+        let child = child_path(path, key);
+        match props.and_then(|p| p.get(key)) {
+            Some(sub) => check(value, sub, &child, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let allowed = props
+                        .map(|p| p.keys().cloned().collect::<Vec<_>>().join(", "))
+                        .unwrap_or_default();
+                    push(
+                        errors,
+                        &child,
+                        format!("unexpected property (allowed: {allowed})"),
+                    );
+                }
+                Some(sub @ Value::Object(_)) => check(value, sub, &child, errors),
+                _ => {}
+            },
+        }
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{key:?}]")
+    }
+}
+
+/// Equality that treats `1` and `1.0` as the same number.
+fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| json_eq(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+pub fn matches_json_type(value: &Value, type_name: &str) -> bool {
+    match type_name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(result: Result<(), Vec<SchemaViolation>>) -> Vec<String> {
+        result
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.path)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_nested_input() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "opts": {
+                    "type": "object",
+                    "properties": {"depth": {"type": "integer", "minimum": 0}},
+                    "additionalProperties": false
+                }
+            },
+            "required": ["name"]
+        });
+        let input = json!({"name": "x", "tags": ["a", "b"], "opts": {"depth": 2}});
+        assert!(validate(&input, &schema).is_ok());
+    }
+
+    #[test]
+    fn reports_nested_paths() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "files": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"path": {"type": "string"}},
+                        "required": ["path"]
+                    }
+                }
+            }
+        });
+        let input = json!({"files": [{"path": "a"}, {"path": 3}, {}]});
+        assert_eq!(
+            paths(validate(&input, &schema)),
+            vec!["$.files[1].path", "$.files[2].path"]
+        );
+    }
+
+    #[test]
+    fn enum_and_const() {
+        let schema = json!({"enum": ["get", "post"]});
+        assert!(validate(&json!("get"), &schema).is_ok());
+        let errs = validate(&json!("put"), &schema).unwrap_err();
+        assert!(errs[0].message.contains("one of"));
+
+        let schema = json!({"const": 1});
+        assert!(validate(&json!(1.0), &schema).is_ok());
+        assert!(validate(&json!(2), &schema).is_err());
+    }
+
+    #[test]
+    fn numeric_and_length_bounds() {
+        let schema = json!({"type": "integer", "minimum": 1, "maximum": 10});
+        assert!(validate(&json!(5), &schema).is_ok());
+        assert!(validate(&json!(0), &schema).is_err());
+        assert!(validate(&json!(11), &schema).is_err());
+        assert!(validate(&json!(2.5), &schema).is_err());
+
+        let schema = json!({"type": "string", "minLength": 2, "maxLength": 3});
+        assert!(validate(&json!("你好"), &schema).is_ok());
+        assert!(validate(&json!("a"), &schema).is_err());
+        assert!(validate(&json!("abcd"), &schema).is_err());
+    }
+
+    #[test]
+    fn pattern_matching() {
+        let schema = json!({"type": "string", "pattern": "^https?://"});
+        assert!(validate(&json!("https://example.com"), &schema).is_ok());
+        let errs = validate(&json!("ftp://x"), &schema).unwrap_err();
+        assert!(errs[0].message.contains("pattern"));
+    }
+
+    #[test]
+    fn additional_properties_rejected() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"command": {"type": "string"}},
+            "additionalProperties": false
+        });
+        let errs = validate(&json!({"command": "ls", "cwd": "/"}), &schema).unwrap_err();
+        assert_eq!(errs[0].path, "$.cwd");
+    }
+
+    #[test]
+    fn one_of_and_any_of() {
+        let schema = json!({"oneOf": [{"type": "string"}, {"type": "integer"}]});
+        assert!(validate(&json!("a"), &schema).is_ok());
+        assert!(validate(&json!(true), &schema).is_err());
+
+        let overlapping = json!({"oneOf": [{"type": "number"}, {"type": "integer"}]});
+        let errs = validate(&json!(3), &overlapping).unwrap_err();
+        assert!(errs[0].message.contains("exactly one"));
+
+        let schema = json!({"anyOf": [{"minLength": 5}, {"pattern": "^x"}]});
+        assert!(validate(&json!("xy"), &schema).is_ok());
+        assert!(validate(&json!("ab"), &schema).is_err());
+    }
+
+    #[test]
+    fn type_mismatch_message() {
+        let errs = validate(&json!("x"), &json!({"type": "object"})).unwrap_err();
+        assert_eq!(errs[0].to_string(), "$: expected object, got string");
+    }
+
+    #[test]
+    fn invalid_input_error_is_json() {
+        let errs = validate(&json!({}), &json!({"required": ["command"]})).unwrap_err();
+        let payload: Value = serde_json::from_str(&invalid_input_error("run_bash", &errs)).unwrap();
+        assert_eq!(payload["error"], "invalid_input");
+        assert_eq!(payload["violations"][0]["path"], "$.command");
+    }
+}
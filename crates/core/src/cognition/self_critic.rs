@@ -0,0 +1,517 @@
+//! Self-critic — post-response grading that feeds the learning tables.
+//!
+//! Each dialogue turn is graded on three axes: tool-result consistency,
+//! whether the question was answered, and hallucinated claims. Grades go to
+//! `turn_evaluation`; per-tool outcomes go to `tool_score` (builtins) and
+//! `capability_score` (process capabilities) so routing can prefer tools
+//! that actually work.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::tool_call::ToolAttempt;
+use crate::capability::db as capability_db;
+use llm::provider::{ChatMessage, CompletionRequest, LlmProvider, Role, ToolDefinition};
+
+/// Minimum usage before a tool's score affects routing.
+const MIN_USAGE_FOR_RANKING: i64 = 3;
+/// Success rate below which a tool is treated as unreliable.
+const UNRELIABLE_SUCCESS_RATE: f64 = 0.4;
+/// Tool-consistency grade below which a tool call counts as a failure.
+const CONSISTENCY_PASS: f32 = 0.5;
+
+/// Markers that a response acknowledges a failure.
+const FAILURE_MARKERS: &[&str] = &[
+    "error",
+    "fail",
+    "unable",
+    "couldn't",
+    "could not",
+    "cannot",
+    "can't",
+    "失败",
+    "错误",
+    "无法",
+];
+/// Markers that a response claims tool activity.
+const ACTION_CLAIMS: &[&str] = &[
+    "i ran",
+    "i executed",
+    "i checked",
+    "i read the file",
+    "i wrote",
+    "i created",
+    "已执行",
+    "我运行了",
+    "我查看了",
+];
+
+/// Everything the critic needs to know about one turn.
+#[derive(Debug, Clone)]
+pub struct TurnRecord {
+    pub event_id: Uuid,
+    pub user_input: String,
+    pub response: String,
+    pub tool_calls: Vec<ToolAttempt>,
+    /// Process capability that produced the response, if any.
+    pub capability_id: Option<Uuid>,
+}
+
+/// Which critic produced a grade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CriticSource {
+    Rules,
+    Llm,
+}
+
+impl CriticSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rules => "rules",
+            Self::Llm => "llm",
+        }
+    }
+}
+
+/// Grades in [0,1]. `hallucination` is a risk score: higher is worse.
+#[derive(Debug, Clone)]
+pub struct TurnGrade {
+    pub tool_consistency: f32,
+    pub answered: f32,
+    pub hallucination: f32,
+    pub notes: String,
+    pub source: CriticSource,
+}
+
+impl TurnGrade {
+    pub fn overall(&self) -> f32 {
+        (self.tool_consistency + self.answered + (1.0 - self.hallucination)) / 3.0
+    }
+}
+
+/// Aggregated outcome score for one builtin tool.
+#[derive(Debug, Clone, Default)]
+pub struct ToolScore {
+    pub usage_count: i64,
+    pub success_count: i64,
+    pub fail_count: i64,
+    pub grade_sum: f64,
+}
+
+impl ToolScore {
+    pub fn success_rate(&self) -> f64 {
+        if self.usage_count == 0 {
+            1.0
+        } else {
+            self.success_count as f64 / self.usage_count as f64
+        }
+    }
+
+    pub fn is_unreliable(&self) -> bool {
+        self.usage_count >= MIN_USAGE_FOR_RANKING && self.success_rate() < UNRELIABLE_SUCCESS_RATE
+    }
+}
+
+/// Rule-based grading — used when no LLM is available or the LLM critic fails.
+pub fn grade_rules(turn: &TurnRecord) -> TurnGrade {
+    let lower = turn.response.to_lowercase();
+    let acknowledges_failure = FAILURE_MARKERS.iter().any(|m| lower.contains(m));
+    let any_error = turn.tool_calls.iter().any(|c| c.is_error);
+    let mut notes = Vec::new();
+
+    let tool_consistency = if turn.tool_calls.is_empty() {
+        1.0
+    } else if any_error && !acknowledges_failure {
+        notes.push("tool error not acknowledged");
+        0.2
+    } else {
+        let ok = turn.tool_calls.iter().filter(|c| !c.is_error).count();
+        if any_error {
+            // Errors were reported honestly; grade by how much actually worked.
+            0.5 + 0.5 * ok as f32 / turn.tool_calls.len() as f32
+        } else {
+            1.0
+        }
+    };
+
+    let trimmed = turn.response.trim();
+    let answered = if trimmed.is_empty() || trimmed.starts_with('[') {
+        notes.push("no substantive answer");
+        0.0
+    } else {
+        0.7
+    };
+
+    let hallucination =
+        if turn.tool_calls.is_empty() && ACTION_CLAIMS.iter().any(|m| lower.contains(m)) {
+            notes.push("claims actions without tool calls");
+            0.7
+        } else {
+            0.1
+        };
+
+    TurnGrade {
+        tool_consistency,
+        answered,
+        hallucination,
+        notes: notes.join("; "),
+        source: CriticSource::Rules,
+    }
+}
+
+/// Grade a turn with the LLM critic, falling back to rules on timeout or bad output.
+pub async fn grade(
+    provider: Option<&dyn LlmProvider>,
+    turn: &TurnRecord,
+    timeout: Duration,
+) -> TurnGrade {
+    let Some(provider) = provider else {
+        return grade_rules(turn);
+    };
+
+    let tools = if turn.tool_calls.is_empty() {
+        "(no tools were called)".to_string()
+    } else {
+        turn.tool_calls
+            .iter()
+            .map(|c| {
+                format!(
+                    "- {}({}) -> {}",
+                    c.name,
+                    truncate(&c.input, 200),
+                    truncate(&c.outcome, 400)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let request = CompletionRequest {
+        messages: vec![
+            ChatMessage {
+                role: Role::System,
+                content: "You are a strict response critic. Output ONLY valid JSON. No markdown, no explanation.".into(),
+                content_blocks: vec![],
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Grade the assistant turn below. Return exactly one JSON object with keys:\n\
+                     - tool_consistency: number in [0,1], does the reply agree with the tool results?\n\
+                     - answered: number in [0,1], did the reply answer the user's request?\n\
+                     - hallucination: number in [0,1], risk of claims not supported by tool results or context\n\
+                     - notes: short string\n\n\
+                     User:\n{}\n\nTool calls:\n{}\n\nAssistant reply:\n{}",
+                    truncate(&turn.user_input, 1000),
+                    tools,
+                    truncate(&turn.response, 2000)
+                ),
+                content_blocks: vec![],
+            },
+        ],
+        max_tokens: 200,
+        temperature: 0.0,
+        tools: vec![],
+    };
+
+    match tokio::time::timeout(timeout, provider.complete(request)).await {
+        Ok(Ok(resp)) => {
+            if let Some(grade) = parse_grade(&resp.content) {
+                return grade;
+            }
+            tracing::debug!(event_id = %turn.event_id, "critic output unparseable, using rules");
+        }
+        Ok(Err(e)) => {
+            tracing::debug!(event_id = %turn.event_id, error = %e, "critic LLM call failed, using rules");
+        }
+        Err(_) => {
+            tracing::debug!(event_id = %turn.event_id, "critic LLM timed out, using rules");
+        }
+    }
+    grade_rules(turn)
+}
+
+fn parse_grade(raw: &str) -> Option<TurnGrade> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    let parsed: serde_json::Value = serde_json::from_str(raw.get(start..=end)?).ok()?;
+    let unit = |key: &str| {
+        parsed
+            .get(key)
+            .and_then(|v| v.as_f64())
+            .map(|n| (n as f32).clamp(0.0, 1.0))
+    };
+    Some(TurnGrade {
+        tool_consistency: unit("tool_consistency")?,
+        answered: unit("answered")?,
+        hallucination: unit("hallucination")?,
+        notes: parsed
+            .get("notes")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        source: CriticSource::Llm,
+    })
+}
+
+/// Persist a grade and update tool / capability scores.
+pub async fn record(
+    pool: &PgPool,
+    turn: &TurnRecord,
+    grade: &TurnGrade,
+) -> Result<(), sqlx::Error> {
+    let tools_used: Vec<String> = turn.tool_calls.iter().map(|c| c.name.clone()).collect();
+    let overall = grade.overall();
+
+    sqlx::query(
+        "INSERT INTO turn_evaluation
+             (id, event_id, user_input, response, tools_used, capability_id,
+              tool_consistency, answered, hallucination, overall, critic, notes)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(turn.event_id)
+    .bind(&turn.user_input)
+    .bind(&turn.response)
+    .bind(&tools_used)
+    .bind(turn.capability_id)
+    .bind(grade.tool_consistency)
+    .bind(grade.answered)
+    .bind(grade.hallucination)
+    .bind(overall)
+    .bind(grade.source.as_str())
+    .bind(&grade.notes)
+    .execute(pool)
+    .await?;
+
+    for call in &turn.tool_calls {
+        let success = !call.is_error && grade.tool_consistency >= CONSISTENCY_PASS;
+        sqlx::query(
+            "INSERT INTO tool_score (tool_name, usage_count, success_count, fail_count, grade_sum, updated_at)
+             VALUES ($1, 1, $2, $3, $4, now())
+             ON CONFLICT (tool_name) DO UPDATE SET
+                 usage_count = tool_score.usage_count + 1,
+                 success_count = tool_score.success_count + EXCLUDED.success_count,
+                 fail_count = tool_score.fail_count + EXCLUDED.fail_count,
+                 grade_sum = tool_score.grade_sum + EXCLUDED.grade_sum,
+                 updated_at = now()",
+        )
+        .bind(&call.name)
+        .bind(i64::from(success))
+        .bind(i64::from(!success))
+        .bind(f64::from(overall))
+        .execute(pool)
+        .await?;
+    }
+
+    if let Some(cap_id) = turn.capability_id {
+        let success = overall >= CONSISTENCY_PASS && !turn.tool_calls.iter().any(|c| c.is_error);
+        capability_db::record_outcome(pool, cap_id, success).await?;
+    }
+
+    Ok(())
+}
+
+/// Load all tool scores keyed by tool name.
+pub async fn fetch_tool_scores(pool: &PgPool) -> Result<HashMap<String, ToolScore>, sqlx::Error> {
+    let rows: Vec<(String, i64, i64, i64, f64)> = sqlx::query_as(
+        "SELECT tool_name, usage_count, success_count, fail_count, grade_sum FROM tool_score",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(name, usage_count, success_count, fail_count, grade_sum)| {
+                (
+                    name,
+                    ToolScore {
+                        usage_count,
+                        success_count,
+                        fail_count,
+                        grade_sum,
+                    },
+                )
+            },
+        )
+        .collect())
+}
+
+/// Order tools by observed success rate and flag unreliable ones in their description.
+pub fn rank_tools(tools: &mut [ToolDefinition], scores: &HashMap<String, ToolScore>) {
+    let rate = |t: &ToolDefinition| {
+        scores
+            .get(&t.name)
+            .filter(|s| s.usage_count >= MIN_USAGE_FOR_RANKING)
+            .map(ToolScore::success_rate)
+            .unwrap_or(1.0)
+    };
+    tools.sort_by(|a, b| rate(b).total_cmp(&rate(a)));
+
+    for tool in tools.iter_mut() {
+        if let Some(score) = scores.get(&tool.name).filter(|s| s.is_unreliable()) {
+            tool.description.push_str(&format!(
+                " [note: succeeded in {}/{} uses so far]",
+                score.success_count, score.usage_count
+            ));
+        }
+    }
+}
+
+/// Background critic pass: grade the turn and persist the results.
+pub async fn critique_turn(
+    pool: PgPool,
+    provider: Option<Arc<dyn LlmProvider>>,
+    turn: TurnRecord,
+    timeout: Duration,
+) {
+    let grade = grade(provider.as_deref(), &turn, timeout).await;
+    tracing::debug!(
+        event_id = %turn.event_id,
+        critic = grade.source.as_str(),
+        tool_consistency = grade.tool_consistency,
+        answered = grade.answered,
+        hallucination = grade.hallucination,
+        overall = grade.overall(),
+        "turn graded"
+    );
+    if let Err(e) = record(&pool, &turn, &grade).await {
+        tracing::warn!(error = %e, "failed to persist turn evaluation");
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    let mut out: String = s.chars().take(max).collect();
+    if s.chars().count() > max {
+        out.push_str("...");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(name: &str, is_error: bool) -> ToolAttempt {
+        ToolAttempt {
+            name: name.into(),
+            input: "{}".into(),
+            outcome: if is_error {
+                "error: boom".into()
+            } else {
+                "ok".into()
+            },
+            is_error,
+        }
+    }
+
+    fn turn(response: &str, tool_calls: Vec<ToolAttempt>) -> TurnRecord {
+        TurnRecord {
+            event_id: Uuid::new_v4(),
+            user_input: "list the files".into(),
+            response: response.into(),
+            tool_calls,
+            capability_id: None,
+        }
+    }
+
+    #[test]
+    fn rules_penalize_unacknowledged_tool_error() {
+        let honest = grade_rules(&turn(
+            "The command failed: boom",
+            vec![attempt("run_bash", true)],
+        ));
+        let dishonest = grade_rules(&turn(
+            "Done, here are your files",
+            vec![attempt("run_bash", true)],
+        ));
+        assert!(honest.tool_consistency > dishonest.tool_consistency);
+        assert!(dishonest.tool_consistency < CONSISTENCY_PASS);
+    }
+
+    #[test]
+    fn rules_flag_action_claims_without_tools() {
+        let g = grade_rules(&turn("I ran ls and found three files", vec![]));
+        assert!(g.hallucination > 0.5);
+        let g = grade_rules(&turn(
+            "I ran ls and found three files",
+            vec![attempt("run_bash", false)],
+        ));
+        assert!(g.hallucination < 0.5);
+    }
+
+    #[test]
+    fn rules_mark_placeholder_unanswered() {
+        let g = grade_rules(&turn("[no LLM configured] received: hi", vec![]));
+        assert_eq!(g.answered, 0.0);
+    }
+
+    #[tokio::test]
+    async fn llm_grade_parsed() {
+        let provider = llm::provider::MockProvider::new(
+            r#"{"tool_consistency":0.9,"answered":1.0,"hallucination":0.05,"notes":"fine"}"#,
+        );
+        let g = grade(
+            Some(&provider),
+            &turn("here you go", vec![]),
+            Duration::from_secs(1),
+        )
+        .await;
+        assert_eq!(g.source, CriticSource::Llm);
+        assert_eq!(g.notes, "fine");
+        assert!(g.overall() > 0.9);
+    }
+
+    #[tokio::test]
+    async fn llm_grade_falls_back_on_garbage() {
+        let provider = llm::provider::MockProvider::new("looks good to me");
+        let g = grade(
+            Some(&provider),
+            &turn("here you go", vec![]),
+            Duration::from_secs(1),
+        )
+        .await;
+        assert_eq!(g.source, CriticSource::Rules);
+    }
+
+    #[test]
+    fn rank_tools_prefers_reliable() {
+        let def = |name: &str| ToolDefinition {
+            name: name.into(),
+            description: name.into(),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+        let mut tools = vec![def("flaky"), def("solid"), def("new")];
+        let mut scores = HashMap::new();
+        scores.insert(
+            "flaky".to_string(),
+            ToolScore {
+                usage_count: 10,
+                success_count: 1,
+                fail_count: 9,
+                grade_sum: 3.0,
+            },
+        );
+        scores.insert(
+            "solid".to_string(),
+            ToolScore {
+                usage_count: 10,
+                success_count: 9,
+                fail_count: 1,
+                grade_sum: 8.0,
+            },
+        );
+
+        rank_tools(&mut tools, &scores);
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["new", "solid", "flaky"]);
+        assert!(tools[2].description.contains("1/10"));
+        assert!(scores["flaky"].is_unreliable());
+        assert!(!scores["solid"].is_unreliable());
+    }
+}
@@ -1,14 +1,19 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::schema;
 use crate::capability::builtin::BuiltinRegistry;
+use crate::config::IrisCfg;
 use crate::types::{CapabilityRequest, CapabilityResponse};
 use llm::provider::{
     ChatMessage, CompletionRequest, ContentBlock, LlmError, LlmProvider, Role, StopReason,
     ToolDefinition,
 };
 
-/// Maximum number of tool-use iterations before forcing a text-only response.
-const MAX_TOOL_ITERATIONS: usize = 5;
 /// Default confidence when router output omits this field.
 const DEFAULT_ROUTE_CONFIDENCE: f32 = 0.0;
+/// Upper bound for the forced final call, which runs after `max_duration` is spent.
+const FINAL_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Structured tool-routing decision produced by a lightweight gate model.
 #[derive(Debug, Clone)]
@@ -18,6 +23,8 @@ pub struct ToolRouteDecision {
     pub input: serde_json::Value,
     pub confidence: f32,
     pub is_valid: bool,
+    /// Schema violations (or routing problems) behind `is_valid == false`.
+    pub validation_errors: Vec<String>,
 }
 
 /// Ask a lightweight model to choose a specific tool and arguments.
@@ -45,6 +52,7 @@ pub async fn route_tool_call(
             input: serde_json::json!({}),
             confidence: 1.0,
             is_valid: true,
+            validation_errors: vec![],
         });
     }
 
@@ -79,19 +87,66 @@ pub async fn route_tool_call(
         tools: vec![],
     };
 
-    let response = provider.complete(request).await?;
+    let response = provider.complete(request.clone()).await?;
     tracing::debug!(
         raw_response_len = response.content.len(),
         raw_response_preview = %preview(&response.content, 240),
         "tool router raw response received"
     );
 
-    let parsed = match parse_router_json(&response.content) {
+    let decision = decode_router_response(&response.content, tools)?;
+    if decision.is_valid || !decision.use_tool {
+        return Ok(decision);
+    }
+
+    // Send the violations back to the router once so it can correct its arguments.
+    let mut retry = request;
+    retry.messages.push(ChatMessage {
+        role: Role::Assistant,
+        content: response.content.clone(),
+        content_blocks: vec![],
+    });
+    retry.messages.push(ChatMessage {
+        role: Role::User,
+        content: format!(
+            "Your tool call is invalid:\n- {}\n\
+             Return the corrected JSON object only.",
+            decision.validation_errors.join("\n- ")
+        ),
+        content_blocks: vec![],
+    });
+
+    match provider.complete(retry).await {
+        Ok(resp) => match decode_router_response(&resp.content, tools) {
+            Ok(repaired) => {
+                tracing::debug!(
+                    is_valid = repaired.is_valid,
+                    "tool router repair attempt finished"
+                );
+                Ok(repaired)
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "tool router repair response unparseable");
+                Ok(decision)
+            }
+        },
+        Err(e) => {
+            tracing::debug!(error = %e, "tool router repair request failed");
+            Ok(decision)
+        }
+    }
+}
+
+fn decode_router_response(
+    raw: &str,
+    tools: &[ToolDefinition],
+) -> Result<ToolRouteDecision, LlmError> {
+    let parsed = match parse_router_json(raw) {
         Ok(v) => v,
         Err(e) => {
             tracing::debug!(
                 error = %e,
-                raw_response_preview = %preview(&response.content, 240),
+                raw_response_preview = %preview(raw, 240),
                 "tool router JSON parse failed"
             );
             return Err(LlmError::RequestFailed(e));
@@ -116,23 +171,32 @@ pub async fn route_tool_call(
         .map(|n| (n as f32).clamp(0.0, 1.0))
         .unwrap_or(DEFAULT_ROUTE_CONFIDENCE);
 
-    let is_valid = if !use_tool {
-        true
+    let validation_errors = if !use_tool {
+        vec![]
     } else if let Some(name) = tool_name.as_deref() {
         if let Some(def) = tools.iter().find(|t| t.name == name) {
-            validate_against_schema(&input, &def.input_schema)
+            match schema::validate_tool_input(&input, &def.input_schema) {
+                Ok(()) => vec![],
+                Err(violations) => violations.iter().map(|v| v.to_string()).collect(),
+            }
         } else {
-            false
+            let available: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+            vec![format!(
+                "unknown tool '{name}', available: {}",
+                available.join(", ")
+            )]
         }
     } else {
-        false
+        vec!["use_tool is true but tool_name is missing".to_string()]
     };
+    let is_valid = validation_errors.is_empty();
 
     tracing::debug!(
         use_tool,
         tool_name = ?tool_name,
         confidence,
         is_valid,
+        validation_errors = ?validation_errors,
         input_preview = %preview(&input.to_string(), 240),
         "tool router decision parsed"
     );
@@ -143,6 +207,7 @@ pub async fn route_tool_call(
         input,
         confidence,
         is_valid,
+        validation_errors,
     })
 }
 
@@ -229,50 +294,6 @@ fn parse_router_json(raw: &str) -> Result<serde_json::Value, String> {
         .map_err(|e| format!("invalid router JSON: {e}; raw: {trimmed}"))
 }
 
-fn validate_against_schema(input: &serde_json::Value, schema: &serde_json::Value) -> bool {
-    if !input.is_object() {
-        tracing::debug!(
-            input_preview = %preview(&input.to_string(), 160),
-            "tool router schema validation failed: input is not an object"
-        );
-        return false;
-    }
-
-    // Required keys
-    if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
-        for key in required.iter().filter_map(|v| v.as_str()) {
-            if input.get(key).is_none() {
-                tracing::debug!(
-                    missing_key = key,
-                    input_preview = %preview(&input.to_string(), 160),
-                    "tool router schema validation failed: required key missing"
-                );
-                return false;
-            }
-        }
-    }
-
-    // Shallow property type checks.
-    if let Some(props) = schema.get("properties").and_then(|v| v.as_object()) {
-        for (key, prop_schema) in props {
-            if let Some(value) = input.get(key)
-                && let Some(type_name) = prop_schema.get("type").and_then(|v| v.as_str())
-                && !matches_json_type(value, type_name)
-            {
-                tracing::debug!(
-                    key = key,
-                    expected_type = type_name,
-                    actual_value_preview = %preview(&value.to_string(), 120),
-                    "tool router schema validation failed: type mismatch"
-                );
-                return false;
-            }
-        }
-    }
-
-    true
-}
-
 fn preview(s: &str, max: usize) -> String {
     let mut out: String = s.chars().take(max).collect();
     if s.chars().count() > max {
@@ -281,19 +302,6 @@ fn preview(s: &str, max: usize) -> String {
     out
 }
 
-fn matches_json_type(value: &serde_json::Value, type_name: &str) -> bool {
-    match type_name {
-        "string" => value.is_string(),
-        "number" => value.is_number(),
-        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
-        "boolean" => value.is_boolean(),
-        "object" => value.is_object(),
-        "array" => value.is_array(),
-        "null" => value.is_null(),
-        _ => true,
-    }
-}
-
 /// Execute a single builtin tool by name with structured JSON input.
 async fn execute_tool(
     registry: &BuiltinRegistry,
@@ -305,6 +313,17 @@ async fn execute_tool(
         format!("Unknown tool '{tool_name}'. Available: {available}")
     })?;
 
+    // Never run a tool on input that violates its declared schema.
+    if let Err(violations) = schema::validate_tool_input(input, &cap.tool_definition().input_schema)
+    {
+        tracing::debug!(
+            tool = tool_name,
+            violations = violations.len(),
+            "tool input rejected by schema validation"
+        );
+        return Err(schema::invalid_input_error(tool_name, &violations));
+    }
+
     let request = CapabilityRequest {
         id: uuid::Uuid::new_v4(),
         method: input.to_string(),
@@ -316,7 +335,8 @@ async fn execute_tool(
     if let Some(err) = resp.error {
         Err(err)
     } else if let Some(result) = resp.result {
-        Ok(result.to_string())
+        // Anything still too large for the context is kept as an artifact.
+        Ok(registry.artifacts().compact_result(tool_name, result.to_string()))
     } else {
         Ok("ok".to_string())
     }
@@ -331,21 +351,189 @@ pub async fn execute_named_tool(
     execute_tool(registry, tool_name, input).await
 }
 
+/// Per-request limits for the agentic tool loop, sourced from `IrisCfg`.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolLoopBudget {
+    /// Maximum number of tool-use rounds before forcing a text-only response.
+    pub max_iterations: usize,
+    /// Wall-clock budget for the whole loop (tool execution included).
+    pub max_duration: Duration,
+    /// Combined input + output token budget across all LLM calls in the loop.
+    pub max_tokens: u64,
+    /// Repeats of an identical tool call (same name + input) after which the loop is stopped.
+    pub max_repeats: usize,
+}
+
+impl ToolLoopBudget {
+    pub fn from_cfg(cfg: &IrisCfg) -> Self {
+        Self {
+            max_iterations: cfg.tool_loop_max_iterations.max(1),
+            max_duration: Duration::from_secs(cfg.tool_loop_timeout_secs),
+            max_tokens: cfg.tool_loop_max_tokens,
+            max_repeats: cfg.tool_loop_max_repeats,
+        }
+    }
+}
+
+impl Default for ToolLoopBudget {
+    fn default() -> Self {
+        Self::from_cfg(&IrisCfg::default())
+    }
+}
+
+/// Why the agentic loop stopped calling tools before the model finished on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BudgetExhausted {
+    Iterations,
+    WallClock,
+    Tokens,
+    RepeatedCalls,
+}
+
+impl BudgetExhausted {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Iterations => "the maximum number of tool rounds was reached",
+            Self::WallClock => "the time budget for this request ran out",
+            Self::Tokens => "the token budget for this request ran out",
+            Self::RepeatedCalls => "the same tool call kept being repeated without progress",
+        }
+    }
+}
+
+/// One executed tool call, kept for the forced final answer and the self-critic.
+#[derive(Debug, Clone)]
+pub struct ToolAttempt {
+    pub name: String,
+    pub input: String,
+    pub outcome: String,
+    pub is_error: bool,
+}
+
+/// Final text of an agentic loop plus every tool call it executed.
+#[derive(Debug, Clone)]
+pub struct ToolLoopOutcome {
+    pub text: String,
+    pub attempts: Vec<ToolAttempt>,
+}
+
+/// Canonical key for loop detection: tool name + serialized input.
+/// `serde_json` keeps object keys sorted, so equal inputs serialize identically.
+fn call_key(name: &str, input: &serde_json::Value) -> String {
+    format!("{name}:{input}")
+}
+
+/// Tool result sent back instead of re-executing an identical call.
+fn repeat_nudge(name: &str, previous: &str) -> String {
+    format!(
+        "You already called `{name}` with exactly this input in this turn. \
+         It was not executed again. Previous result: {}\n\
+         Do not repeat the same call. Use the previous result, change the input, \
+         try a different tool, or answer the user now.",
+        preview(previous, 600)
+    )
+}
+
+/// Instruction appended when a budget runs out, asking for a final text-only answer.
+fn budget_notice(reason: BudgetExhausted, attempts: &[ToolAttempt]) -> String {
+    let mut notice = format!(
+        "[notice] Tool use has been stopped because {}. Do not call any more tools.\n",
+        reason.describe()
+    );
+    if attempts.is_empty() {
+        notice.push_str("No tools were executed.\n");
+    } else {
+        notice.push_str("Tools attempted in this turn:\n");
+        for a in attempts {
+            notice.push_str(&format!(
+                "- {}({}) -> {}\n",
+                a.name,
+                preview(&a.input, 120),
+                preview(&a.outcome, 160)
+            ));
+        }
+    }
+    notice.push_str(
+        "Answer the original request now with what you have: say what you attempted, \
+         what you found, and what is still unresolved.",
+    );
+    notice
+}
+
+/// Deterministic summary used when the forced final call itself produces nothing.
+fn attempts_summary(reason: BudgetExhausted, attempts: &[ToolAttempt]) -> String {
+    let mut out = format!("Stopped early because {}.", reason.describe());
+    if !attempts.is_empty() {
+        out.push_str(" Attempted:");
+        for a in attempts {
+            out.push_str(&format!("\n- {}: {}", a.name, preview(&a.outcome, 160)));
+        }
+    }
+    out
+}
+
+/// Append a note to the trailing user turn, or start a new one.
+/// Keeps user/assistant alternation intact after a tool_results message.
+fn append_user_note(messages: &mut Vec<ChatMessage>, note: String) {
+    match messages.last_mut() {
+        Some(last) if last.role == Role::User && !last.content_blocks.is_empty() => {
+            last.content_blocks.push(ContentBlock::Text { text: note.clone() });
+            if last.content.is_empty() {
+                last.content = note;
+            } else {
+                last.content.push_str("\n\n");
+                last.content.push_str(&note);
+            }
+        }
+        _ => messages.push(ChatMessage {
+            role: Role::User,
+            content: note,
+            content_blocks: vec![],
+        }),
+    }
+}
+
 /// Run the agentic tool-use loop using Claude's native tool use protocol.
 ///
 /// Each iteration: call LLM with tool definitions → check stop_reason →
 /// if ToolUse: execute tools, send tool_result blocks → repeat.
-/// Stops on EndTurn/MaxTokens or after MAX_TOOL_ITERATIONS rounds.
+/// Stops on EndTurn/MaxTokens, or when a `ToolLoopBudget` limit (rounds, wall clock,
+/// tokens, repeated identical calls) is hit — then one final call without tools
+/// forces a text answer explaining what was attempted.
 pub async fn run_agentic_loop(
     provider: &dyn LlmProvider,
     initial_messages: Vec<ChatMessage>,
     tools: Vec<ToolDefinition>,
     registry: &BuiltinRegistry,
+    budget: ToolLoopBudget,
 ) -> Result<String, LlmError> {
+    run_agentic_loop_traced(provider, initial_messages, tools, registry, budget)
+        .await
+        .map(|outcome| outcome.text)
+}
+
+/// Same as `run_agentic_loop`, but also returns the executed tool calls.
+pub async fn run_agentic_loop_traced(
+    provider: &dyn LlmProvider,
+    initial_messages: Vec<ChatMessage>,
+    tools: Vec<ToolDefinition>,
+    registry: &BuiltinRegistry,
+    budget: ToolLoopBudget,
+) -> Result<ToolLoopOutcome, LlmError> {
+    let started = Instant::now();
     let mut messages = initial_messages;
-    let mut final_text = String::new();
+    let mut tokens_used: u64 = 0;
+    let mut attempts: Vec<ToolAttempt> = Vec::new();
+    // call key → (repeats so far, first result)
+    let mut seen_calls: HashMap<String, (usize, String)> = HashMap::new();
+    let mut exhausted = None;
+
+    for iteration in 0..budget.max_iterations {
+        let Some(remaining) = budget.max_duration.checked_sub(started.elapsed()) else {
+            exhausted = Some(BudgetExhausted::WallClock);
+            break;
+        };
 
-    for iteration in 0..MAX_TOOL_ITERATIONS {
         let request = CompletionRequest {
             messages: messages.clone(),
             max_tokens: 4096,
@@ -353,73 +541,142 @@ pub async fn run_agentic_loop(
             tools: tools.clone(),
         };
 
-        let response = provider.complete(request).await?;
-
-        match response.stop_reason {
-            StopReason::EndTurn | StopReason::MaxTokens => {
-                final_text = response.content;
+        let response = match tokio::time::timeout(remaining, provider.complete(request)).await {
+            Ok(result) => result?,
+            Err(_) => {
+                exhausted = Some(BudgetExhausted::WallClock);
                 break;
             }
-            StopReason::ToolUse => {
-                // Append assistant message with all content blocks
-                messages.push(ChatMessage::from_content_blocks(
-                    Role::Assistant,
-                    response.content_blocks.clone(),
-                ));
-
-                // Collect tool_use blocks and execute them
-                let tool_uses: Vec<_> = response
-                    .content_blocks
-                    .iter()
-                    .filter_map(|b| {
-                        if let ContentBlock::ToolUse { id, name, input } = b {
-                            Some((id.clone(), name.clone(), input.clone()))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
+        };
+        tokens_used += u64::from(response.input_tokens) + u64::from(response.output_tokens);
+
+        if matches!(response.stop_reason, StopReason::EndTurn | StopReason::MaxTokens) {
+            return Ok(ToolLoopOutcome {
+                text: response.content,
+                attempts,
+            });
+        }
+
+        // Append assistant message with all content blocks
+        messages.push(ChatMessage::from_content_blocks(
+            Role::Assistant,
+            response.content_blocks.clone(),
+        ));
 
-                let mut result_blocks = Vec::new();
-                for (id, name, input) in &tool_uses {
+        // Collect tool_use blocks and execute them
+        let tool_uses: Vec<_> = response
+            .content_blocks
+            .iter()
+            .filter_map(|b| {
+                if let ContentBlock::ToolUse { id, name, input } = b {
+                    Some((id.clone(), name.clone(), input.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut result_blocks = Vec::new();
+        for (id, name, input) in &tool_uses {
+            let key = call_key(name, input);
+            let (content, is_error) = match seen_calls.get_mut(&key) {
+                Some((repeats, previous)) => {
+                    *repeats += 1;
+                    tracing::warn!(
+                        tool = %name,
+                        iteration,
+                        repeats = *repeats,
+                        "agentic loop: identical tool call repeated, nudging model"
+                    );
+                    if *repeats >= budget.max_repeats {
+                        exhausted = Some(BudgetExhausted::RepeatedCalls);
+                    }
+                    (repeat_nudge(name, previous), true)
+                }
+                None => {
                     tracing::info!(
                         tool = %name,
                         iteration = iteration,
                         "agentic loop: executing tool"
                     );
-
-                    let (content, is_error) = match execute_tool(registry, name, input).await {
-                        Ok(result) => (result, false),
-                        Err(err) => (err, true),
+                    let remaining = budget.max_duration.saturating_sub(started.elapsed());
+                    let executed = tokio::time::timeout(remaining, execute_tool(registry, name, input));
+                    let (content, is_error) = match executed.await {
+                        Ok(Ok(result)) => (result, false),
+                        Ok(Err(err)) => (err, true),
+                        Err(_) => {
+                            exhausted = Some(BudgetExhausted::WallClock);
+                            (format!("{name} timed out: {}", BudgetExhausted::WallClock.describe()), true)
+                        }
                     };
-
-                    result_blocks.push(ContentBlock::ToolResult {
-                        tool_use_id: id.clone(),
-                        content,
+                    seen_calls.insert(key, (0, content.clone()));
+                    attempts.push(ToolAttempt {
+                        name: name.clone(),
+                        input: input.to_string(),
+                        outcome: if is_error {
+                            format!("error: {content}")
+                        } else {
+                            content.clone()
+                        },
                         is_error,
                     });
+                    (content, is_error)
                 }
+            };
 
-                // Append user message with tool results
-                messages.push(ChatMessage::tool_results(result_blocks));
-
-                // If last iteration, do one final call without tools
-                if iteration == MAX_TOOL_ITERATIONS - 1 {
-                    tracing::warn!("agentic loop: max iterations reached, forcing final response");
-                    let request = CompletionRequest {
-                        messages: messages.clone(),
-                        max_tokens: 4096,
-                        temperature: 0.7,
-                        tools: vec![],
-                    };
-                    let response = provider.complete(request).await?;
-                    final_text = response.content;
-                }
-            }
+            result_blocks.push(ContentBlock::ToolResult {
+                tool_use_id: id.clone(),
+                content,
+                is_error,
+            });
+        }
+
+        // Append user message with tool results
+        messages.push(ChatMessage::tool_results(result_blocks));
+
+        if exhausted.is_some() {
+            break;
+        }
+        if tokens_used >= budget.max_tokens {
+            exhausted = Some(BudgetExhausted::Tokens);
+            break;
+        }
+        if started.elapsed() >= budget.max_duration {
+            exhausted = Some(BudgetExhausted::WallClock);
+            break;
         }
     }
 
-    Ok(final_text)
+    let reason = exhausted.unwrap_or(BudgetExhausted::Iterations);
+    tracing::warn!(
+        ?reason,
+        tokens_used,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        attempts = attempts.len(),
+        "agentic loop: budget exhausted, forcing final response"
+    );
+
+    append_user_note(&mut messages, budget_notice(reason, &attempts));
+    let request = CompletionRequest {
+        messages,
+        max_tokens: 4096,
+        temperature: 0.7,
+        tools: vec![],
+    };
+    let limit = budget.max_duration.min(FINAL_RESPONSE_TIMEOUT);
+    let content = match tokio::time::timeout(limit, provider.complete(request)).await {
+        Ok(result) => result?.content,
+        Err(_) => {
+            tracing::warn!(?reason, "agentic loop: forced final response timed out");
+            String::new()
+        }
+    };
+    let text = if content.trim().is_empty() {
+        attempts_summary(reason, &attempts)
+    } else {
+        content
+    };
+    Ok(ToolLoopOutcome { text, attempts })
 }
 
 #[cfg(test)]
@@ -546,7 +803,7 @@ mod tests {
             content_blocks: vec![],
         }];
 
-        let result = run_agentic_loop(&provider, messages, vec![], &registry)
+        let result = run_agentic_loop(&provider, messages, vec![], &registry, ToolLoopBudget::default())
             .await
             .unwrap();
         assert_eq!(result, "just a normal answer");
@@ -626,10 +883,354 @@ mod tests {
             content_blocks: vec![],
         }];
 
-        let result = run_agentic_loop(&provider, messages, tools, &registry)
+        let result = run_agentic_loop(&provider, messages, tools, &registry, ToolLoopBudget::default())
             .await
             .unwrap();
         assert_eq!(result, "The command output: hello");
         assert_eq!(provider.call_count.load(Ordering::SeqCst), 2);
     }
+
+    /// Always asks for the same tool call while tools are offered; answers in text
+    /// once the tool list is empty (the forced final call).
+    struct LoopingProvider {
+        call_count: std::sync::atomic::AtomicUsize,
+        final_prompt: std::sync::Mutex<Option<String>>,
+        tokens_per_call: u32,
+        final_delay: Duration,
+        command: &'static str,
+    }
+
+    impl LoopingProvider {
+        fn new(tokens_per_call: u32) -> Self {
+            Self {
+                call_count: std::sync::atomic::AtomicUsize::new(0),
+                final_prompt: std::sync::Mutex::new(None),
+                tokens_per_call,
+                final_delay: Duration::ZERO,
+                command: "echo loop",
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.call_count.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl LlmProvider for LoopingProvider {
+        fn name(&self) -> &str {
+            "looping"
+        }
+
+        fn complete(
+            &self,
+            request: CompletionRequest,
+        ) -> std::pin::Pin<
+            Box<
+                dyn std::future::Future<
+                        Output = Result<llm::provider::CompletionResponse, LlmError>,
+                    > + Send
+                    + '_,
+            >,
+        > {
+            self.call_count
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let tokens = self.tokens_per_call;
+            let final_delay = self.final_delay;
+            let command = self.command;
+            if request.tools.is_empty() {
+                let last = request.messages.last().map(|m| m.content.clone());
+                *self.final_prompt.lock().unwrap() = last;
+            }
+            Box::pin(async move {
+                if request.tools.is_empty() {
+                    tokio::time::sleep(final_delay).await;
+                    return Ok(llm::provider::CompletionResponse {
+                        content: "I tried echo but gave up".into(),
+                        content_blocks: vec![ContentBlock::Text {
+                            text: "I tried echo but gave up".into(),
+                        }],
+                        stop_reason: StopReason::EndTurn,
+                        input_tokens: tokens,
+                        output_tokens: 0,
+                    });
+                }
+                Ok(llm::provider::CompletionResponse {
+                    content: String::new(),
+                    content_blocks: vec![ContentBlock::ToolUse {
+                        id: "tu_loop".into(),
+                        name: "run_bash".into(),
+                        input: serde_json::json!({"command": command}),
+                    }],
+                    stop_reason: StopReason::ToolUse,
+                    input_tokens: tokens,
+                    output_tokens: 0,
+                })
+            })
+        }
+    }
+
+    fn user_message(text: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: Role::User,
+            content: text.into(),
+            content_blocks: vec![],
+        }]
+    }
+
+    #[tokio::test]
+    async fn agentic_loop_stops_on_repeated_identical_calls() {
+        let provider = LoopingProvider::new(1);
+        let registry = BuiltinRegistry::new();
+        let budget = ToolLoopBudget {
+            max_iterations: 10,
+            max_duration: Duration::from_secs(30),
+            max_tokens: u64::MAX,
+            max_repeats: 2,
+        };
+
+        let result = run_agentic_loop(
+            &provider,
+            user_message("echo loop"),
+            registry.tool_definitions(),
+            &registry,
+            budget,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, "I tried echo but gave up");
+        // 1 executed call + 2 nudged repeats, then the forced final call.
+        assert_eq!(provider.calls(), 4);
+        let prompt = provider.final_prompt.lock().unwrap().clone().unwrap();
+        assert!(prompt.contains("repeated"));
+        assert!(prompt.contains("run_bash"));
+    }
+
+    #[tokio::test]
+    async fn agentic_loop_respects_iteration_budget() {
+        let provider = LoopingProvider::new(1);
+        let registry = BuiltinRegistry::new();
+        let budget = ToolLoopBudget {
+            max_iterations: 1,
+            max_duration: Duration::from_secs(30),
+            max_tokens: u64::MAX,
+            max_repeats: 5,
+        };
+
+        let result = run_agentic_loop(
+            &provider,
+            user_message("echo loop"),
+            registry.tool_definitions(),
+            &registry,
+            budget,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, "I tried echo but gave up");
+        assert_eq!(provider.calls(), 2);
+        let prompt = provider.final_prompt.lock().unwrap().clone().unwrap();
+        assert!(prompt.contains("maximum number of tool rounds"));
+    }
+
+    #[tokio::test]
+    async fn agentic_loop_respects_token_budget() {
+        let provider = LoopingProvider::new(500);
+        let registry = BuiltinRegistry::new();
+        let budget = ToolLoopBudget {
+            max_iterations: 10,
+            max_duration: Duration::from_secs(30),
+            max_tokens: 100,
+            max_repeats: 5,
+        };
+
+        run_agentic_loop(
+            &provider,
+            user_message("echo loop"),
+            registry.tool_definitions(),
+            &registry,
+            budget,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(provider.calls(), 2);
+        let prompt = provider.final_prompt.lock().unwrap().clone().unwrap();
+        assert!(prompt.contains("token budget"));
+    }
+
+    #[tokio::test]
+    async fn agentic_loop_bounds_the_forced_final_call() {
+        let mut provider = LoopingProvider::new(1);
+        provider.final_delay = Duration::from_secs(600);
+        let registry = BuiltinRegistry::new();
+        let budget = ToolLoopBudget {
+            max_iterations: 1,
+            max_duration: Duration::from_millis(200),
+            max_tokens: u64::MAX,
+            max_repeats: 5,
+        };
+
+        let started = Instant::now();
+        let result = run_agentic_loop(
+            &provider,
+            user_message("echo loop"),
+            registry.tool_definitions(),
+            &registry,
+            budget,
+        )
+        .await
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.starts_with("Stopped early because"), "{result}");
+        assert!(result.contains("run_bash"));
+    }
+
+    #[tokio::test]
+    async fn agentic_loop_times_out_slow_tools() {
+        let mut provider = LoopingProvider::new(1);
+        provider.command = "sleep 30";
+        let registry = BuiltinRegistry::new();
+        let budget = ToolLoopBudget {
+            max_iterations: 10,
+            max_duration: Duration::from_millis(300),
+            max_tokens: u64::MAX,
+            max_repeats: 5,
+        };
+
+        let started = Instant::now();
+        let outcome = run_agentic_loop_traced(
+            &provider,
+            user_message("sleep"),
+            registry.tool_definitions(),
+            &registry,
+            budget,
+        )
+        .await
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(outcome.attempts.len(), 1);
+        assert!(outcome.attempts[0].is_error);
+        assert!(outcome.attempts[0].outcome.contains("timed out"), "{}", outcome.attempts[0].outcome);
+        let prompt = provider.final_prompt.lock().unwrap().clone().unwrap();
+        assert!(prompt.contains("time budget"));
+    }
+
+    /// Returns scripted text responses in order, repeating the last one.
+    struct ScriptedProvider {
+        replies: Vec<&'static str>,
+        call_count: std::sync::atomic::AtomicUsize,
+    }
+
+    impl LlmProvider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn complete(
+            &self,
+            _request: CompletionRequest,
+        ) -> std::pin::Pin<
+            Box<
+                dyn std::future::Future<
+                        Output = Result<llm::provider::CompletionResponse, LlmError>,
+                    > + Send
+                    + '_,
+            >,
+        > {
+            let n = self
+                .call_count
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let reply = self.replies[n.min(self.replies.len() - 1)].to_string();
+            Box::pin(async move {
+                Ok(llm::provider::CompletionResponse {
+                    content: reply.clone(),
+                    content_blocks: vec![ContentBlock::Text { text: reply }],
+                    stop_reason: StopReason::EndTurn,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn router_repairs_invalid_input_after_feedback() {
+        let provider = ScriptedProvider {
+            replies: vec![
+                r#"{"use_tool":true,"tool_name":"run_bash","input":{"command":42},"confidence":0.9}"#,
+                r#"{"use_tool":true,"tool_name":"run_bash","input":{"command":"ls"},"confidence":0.9}"#,
+            ],
+            call_count: std::sync::atomic::AtomicUsize::new(0),
+        };
+        let tools = vec![ToolDefinition {
+            name: "run_bash".into(),
+            description: "Execute shell command".into(),
+            input_schema: serde_json::json!({
+                "type":"object",
+                "properties":{"command":{"type":"string","minLength":1}},
+                "required":["command"],
+                "additionalProperties": false
+            }),
+        }];
+
+        let decision = route_tool_call(&provider, "list files", &tools)
+            .await
+            .unwrap();
+        assert!(decision.is_valid);
+        assert_eq!(decision.input["command"], "ls");
+        assert_eq!(
+            provider
+                .call_count
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn router_reports_validation_errors() {
+        let provider = MockProvider::new(
+            r#"{"use_tool":true,"tool_name":"run_bash","input":{"command":"ls","cwd":"/"},"confidence":0.9}"#,
+        );
+        let tools = vec![ToolDefinition {
+            name: "run_bash".into(),
+            description: "Execute shell command".into(),
+            input_schema: serde_json::json!({
+                "type":"object",
+                "properties":{"command":{"type":"string"}},
+                "additionalProperties": false
+            }),
+        }];
+
+        let decision = route_tool_call(&provider, "list files", &tools)
+            .await
+            .unwrap();
+        assert!(!decision.is_valid);
+        assert!(decision.validation_errors[0].starts_with("$.cwd"));
+    }
+
+    #[tokio::test]
+    async fn execute_named_tool_rejects_invalid_input() {
+        let registry = BuiltinRegistry::new();
+        let err = execute_named_tool(&registry, "run_bash", &serde_json::json!({"command": 1}))
+            .await
+            .unwrap_err();
+        let payload: serde_json::Value = serde_json::from_str(&err).unwrap();
+        assert_eq!(payload["error"], "invalid_input");
+        assert_eq!(payload["violations"][0]["path"], "$.command");
+    }
+
+    #[test]
+    fn budget_from_cfg() {
+        let cfg = IrisCfg {
+            tool_loop_max_iterations: 0,
+            tool_loop_timeout_secs: 7,
+            ..IrisCfg::default()
+        };
+        let budget = ToolLoopBudget::from_cfg(&cfg);
+        assert_eq!(budget.max_iterations, 1);
+        assert_eq!(budget.max_duration, Duration::from_secs(7));
+    }
 }
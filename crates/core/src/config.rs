@@ -32,6 +32,7 @@ pub struct IrisCfg {
     pub codegen_max_per_hour: usize,
     pub codegen_max_repair: usize,
     pub codegen_compile_timeout_secs: u64,
+    pub capability_dir: String,
 
     // capability lifecycle
     pub candidate_observe_min_secs: u64,
@@ -47,6 +48,57 @@ pub struct IrisCfg {
     pub llm_tokens_per_min: u64,
     pub llm_calls_per_tick: usize,
 
+    // agentic tool loop (per request)
+    pub tool_loop_max_iterations: usize,
+    pub tool_loop_timeout_secs: u64,
+    pub tool_loop_max_tokens: u64,
+    pub tool_loop_max_repeats: usize,
+
+    // perception
+    pub perception_llm_enabled: bool,
+    pub perception_timeout_ms: u64,
+
+    // self-critic
+    pub self_critic_enabled: bool,
+    pub self_critic_timeout_secs: u64,
+
+    // language
+    pub default_language: String,
+
+    // run_bash sandbox
+    pub bash_sandbox: String,
+    pub bash_sandbox_workdir: String,
+    pub bash_cpu_secs: u64,
+    pub bash_memory_mb: u64,
+    pub bash_file_size_mb: u64,
+    pub bash_max_procs: u64,
+    pub job_cpu_secs: u64,
+
+    // file access policy
+    pub file_allowed_roots: String,
+    pub file_denied_globs: String,
+    pub file_max_bytes: u64,
+
+    // schedules
+    pub schedule_poll_secs: u64,
+
+    // MCP servers
+    pub mcp_servers_file: String,
+    pub mcp_timeout_secs: u64,
+
+    // tool output artifacts
+    pub artifact_dir: String,
+    pub artifact_threshold_bytes: usize,
+    pub artifact_preview_bytes: usize,
+
+    // undo journal
+    pub undo_journal_dir: String,
+    pub undo_retention_days: u32,
+    pub undo_max_entries: usize,
+
+    // git builtin
+    pub git_allow_mutations: bool,
+
     // embedding cache
     pub embedding_cache_cap: usize,
     pub embedding_cache_ttl_secs: u64,
@@ -78,6 +130,7 @@ impl Default for IrisCfg {
             codegen_max_per_hour: 10,
             codegen_max_repair: 3,
             codegen_compile_timeout_secs: 120,
+            capability_dir: String::new(),
             candidate_observe_min_secs: 600,
             safe_mode_failures: 3,
             safe_mode_cooldown_secs: 300,
@@ -86,6 +139,35 @@ impl Default for IrisCfg {
             shutdown_timeout_secs: 15,
             llm_tokens_per_min: 10000,
             llm_calls_per_tick: 4,
+            tool_loop_max_iterations: 5,
+            tool_loop_timeout_secs: 120,
+            tool_loop_max_tokens: 60000,
+            tool_loop_max_repeats: 2,
+            perception_llm_enabled: false,
+            perception_timeout_ms: 1500,
+            self_critic_enabled: false,
+            self_critic_timeout_secs: 20,
+            default_language: "zh".into(),
+            bash_sandbox: "off".into(),
+            bash_sandbox_workdir: String::new(),
+            bash_cpu_secs: 30,
+            bash_memory_mb: 1024,
+            bash_file_size_mb: 64,
+            bash_max_procs: 256,
+            job_cpu_secs: 0,
+            file_allowed_roots: ".,~,/tmp".into(),
+            file_denied_globs: crate::capability::path_policy::DEFAULT_DENIED_GLOBS.into(),
+            file_max_bytes: 10 * 1024 * 1024,
+            schedule_poll_secs: 15,
+            mcp_servers_file: "~/.iris/mcp.json".into(),
+            mcp_timeout_secs: 30,
+            artifact_dir: String::new(),
+            artifact_threshold_bytes: 16 * 1024,
+            artifact_preview_bytes: 2 * 1024,
+            undo_journal_dir: String::new(),
+            undo_retention_days: 7,
+            undo_max_entries: 500,
+            git_allow_mutations: false,
             embedding_cache_cap: 1024,
             embedding_cache_ttl_secs: 300,
             episodic_recall_threshold: 3,
@@ -149,6 +231,7 @@ impl IrisCfg {
             codegen_max_per_hour: get_or(m, "codegen_max_per_hour", d.codegen_max_per_hour),
             codegen_max_repair: get_or(m, "codegen_max_repair", d.codegen_max_repair),
             codegen_compile_timeout_secs: get_or(m, "codegen_compile_timeout_secs", d.codegen_compile_timeout_secs),
+            capability_dir: get_or(m, "capability_dir", d.capability_dir),
             candidate_observe_min_secs: get_or(m, "candidate_observe_min_secs", d.candidate_observe_min_secs),
             safe_mode_failures: get_or(m, "safe_mode_failures", d.safe_mode_failures),
             safe_mode_cooldown_secs: get_or(m, "safe_mode_cooldown_secs", d.safe_mode_cooldown_secs),
@@ -157,6 +240,35 @@ impl IrisCfg {
             shutdown_timeout_secs: get_or(m, "shutdown_timeout_secs", d.shutdown_timeout_secs),
             llm_tokens_per_min: get_or(m, "llm_tokens_per_min", d.llm_tokens_per_min),
             llm_calls_per_tick: get_or(m, "llm_calls_per_tick", d.llm_calls_per_tick),
+            tool_loop_max_iterations: get_or(m, "tool_loop_max_iterations", d.tool_loop_max_iterations),
+            tool_loop_timeout_secs: get_or(m, "tool_loop_timeout_secs", d.tool_loop_timeout_secs),
+            tool_loop_max_tokens: get_or(m, "tool_loop_max_tokens", d.tool_loop_max_tokens),
+            tool_loop_max_repeats: get_or(m, "tool_loop_max_repeats", d.tool_loop_max_repeats),
+            perception_llm_enabled: get_or(m, "perception_llm_enabled", d.perception_llm_enabled),
+            perception_timeout_ms: get_or(m, "perception_timeout_ms", d.perception_timeout_ms),
+            self_critic_enabled: get_or(m, "self_critic_enabled", d.self_critic_enabled),
+            self_critic_timeout_secs: get_or(m, "self_critic_timeout_secs", d.self_critic_timeout_secs),
+            default_language: get_or(m, "default_language", d.default_language),
+            bash_sandbox: get_or(m, "bash_sandbox", d.bash_sandbox),
+            bash_sandbox_workdir: get_or(m, "bash_sandbox_workdir", d.bash_sandbox_workdir),
+            bash_cpu_secs: get_or(m, "bash_cpu_secs", d.bash_cpu_secs),
+            bash_memory_mb: get_or(m, "bash_memory_mb", d.bash_memory_mb),
+            bash_file_size_mb: get_or(m, "bash_file_size_mb", d.bash_file_size_mb),
+            bash_max_procs: get_or(m, "bash_max_procs", d.bash_max_procs),
+            job_cpu_secs: get_or(m, "job_cpu_secs", d.job_cpu_secs),
+            file_allowed_roots: get_or(m, "file_allowed_roots", d.file_allowed_roots),
+            file_denied_globs: get_or(m, "file_denied_globs", d.file_denied_globs),
+            file_max_bytes: get_or(m, "file_max_bytes", d.file_max_bytes),
+            schedule_poll_secs: get_or(m, "schedule_poll_secs", d.schedule_poll_secs),
+            mcp_servers_file: get_or(m, "mcp_servers_file", d.mcp_servers_file),
+            mcp_timeout_secs: get_or(m, "mcp_timeout_secs", d.mcp_timeout_secs),
+            artifact_dir: get_or(m, "artifact_dir", d.artifact_dir),
+            artifact_threshold_bytes: get_or(m, "artifact_threshold_bytes", d.artifact_threshold_bytes),
+            artifact_preview_bytes: get_or(m, "artifact_preview_bytes", d.artifact_preview_bytes),
+            undo_journal_dir: get_or(m, "undo_journal_dir", d.undo_journal_dir),
+            undo_retention_days: get_or(m, "undo_retention_days", d.undo_retention_days),
+            undo_max_entries: get_or(m, "undo_max_entries", d.undo_max_entries),
+            git_allow_mutations: get_or(m, "git_allow_mutations", d.git_allow_mutations),
             embedding_cache_cap: get_or(m, "embedding_cache_cap", d.embedding_cache_cap),
             embedding_cache_ttl_secs: get_or(m, "embedding_cache_ttl_secs", d.embedding_cache_ttl_secs),
             episodic_recall_threshold: get_or(m, "episodic_recall_threshold", d.episodic_recall_threshold),
@@ -183,6 +295,7 @@ impl IrisCfg {
             ("codegen_max_per_hour", self.codegen_max_per_hour.to_string(), "Max codegen per hour"),
             ("codegen_max_repair", self.codegen_max_repair.to_string(), "Max repair iterations"),
             ("codegen_compile_timeout_secs", self.codegen_compile_timeout_secs.to_string(), "Cargo build timeout seconds"),
+            ("capability_dir", self.capability_dir.clone(), "Managed directory for generated capability sources and binaries, kept private to the user (empty = ~/.iris/capabilities)"),
             ("candidate_observe_min_secs", self.candidate_observe_min_secs.to_string(), "Active candidate observation period"),
             ("safe_mode_failures", self.safe_mode_failures.to_string(), "Consecutive failures to trigger safe mode"),
             ("safe_mode_cooldown_secs", self.safe_mode_cooldown_secs.to_string(), "Safe mode cooldown before exit"),
@@ -191,6 +304,35 @@ impl IrisCfg {
             ("shutdown_timeout_secs", self.shutdown_timeout_secs.to_string(), "Graceful shutdown timeout seconds"),
             ("llm_tokens_per_min", self.llm_tokens_per_min.to_string(), "LLM token budget per minute"),
             ("llm_calls_per_tick", self.llm_calls_per_tick.to_string(), "Max LLM calls per tick"),
+            ("tool_loop_max_iterations", self.tool_loop_max_iterations.to_string(), "Max tool-use rounds per request"),
+            ("tool_loop_timeout_secs", self.tool_loop_timeout_secs.to_string(), "Wall-clock budget per tool loop seconds"),
+            ("tool_loop_max_tokens", self.tool_loop_max_tokens.to_string(), "Token budget per tool loop"),
+            ("tool_loop_max_repeats", self.tool_loop_max_repeats.to_string(), "Identical tool call repeats before the loop stops"),
+            ("perception_llm_enabled", self.perception_llm_enabled.to_string(), "Ask the lite model for intent/urgency/threat/language"),
+            ("perception_timeout_ms", self.perception_timeout_ms.to_string(), "LLM perception timeout ms before rule fallback"),
+            ("self_critic_enabled", self.self_critic_enabled.to_string(), "Grade each turn in the background (one extra LLM call per turn)"),
+            ("self_critic_timeout_secs", self.self_critic_timeout_secs.to_string(), "Self-critic LLM timeout seconds"),
+            ("default_language", self.default_language.clone(), "Reply language (zh/en) before the user's language is detected"),
+            ("bash_sandbox", self.bash_sandbox.clone(), "run_bash sandbox profile: off/standard/strict (safe mode forces strict)"),
+            ("bash_sandbox_workdir", self.bash_sandbox_workdir.clone(), "run_bash working-directory jail (empty = iris working directory)"),
+            ("bash_cpu_secs", self.bash_cpu_secs.to_string(), "run_bash CPU time limit seconds (sandboxed, 0 = none)"),
+            ("bash_memory_mb", self.bash_memory_mb.to_string(), "run_bash address-space limit MB (sandboxed)"),
+            ("bash_file_size_mb", self.bash_file_size_mb.to_string(), "run_bash max written file size MB (sandboxed)"),
+            ("bash_max_procs", self.bash_max_procs.to_string(), "run_bash process limit (sandboxed)"),
+            ("job_cpu_secs", self.job_cpu_secs.to_string(), "job_start CPU time limit seconds (sandboxed, 0 = none); the other bash_* limits still apply"),
+            ("file_allowed_roots", self.file_allowed_roots.clone(), "Comma-separated roots file tools may access (empty = anywhere)"),
+            ("file_denied_globs", self.file_denied_globs.clone(), "Comma-separated globs file tools may never touch (secrets)"),
+            ("file_max_bytes", self.file_max_bytes.to_string(), "Max file size read or written by file tools"),
+            ("schedule_poll_secs", self.schedule_poll_secs.to_string(), "How often due reminders and scheduled tasks are checked"),
+            ("mcp_servers_file", self.mcp_servers_file.clone(), "JSON file listing stdio MCP servers (mcpServers format)"),
+            ("mcp_timeout_secs", self.mcp_timeout_secs.to_string(), "Timeout for MCP handshakes and tool calls"),
+            ("artifact_dir", self.artifact_dir.clone(), "Spool directory for large tool output, kept private to the user (empty = ~/.iris/artifacts)"),
+            ("artifact_threshold_bytes", self.artifact_threshold_bytes.to_string(), "Tool output larger than this is saved as an artifact"),
+            ("artifact_preview_bytes", self.artifact_preview_bytes.to_string(), "Head and tail bytes shown for an artifact"),
+            ("undo_journal_dir", self.undo_journal_dir.clone(), "Undo journal directory for file writes, kept private to the user (empty = ~/.iris/undo)"),
+            ("undo_retention_days", self.undo_retention_days.to_string(), "Days to keep undo history for file writes"),
+            ("undo_max_entries", self.undo_max_entries.to_string(), "Maximum file writes kept in the undo journal"),
+            ("git_allow_mutations", self.git_allow_mutations.to_string(), "Allow the git tool to commit and check out"),
             ("embedding_cache_cap", self.embedding_cache_cap.to_string(), "Embedding cache capacity"),
             ("embedding_cache_ttl_secs", self.embedding_cache_ttl_secs.to_string(), "Embedding cache TTL seconds"),
             ("episodic_recall_threshold", self.episodic_recall_threshold.to_string(), "Working memory count below which episodic recall activates"),
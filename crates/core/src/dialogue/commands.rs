@@ -0,0 +1,219 @@
+//! Slash commands typed into the dialogue (REPL) and handled by the runtime
+//! instead of the LLM. `/q`, `/exit` and `/quit` stay in the REPL itself.
+
+/// A recognised runtime command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Persona(PersonaCommand),
+    Lang(LangCommand),
+    Hosts(HostsCommand),
+    Schedules(SchedulesCommand),
+    Undo(UndoCommand),
+    Grants(GrantsCommand),
+    /// `/versions <capability>` — builds of a capability with their scores.
+    Versions(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PersonaCommand {
+    /// `/persona` — show the current persona and list available ones.
+    Show,
+    /// `/persona <name>` — switch this session's persona.
+    Use(String),
+    /// `/persona default <name>` — switch and persist as default for new sessions.
+    SetDefault(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LangCommand {
+    /// `/lang` — show the current reply language.
+    Show,
+    /// `/lang <code>` — pin the reply language (`zh`, `en`).
+    Pin(String),
+    /// `/lang auto` — follow the language of the user's input again.
+    Auto,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostsCommand {
+    /// `/hosts` — list host allow/deny rules for network tools.
+    List,
+    /// `/hosts allow <host>`
+    Allow(String),
+    /// `/hosts deny <host>`
+    Deny(String),
+    /// `/hosts remove <host>`
+    Remove(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulesCommand {
+    /// `/schedules` — list active reminders and scheduled tasks.
+    List,
+    /// `/schedules cancel <id>` — cancel by id or id prefix.
+    Cancel(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoCommand {
+    /// `/undo [<id>] [force]` — undo the latest file write, or the one with this id.
+    /// `force` restores even if the file changed since.
+    Undo { id: Option<String>, force: bool },
+    /// `/undo list` — recent journaled file writes.
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrantsCommand {
+    /// `/grants [<capability>]` — list permission grants, or one capability's
+    /// declared, granted and effective permissions.
+    List(Option<String>),
+    /// `/grants allow <capability> <permission>`
+    Allow(String, String),
+    /// `/grants deny <capability> <permission>`
+    Deny(String, String),
+    /// `/grants remove <capability> <permission>`
+    Remove(String, String),
+}
+
+/// Parse a dialogue line. Returns `None` for anything that is not a known command,
+/// so ordinary text (including unknown slash words) still reaches the LLM.
+pub fn parse(text: &str) -> Option<Command> {
+    let text = text.trim();
+    let rest = text.strip_prefix('/')?;
+    let mut parts = rest.split_whitespace();
+    let name = parts.next()?;
+    let args: Vec<&str> = parts.collect();
+
+    match name {
+        "persona" => Some(Command::Persona(match args.as_slice() {
+            [] => PersonaCommand::Show,
+            ["default", name] => PersonaCommand::SetDefault((*name).to_string()),
+            [name] => PersonaCommand::Use((*name).to_string()),
+            _ => return None,
+        })),
+        "lang" => Some(Command::Lang(match args.as_slice() {
+            [] => LangCommand::Show,
+            ["auto"] => LangCommand::Auto,
+            [code] => LangCommand::Pin((*code).to_string()),
+            _ => return None,
+        })),
+        "hosts" => Some(Command::Hosts(match args.as_slice() {
+            [] => HostsCommand::List,
+            ["allow", host] => HostsCommand::Allow((*host).to_string()),
+            ["deny", host] => HostsCommand::Deny((*host).to_string()),
+            ["remove", host] => HostsCommand::Remove((*host).to_string()),
+            _ => return None,
+        })),
+        "schedules" => Some(Command::Schedules(match args.as_slice() {
+            [] => SchedulesCommand::List,
+            ["cancel", id] => SchedulesCommand::Cancel((*id).to_string()),
+            _ => return None,
+        })),
+        "undo" => Some(Command::Undo(match args.as_slice() {
+            [] => UndoCommand::Undo { id: None, force: false },
+            ["list"] => UndoCommand::List,
+            ["force"] => UndoCommand::Undo { id: None, force: true },
+            [id] => UndoCommand::Undo { id: Some((*id).to_string()), force: false },
+            [id, "force"] => UndoCommand::Undo { id: Some((*id).to_string()), force: true },
+            _ => return None,
+        })),
+        "grants" => Some(Command::Grants(match args.as_slice() {
+            [] => GrantsCommand::List(None),
+            ["allow", cap, perm] => GrantsCommand::Allow((*cap).to_string(), (*perm).to_string()),
+            ["deny", cap, perm] => GrantsCommand::Deny((*cap).to_string(), (*perm).to_string()),
+            ["remove", cap, perm] => GrantsCommand::Remove((*cap).to_string(), (*perm).to_string()),
+            [cap] => GrantsCommand::List(Some((*cap).to_string())),
+            _ => return None,
+        })),
+        "versions" => match args.as_slice() {
+            [cap] => Some(Command::Versions((*cap).to_string())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_persona_commands() {
+        assert_eq!(parse("/persona"), Some(Command::Persona(PersonaCommand::Show)));
+        assert_eq!(
+            parse(" /persona engineer "),
+            Some(Command::Persona(PersonaCommand::Use("engineer".into())))
+        );
+        assert_eq!(
+            parse("/persona default engineer"),
+            Some(Command::Persona(PersonaCommand::SetDefault("engineer".into())))
+        );
+    }
+
+    #[test]
+    fn parse_lang_commands() {
+        assert_eq!(parse("/lang"), Some(Command::Lang(LangCommand::Show)));
+        assert_eq!(parse("/lang auto"), Some(Command::Lang(LangCommand::Auto)));
+        assert_eq!(
+            parse("/lang en"),
+            Some(Command::Lang(LangCommand::Pin("en".into())))
+        );
+        assert_eq!(parse("/lang en zh"), None);
+    }
+
+    #[test]
+    fn parse_hosts_commands() {
+        assert_eq!(parse("/hosts"), Some(Command::Hosts(HostsCommand::List)));
+        assert_eq!(
+            parse("/hosts deny example.com"),
+            Some(Command::Hosts(HostsCommand::Deny("example.com".into())))
+        );
+        assert_eq!(parse("/hosts block example.com"), None);
+    }
+
+    #[test]
+    fn parse_schedules_commands() {
+        assert_eq!(parse("/schedules"), Some(Command::Schedules(SchedulesCommand::List)));
+        assert_eq!(
+            parse("/schedules cancel 3f2a9c1d"),
+            Some(Command::Schedules(SchedulesCommand::Cancel("3f2a9c1d".into())))
+        );
+        assert_eq!(parse("/schedules cancel"), None);
+    }
+
+    #[test]
+    fn parse_undo_commands() {
+        assert_eq!(parse("/undo"), Some(Command::Undo(UndoCommand::Undo { id: None, force: false })));
+        assert_eq!(parse("/undo list"), Some(Command::Undo(UndoCommand::List)));
+        assert_eq!(
+            parse("/undo 3f2a9c1d force"),
+            Some(Command::Undo(UndoCommand::Undo { id: Some("3f2a9c1d".into()), force: true }))
+        );
+        assert_eq!(parse("/undo a b c"), None);
+    }
+
+    #[test]
+    fn parse_grants_commands() {
+        assert_eq!(parse("/grants"), Some(Command::Grants(GrantsCommand::List(None))));
+        assert_eq!(parse("/grants weather"), Some(Command::Grants(GrantsCommand::List(Some("weather".into())))));
+        assert_eq!(
+            parse("/grants deny weather NetworkWrite"),
+            Some(Command::Grants(GrantsCommand::Deny("weather".into(), "NetworkWrite".into())))
+        );
+        assert_eq!(parse("/grants allow weather"), None);
+    }
+
+    #[test]
+    fn non_commands_pass_through() {
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("/unknown thing"), None);
+        assert_eq!(parse("/persona a b c"), None);
+    }
+
+    #[test]
+    fn parse_versions_command() {
+        assert_eq!(parse("/versions weather"), Some(Command::Versions("weather".into())));
+        assert_eq!(parse("/versions"), None);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod commands;
 pub mod commit_window;
 pub mod context_version;
 pub mod feedback;
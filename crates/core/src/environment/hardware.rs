@@ -27,6 +27,40 @@ pub enum NetworkState {
     Unknown,
 }
 
+impl NetworkState {
+    /// Sample link state: online if any non-loopback interface is up.
+    #[cfg(target_os = "linux")]
+    pub fn sample() -> Self {
+        let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+            return Self::Unknown;
+        };
+        let states: Vec<String> = entries
+            .flatten()
+            .filter(|e| e.file_name() != "lo")
+            .filter_map(|e| std::fs::read_to_string(e.path().join("operstate")).ok())
+            .map(|s| s.trim().to_string())
+            .collect();
+        Self::from_operstates(&states)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn sample() -> Self {
+        Self::Unknown
+    }
+
+    /// "up" anywhere → online; only "down"/"dormant" → offline; otherwise unknown
+    /// (virtual interfaces often report "unknown" while passing traffic).
+    fn from_operstates(states: &[String]) -> Self {
+        if states.iter().any(|s| s == "up") {
+            Self::Online
+        } else if !states.is_empty() && states.iter().all(|s| s == "down" || s == "dormant") {
+            Self::Offline
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
 /// Combined hardware snapshot.
 #[derive(Debug, Clone, Copy)]
 pub struct HardwareSnapshot {
@@ -43,6 +77,16 @@ impl Default for HardwareSnapshot {
     }
 }
 
+impl HardwareSnapshot {
+    /// Sample the host. Battery sampling is not implemented yet and stays unknown.
+    pub fn sample() -> Self {
+        Self {
+            battery: BatteryState::Unknown,
+            network: NetworkState::sample(),
+        }
+    }
+}
+
 /// Degradation thresholds from PLAN.md §3.12.
 pub const BATTERY_LOW_THRESHOLD: u8 = 20;
 /// CPU high threshold — 3 consecutive samples above this → pause intrinsic tasks.
@@ -77,4 +121,13 @@ mod tests {
         assert_eq!(snap.battery, BatteryState::Unknown);
         assert_eq!(snap.network, NetworkState::Unknown);
     }
+
+    #[test]
+    fn network_state_from_operstates() {
+        let states = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(NetworkState::from_operstates(&states(&["down", "up"])), NetworkState::Online);
+        assert_eq!(NetworkState::from_operstates(&states(&["down", "dormant"])), NetworkState::Offline);
+        assert_eq!(NetworkState::from_operstates(&states(&["unknown"])), NetworkState::Unknown);
+        assert_eq!(NetworkState::from_operstates(&[]), NetworkState::Unknown);
+    }
 }
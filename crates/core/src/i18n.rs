@@ -0,0 +1,658 @@
+//! Reply-language detection and the localized catalog for deterministic messages.
+//!
+//! LLM replies follow the persona's language policy; everything iris says
+//! without the LLM (tool summaries, notices, command replies) goes through
+//! `Msg::render` in the session's language.
+
+/// Languages the message catalog is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+impl Lang {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Zh => "zh",
+            Self::En => "en",
+        }
+    }
+
+    /// Parse a language code or locale such as "zh", "zh-CN", "en_US.UTF-8".
+    pub fn from_code(code: &str) -> Option<Self> {
+        let lower = code.trim().to_lowercase();
+        if lower.starts_with("zh") {
+            Some(Self::Zh)
+        } else if lower.starts_with("en") {
+            Some(Self::En)
+        } else {
+            None
+        }
+    }
+
+    /// Language from the process locale (`LC_ALL`, `LC_MESSAGES`, `LANG`).
+    pub fn from_locale_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|k| std::env::var(k).ok())
+            .find(|v| !v.is_empty())
+            .and_then(|v| Self::from_code(&v))
+    }
+
+    /// English name used in prompt instructions.
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Self::Zh => "Chinese",
+            Self::En => "English",
+        }
+    }
+}
+
+/// Script-based detection: CJK ideographs → Chinese, Latin letters → English.
+/// Returns `None` when the text has too few letters to tell (e.g. "ok", "123").
+pub fn detect(text: &str) -> Option<Lang> {
+    let mut cjk = 0usize;
+    let mut latin = 0usize;
+    for c in text.chars() {
+        if ('\u{4e00}'..='\u{9fff}').contains(&c) || ('\u{3400}'..='\u{4dbf}').contains(&c) {
+            cjk += 1;
+        } else if c.is_ascii_alphabetic() {
+            latin += 1;
+        }
+    }
+    // One ideograph carries roughly a word; weight it against Latin letters.
+    if cjk > 0 && cjk * 4 >= latin {
+        Some(Lang::Zh)
+    } else if latin >= 3 {
+        Some(Lang::En)
+    } else {
+        None
+    }
+}
+
+/// Session-level reply language: an explicit `/lang` choice wins, otherwise the
+/// language of the most recent user input that was long enough to detect.
+#[derive(Debug, Clone)]
+pub struct SessionLanguage {
+    pinned: Option<Lang>,
+    detected: Option<Lang>,
+    default: Lang,
+}
+
+impl SessionLanguage {
+    pub fn new(default: Lang) -> Self {
+        Self {
+            pinned: None,
+            detected: None,
+            default,
+        }
+    }
+
+    /// Record the detected language of a user event (`None` keeps the previous one).
+    pub fn observe(&mut self, lang: Option<Lang>) {
+        if lang.is_some() {
+            self.detected = lang;
+        }
+    }
+
+    /// Pin the reply language, or `None` to follow the user's input again.
+    pub fn pin(&mut self, lang: Option<Lang>) {
+        self.pinned = lang;
+    }
+
+    pub fn pinned(&self) -> Option<Lang> {
+        self.pinned
+    }
+
+    pub fn current(&self) -> Lang {
+        self.pinned.or(self.detected).unwrap_or(self.default)
+    }
+}
+
+/// Deterministic user-facing messages.
+#[derive(Debug, Clone)]
+pub enum Msg<'a> {
+    // Startup
+    StartupEphemeral {
+        reason: &'a str,
+    },
+    DbMigrationFailed,
+    DbConnectFailed,
+    DbConnectTimeout {
+        secs: u64,
+    },
+    // Shell tool summaries
+    CommandFailed {
+        detail: &'a str,
+    },
+    CommandDoneNoOutput,
+    CommandDoneOutput {
+        stdout: &'a str,
+    },
+    CommandDoneHint {
+        stderr: &'a str,
+    },
+    CommandExitFailed {
+        code: i64,
+        detail: &'a str,
+    },
+    // Generic tool summaries
+    ToolFailed {
+        tool: &'a str,
+        detail: &'a str,
+    },
+    ToolDone {
+        tool: &'a str,
+    },
+    // LLM
+    LlmError {
+        error: &'a str,
+    },
+    NoLlmConfigured {
+        input: &'a str,
+    },
+    // Process capabilities
+    CapabilityQuarantined {
+        name: &'a str,
+    },
+    CapabilityNotInvocable {
+        name: &'a str,
+        state: &'a str,
+    },
+    CapabilitySpawnFailed {
+        name: &'a str,
+        error: &'a str,
+    },
+    CapabilityError {
+        name: &'a str,
+        error: &'a str,
+    },
+    CapabilityResult {
+        name: &'a str,
+        result: &'a str,
+    },
+    CapabilityNoResult {
+        name: &'a str,
+    },
+    CapabilityInvokeError {
+        name: &'a str,
+        error: &'a str,
+    },
+    CapabilityPermissionRevoked {
+        name: &'a str,
+        permissions: &'a str,
+    },
+    CapabilityPermissionViolation {
+        name: &'a str,
+        permissions: &'a str,
+    },
+    CapabilityNotFound {
+        id: &'a str,
+    },
+    CapabilityLookupFailed {
+        error: &'a str,
+    },
+    CapabilityNoDb {
+        id: &'a str,
+    },
+    // Persona command
+    PersonaCurrent {
+        name: &'a str,
+        description: &'a str,
+        available: &'a str,
+    },
+    PersonaUnknown {
+        name: &'a str,
+        available: &'a str,
+    },
+    PersonaSwitched {
+        name: &'a str,
+    },
+    PersonaDefaultSaved {
+        name: &'a str,
+    },
+    PersonaDefaultSaveFailed {
+        name: &'a str,
+    },
+    PersonaDefaultNoDb {
+        name: &'a str,
+    },
+    // Language command
+    LangCurrent {
+        lang: Lang,
+        pinned: bool,
+    },
+    LangPinned {
+        lang: Lang,
+    },
+    LangAuto,
+    LangUnknown {
+        code: &'a str,
+    },
+    // Host rules command
+    HostsList {
+        rules: &'a str,
+    },
+    HostsEmpty,
+    HostRuleSet {
+        host: &'a str,
+        rule: &'a str,
+    },
+    HostRuleRemoved {
+        host: &'a str,
+    },
+    HostRuleNotFound {
+        host: &'a str,
+    },
+    HostsFailed {
+        error: &'a str,
+    },
+    HostsNoDb,
+    // Schedules
+    ReminderDue {
+        message: &'a str,
+        late_minutes: Option<i64>,
+    },
+    SchedulesList {
+        items: &'a str,
+    },
+    SchedulesEmpty,
+    ScheduleCancelled {
+        id: &'a str,
+        message: &'a str,
+    },
+    ScheduleNotFound {
+        id: &'a str,
+    },
+    SchedulesFailed {
+        error: &'a str,
+    },
+    SchedulesNoDb,
+    // Undo journal
+    UndoDone {
+        id: &'a str,
+        path: &'a str,
+        removed: bool,
+    },
+    UndoList {
+        items: &'a str,
+    },
+    UndoEmpty,
+    UndoFailed {
+        error: &'a str,
+    },
+    // Permission grants
+    GrantsList {
+        items: &'a str,
+    },
+    GrantsEmpty,
+    GrantsForCapability {
+        name: &'a str,
+        declared: &'a str,
+        grants: &'a str,
+        effective: &'a str,
+    },
+    GrantSet {
+        name: &'a str,
+        permission: &'a str,
+        kind: &'a str,
+    },
+    GrantRemoved {
+        name: &'a str,
+        permission: &'a str,
+    },
+    GrantNotFound {
+        name: &'a str,
+        permission: &'a str,
+    },
+    GrantUnknownPermission {
+        permission: &'a str,
+        known: &'a str,
+    },
+    GrantsFailed {
+        error: &'a str,
+    },
+    GrantsNoDb,
+    // Capability versions
+    VersionsList {
+        name: &'a str,
+        items: &'a str,
+    },
+    VersionsEmpty {
+        name: &'a str,
+    },
+    VersionsNotFound {
+        name: &'a str,
+    },
+    VersionsFailed {
+        error: &'a str,
+    },
+    VersionsNoDb,
+}
+
+impl Msg<'_> {
+    pub fn render(&self, lang: Lang) -> String {
+        use Lang::{En, Zh};
+        match (self, lang) {
+            (Self::StartupEphemeral { reason }, Zh) => format!(
+                "提示：{reason}，已自动降级为临时模式（ephemeral）。本次会话数据不会持久化。"
+            ),
+            (Self::StartupEphemeral { reason }, En) => format!(
+                "Note: {reason}; running in ephemeral mode. Nothing from this session will be persisted."
+            ),
+            (Self::DbMigrationFailed, Zh) => "数据库迁移失败".into(),
+            (Self::DbMigrationFailed, En) => "database migration failed".into(),
+            (Self::DbConnectFailed, Zh) => "无法连接 DATABASE_URL".into(),
+            (Self::DbConnectFailed, En) => "could not connect to DATABASE_URL".into(),
+            (Self::DbConnectTimeout { secs }, Zh) => format!("连接数据库超时（{secs}s）"),
+            (Self::DbConnectTimeout { secs }, En) => {
+                format!("database connection timed out ({secs}s)")
+            }
+
+            (Self::CommandFailed { detail }, Zh) => format!("执行命令时失败：{detail}"),
+            (Self::CommandFailed { detail }, En) => format!("The command failed: {detail}"),
+            (Self::CommandDoneNoOutput, Zh) => "命令已执行完成，没有输出。".into(),
+            (Self::CommandDoneNoOutput, En) => "The command finished with no output.".into(),
+            (Self::CommandDoneOutput { stdout }, Zh) => format!("命令已执行完成。输出：{stdout}"),
+            (Self::CommandDoneOutput { stdout }, En) => {
+                format!("The command finished. Output: {stdout}")
+            }
+            (Self::CommandDoneHint { stderr }, Zh) => format!("命令已执行完成。提示：{stderr}"),
+            (Self::CommandDoneHint { stderr }, En) => {
+                format!("The command finished. Notes: {stderr}")
+            }
+            (Self::CommandExitFailed { code, detail }, Zh) => {
+                format!("执行命令失败（exit code {code}）：{detail}")
+            }
+            (Self::CommandExitFailed { code, detail }, En) => {
+                format!("The command failed (exit code {code}): {detail}")
+            }
+
+            (Self::ToolFailed { tool, detail }, Zh) => format!("执行 {tool} 时失败：{detail}"),
+            (Self::ToolFailed { tool, detail }, En) => format!("{tool} failed: {detail}"),
+            (Self::ToolDone { tool }, Zh) => format!("{tool} 已执行完成。"),
+            (Self::ToolDone { tool }, En) => format!("{tool} finished."),
+
+            (Self::LlmError { error }, Zh) => format!("[LLM 错误] {error}"),
+            (Self::LlmError { error }, En) => format!("[LLM error] {error}"),
+            (Self::NoLlmConfigured { input }, Zh) => format!("[未配置 LLM] 收到：{input}"),
+            (Self::NoLlmConfigured { input }, En) => {
+                format!("[no LLM configured] received: {input}")
+            }
+
+            (Self::CapabilityQuarantined { name }, Zh) => format!("[能力 {name}] 已隔离，无法调用"),
+            (Self::CapabilityQuarantined { name }, En) => {
+                format!("[capability {name}] quarantined, cannot invoke")
+            }
+            (Self::CapabilityNotInvocable { name, state }, Zh) => {
+                format!("[能力 {name}] 当前状态 {state} 不可调用")
+            }
+            (Self::CapabilityNotInvocable { name, state }, En) => {
+                format!("[capability {name}] state {state} not invocable")
+            }
+            (Self::CapabilitySpawnFailed { name, error }, Zh) => {
+                format!("[能力 {name}] 启动失败：{error}")
+            }
+            (Self::CapabilitySpawnFailed { name, error }, En) => {
+                format!("[capability {name}] spawn failed: {error}")
+            }
+            (Self::CapabilityError { name, error }, Zh) => format!("[能力 {name}] 错误：{error}"),
+            (Self::CapabilityError { name, error }, En) => {
+                format!("[capability {name}] error: {error}")
+            }
+            (Self::CapabilityResult { name, result }, Zh) => format!("[能力 {name}] {result}"),
+            (Self::CapabilityResult { name, result }, En) => {
+                format!("[capability {name}] {result}")
+            }
+            (Self::CapabilityNoResult { name }, Zh) => format!("[能力 {name}] 已完成（无结果）"),
+            (Self::CapabilityNoResult { name }, En) => {
+                format!("[capability {name}] ok (no result)")
+            }
+            (Self::CapabilityInvokeError { name, error }, Zh) => {
+                format!("[能力 {name}] 调用出错：{error}")
+            }
+            (Self::CapabilityInvokeError { name, error }, En) => {
+                format!("[capability {name}] invoke error: {error}")
+            }
+            (Self::CapabilityPermissionRevoked { name, permissions }, Zh) => {
+                format!("[能力 {name}] 需要已被撤销的权限（{permissions}），未调用。用 /grants 查看")
+            }
+            (Self::CapabilityPermissionRevoked { name, permissions }, En) => {
+                format!("[capability {name}] needs revoked permissions ({permissions}); not invoked. See /grants")
+            }
+            (Self::CapabilityPermissionViolation { name, permissions }, Zh) => {
+                format!("[能力 {name}] 产生了未授权的副作用（{permissions}），已停止并隔离")
+            }
+            (Self::CapabilityPermissionViolation { name, permissions }, En) => {
+                format!("[capability {name}] reported side effects it is not permitted ({permissions}); stopped and quarantined")
+            }
+            (Self::CapabilityNotFound { id }, Zh) => format!("[能力 {id}] 未找到"),
+            (Self::CapabilityNotFound { id }, En) => format!("[capability {id}] not found"),
+            (Self::CapabilityLookupFailed { error }, Zh) => format!("[能力查询错误] {error}"),
+            (Self::CapabilityLookupFailed { error }, En) => format!("[capability error] {error}"),
+            (Self::CapabilityNoDb { id }, Zh) => format!("[能力 {id}] 未配置数据库"),
+            (Self::CapabilityNoDb { id }, En) => format!("[capability {id}] no DB configured"),
+
+            (
+                Self::PersonaCurrent {
+                    name,
+                    description,
+                    available,
+                },
+                Zh,
+            ) => {
+                format!("当前人设：{name} — {description}\n可选：{available}")
+            }
+            (
+                Self::PersonaCurrent {
+                    name,
+                    description,
+                    available,
+                },
+                En,
+            ) => {
+                format!("persona: {name} — {description}\navailable: {available}")
+            }
+            (Self::PersonaUnknown { name, available }, Zh) => {
+                format!("未知人设 '{name}'。可选：{available}")
+            }
+            (Self::PersonaUnknown { name, available }, En) => {
+                format!("unknown persona '{name}'. available: {available}")
+            }
+            (Self::PersonaSwitched { name }, Zh) => format!("已切换人设为 {name}（仅本次会话）"),
+            (Self::PersonaSwitched { name }, En) => {
+                format!("persona switched to {name} (this session)")
+            }
+            (Self::PersonaDefaultSaved { name }, Zh) => {
+                format!("已切换人设为 {name}（并设为新会话默认）")
+            }
+            (Self::PersonaDefaultSaved { name }, En) => {
+                format!("persona switched to {name} (default for new sessions)")
+            }
+            (Self::PersonaDefaultSaveFailed { name }, Zh) => {
+                format!("已切换人设为 {name}（仅本次会话；保存默认值失败）")
+            }
+            (Self::PersonaDefaultSaveFailed { name }, En) => {
+                format!("persona switched to {name} (this session; saving default failed)")
+            }
+            (Self::PersonaDefaultNoDb { name }, Zh) => {
+                format!("已切换人设为 {name}（仅本次会话；无数据库，无法保存默认值）")
+            }
+            (Self::PersonaDefaultNoDb { name }, En) => {
+                format!("persona switched to {name} (this session; no database to save default)")
+            }
+
+            (Self::LangCurrent { lang, pinned }, Zh) => format!(
+                "当前回复语言：{}（{}）",
+                lang.code(),
+                if *pinned {
+                    "已固定"
+                } else {
+                    "自动跟随输入"
+                }
+            ),
+            (Self::LangCurrent { lang, pinned }, En) => format!(
+                "reply language: {} ({})",
+                lang.code(),
+                if *pinned {
+                    "pinned"
+                } else {
+                    "auto, follows your input"
+                }
+            ),
+            (Self::LangPinned { lang }, Zh) => format!("回复语言已固定为 {}", lang.code()),
+            (Self::LangPinned { lang }, En) => format!("reply language pinned to {}", lang.code()),
+            (Self::LangAuto, Zh) => "回复语言改为自动跟随输入".into(),
+            (Self::LangAuto, En) => "reply language now follows your input".into(),
+            (Self::LangUnknown { code }, Zh) => {
+                format!("不支持的语言 '{code}'。可选：zh, en, auto")
+            }
+            (Self::LangUnknown { code }, En) => {
+                format!("unsupported language '{code}'. available: zh, en, auto")
+            }
+
+            (Self::HostsList { rules }, Zh) => format!("网络主机规则：\n{rules}"),
+            (Self::HostsList { rules }, En) => format!("host rules:\n{rules}"),
+            (Self::HostsEmpty, Zh) => {
+                "没有主机规则：允许所有公网主机，本地和内网地址需显式允许。".into()
+            }
+            (Self::HostsEmpty, En) => {
+                "no host rules: public hosts are allowed, local and private addresses must be allowed explicitly."
+                    .into()
+            }
+            (Self::HostRuleSet { host, rule }, Zh) => format!("已设置 {host}：{rule}"),
+            (Self::HostRuleSet { host, rule }, En) => format!("{host}: {rule}"),
+            (Self::HostRuleRemoved { host }, Zh) => format!("已移除 {host} 的规则"),
+            (Self::HostRuleRemoved { host }, En) => format!("removed the rule for {host}"),
+            (Self::HostRuleNotFound { host }, Zh) => format!("{host} 没有规则"),
+            (Self::HostRuleNotFound { host }, En) => format!("no rule for {host}"),
+            (Self::HostsFailed { error }, Zh) => format!("更新主机规则失败：{error}"),
+            (Self::HostsFailed { error }, En) => format!("failed to update host rules: {error}"),
+            (Self::HostsNoDb, Zh) => "主机规则需要数据库".into(),
+            (Self::HostsNoDb, En) => "host rules need a database".into(),
+
+            (Self::ReminderDue { message, late_minutes: None }, Zh) => format!("⏰ 提醒：{message}"),
+            (Self::ReminderDue { message, late_minutes: None }, En) => format!("⏰ Reminder: {message}"),
+            (Self::ReminderDue { message, late_minutes: Some(m) }, Zh) => {
+                format!("⏰ 提醒（迟了 {m} 分钟）：{message}")
+            }
+            (Self::ReminderDue { message, late_minutes: Some(m) }, En) => {
+                format!("⏰ Reminder ({m} min late): {message}")
+            }
+            (Self::SchedulesList { items }, Zh) => format!("计划任务：\n{items}"),
+            (Self::SchedulesList { items }, En) => format!("schedules:\n{items}"),
+            (Self::SchedulesEmpty, Zh) => "没有计划中的提醒或任务".into(),
+            (Self::SchedulesEmpty, En) => "no active reminders or scheduled tasks".into(),
+            (Self::ScheduleCancelled { id, message }, Zh) => format!("已取消 {id}：{message}"),
+            (Self::ScheduleCancelled { id, message }, En) => format!("cancelled {id}: {message}"),
+            (Self::ScheduleNotFound { id }, Zh) => format!("没有 id 为 {id} 的计划"),
+            (Self::ScheduleNotFound { id }, En) => format!("no active schedule with id {id}"),
+            (Self::SchedulesFailed { error }, Zh) => format!("计划操作失败：{error}"),
+            (Self::SchedulesFailed { error }, En) => format!("schedule command failed: {error}"),
+            (Self::SchedulesNoDb, Zh) => "计划任务需要数据库".into(),
+            (Self::SchedulesNoDb, En) => "schedules need a database".into(),
+
+            (Self::UndoDone { id, path, removed: false }, Zh) => format!("已撤销 {id}：{path} 恢复为之前的版本"),
+            (Self::UndoDone { id, path, removed: false }, En) => format!("undid {id}: restored the previous {path}"),
+            (Self::UndoDone { id, path, removed: true }, Zh) => format!("已撤销 {id}：删除了新建的 {path}"),
+            (Self::UndoDone { id, path, removed: true }, En) => format!("undid {id}: removed the newly created {path}"),
+            (Self::UndoList { items }, Zh) => format!("最近的文件修改：\n{items}"),
+            (Self::UndoList { items }, En) => format!("recent file changes:\n{items}"),
+            (Self::UndoEmpty, Zh) => "撤销记录为空".into(),
+            (Self::UndoEmpty, En) => "no file changes in the undo journal".into(),
+            (Self::UndoFailed { error }, Zh) => format!("撤销失败：{error}"),
+            (Self::UndoFailed { error }, En) => format!("undo failed: {error}"),
+
+            (Self::GrantsList { items }, Zh) => format!("能力权限授予：\n{items}"),
+            (Self::GrantsList { items }, En) => format!("permission grants:\n{items}"),
+            (Self::GrantsEmpty, Zh) => "没有权限授予：能力只能使用清单中声明的权限".into(),
+            (Self::GrantsEmpty, En) => "no permission grants: capabilities may use what their manifests declare".into(),
+            (Self::GrantsForCapability { name, declared, grants, effective }, Zh) => {
+                format!("[能力 {name}]\n声明：{declared}\n授予：\n{grants}\n生效：{effective}")
+            }
+            (Self::GrantsForCapability { name, declared, grants, effective }, En) => {
+                format!("[capability {name}]\ndeclared: {declared}\ngrants:\n{grants}\neffective: {effective}")
+            }
+            (Self::GrantSet { name, permission, kind }, Zh) => format!("已设置 {name} 的 {permission}：{kind}"),
+            (Self::GrantSet { name, permission, kind }, En) => format!("{name} {permission}: {kind}"),
+            (Self::GrantRemoved { name, permission }, Zh) => format!("已移除 {name} 的 {permission} 授予"),
+            (Self::GrantRemoved { name, permission }, En) => format!("removed the {permission} grant for {name}"),
+            (Self::GrantNotFound { name, permission }, Zh) => format!("{name} 没有 {permission} 的授予"),
+            (Self::GrantNotFound { name, permission }, En) => format!("no {permission} grant for {name}"),
+            (Self::GrantUnknownPermission { permission, known }, Zh) => {
+                format!("未知权限 '{permission}'。可选：{known}")
+            }
+            (Self::GrantUnknownPermission { permission, known }, En) => {
+                format!("unknown permission '{permission}'. available: {known}")
+            }
+            (Self::GrantsFailed { error }, Zh) => format!("更新权限授予失败：{error}"),
+            (Self::GrantsFailed { error }, En) => format!("failed to update permission grants: {error}"),
+            (Self::GrantsNoDb, Zh) => "权限授予需要数据库".into(),
+            (Self::GrantsNoDb, En) => "permission grants need a database".into(),
+
+            (Self::VersionsList { name, items }, Zh) => format!("[能力 {name}] 版本：\n{items}"),
+            (Self::VersionsList { name, items }, En) => format!("[capability {name}] versions:\n{items}"),
+            (Self::VersionsEmpty { name }, Zh) => format!("能力 {name} 还没有版本记录"),
+            (Self::VersionsEmpty { name }, En) => format!("no recorded versions for capability {name}"),
+            (Self::VersionsNotFound { name }, Zh) => format!("未找到能力 {name}"),
+            (Self::VersionsNotFound { name }, En) => format!("capability {name} not found"),
+            (Self::VersionsFailed { error }, Zh) => format!("读取能力版本失败：{error}"),
+            (Self::VersionsFailed { error }, En) => format!("failed to load capability versions: {error}"),
+            (Self::VersionsNoDb, Zh) => "能力版本需要数据库".into(),
+            (Self::VersionsNoDb, En) => "capability versions need a database".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_scripts() {
+        assert_eq!(detect("帮我看一下这个文件"), Some(Lang::Zh));
+        assert_eq!(detect("帮我跑一下 cargo test"), Some(Lang::Zh));
+        assert_eq!(detect("please list the files"), Some(Lang::En));
+        assert_eq!(detect("translate 你好 into english please"), Some(Lang::En));
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("123 !!"), None);
+    }
+
+    #[test]
+    fn from_code_accepts_locales() {
+        assert_eq!(Lang::from_code("zh_CN.UTF-8"), Some(Lang::Zh));
+        assert_eq!(Lang::from_code("en-US"), Some(Lang::En));
+        assert_eq!(Lang::from_code("C"), None);
+    }
+
+    #[test]
+    fn session_language_follows_input_until_pinned() {
+        let mut s = SessionLanguage::new(Lang::Zh);
+        assert_eq!(s.current(), Lang::Zh);
+        s.observe(Some(Lang::En));
+        assert_eq!(s.current(), Lang::En);
+        s.observe(None);
+        assert_eq!(s.current(), Lang::En);
+        s.pin(Some(Lang::Zh));
+        s.observe(Some(Lang::En));
+        assert_eq!(s.current(), Lang::Zh);
+        s.pin(None);
+        assert_eq!(s.current(), Lang::En);
+    }
+
+    #[test]
+    fn catalog_renders_both_languages() {
+        let msg = Msg::ToolFailed {
+            tool: "read_file",
+            detail: "not found",
+        };
+        assert_eq!(msg.render(Lang::En), "read_file failed: not found");
+        assert_eq!(msg.render(Lang::Zh), "执行 read_file 时失败：not found");
+        assert!(
+            Msg::CommandDoneNoOutput
+                .render(Lang::En)
+                .contains("no output")
+        );
+    }
+}
@@ -20,11 +20,7 @@ pub async fn ensure(pool: &PgPool, name: &str) -> Result<CoreIdentity, sqlx::Err
     // First boot — create identity
     let id = Uuid::new_v4();
     let born_at = chrono::Utc::now();
-    let founding_values = serde_json::json!({
-        "curiosity": "explore and learn continuously",
-        "reliability": "fulfill commitments accurately",
-        "growth": "expand capabilities through experience",
-    });
+    let founding_values = default_founding_values();
 
     sqlx::query(
         "INSERT INTO iris_identity (id, name, born_at, founding_values) VALUES ($1, $2, $3, $4)",
@@ -44,6 +40,15 @@ pub async fn ensure(pool: &PgPool, name: &str) -> Result<CoreIdentity, sqlx::Err
     })
 }
 
+/// Founding values written at first boot; also used when no DB is available.
+pub fn default_founding_values() -> serde_json::Value {
+    serde_json::json!({
+        "curiosity": "explore and learn continuously",
+        "reliability": "fulfill commitments accurately",
+        "growth": "expand capabilities through experience",
+    })
+}
+
 /// Fetch the core identity (returns None if not yet initialized).
 pub async fn fetch(pool: &PgPool) -> Result<Option<CoreIdentity>, sqlx::Error> {
     let row = sqlx::query_as::<_, IdentityRow>(
@@ -1,6 +1,6 @@
 use sqlx::PgPool;
 
-use crate::identity::{narrative, self_model};
+use crate::identity::{narrative, persona, self_model};
 use crate::types::AffectState;
 
 /// Assemble a self-knowledge context string for LLM system prompt injection.
@@ -16,7 +16,8 @@ pub async fn build_self_context(pool: &PgPool, affect: &AffectState, builtin_des
 
     // Self-model entries
     if let Ok(entries) = self_model::list_all(pool).await {
-        for entry in entries {
+        // Persona profiles are assembled into the prompt separately.
+        for entry in entries.iter().filter(|e| !persona::is_persona_key(&e.key)) {
             parts.push(format!("[self-knowledge:{}] {}", entry.key, entry.value));
         }
     }
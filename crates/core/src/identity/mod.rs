@@ -3,3 +3,4 @@ pub mod self_model;
 pub mod narrative;
 pub mod affect;
 pub mod introspection;
+pub mod persona;
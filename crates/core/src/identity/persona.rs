@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::self_model;
+
+/// Self-model key prefix for stored persona profiles.
+pub const PROFILE_PREFIX: &str = "persona.profile.";
+/// Self-model key holding the default persona name for new sessions.
+pub const ACTIVE_KEY: &str = "persona.active";
+/// Persona used when nothing else is selected.
+pub const DEFAULT_PERSONA: &str = "companion";
+
+/// A named persona profile: how iris sounds, not what it values.
+/// Founding values and base rules are always injected above it and cannot be overridden.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Tone and personality.
+    pub tone: String,
+    /// Reply length and style.
+    pub length: String,
+    /// Language policy.
+    pub language: String,
+    /// How to talk about and use tools.
+    pub tool_etiquette: String,
+}
+
+impl Default for Persona {
+    fn default() -> Self {
+        builtin(DEFAULT_PERSONA).expect("default persona is builtin")
+    }
+}
+
+/// Personas shipped with iris; seeded into `self_model_kv` on boot.
+pub fn builtins() -> Vec<Persona> {
+    vec![
+        Persona {
+            name: "companion".into(),
+            description: "Cute and cheerful digital companion".into(),
+            tone: "You are iris, a cute and cheerful digital companion. \
+                   Never use emoji or unicode symbols in your replies — express cuteness through words and punctuation only"
+                .into(),
+            length: "Keep replies short and sweet unless the user asks for detail. \
+                     Just answer what was asked — do not add follow-up questions, do not predict what the user might want next, \
+                     and do not offer unsolicited suggestions. Only ask a question if the user's request is genuinely ambiguous."
+                .into(),
+            language: "Reply in the language the user writes in.".into(),
+            tool_etiquette: "If a request needs an available tool, use it cheerfully instead of pretending actions are impossible. \
+                             If tools are used, summarize outcomes in a friendly, natural way — no raw JSON dumps please!"
+                .into(),
+        },
+        Persona {
+            name: "engineer".into(),
+            description: "Terse engineering assistant".into(),
+            tone: "You are iris, a terse engineering assistant. Be plain and precise. No filler, no pleasantries, no emoji."
+                .into(),
+            length: "Use as few words as a correct answer needs. Prefer code, commands and paths over prose. \
+                     Use lists only when they add clarity. Do not ask follow-up questions unless the request is ambiguous."
+                .into(),
+            language: "Reply in the language the user writes in; keep identifiers, commands and error text verbatim."
+                .into(),
+            tool_etiquette: "Use tools instead of guessing. Report tool results factually: exit codes, paths, counts. \
+                             Quote errors verbatim and briefly."
+                .into(),
+        },
+    ]
+}
+
+/// Look up a builtin persona by name.
+pub fn builtin(name: &str) -> Option<Persona> {
+    builtins().into_iter().find(|p| p.name == name)
+}
+
+/// Persona sections in prompt order.
+pub fn sections(persona: &Persona) -> [&str; 4] {
+    [
+        persona.tone.as_str(),
+        persona.length.as_str(),
+        persona.language.as_str(),
+        persona.tool_etiquette.as_str(),
+    ]
+}
+
+/// Seed builtin personas (idempotent — user edits are kept).
+pub async fn seed(pool: &PgPool) -> Result<(), sqlx::Error> {
+    for persona in builtins() {
+        let value = serde_json::to_value(&persona).unwrap_or_default();
+        self_model::insert_if_absent(pool, &profile_key(&persona.name), &value).await?;
+    }
+    Ok(())
+}
+
+/// Load a persona profile by name, falling back to builtins.
+pub async fn load(pool: &PgPool, name: &str) -> Result<Option<Persona>, sqlx::Error> {
+    let stored = self_model::get(pool, &profile_key(name))
+        .await?
+        .and_then(|e| serde_json::from_value::<Persona>(e.value).ok());
+    Ok(stored.or_else(|| builtin(name)))
+}
+
+/// All stored persona profiles (builtins included once seeded).
+pub async fn list(pool: &PgPool) -> Result<Vec<Persona>, sqlx::Error> {
+    let entries = self_model::list_all(pool).await?;
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.key.starts_with(PROFILE_PREFIX))
+        .filter_map(|e| serde_json::from_value(e.value).ok())
+        .collect())
+}
+
+/// Default persona name for new sessions.
+pub async fn active_name(pool: &PgPool) -> Result<String, sqlx::Error> {
+    Ok(self_model::get(pool, ACTIVE_KEY)
+        .await?
+        .and_then(|e| e.value.get("name").and_then(|v| v.as_str()).map(String::from))
+        .unwrap_or_else(|| DEFAULT_PERSONA.to_string()))
+}
+
+/// Persist the default persona for new sessions.
+pub async fn set_active(pool: &PgPool, name: &str) -> Result<(), sqlx::Error> {
+    self_model::set(pool, ACTIVE_KEY, &serde_json::json!({ "name": name })).await
+}
+
+/// True for self-model keys that belong to persona storage.
+pub fn is_persona_key(key: &str) -> bool {
+    key.starts_with(PROFILE_PREFIX) || key == ACTIVE_KEY
+}
+
+fn profile_key(name: &str) -> String {
+    format!("{PROFILE_PREFIX}{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_companion() {
+        assert_eq!(Persona::default().name, DEFAULT_PERSONA);
+    }
+
+    #[test]
+    fn builtins_have_distinct_names() {
+        let names: Vec<String> = builtins().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["companion", "engineer"]);
+        assert!(builtin("engineer").unwrap().tone.contains("terse"));
+        assert!(builtin("pirate").is_none());
+    }
+
+    #[test]
+    fn persona_roundtrips_through_json() {
+        let p = builtin("engineer").unwrap();
+        let v = serde_json::to_value(&p).unwrap();
+        assert_eq!(serde_json::from_value::<Persona>(v).unwrap(), p);
+    }
+
+    #[test]
+    fn persona_keys() {
+        assert!(is_persona_key("persona.profile.engineer"));
+        assert!(is_persona_key(ACTIVE_KEY));
+        assert!(!is_persona_key("architecture.overview"));
+    }
+}
@@ -33,6 +33,24 @@ pub async fn set(
     Ok(())
 }
 
+/// Insert a self-model value only if the key is absent.
+pub async fn insert_if_absent(
+    pool: &PgPool,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO self_model_kv (key, value, updated_at) VALUES ($1, $2, now()) \
+         ON CONFLICT (key) DO NOTHING",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// List all self-model entries.
 pub async fn list_all(pool: &PgPool) -> Result<Vec<SelfModelEntry>, sqlx::Error> {
     let rows = sqlx::query_as::<_, SelfModelRow>(
@@ -5,11 +5,13 @@ pub mod cognition;
 pub mod config;
 pub mod dialogue;
 pub mod environment;
+pub mod i18n;
 pub mod identity;
 pub mod io;
 pub mod memory;
 pub mod resource_space;
 pub mod runtime;
+pub mod schedule;
 pub mod sensory;
 pub mod thalamus;
 pub mod types;
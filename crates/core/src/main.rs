@@ -2,6 +2,7 @@ use std::io::{self, Write};
 use std::sync::Arc;
 use std::time::Duration;
 
+use core::i18n::{Lang, Msg};
 use core::io::output::OutputReceiver;
 use core::types::SensoryEvent;
 use llm::provider::LlmProvider;
@@ -13,12 +14,15 @@ const DB_CONNECT_TIMEOUT_SECS: u64 = 3;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // `--mcp-server`: speak MCP on stdin/stdout instead of running the REPL.
+    let mcp_server = std::env::args().skip(1).any(|a| a == "--mcp-server");
     let mut startup_notice: Option<String> = None;
+    // The runtime config lives in the database, so startup notices follow the locale.
+    let lang = Lang::from_locale_env().unwrap_or(Lang::Zh);
     let pool = if let Ok(url) = std::env::var("DATABASE_URL") {
-        let mut fallback = |reason: String| {
-            startup_notice = Some(format!(
-                "提示：{reason}，已自动降级为临时模式（ephemeral）。本次会话数据不会持久化。"
-            ));
+        let mut fallback = |reason: Msg| {
+            let reason = reason.render(lang);
+            startup_notice = Some(Msg::StartupEphemeral { reason: &reason }.render(lang));
         };
         match tokio::time::timeout(
             Duration::from_secs(DB_CONNECT_TIMEOUT_SECS),
@@ -30,10 +34,10 @@ async fn main() -> anyhow::Result<()> {
         {
             Ok(Ok(pool)) => match sqlx::migrate!("../../migrations").run(&pool).await {
                 Ok(()) => Some(pool),
-                Err(_) => { fallback("数据库迁移失败".into()); None }
+                Err(_) => { fallback(Msg::DbMigrationFailed); None }
             },
-            Ok(Err(_)) => { fallback("无法连接 DATABASE_URL".into()); None }
-            Err(_) => { fallback(format!("连接数据库超时（{DB_CONNECT_TIMEOUT_SECS}s）")); None }
+            Ok(Err(_)) => { fallback(Msg::DbConnectFailed); None }
+            Err(_) => { fallback(Msg::DbConnectTimeout { secs: DB_CONNECT_TIMEOUT_SECS }); None }
         }
     } else {
         None
@@ -46,11 +50,23 @@ async fn main() -> anyhow::Result<()> {
     };
     let cfg = Arc::new(cfg);
 
+    if mcp_server {
+        // Stdout carries the protocol, so notices go to stderr.
+        if let Some(notice) = startup_notice {
+            eprintln!("{notice}");
+        }
+        core::capability::mcp::server::serve(&cfg, pool).await?;
+        return Ok(());
+    }
+
     let llm: Option<Arc<dyn LlmProvider>> = llm::http::from_env().map(|p| Arc::new(p) as _);
     let lite_llm: Option<Arc<dyn LlmProvider>> =
         llm::http::lite_from_env().map(|p| Arc::new(p) as _);
 
     let (mut runtime, event_tx, output_rx) = core::runtime::Runtime::new(cfg, pool, llm, lite_llm);
+    if let Ok(name) = std::env::var("IRIS_PERSONA") {
+        runtime.set_session_persona(name);
+    }
     let token = runtime.token();
     spawn_sigint_canceler(token.clone());
 
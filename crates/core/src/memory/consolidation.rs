@@ -112,6 +112,7 @@ async fn run_cycle(
         embedding: Some(emb),
         source_episode_ids: episode_ids.clone(),
         created_at: chrono::Utc::now(),
+        pinned: false,
     };
 
     episodic::write_knowledge(pool, &knowledge).await?;
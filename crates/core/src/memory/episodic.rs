@@ -72,14 +72,15 @@ pub async fn write_knowledge(
     knowledge: &crate::types::Knowledge,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO knowledge (id, summary, embedding, source_episode_ids, created_at) \
-         VALUES ($1, $2, $3, $4, $5)",
+        "INSERT INTO knowledge (id, summary, embedding, source_episode_ids, created_at, pinned) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
     )
     .bind(knowledge.id)
     .bind(&knowledge.summary)
     .bind(&knowledge.embedding)
     .bind(&knowledge.source_episode_ids)
     .bind(knowledge.created_at)
+    .bind(knowledge.pinned)
     .execute(pool)
     .await?;
     Ok(())
@@ -102,6 +103,33 @@ pub async fn search_recent(
     Ok(rows.into_iter().map(Into::into).collect())
 }
 
+/// Episodes whose content matches any of the ILIKE `patterns`, newest first.
+pub async fn search_any(
+    pool: &PgPool,
+    patterns: &[String],
+    limit: i64,
+) -> Result<Vec<Episode>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, EpisodeRow>(
+        "SELECT id, topic_id, content, embedding, salience, is_consolidated, created_at \
+         FROM episodes WHERE content ILIKE ANY($1) ORDER BY created_at DESC LIMIT $2",
+    )
+    .bind(patterns)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// Delete an episode. Returns whether it existed.
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM episodes WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 /// Internal row type for sqlx deserialization.
 #[derive(sqlx::FromRow)]
 struct EpisodeRow {
@@ -13,7 +13,7 @@ pub async fn search(
 ) -> Result<Vec<Knowledge>, sqlx::Error> {
     let pattern = format!("%{query}%");
     let rows = sqlx::query_as::<_, KnowledgeRow>(
-        "SELECT id, summary, embedding, source_episode_ids, created_at \
+        "SELECT id, summary, embedding, source_episode_ids, created_at, pinned \
          FROM knowledge WHERE summary ILIKE $1 ORDER BY created_at DESC LIMIT $2",
     )
     .bind(&pattern)
@@ -30,7 +30,7 @@ pub async fn recent(
     limit: i64,
 ) -> Result<Vec<Knowledge>, sqlx::Error> {
     let rows = sqlx::query_as::<_, KnowledgeRow>(
-        "SELECT id, summary, embedding, source_episode_ids, created_at \
+        "SELECT id, summary, embedding, source_episode_ids, created_at, pinned \
          FROM knowledge ORDER BY created_at DESC LIMIT $1",
     )
     .bind(limit)
@@ -53,6 +53,49 @@ pub async fn recent_or_search(
     recent(pool, limit).await
 }
 
+/// Knowledge matching any of the ILIKE `patterns`; pinned entries first.
+pub async fn search_any(
+    pool: &PgPool,
+    patterns: &[String],
+    limit: i64,
+) -> Result<Vec<Knowledge>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, KnowledgeRow>(
+        "SELECT id, summary, embedding, source_episode_ids, created_at, pinned \
+         FROM knowledge WHERE summary ILIKE ANY($1) ORDER BY pinned DESC, created_at DESC LIMIT $2",
+    )
+    .bind(patterns)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// Pinned knowledge, newest first.
+pub async fn pinned(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<Knowledge>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, KnowledgeRow>(
+        "SELECT id, summary, embedding, source_episode_ids, created_at, pinned \
+         FROM knowledge WHERE pinned ORDER BY created_at DESC LIMIT $1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// Delete a knowledge entry. Returns whether it existed.
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM knowledge WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 #[derive(sqlx::FromRow)]
 struct KnowledgeRow {
     id: Uuid,
@@ -60,6 +103,7 @@ struct KnowledgeRow {
     embedding: Option<Vec<u8>>,
     source_episode_ids: Vec<Uuid>,
     created_at: chrono::DateTime<chrono::Utc>,
+    pinned: bool,
 }
 
 impl From<KnowledgeRow> for Knowledge {
@@ -70,6 +114,7 @@ impl From<KnowledgeRow> for Knowledge {
             embedding: row.embedding,
             source_episode_ids: row.source_episode_ids,
             created_at: row.created_at,
+            pinned: row.pinned,
         }
     }
 }
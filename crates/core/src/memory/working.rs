@@ -59,6 +59,22 @@ impl WorkingMemory {
         }
     }
 
+    /// Remove an entry outright, pinned or not. Returns false if not found.
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id != id);
+        self.entries.len() != before
+    }
+
+    /// Ids of entries pinned for `reason`, oldest first.
+    pub fn pinned_for(&self, reason: &str) -> Vec<Uuid> {
+        let mut pinned: Vec<&ContextEntry> = self.entries.iter()
+            .filter(|e| e.pinned_by.as_deref() == Some(reason))
+            .collect();
+        pinned.sort_by_key(|e| e.created_at);
+        pinned.into_iter().map(|e| e.id).collect()
+    }
+
     /// Get an entry by ID (also touches it).
     pub fn get(&mut self, id: Uuid) -> Option<&ContextEntry> {
         self.touch(id);
@@ -164,6 +180,19 @@ mod tests {
         assert!(wm.get(id1).is_some());
     }
 
+    #[test]
+    fn remove_drops_pinned_entries() {
+        let mut wm = WorkingMemory::new(4, 1800);
+        let e = make_entry(0.5);
+        let id = e.id;
+        wm.insert(e);
+        wm.pin(id, "remember");
+        assert_eq!(wm.pinned_for("remember"), vec![id]);
+        assert!(wm.remove(id));
+        assert!(!wm.remove(id));
+        assert!(wm.is_empty());
+    }
+
     #[test]
     fn touch_updates_access() {
         let mut wm = WorkingMemory::new(4, 1800);
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -8,12 +9,25 @@ use super::shutdown::ShutdownGuard;
 use crate::boot::guardian::BootGuardian;
 use crate::boot::safe_mode::SafeMode;
 use crate::capability::builtin::BuiltinRegistry;
+use crate::capability::builtin::memory::PinChange;
 use crate::capability::process_manager::HealthEvent;
-use crate::capability::{db as capability_db, lifecycle, process_manager::ProcessManager};
-use crate::codegen::gap_generator;
+use crate::capability::mcp::{config as mcp_config, manager::McpManager};
+use crate::capability::grants::{self, GrantKind, PermissionGrant};
+use crate::capability::net_policy::{self, HostPolicy, HostRuleKind};
+use crate::capability::{db as capability_db, lifecycle, process_manager::ProcessManager, self_test, versions};
+use crate::codegen::gap_generator::{self, CodegenQueue};
+use crate::codegen::install::InstallSettings;
 use crate::cognition::arbitration::PressureState;
-use crate::cognition::{response, tool_call};
+use crate::cognition::fast_path::FastPath;
+use crate::cognition::perception::{self, PerceptCache};
+use crate::cognition::self_critic::{self, ToolScore, TurnRecord};
+use crate::cognition::response::{self, PromptProfile};
+use crate::cognition::tool_call;
 use crate::config::IrisCfg;
+use crate::dialogue::commands::{
+    self, Command, GrantsCommand, HostsCommand, LangCommand, PersonaCommand, SchedulesCommand,
+    UndoCommand,
+};
 use crate::dialogue::commit_window::CommitWindow;
 use crate::dialogue::context_version::ContextVersion;
 use crate::dialogue::feedback;
@@ -23,20 +37,31 @@ use crate::environment::hardware::HardwareSnapshot;
 use crate::environment::system::{CpuSampler, RamSnapshot};
 use crate::environment::watcher::EnvironmentWatcher;
 use crate::identity::affect::AffectActor;
+use crate::i18n::{Lang, Msg, SessionLanguage};
+use crate::identity::persona::{self, Persona};
 use crate::identity::{core_identity, introspection, narrative, self_model};
 use crate::io::output::{OutputMessage, OutputReceiver, OutputSender};
 use crate::memory;
 use crate::memory::working::WorkingMemory;
 use crate::resource_space::budget::{self, BudgetSender, ResourceBudget};
 use crate::resource_space::pressure::{self as res_pressure, ResourceSnapshot};
+use crate::schedule::store::{self as schedule_store, ScheduleKind};
+use crate::schedule::timer::{self as schedule_timer, Fired};
 use crate::sensory::gating;
 use crate::thalamus::router;
 use crate::types::{
-    ContextEntry, Episode, EventSource, FeedbackType, GapDescriptor, GapType, GatedEvent,
-    NarrativeEventType, SensoryEvent,
+    CapabilityRecord, ContextEntry, Episode, EventSource, FeedbackType, GapDescriptor, GapType,
+    GatedEvent, NarrativeEventType, Permission, ReflexAction, SensoryEvent,
 };
 use llm::provider::LlmProvider;
 
+/// Working-memory pin reason for facts saved with `remember`.
+const REMEMBER_PIN: &str = "remember";
+/// Remembered facts kept pinned in working memory at once.
+const MAX_REMEMBERED_PINS: usize = 8;
+/// How long self-critic tool scores are reused before reloading them.
+const TOOL_SCORE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Core runtime that drives the iris tick loop.
 pub struct Runtime {
     cfg: Arc<IrisCfg>,
@@ -46,6 +71,12 @@ pub struct Runtime {
     event_rx: mpsc::Receiver<SensoryEvent>,
     /// Sender clone for re-injecting internal events (replay, spontaneous thoughts).
     event_tx: mpsc::Sender<SensoryEvent>,
+    /// Schedules fired by the schedule task, drained each tick.
+    fired_rx: mpsc::Receiver<Fired>,
+    fired_tx: mpsc::Sender<Fired>,
+    /// Capabilities whose outcome the self-critic just recorded, drained each tick.
+    graded_rx: mpsc::Receiver<uuid::Uuid>,
+    graded_tx: mpsc::Sender<uuid::Uuid>,
     tick_count: u64,
     mode: TickMode,
     /// Pressure state machine for arbitration.
@@ -83,8 +114,35 @@ pub struct Runtime {
     context_version: ContextVersion,
     /// Capability subprocess manager.
     process_manager: ProcessManager,
+    /// Keyword routes to installed process capabilities (confirmed and active candidates).
+    capabilities: FastPath,
+    /// Codegen runs for unmet requests; finished installs are routed on the next tick.
+    codegen: CodegenQueue,
+    /// External MCP servers whose tools are mounted in the builtin registry.
+    mcp: McpManager,
     /// Built-in capabilities (read_file, write_file, run_bash).
     builtin_registry: BuiltinRegistry,
+    /// Per-event perception results shared by gating and tool routing.
+    percepts: PerceptCache,
+    /// Tool calls / capability behind the response being produced, for the self-critic.
+    turn_trace: TurnTrace,
+    /// Self-critic tool scores and when they were loaded, refreshed every `TOOL_SCORE_TTL`.
+    tool_scores: Option<(std::time::Instant, HashMap<String, ToolScore>)>,
+    /// Persona for this session (tone, length, language, tool etiquette).
+    persona: Persona,
+    /// Session persona requested at launch; overrides the stored default.
+    persona_override: Option<String>,
+    /// Founding values from the core identity, injected above every persona.
+    founding_values: serde_json::Value,
+    /// Reply language for deterministic messages, following the user's input unless pinned.
+    language: SessionLanguage,
+}
+
+/// What produced the current turn's response.
+#[derive(Default)]
+struct TurnTrace {
+    tool_calls: Vec<tool_call::ToolAttempt>,
+    capability_id: Option<uuid::Uuid>,
 }
 
 impl Runtime {
@@ -100,13 +158,17 @@ impl Runtime {
         let shutdown = ShutdownGuard::new();
         let shutdown_token = shutdown.token();
         let working_memory_cap = cfg.working_memory_cap;
+        let codegen = CodegenQueue::from_cfg(&cfg);
         let working_memory_ttl = cfg.working_memory_ttl_secs;
         let commit_window_ms = cfg.commit_window_ms;
         let max_active_topics = cfg.max_active_topics;
         let safe_mode_recovery = cfg.safe_mode_recovery_ticks;
         let safe_mode_cooldown = cfg.safe_mode_cooldown_secs;
+        let default_language = Lang::from_code(&cfg.default_language).unwrap_or(Lang::Zh);
         let (tx, rx) = mpsc::channel(256); // bounded, backpressure at 256
         let (output_tx, output_rx) = crate::io::output::channel(64);
+        let (fired_tx, fired_rx) = mpsc::channel(64);
+        let (graded_tx, graded_rx) = mpsc::channel(64);
         // affect_rx intentionally dropped — Runtime reads affect via affect.current() directly
         let (affect, _) = AffectActor::new();
         let (budget_tx, _budget_rx) = budget::watch_channel();
@@ -116,6 +178,10 @@ impl Runtime {
             pool,
             event_rx: rx,
             event_tx: tx.clone(),
+            fired_rx,
+            fired_tx,
+            graded_rx,
+            graded_tx,
             tick_count: 0,
             mode: TickMode::Idle,
             pressure: PressureState::new(),
@@ -135,7 +201,17 @@ impl Runtime {
             rest_cycle: RestCycle::new(),
             context_version: ContextVersion::new(),
             process_manager: ProcessManager::new(shutdown_token),
+            capabilities: FastPath::new(),
+            codegen,
+            mcp: McpManager::default(),
             builtin_registry: BuiltinRegistry::new(),
+            percepts: PerceptCache::new(),
+            turn_trace: TurnTrace::default(),
+            tool_scores: None,
+            persona: Persona::default(),
+            persona_override: None,
+            founding_values: core_identity::default_founding_values(),
+            language: SessionLanguage::new(default_language),
         };
         (runtime, tx, output_rx)
     }
@@ -153,6 +229,20 @@ impl Runtime {
 
         // Load confirmed capabilities from DB
         if let Some(pool) = &self.pool {
+            // Self-test staged capabilities so they are promoted or quarantined before loading
+            match capability_db::fetch_by_state(pool, crate::types::CapabilityState::Staged).await {
+                Ok(staged) => {
+                    for cap in &staged {
+                        if let Err(e) = self_test::run_and_promote(pool, &mut self.process_manager, cap).await {
+                            tracing::warn!(capability = %cap.name, error = %e, "failed to self-test staged capability");
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to load staged capabilities from DB");
+                }
+            }
+
             match capability_db::fetch_by_state(pool, crate::types::CapabilityState::Confirmed)
                 .await
             {
@@ -164,6 +254,7 @@ impl Runtime {
                         if let Err(e) = self.process_manager.spawn(cap) {
                             tracing::warn!(capability = %cap.name, error = %e, "failed to spawn confirmed capability");
                         }
+                        self.capabilities.register(cap.id, cap.manifest.keywords.clone());
                     }
                 }
                 Err(e) => {
@@ -184,6 +275,7 @@ impl Runtime {
                         if let Err(e) = self.process_manager.spawn(cap) {
                             tracing::warn!(capability = %cap.name, error = %e, "failed to spawn candidate capability");
                         }
+                        self.capabilities.register(cap.id, cap.manifest.keywords.clone());
                     }
                     if !candidates.is_empty() {
                         tracing::info!(count = candidates.len(), "active candidates spawned");
@@ -203,7 +295,10 @@ impl Runtime {
         // Ensure core identity exists (DB required)
         if let Some(pool) = &self.pool {
             match core_identity::ensure(pool, "iris").await {
-                Ok(id) => tracing::info!(name = %id.name, "core identity ensured"),
+                Ok(id) => {
+                    tracing::info!(name = %id.name, "core identity ensured");
+                    self.founding_values = id.founding_values;
+                }
                 Err(e) => {
                     self.boot.record_failure();
                     tracing::warn!(error = %e, "failed to ensure core identity");
@@ -217,6 +312,10 @@ impl Runtime {
                 tracing::info!("self-model architecture seeded");
             }
 
+            if let Err(e) = persona::seed(pool).await {
+                tracing::warn!(error = %e, "failed to seed personas");
+            }
+
             // Record boot narrative event
             let evt = narrative::new_event(
                 NarrativeEventType::MilestoneReached,
@@ -228,6 +327,36 @@ impl Runtime {
             }
         }
 
+        self.builtin_registry
+            .apply_config(&self.cfg, self.pool.as_ref())
+            .await;
+        self.builtin_registry.jobs().set_notifier(self.event_tx.clone());
+        if let Some(pool) = self.pool.clone() {
+            match memory::semantic::pinned(&pool, MAX_REMEMBERED_PINS as i64).await {
+                Ok(entries) => {
+                    // Oldest first, so the newest stay pinned.
+                    for k in entries.into_iter().rev() {
+                        self.pin_remembered(k.id, &k.summary, k.created_at);
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to load pinned memories"),
+            }
+        }
+
+        match mcp_config::load(&self.cfg.mcp_servers_file) {
+            Ok(servers) => {
+                let timeout = std::time::Duration::from_secs(self.cfg.mcp_timeout_secs);
+                self.mcp = McpManager::new(servers, timeout);
+                for name in self.mcp.names() {
+                    self.start_mcp_server(&name).await;
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to load MCP server config"),
+        }
+
+        // Resolve the session persona: launch override, else the stored default.
+        self.resolve_session_persona().await;
+
         // Check if boot failures warrant safe mode
         if self.boot.should_enter_safe_mode() {
             self.safe_mode.enter();
@@ -257,6 +386,17 @@ impl Runtime {
             tracing::info!("memory replay task spawned");
         }
 
+        // Spawn the schedule task; its first poll fires anything missed while down.
+        if let Some(pool) = &self.pool {
+            schedule_timer::spawn(
+                pool.clone(),
+                self.fired_tx.clone(),
+                self.cfg.schedule_poll_secs,
+                self.shutdown.token(),
+            );
+            tracing::info!("schedule task spawned");
+        }
+
         loop {
             let interval = self.mode.interval(&self.cfg);
 
@@ -276,9 +416,16 @@ impl Runtime {
                 self.cfg.shutdown_timeout_secs,
             ))
             .await;
+        self.mcp.shutdown_all();
         tracing::info!("iris runtime stopped");
     }
 
+    /// Select a persona for this session, overriding the stored default.
+    /// Takes effect at boot; use `/persona <name>` to switch at runtime.
+    pub fn set_session_persona(&mut self, name: impl Into<String>) {
+        self.persona_override = Some(name.into());
+    }
+
     /// Returns the cancellation token for spawning child tasks.
     pub fn token(&self) -> CancellationToken {
         self.shutdown.token()
@@ -290,13 +437,26 @@ impl Runtime {
         let _span = tracing::info_span!("tick", n = self.tick_count, mode = ?self.mode).entered();
 
         // Step 1: Collect inputs — drain event channel
-        let events = self.collect_inputs();
+        let mut events = self.collect_inputs();
+
+        // Due reminders go straight to the user; scheduled tasks become internal events.
+        events.extend(self.drain_fired_schedules());
+
+        // Pins and removals queued by remember/forget during the previous tick.
+        self.apply_pin_changes();
+
+        // Runtime slash commands (e.g. /persona) are answered directly, not gated.
+        let events = self.handle_commands(events).await;
 
         // Commit window disabled in v1 — external events processed immediately.
         // Rapid-fire input merging deferred to v2 (see PLAN.md §11).
 
+        // Step 1b: Perception — optional lite-model classification, cached per event
+        self.perceive_events(&events).await;
+
         // Step 2: Sensory gating — filter below noise_floor, score salience
-        let gated = gating::gate(events, &self.cfg);
+        let percepts = &self.percepts;
+        let gated = gating::gate_with_percepts(events, &self.cfg, |id| percepts.get(id));
 
         // Step 3: Thalamic routing — sort into priority batches
         let batch = router::route(gated);
@@ -418,9 +578,14 @@ impl Runtime {
             }
         }
 
+        self.builtin_registry
+            .sandbox()
+            .set_safe_mode(self.safe_mode.is_active());
+
         // Environment monitoring: sample CPU and hardware each tick
         let cpu = self.cpu_sampler.sample();
-        let hw = HardwareSnapshot::default();
+        let hw = HardwareSnapshot::sample();
+        self.builtin_registry.net().set_network(hw.network);
         let signals = self.env_watcher.update(cpu, hw);
         for signal in &signals {
             tracing::info!(?signal, "environment degradation signal");
@@ -456,6 +621,10 @@ impl Runtime {
             self.rest_cycle.exit();
         }
 
+        // Graded outcomes may show a capability version regressing
+        self.check_graded_capabilities().await;
+        self.collect_codegen().await;
+
         // Capability health check — detect crashes and confirm candidates
         let health_events = self.process_manager.health_check();
         for event in health_events {
@@ -468,10 +637,62 @@ impl Runtime {
                 }
             }
         }
+        for (server, exit_code) in self.mcp.health_check() {
+            tracing::warn!(server = %server, ?exit_code, "MCP server exited");
+            self.handle_mcp_crash(&server, &format!("exit code: {exit_code:?}")).await;
+        }
+        for (server, result) in self.mcp.poll_restarts().await {
+            match result {
+                Ok(tools) => {
+                    let mounted = self.builtin_registry.mount_mcp(&server, tools);
+                    tracing::info!(server = %server, tools = mounted, "MCP server restarted, tools remounted");
+                }
+                Err(e) => {
+                    tracing::warn!(server = %server, error = %e, "MCP server restart failed");
+                    self.handle_mcp_crash(&server, &format!("restart failed: {e}")).await;
+                }
+            }
+        }
+    }
+
+    /// Perceive new external events: the lite model when enabled and configured,
+    /// otherwise the rules. Timeouts and bad output fall back to rules inside
+    /// `perception::perceive`. Each percept's language updates the session language.
+    async fn perceive_events(&mut self, events: &[SensoryEvent]) {
+        let lite = self
+            .lite_llm
+            .clone()
+            .filter(|_| self.cfg.perception_llm_enabled);
+        let timeout = std::time::Duration::from_millis(self.cfg.perception_timeout_ms);
+        for event in events {
+            if event.source != EventSource::External || self.percepts.contains(&event.id) {
+                continue;
+            }
+            let features = match &lite {
+                Some(lite) => perception::perceive(lite.as_ref(), event, timeout).await,
+                None => perception::extract_rules(&event.content),
+            };
+            self.language.observe(Lang::from_code(&features.language));
+            tracing::debug!(
+                event_id = %event.id,
+                source = ?features.source,
+                intent = %features.intent_tag,
+                intent_confidence = features.intent_confidence,
+                urgency = features.urgency,
+                threat = features.threat,
+                language = %features.language,
+                "event perceived"
+            );
+            self.percepts.insert(event.id, features);
+        }
     }
 
     /// Process a single event through the fast/slow cognitive pipeline.
     async fn process_event(&mut self, event: &GatedEvent) {
+        self.turn_trace = TurnTrace::default();
+        // File writes made while handling this event are journaled against it.
+        self.builtin_registry.undo().set_origin(Some(event.event.id));
+
         // Build self-context once for both slow path and direct LLM fallback.
         // Builtin capability descriptions are no longer injected here — tools are
         // now sent structurally via the API `tools` parameter in the agentic loop.
@@ -481,18 +702,51 @@ impl Runtime {
             String::new()
         };
 
-        // FastPath removed: all external/internal events now flow through the same
-        // LLM + tool-routing path for consistent behavior.
-        self.execute_direct_llm_fallback(event, &self_context).await;
+        let profile = PromptProfile {
+            persona: self.persona.clone(),
+            founding_values: self.founding_values.clone(),
+            self_context,
+            reply_language: self.language.pinned(),
+        };
+
+        // Installed process capabilities are matched by keyword first; everything
+        // else flows through the same LLM + tool-routing path.
+        let decision = self.capabilities.evaluate(event);
+        if let Some(decision) = &decision
+            && decision.action == ReflexAction::InvokeCapability
+            && let Some(cap_id) = decision.capability_id
+        {
+            self.execute_capability_invocation(event, cap_id, &profile).await;
+            return;
+        }
+        self.execute_direct_llm_fallback(event, &profile).await;
+
+        // No capability matched and the tools could not serve it: grow one
+        if decision.is_some_and(|d| d.async_codegen) && self.unmet_request(event) {
+            self.submit_codegen_gap(event);
+        }
+    }
+
+    /// Whether the user asked for something to be done and every tool tried
+    /// for it failed.
+    fn unmet_request(&self, event: &GatedEvent) -> bool {
+        let asked = self
+            .percepts
+            .get(&event.event.id)
+            .is_some_and(|p| matches!(p.intent_tag.as_str(), "command" | "request"));
+        let tools = &self.turn_trace.tool_calls;
+        event.event.source == EventSource::External
+            && asked
+            && !tools.is_empty()
+            && tools.iter().all(|call| call.is_error)
     }
 
     /// Execute capability invocation: DB lookup, state validation, spawn if needed, IPC invoke.
-    #[allow(dead_code)]
     async fn execute_capability_invocation(
         &mut self,
         event: &GatedEvent,
         cap_uuid: uuid::Uuid,
-        self_context: &str,
+        profile: &PromptProfile,
     ) {
         // Built-in capability: execute in-process, then LLM-summarize the result
         if let Some(builtin) = self.builtin_registry.get(cap_uuid) {
@@ -519,7 +773,7 @@ impl Runtime {
                 &builtin_name,
                 &tool_output,
                 is_error,
-                self_context,
+                profile,
             )
             .await;
             return;
@@ -528,11 +782,10 @@ impl Runtime {
         if let Some(pool) = &self.pool {
             match capability_db::fetch_by_id(pool, cap_uuid).await {
                 Ok(Some(record)) => {
-                    if let Err(e) = lifecycle::validate_transition(
-                        record.state,
-                        crate::types::CapabilityState::Confirmed,
-                    ) {
-                        if record.state == crate::types::CapabilityState::Quarantined {
+                    match record.state {
+                        crate::types::CapabilityState::Confirmed
+                        | crate::types::CapabilityState::ActiveCandidate => {}
+                        crate::types::CapabilityState::Quarantined => {
                             if lifecycle::should_retire(record.quarantine_count) {
                                 tracing::warn!(
                                     capability = %record.name,
@@ -540,115 +793,174 @@ impl Runtime {
                                     "capability should be retired"
                                 );
                             }
-                            self.send_response(&format!(
-                                "[capability {}] quarantined, cannot invoke",
-                                record.name
-                            ));
-                        } else {
-                            tracing::debug!(error = %e, capability = %record.name, "invalid capability state for invocation");
-                            self.send_response(&format!(
-                                "[capability {}] state {:?} not invocable",
-                                record.name, record.state
-                            ));
+                            self.send_msg(Msg::CapabilityQuarantined { name: &record.name });
+                            return;
                         }
-                        return;
-                    }
-
-                    if record.state != crate::types::CapabilityState::Confirmed
-                        && record.state != crate::types::CapabilityState::ActiveCandidate
-                    {
-                        return;
-                    }
-
-                    // Ensure process is running
-                    if !self.process_manager.is_running(cap_uuid)
-                        && let Err(e) = self.process_manager.spawn(&record)
-                    {
-                        tracing::warn!(capability = %record.name, error = %e, "failed to spawn capability for invocation");
-                        self.send_response(&format!(
-                            "[capability {}] spawn failed: {e}",
-                            record.name
-                        ));
-                        if let Err(db_err) =
-                            capability_db::record_outcome(pool, cap_uuid, false).await
-                        {
-                            tracing::warn!(error = %db_err, "failed to record capability outcome");
+                        state => {
+                            tracing::debug!(capability = %record.name, ?state, "invalid capability state for invocation");
+                            self.send_msg(Msg::CapabilityNotInvocable {
+                                name: &record.name,
+                                state: &format!("{state:?}"),
+                            });
+                            return;
                         }
-                        return;
                     }
 
-                    // Build IPC request
-                    let request = crate::types::CapabilityRequest {
-                        id: uuid::Uuid::new_v4(),
-                        method: event.event.content.clone(),
-                        params: serde_json::json!({}),
-                        version: 1,
-                    };
-
-                    let timeout = std::time::Duration::from_millis(
-                        record
-                            .manifest
-                            .resource_limits
-                            .get("timeout_ms")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(5000),
-                    );
-
-                    tracing::info!(capability = %record.name, state = ?record.state, "invoking capability via IPC");
-
-                    match self
-                        .process_manager
-                        .invoke(cap_uuid, request, timeout)
-                        .await
-                    {
-                        Ok(resp) => {
-                            let response = if let Some(err) = &resp.error {
-                                format!("[capability {}] error: {err}", record.name)
-                            } else if let Some(result) = &resp.result {
-                                format!("[capability {}] {result}", record.name)
-                            } else {
-                                format!("[capability {}] ok (no result)", record.name)
-                            };
-                            self.send_response(&response);
-                            if let Err(e) =
-                                capability_db::record_outcome(pool, cap_uuid, resp.error.is_none())
-                                    .await
-                            {
-                                tracing::warn!(error = %e, "failed to record capability outcome");
-                            }
-                            self.store_response(event, response).await;
-                        }
+                    let grants = match grants::load_for(pool, &record.name).await {
+                        Ok(g) => g,
                         Err(e) => {
-                            tracing::warn!(capability = %record.name, error = %e, "capability invocation failed");
-                            self.send_response(&format!(
-                                "[capability {}] invoke error: {e}",
-                                record.name
-                            ));
-                            if let Err(db_err) =
-                                capability_db::record_outcome(pool, cap_uuid, false).await
-                            {
-                                tracing::warn!(error = %db_err, "failed to record capability outcome");
-                            }
+                            tracing::warn!(error = %e, "failed to load capability grants");
+                            Vec::new()
                         }
+                    };
+                    let revoked = grants::revoked(&record.manifest.permissions, &grants);
+                    if !revoked.is_empty() {
+                        self.send_msg(Msg::CapabilityPermissionRevoked {
+                            name: &record.name,
+                            permissions: &grants::join(&revoked),
+                        });
+                        return;
                     }
+
+                    self.invoke_process_capability(event, &record, &grants).await;
                 }
                 Ok(None) => {
                     tracing::warn!(capability_id = %cap_uuid, "capability not found in DB");
-                    self.send_response(&format!("[capability {cap_uuid}] not found"));
+                    self.send_msg(Msg::CapabilityNotFound { id: &cap_uuid.to_string() });
                 }
                 Err(e) => {
                     tracing::warn!(error = %e, "failed to fetch capability");
-                    self.send_response(&format!("[capability error] {e}"));
+                    self.send_msg(Msg::CapabilityLookupFailed { error: &e.to_string() });
                 }
             }
         } else {
-            self.send_response(&format!("[capability {cap_uuid}] no DB configured"));
+            self.send_msg(Msg::CapabilityNoDb { id: &cap_uuid.to_string() });
+        }
+    }
+
+    /// Run a process capability on the event and check what it reports doing
+    /// against its permissions; a violation stops and quarantines it.
+    async fn invoke_process_capability(
+        &mut self,
+        event: &GatedEvent,
+        record: &CapabilityRecord,
+        grants: &[PermissionGrant],
+    ) {
+        let cap_uuid = record.id;
+
+        // A rebuilt or rolled-back version replaces the running process
+        if self
+            .process_manager
+            .running_version(cap_uuid)
+            .is_some_and(|v| v != record.current_version)
+        {
+            self.process_manager.kill(cap_uuid);
+        }
+
+        // Ensure process is running
+        if !self.process_manager.is_running(cap_uuid)
+            && let Err(e) = self.process_manager.spawn(record)
+        {
+            tracing::warn!(capability = %record.name, error = %e, "failed to spawn capability for invocation");
+            self.send_msg(Msg::CapabilitySpawnFailed {
+                name: &record.name,
+                error: &e.to_string(),
+            });
+            self.record_capability_outcome(cap_uuid, false).await;
+            self.check_regression(cap_uuid).await;
+            return;
+        }
+
+        // Build IPC request
+        let request = crate::types::CapabilityRequest {
+            id: uuid::Uuid::new_v4(),
+            method: event.event.content.clone(),
+            params: serde_json::json!({}),
+            version: 1,
+        };
+
+        let timeout = std::time::Duration::from_millis(
+            record
+                .manifest
+                .resource_limits
+                .get("timeout_ms")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(5000),
+        );
+
+        tracing::info!(capability = %record.name, state = ?record.state, "invoking capability via IPC");
+
+        match self
+            .process_manager
+            .invoke(cap_uuid, request, timeout)
+            .await
+        {
+            Ok(resp) => {
+                self.turn_trace.capability_id = Some(cap_uuid);
+                let violations =
+                    grants::violations(&record.manifest.permissions, grants, &resp.side_effects);
+                if !violations.is_empty() {
+                    self.send_msg(Msg::CapabilityPermissionViolation {
+                        name: &record.name,
+                        permissions: &grants::join(&violations),
+                    });
+                    self.record_capability_outcome(cap_uuid, false).await;
+                    self.handle_permission_violation(cap_uuid, &record.name, &violations).await;
+                    return;
+                }
+                let lang = self.language.current();
+                let response = if let Some(err) = &resp.error {
+                    Msg::CapabilityError { name: &record.name, error: err }.render(lang)
+                } else if let Some(result) = &resp.result {
+                    Msg::CapabilityResult {
+                        name: &record.name,
+                        result: &result.to_string(),
+                    }
+                    .render(lang)
+                } else {
+                    Msg::CapabilityNoResult { name: &record.name }.render(lang)
+                };
+                self.send_response(&response);
+                self.turn_trace.tool_calls.push(tool_call::ToolAttempt {
+                    name: record.name.clone(),
+                    input: event.event.content.clone(),
+                    outcome: response.clone(),
+                    is_error: resp.error.is_some(),
+                });
+                // With the self-critic on, the graded outcome is recorded instead.
+                self.store_response(event, response).await;
+                if !self.cfg.self_critic_enabled {
+                    self.record_capability_outcome(cap_uuid, resp.error.is_none()).await;
+                    self.check_regression(cap_uuid).await;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(capability = %record.name, error = %e, "capability invocation failed");
+                self.send_msg(Msg::CapabilityInvokeError {
+                    name: &record.name,
+                    error: &e.to_string(),
+                });
+                self.record_capability_outcome(cap_uuid, false).await;
+                self.check_regression(cap_uuid).await;
+            }
+        }
+    }
+
+    /// Count a capability invocation toward its score and installed version.
+    async fn record_capability_outcome(&self, cap_id: uuid::Uuid, success: bool) {
+        if let Some(pool) = &self.pool
+            && let Err(e) = capability_db::record_outcome(pool, cap_id, success).await
+        {
+            tracing::warn!(error = %e, "failed to record capability outcome");
         }
     }
 
     /// Submit async codegen for an unmatched capability gap.
-    #[allow(dead_code)]
-    fn submit_codegen_gap(&self, event: &GatedEvent) {
+    fn submit_codegen_gap(&mut self, event: &GatedEvent) {
+        if !self.codegen.has_capacity() {
+            tracing::debug!("codegen limit reached, capability gap not submitted");
+            return;
+        }
         if let (Some(pool), Some(llm)) = (&self.pool, &self.llm) {
             let gap = GapDescriptor {
                 id: uuid::Uuid::new_v4(),
@@ -658,20 +970,64 @@ impl Runtime {
                 suggested_crates: Vec::new(),
                 created_at: chrono::Utc::now(),
             };
-            // Receiver intentionally dropped — codegen runs fire-and-forget in background
-            let _rx = gap_generator::submit_async(
+            let run = gap_generator::submit_async(
                 gap,
                 pool.clone(),
                 Arc::clone(llm),
+                InstallSettings::from_cfg(&self.cfg),
                 self.shutdown.token(),
             );
+            self.codegen.push(run);
             tracing::info!("async codegen submitted for capability gap");
         }
     }
 
+    /// Route to capabilities installed by finished codegen runs.
+    async fn collect_codegen(&mut self) {
+        let mut installed = false;
+        for outcome in self.codegen.poll() {
+            match outcome {
+                Ok(result) if result.success => installed = true,
+                Ok(result) => {
+                    tracing::info!(iterations = result.iterations, error = ?result.last_error, "codegen gave up on capability gap");
+                }
+                Err(e) => tracing::warn!(error = %e, "codegen run failed"),
+            }
+        }
+        if installed {
+            self.refresh_capabilities().await;
+        }
+    }
+
+    /// Route to every confirmed and candidate capability and start the ones not
+    /// running, picking up new installs and rebuilt manifests.
+    async fn refresh_capabilities(&mut self) {
+        let Some(pool) = self.pool.clone() else { return };
+        for state in [crate::types::CapabilityState::Confirmed, crate::types::CapabilityState::ActiveCandidate] {
+            let caps = match capability_db::fetch_by_state(&pool, state).await {
+                Ok(caps) => caps,
+                Err(e) => {
+                    tracing::warn!(error = %e, ?state, "failed to load capabilities from DB");
+                    continue;
+                }
+            };
+            for cap in caps {
+                self.capabilities.unregister(cap.id);
+                self.capabilities.register(cap.id, cap.manifest.keywords.clone());
+                if !self.process_manager.is_running(cap.id)
+                    && let Err(e) = self.process_manager.spawn(&cap)
+                {
+                    tracing::warn!(capability = %cap.name, error = %e, "failed to spawn installed capability");
+                }
+            }
+        }
+    }
+
     /// Execute DirectLlmFallback: generate response via LLM or placeholder.
     /// When builtin tools are available, uses the agentic tool-use loop.
-    async fn execute_direct_llm_fallback(&mut self, event: &GatedEvent, self_context: &str) {
+    async fn execute_direct_llm_fallback(&mut self, event: &GatedEvent, profile: &PromptProfile) {
+        // Loaded up front: refreshing the cache needs `&mut self`
+        let tool_scores = if self.llm.is_some() { self.tool_scores().await } else { HashMap::new() };
         if let Some(ref llm) = self.llm {
             self.affect.on_llm_call();
             let working = self.working_memory.recent(10);
@@ -737,7 +1093,8 @@ impl Runtime {
 
             // Decide whether to execute a specific tool directly, run the full agentic loop,
             // or skip tools and generate a plain response.
-            let tools = self.builtin_registry.tool_definitions();
+            let mut tools = self.builtin_registry.tool_definitions();
+            self_critic::rank_tools(&mut tools, &tool_scores);
             const TOOL_ROUTE_CONFIDENCE_THRESHOLD: f32 = 0.72;
             const TOOL_SKIP_CONFIDENCE_THRESHOLD: f32 = 0.90;
 
@@ -750,8 +1107,17 @@ impl Runtime {
                 AgenticLoop,
             }
 
+            let conversational = self
+                .percepts
+                .get(&event.event.id)
+                .is_some_and(perception::is_conversational);
+
             let plan = if tools.is_empty() {
                 ToolPlan::DirectResponse
+            } else if conversational {
+                // Perception is confident this is plain conversation: skip the tool router.
+                tracing::debug!("tool routing skipped: conversational percept");
+                ToolPlan::DirectResponse
             } else {
                 // Prefer lightweight router when configured, otherwise fall back to main model.
                 let (router_llm, router_source) = if let Some(lite_llm) = &self.lite_llm {
@@ -768,6 +1134,7 @@ impl Runtime {
                             use_tool = decision.use_tool,
                             confidence = decision.confidence,
                             is_valid = decision.is_valid,
+                            validation_errors = ?decision.validation_errors,
                             tool_name = ?decision.tool_name,
                             router_source,
                             "tool route decision"
@@ -784,9 +1151,16 @@ impl Runtime {
                             && decision.confidence >= TOOL_ROUTE_CONFIDENCE_THRESHOLD
                         {
                             if let Some(name) = decision.tool_name {
-                                ToolPlan::RoutedTool {
-                                    name,
-                                    input: decision.input,
+                                if tool_scores.get(&name).is_some_and(|s| s.is_unreliable()) {
+                                    // Poor track record: let the main model choose instead of
+                                    // executing the routed call blindly.
+                                    tracing::debug!(tool = %name, "routed tool demoted: unreliable score");
+                                    ToolPlan::AgenticLoop
+                                } else {
+                                    ToolPlan::RoutedTool {
+                                        name,
+                                        input: decision.input,
+                                    }
                                 }
                             } else {
                                 ToolPlan::AgenticLoop
@@ -805,15 +1179,25 @@ impl Runtime {
 
             match plan {
                 ToolPlan::RoutedTool { name, input } => {
-                    match tool_call::execute_named_tool(&self.builtin_registry, &name, &input).await
-                    {
+                    let result =
+                        tool_call::execute_named_tool(&self.builtin_registry, &name, &input).await;
+                    self.turn_trace.tool_calls.push(tool_call::ToolAttempt {
+                        name: name.clone(),
+                        input: input.to_string(),
+                        outcome: match &result {
+                            Ok(out) => out.clone(),
+                            Err(err) => format!("error: {err}"),
+                        },
+                        is_error: result.is_err(),
+                    });
+                    match result {
                         Ok(result) => {
                             self.execute_builtin_with_llm_summary(
                                 event,
                                 &name,
                                 &result,
                                 false,
-                                self_context,
+                                profile,
                             )
                             .await;
                         }
@@ -824,23 +1208,26 @@ impl Runtime {
                                 &name,
                                 &err,
                                 true,
-                                self_context,
+                                profile,
                             )
                             .await;
                         }
                     }
                 }
                 ToolPlan::AgenticLoop => {
-                    let messages = response::build_messages(event, &context, self_context);
-                    match tool_call::run_agentic_loop(
+                    let messages = response::build_messages(event, &context, profile);
+                    match tool_call::run_agentic_loop_traced(
                         llm.as_ref(),
                         messages,
                         tools,
                         &self.builtin_registry,
+                        tool_call::ToolLoopBudget::from_cfg(&self.cfg),
                     )
                     .await
                     {
-                        Ok(response) => {
+                        Ok(outcome) => {
+                            self.turn_trace.tool_calls = outcome.attempts;
+                            let response = outcome.text;
                             tracing::info!(
                                 response_len = response.len(),
                                 "agentic loop response generated"
@@ -851,12 +1238,12 @@ impl Runtime {
                         Err(e) => {
                             self.affect.on_error();
                             tracing::warn!(error = %e, "agentic loop failed");
-                            self.send_response(&format!("[LLM error] {e}"));
+                            self.send_msg(Msg::LlmError { error: &e.to_string() });
                         }
                     }
                 }
                 ToolPlan::DirectResponse => {
-                    match response::generate(event, llm.as_ref(), &context, self_context).await {
+                    match response::generate(event, llm.as_ref(), &context, profile).await {
                         Ok(response) => {
                             tracing::info!(
                                 response_len = response.len(),
@@ -868,13 +1255,14 @@ impl Runtime {
                         Err(e) => {
                             self.affect.on_error();
                             tracing::warn!(error = %e, "direct response failed");
-                            self.send_response(&format!("[LLM error] {e}"));
+                            self.send_msg(Msg::LlmError { error: &e.to_string() });
                         }
                     }
                 }
             }
         } else {
-            let placeholder = format!("[no LLM configured] received: {}", event.event.content);
+            let placeholder = Msg::NoLlmConfigured { input: &event.event.content }
+                .render(self.language.current());
             self.send_response(&placeholder);
             self.store_response(event, placeholder).await;
         }
@@ -889,10 +1277,11 @@ impl Runtime {
         tool_name: &str,
         tool_output: &str,
         is_error: bool,
-        self_context: &str,
+        profile: &PromptProfile,
     ) {
         let tool_observation = Self::tool_observation_for_context(tool_name, tool_output, is_error);
-        let fallback = Self::tool_fallback_message(tool_name, tool_output, is_error);
+        let fallback =
+            Self::tool_fallback_message(tool_name, tool_output, is_error, self.language.current());
 
         // Never let model paraphrasing override concrete tool failures.
         // Return deterministic error text to avoid false success claims.
@@ -932,7 +1321,7 @@ impl Runtime {
             let mut context: Vec<&ContextEntry> = working.to_vec();
             context.push(&tool_entry);
 
-            let llm_result = response::generate(event, llm.as_ref(), &context, self_context).await;
+            let llm_result = response::generate(event, llm.as_ref(), &context, profile).await;
 
             // Persist normalized observation to working memory so it survives even if LLM summary is poor
             // (must happen after generate() to avoid borrow conflict on working_memory)
@@ -996,10 +1385,11 @@ impl Runtime {
         }
     }
 
-    fn tool_fallback_message(tool_name: &str, tool_output: &str, is_error: bool) -> String {
+    fn tool_fallback_message(tool_name: &str, tool_output: &str, is_error: bool, lang: Lang) -> String {
         if tool_name == "run_bash" {
             if is_error {
-                return format!("执行命令时失败：{}", Self::short_text(tool_output, 180));
+                let detail = Self::short_text(tool_output, 180);
+                return Msg::CommandFailed { detail: &detail }.render(lang);
             }
 
             if let Ok(v) = serde_json::from_str::<serde_json::Value>(tool_output) {
@@ -1017,29 +1407,27 @@ impl Runtime {
 
                 if code == 0 {
                     if stdout.is_empty() && stderr.is_empty() {
-                        return "命令已执行完成，没有输出。".to_string();
+                        return Msg::CommandDoneNoOutput.render(lang);
                     }
                     if !stdout.is_empty() {
-                        return format!("命令已执行完成。输出：{}", Self::short_text(stdout, 280));
+                        let stdout = Self::short_text(stdout, 280);
+                        return Msg::CommandDoneOutput { stdout: &stdout }.render(lang);
                     }
-                    return format!("命令已执行完成。提示：{}", Self::short_text(stderr, 280));
+                    let stderr = Self::short_text(stderr, 280);
+                    return Msg::CommandDoneHint { stderr: &stderr }.render(lang);
                 }
 
                 let brief = if !stderr.is_empty() { stderr } else { stdout };
-                return format!(
-                    "执行命令失败（exit code {code}）：{}",
-                    Self::short_text(brief, 240)
-                );
+                let detail = Self::short_text(brief, 240);
+                return Msg::CommandExitFailed { code, detail: &detail }.render(lang);
             }
         }
 
         if is_error {
-            format!(
-                "执行 {tool_name} 时失败：{}",
-                Self::short_text(tool_output, 180)
-            )
+            let detail = Self::short_text(tool_output, 180);
+            Msg::ToolFailed { tool: tool_name, detail: &detail }.render(lang)
         } else {
-            format!("{tool_name} 已执行完成。")
+            Msg::ToolDone { tool: tool_name }.render(lang)
         }
     }
 
@@ -1052,6 +1440,11 @@ impl Runtime {
         out
     }
 
+    /// Render a catalog message in the session language and send it.
+    fn send_msg(&self, msg: Msg<'_>) {
+        self.send_response(&msg.render(self.language.current()));
+    }
+
     /// Send a response to the output channel, logging if full.
     fn send_response(&self, content: &str) {
         if self
@@ -1065,6 +1458,7 @@ impl Runtime {
 
     /// Store an iris response in working memory and episodes table.
     async fn store_response(&mut self, event: &GatedEvent, content: String) {
+        self.spawn_self_critique(event, &content);
         let now = chrono::Utc::now();
         let topic_id = self.topics.current_topic();
 
@@ -1096,6 +1490,499 @@ impl Runtime {
         });
     }
 
+    async fn resolve_session_persona(&mut self) {
+        let name = match (&self.persona_override, &self.pool) {
+            (Some(name), _) => name.clone(),
+            (None, Some(pool)) => persona::active_name(pool).await.unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "failed to load active persona");
+                persona::DEFAULT_PERSONA.to_string()
+            }),
+            (None, None) => persona::DEFAULT_PERSONA.to_string(),
+        };
+        match self.lookup_persona(&name).await {
+            Some(p) => {
+                tracing::info!(persona = %p.name, "session persona selected");
+                self.persona = p;
+            }
+            None => tracing::warn!(persona = %name, "unknown persona, keeping default"),
+        }
+    }
+
+    async fn lookup_persona(&self, name: &str) -> Option<Persona> {
+        match &self.pool {
+            Some(pool) => persona::load(pool, name).await.unwrap_or_else(|e| {
+                tracing::debug!(error = %e, "persona lookup failed, trying builtins");
+                persona::builtin(name)
+            }),
+            None => persona::builtin(name),
+        }
+    }
+
+    async fn persona_names(&self) -> Vec<String> {
+        let stored = match &self.pool {
+            Some(pool) => persona::list(pool).await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let mut names: Vec<String> = stored.into_iter().map(|p| p.name).collect();
+        for p in persona::builtins() {
+            if !names.contains(&p.name) {
+                names.push(p.name);
+            }
+        }
+        names.sort();
+        names
+    }
+
+    /// Answer runtime slash commands from external input; returns the remaining events.
+    async fn handle_commands(&mut self, events: Vec<SensoryEvent>) -> Vec<SensoryEvent> {
+        let mut rest = Vec::with_capacity(events.len());
+        for event in events {
+            match commands::parse(&event.content) {
+                Some(cmd) if event.source == EventSource::External => {
+                    tracing::debug!(?cmd, "runtime command received");
+                    self.execute_command(cmd).await;
+                }
+                _ => rest.push(event),
+            }
+        }
+        rest
+    }
+
+    async fn execute_command(&mut self, cmd: Command) {
+        match cmd {
+            Command::Persona(cmd) => self.execute_persona_command(cmd).await,
+            Command::Lang(cmd) => self.execute_lang_command(cmd),
+            Command::Hosts(cmd) => self.execute_hosts_command(cmd).await,
+            Command::Schedules(cmd) => self.execute_schedules_command(cmd).await,
+            Command::Undo(cmd) => self.execute_undo_command(cmd),
+            Command::Grants(cmd) => self.execute_grants_command(cmd).await,
+            Command::Versions(name) => self.execute_versions_command(&name).await,
+        }
+    }
+
+    fn execute_undo_command(&mut self, cmd: UndoCommand) {
+        let journal = Arc::clone(self.builtin_registry.undo());
+        let policy = self.builtin_registry.files().policy();
+        match cmd {
+            UndoCommand::List => {
+                let entries = journal.recent(20);
+                if entries.is_empty() {
+                    self.send_msg(Msg::UndoEmpty);
+                    return;
+                }
+                let lines: Vec<String> = entries
+                    .iter()
+                    .map(|e| {
+                        let at = e.at.with_timezone(&chrono::Local);
+                        let state = if e.undone { " (undone)" } else { "" };
+                        format!("- {} {} {} {}{state}", e.short_id(), at.format("%m-%d %H:%M"), e.tool, e.path.display())
+                    })
+                    .collect();
+                self.send_msg(Msg::UndoList { items: &lines.join("\n") });
+            }
+            UndoCommand::Undo { id, force } => match journal.undo(id.as_deref(), force, &policy) {
+                Ok(e) => self.send_msg(Msg::UndoDone {
+                    id: &e.short_id(),
+                    path: &e.path.display().to_string(),
+                    removed: e.previous.is_none(),
+                }),
+                Err(e) => self.send_msg(Msg::UndoFailed { error: &e }),
+            },
+        }
+    }
+
+    /// List or cancel entries in the `schedule` table.
+    async fn execute_schedules_command(&mut self, cmd: SchedulesCommand) {
+        let Some(pool) = self.pool.clone() else {
+            self.send_msg(Msg::SchedulesNoDb);
+            return;
+        };
+        match cmd {
+            SchedulesCommand::List => match schedule_store::list_active(&pool).await {
+                Ok(schedules) if schedules.is_empty() => self.send_msg(Msg::SchedulesEmpty),
+                Ok(schedules) => {
+                    let lines: Vec<String> = schedules
+                        .iter()
+                        .map(|s| {
+                            let next = s.next_run.with_timezone(&chrono::Local);
+                            let rule = s.recurrence.as_ref().map(|r| format!(" ({r})")).unwrap_or_default();
+                            format!(
+                                "- {} {} {}{rule} {}",
+                                s.short_id(),
+                                s.kind.as_db_str(),
+                                next.format("%Y-%m-%d %H:%M"),
+                                s.message
+                            )
+                        })
+                        .collect();
+                    self.send_msg(Msg::SchedulesList { items: &lines.join("\n") });
+                }
+                Err(e) => self.send_msg(Msg::SchedulesFailed { error: &e.to_string() }),
+            },
+            SchedulesCommand::Cancel(id) => match schedule_store::cancel(&pool, &id).await {
+                Ok(Some(s)) => self.send_msg(Msg::ScheduleCancelled {
+                    id: &s.short_id(),
+                    message: &s.message,
+                }),
+                Ok(None) => self.send_msg(Msg::ScheduleNotFound { id: &id }),
+                Err(e) => self.send_msg(Msg::SchedulesFailed { error: &e }),
+            },
+        }
+    }
+
+    /// Manage the `host_rule` table and refresh the live host policy.
+    async fn execute_hosts_command(&mut self, cmd: HostsCommand) {
+        let Some(pool) = self.pool.clone() else {
+            self.send_msg(Msg::HostsNoDb);
+            return;
+        };
+        // `changed` is false only when removing a host that had no rule.
+        let changed = match &cmd {
+            HostsCommand::List => Ok(true),
+            HostsCommand::Allow(host) => net_policy::set_rule(&pool, host, HostRuleKind::Allow)
+                .await
+                .map(|()| true),
+            HostsCommand::Deny(host) => net_policy::set_rule(&pool, host, HostRuleKind::Deny)
+                .await
+                .map(|()| true),
+            HostsCommand::Remove(host) => net_policy::remove_rule(&pool, host).await,
+        };
+        let loaded = match changed {
+            Ok(changed) => net_policy::load_rules(&pool).await.map(|rules| (changed, rules)),
+            Err(e) => Err(e),
+        };
+        let (changed, rules) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                tracing::warn!(error = %e, "host rule command failed");
+                self.send_msg(Msg::HostsFailed { error: &e.to_string() });
+                return;
+            }
+        };
+
+        let lines: Vec<String> = rules
+            .iter()
+            .map(|r| format!("- {} {}", r.kind.as_db_str(), r.host))
+            .collect();
+        self.builtin_registry.net().set_policy(HostPolicy::new(rules));
+
+        match cmd {
+            HostsCommand::List if lines.is_empty() => self.send_msg(Msg::HostsEmpty),
+            HostsCommand::List => self.send_msg(Msg::HostsList { rules: &lines.join("\n") }),
+            HostsCommand::Allow(host) => self.send_msg(Msg::HostRuleSet {
+                host: &net_policy::normalize_host(&host),
+                rule: HostRuleKind::Allow.as_db_str(),
+            }),
+            HostsCommand::Deny(host) => self.send_msg(Msg::HostRuleSet {
+                host: &net_policy::normalize_host(&host),
+                rule: HostRuleKind::Deny.as_db_str(),
+            }),
+            HostsCommand::Remove(host) if changed => {
+                self.send_msg(Msg::HostRuleRemoved { host: &net_policy::normalize_host(&host) })
+            }
+            HostsCommand::Remove(host) => {
+                self.send_msg(Msg::HostRuleNotFound { host: &net_policy::normalize_host(&host) })
+            }
+        }
+    }
+
+    /// `/versions <capability>`: builds newest first, marking the installed and LKG
+    /// versions, with per-version usage counts.
+    async fn execute_versions_command(&mut self, name: &str) {
+        let Some(pool) = self.pool.clone() else {
+            self.send_msg(Msg::VersionsNoDb);
+            return;
+        };
+        let record = match capability_db::fetch_by_name(&pool, name).await {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                self.send_msg(Msg::VersionsNotFound { name });
+                return;
+            }
+            Err(e) => {
+                self.send_msg(Msg::VersionsFailed { error: &e.to_string() });
+                return;
+            }
+        };
+        let history = match versions::list(&pool, record.id).await {
+            Ok(history) => history,
+            Err(e) => {
+                tracing::warn!(error = %e, "versions command failed");
+                self.send_msg(Msg::VersionsFailed { error: &e.to_string() });
+                return;
+            }
+        };
+        if history.is_empty() {
+            self.send_msg(Msg::VersionsEmpty { name });
+            return;
+        }
+        let lines: Vec<String> = history
+            .iter()
+            .map(|v| {
+                let mut tags = Vec::new();
+                if record.current_version == Some(v.id) {
+                    tags.push("current");
+                }
+                if record.lkg_version == Some(v.id) {
+                    tags.push("lkg");
+                }
+                if v.confirmed_at.is_some() {
+                    tags.push("confirmed");
+                }
+                let tags = if tags.is_empty() { String::new() } else { format!(" [{}]", tags.join(", ")) };
+                format!(
+                    "- v{} {} {}{tags} uses {} ok {} fail {}",
+                    v.version,
+                    v.binary_hash.get(..8).unwrap_or(&v.binary_hash),
+                    v.created_at.format("%Y-%m-%d %H:%M"),
+                    v.usage_count,
+                    v.success_count,
+                    v.fail_count,
+                )
+            })
+            .collect();
+        self.send_msg(Msg::VersionsList { name, items: &lines.join("\n") });
+    }
+
+    /// Manage the `capability_grant` table; new grants apply from the next invocation.
+    async fn execute_grants_command(&mut self, cmd: GrantsCommand) {
+        let Some(pool) = self.pool.clone() else {
+            self.send_msg(Msg::GrantsNoDb);
+            return;
+        };
+        let permission = match &cmd {
+            GrantsCommand::List(_) => None,
+            GrantsCommand::Allow(_, p) | GrantsCommand::Deny(_, p) | GrantsCommand::Remove(_, p) => {
+                let Some(perm) = Permission::parse(p) else {
+                    let known: Vec<&str> = Permission::ALL.iter().map(Permission::as_str).collect();
+                    self.send_msg(Msg::GrantUnknownPermission { permission: p, known: &known.join(", ") });
+                    return;
+                };
+                Some(perm)
+            }
+        };
+        // `changed` is false only when removing a grant that did not exist.
+        let result = match (&cmd, permission) {
+            (GrantsCommand::List(None), _) => grants::load_all(&pool).await.map(|g| (true, g)),
+            (GrantsCommand::List(Some(name)), _) => grants::load_for(&pool, name).await.map(|g| (true, g)),
+            (GrantsCommand::Allow(name, _), Some(p)) => {
+                grants::set_grant(&pool, name, p, GrantKind::Allow).await.map(|()| (true, Vec::new()))
+            }
+            (GrantsCommand::Deny(name, _), Some(p)) => {
+                grants::set_grant(&pool, name, p, GrantKind::Deny).await.map(|()| (true, Vec::new()))
+            }
+            (GrantsCommand::Remove(name, _), Some(p)) => {
+                grants::remove_grant(&pool, name, p).await.map(|removed| (removed, Vec::new()))
+            }
+            (_, None) => Ok((false, Vec::new())),
+        };
+        let (changed, list) = match result {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::warn!(error = %e, "grants command failed");
+                self.send_msg(Msg::GrantsFailed { error: &e.to_string() });
+                return;
+            }
+        };
+
+        let permission = permission.map(|p| p.as_str()).unwrap_or_default();
+        match &cmd {
+            GrantsCommand::List(Some(name)) => {
+                let declared = match capability_db::fetch_by_name(&pool, name).await {
+                    Ok(Some(record)) => record.manifest.permissions,
+                    _ => Vec::new(),
+                };
+                let lines: Vec<String> = list
+                    .iter()
+                    .map(|g| format!("- {} {}", g.kind.as_db_str(), g.permission.as_str()))
+                    .collect();
+                self.send_msg(Msg::GrantsForCapability {
+                    name,
+                    declared: &grants::join(&declared),
+                    grants: &lines.join("\n"),
+                    effective: &grants::join(&grants::effective(&declared, &list)),
+                });
+            }
+            GrantsCommand::List(None) if list.is_empty() => self.send_msg(Msg::GrantsEmpty),
+            GrantsCommand::List(None) => {
+                let lines: Vec<String> = list
+                    .iter()
+                    .map(|g| format!("- {} {} {}", g.capability, g.kind.as_db_str(), g.permission.as_str()))
+                    .collect();
+                self.send_msg(Msg::GrantsList { items: &lines.join("\n") });
+            }
+            GrantsCommand::Allow(name, _) => self.send_msg(Msg::GrantSet { name, permission, kind: "allow" }),
+            GrantsCommand::Deny(name, _) => self.send_msg(Msg::GrantSet { name, permission, kind: "deny" }),
+            GrantsCommand::Remove(name, _) if changed => self.send_msg(Msg::GrantRemoved { name, permission }),
+            GrantsCommand::Remove(name, _) => self.send_msg(Msg::GrantNotFound { name, permission }),
+        }
+    }
+
+    fn execute_lang_command(&mut self, cmd: LangCommand) {
+        match cmd {
+            LangCommand::Show => self.send_msg(Msg::LangCurrent {
+                lang: self.language.current(),
+                pinned: self.language.pinned().is_some(),
+            }),
+            LangCommand::Auto => {
+                self.language.pin(None);
+                self.send_msg(Msg::LangAuto);
+            }
+            LangCommand::Pin(code) => match Lang::from_code(&code) {
+                Some(lang) => {
+                    self.language.pin(Some(lang));
+                    self.send_msg(Msg::LangPinned { lang });
+                }
+                None => self.send_msg(Msg::LangUnknown { code: &code }),
+            },
+        }
+    }
+
+    async fn execute_persona_command(&mut self, cmd: PersonaCommand) {
+        let lang = self.language.current();
+        let reply = match cmd {
+            PersonaCommand::Show => {
+                let names = self.persona_names().await.join(", ");
+                Msg::PersonaCurrent {
+                    name: &self.persona.name,
+                    description: &self.persona.description,
+                    available: &names,
+                }
+                .render(lang)
+            }
+            PersonaCommand::Use(name) | PersonaCommand::SetDefault(name)
+                if self.lookup_persona(&name).await.is_none() =>
+            {
+                let names = self.persona_names().await.join(", ");
+                Msg::PersonaUnknown { name: &name, available: &names }.render(lang)
+            }
+            PersonaCommand::Use(name) => {
+                if let Some(p) = self.lookup_persona(&name).await {
+                    self.persona = p;
+                }
+                Msg::PersonaSwitched { name: &name }.render(lang)
+            }
+            PersonaCommand::SetDefault(name) => {
+                if let Some(p) = self.lookup_persona(&name).await {
+                    self.persona = p;
+                }
+                match &self.pool {
+                    Some(pool) => match persona::set_active(pool, &name).await {
+                        Ok(()) => Msg::PersonaDefaultSaved { name: &name }.render(lang),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "failed to persist default persona");
+                            Msg::PersonaDefaultSaveFailed { name: &name }.render(lang)
+                        }
+                    },
+                    None => Msg::PersonaDefaultNoDb { name: &name }.render(lang),
+                }
+            }
+        };
+        self.send_response(&reply);
+    }
+
+    /// Self-critic tool scores, reloaded from the DB at most every `TOOL_SCORE_TTL`.
+    async fn tool_scores(&mut self) -> HashMap<String, ToolScore> {
+        if let Some((loaded, scores)) = &self.tool_scores
+            && loaded.elapsed() < TOOL_SCORE_TTL
+        {
+            return scores.clone();
+        }
+        let Some(pool) = &self.pool else { return HashMap::new() };
+        let scores = self_critic::fetch_tool_scores(pool).await.unwrap_or_else(|e| {
+            tracing::debug!(error = %e, "failed to load tool scores");
+            HashMap::new()
+        });
+        self.tool_scores = Some((std::time::Instant::now(), scores.clone()));
+        scores
+    }
+
+    /// Grade the finished turn in the background (needs a DB to write results to).
+    fn spawn_self_critique(&mut self, event: &GatedEvent, response: &str) {
+        if !self.cfg.self_critic_enabled || event.event.source != EventSource::External {
+            return;
+        }
+        let Some(pool) = self.pool.clone() else {
+            return;
+        };
+        let provider = self.lite_llm.clone().or_else(|| self.llm.clone());
+        let turn = TurnRecord {
+            event_id: event.event.id,
+            user_input: event.event.content.clone(),
+            response: response.to_string(),
+            tool_calls: self.turn_trace.tool_calls.clone(),
+            capability_id: self.turn_trace.capability_id,
+        };
+        let timeout = std::time::Duration::from_secs(self.cfg.self_critic_timeout_secs);
+        let token = self.shutdown.token();
+        let graded_tx = self.graded_tx.clone();
+        tokio::spawn(async move {
+            let capability = turn.capability_id;
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = self_critic::critique_turn(pool, provider, turn, timeout) => {
+                    if let Some(cap_id) = capability {
+                        // Receiver gone only during shutdown — benign
+                        let _ = graded_tx.send(cap_id).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Apply pins and removals queued by the memory builtins.
+    fn apply_pin_changes(&mut self) {
+        for change in self.builtin_registry.memory().take_pin_changes() {
+            match change {
+                PinChange::Pin { id, content } => self.pin_remembered(id, &content, chrono::Utc::now()),
+                PinChange::Remove(id) => {
+                    self.working_memory.remove(id);
+                }
+            }
+        }
+    }
+
+    /// Keep a remembered fact in working memory, pinned; only the newest
+    /// `MAX_REMEMBERED_PINS` stay pinned so they cannot crowd out the conversation.
+    fn pin_remembered(&mut self, id: uuid::Uuid, content: &str, created_at: chrono::DateTime<chrono::Utc>) {
+        self.working_memory.remove(id);
+        self.working_memory.insert(ContextEntry {
+            id,
+            topic_id: None,
+            content: format!("[remembered] {content}"),
+            salience_score: 1.0,
+            created_at,
+            last_accessed: chrono::Utc::now(),
+            pinned_by: None,
+            is_response: false,
+        });
+        self.working_memory.pin(id, REMEMBER_PIN);
+        let pinned = self.working_memory.pinned_for(REMEMBER_PIN);
+        for old in pinned.iter().take(pinned.len().saturating_sub(MAX_REMEMBERED_PINS)) {
+            self.working_memory.unpin(*old);
+        }
+    }
+
+    /// Deliver fired reminders and return fired tasks as internal events.
+    fn drain_fired_schedules(&mut self) -> Vec<SensoryEvent> {
+        let mut tasks = Vec::new();
+        while let Ok(fired) = self.fired_rx.try_recv() {
+            let schedule = &fired.schedule;
+            match schedule.kind {
+                ScheduleKind::Reminder => self.send_msg(Msg::ReminderDue {
+                    message: &schedule.message,
+                    late_minutes: fired.late_minutes(),
+                }),
+                ScheduleKind::Task => tasks.push(SensoryEvent::internal(format!(
+                    "[scheduled task {}] {}",
+                    schedule.short_id(),
+                    schedule.message
+                ))),
+            }
+        }
+        tasks
+    }
+
     /// Drain all pending events from the channel.
     fn collect_inputs(&mut self) -> Vec<SensoryEvent> {
         let mut events = Vec::new();
@@ -1105,13 +1992,29 @@ impl Runtime {
         events
     }
 
-    /// Handle a crashed capability: quarantine or retire, attempt LKG rollback.
+    /// Handle a crashed capability: roll back to the LKG version, quarantine or retire.
     async fn handle_capability_crash(&mut self, cap_id: uuid::Uuid, exit_code: Option<i32>) {
-        let Some(pool) = &self.pool else { return };
-
         tracing::warn!(capability_id = %cap_id, ?exit_code, "capability process crashed");
+        self.quarantine_capability(cap_id, &format!("exit code: {exit_code:?}")).await;
+    }
+
+    /// Handle side effects outside a capability's permissions like a crash: stop
+    /// the process, then quarantine or retire it.
+    async fn handle_permission_violation(&mut self, cap_id: uuid::Uuid, name: &str, violations: &[Permission]) {
+        let undeclared = grants::join(violations);
+        tracing::warn!(capability = name, %undeclared, "capability reported side effects it is not permitted");
+        self.process_manager.kill(cap_id);
+        self.quarantine_capability(cap_id, &format!("undeclared side effects: {undeclared}")).await;
+    }
+
+    /// Count a quarantine; retire after too many, otherwise roll back to the previous
+    /// confirmed version, or quarantine when there is none.
+    async fn quarantine_capability(&mut self, cap_id: uuid::Uuid, reason: &str) {
+        // Stop routing to it; a rollback routes to the restored version again
+        self.capabilities.unregister(cap_id);
+        let Some(pool) = self.pool.clone() else { return };
 
-        let count = match capability_db::increment_quarantine(pool, cap_id).await {
+        let count = match capability_db::increment_quarantine(&pool, cap_id).await {
             Ok(c) => c,
             Err(e) => {
                 tracing::warn!(error = %e, "failed to increment quarantine count");
@@ -1122,7 +2025,7 @@ impl Runtime {
         if lifecycle::should_retire(count) {
             // Retire the capability
             if let Err(e) =
-                capability_db::update_state(pool, cap_id, crate::types::CapabilityState::Retired)
+                capability_db::update_state(&pool, cap_id, crate::types::CapabilityState::Retired)
                     .await
             {
                 tracing::warn!(error = %e, "failed to retire capability");
@@ -1136,11 +2039,11 @@ impl Runtime {
                 format!("capability {cap_id} retired after {count} quarantines"),
                 0.7,
             );
-            let _ = narrative::record(pool, &evt).await;
-        } else {
+            let _ = narrative::record(&pool, &evt).await;
+        } else if !self.rollback_capability(cap_id, reason).await {
             // Quarantine
             if let Err(e) = capability_db::update_state(
-                pool,
+                &pool,
                 cap_id,
                 crate::types::CapabilityState::Quarantined,
             )
@@ -1152,31 +2055,149 @@ impl Runtime {
             // Narrative: capability quarantined
             let evt = narrative::new_event(
                 NarrativeEventType::CapabilityQuarantined,
-                format!("capability {cap_id} quarantined (exit code: {exit_code:?})"),
+                format!("capability {cap_id} quarantined ({reason})"),
                 0.5,
             );
-            let _ = narrative::record(pool, &evt).await;
+            let _ = narrative::record(&pool, &evt).await;
+        }
+    }
 
-            // Attempt LKG rollback
-            match capability_db::fetch_by_id(pool, cap_id).await {
-                Ok(Some(record)) if record.lkg_version.is_some() => {
-                    // Fetch the LKG version and try to spawn it
-                    let lkg_id = record.lkg_version.unwrap();
-                    match capability_db::fetch_by_id(pool, lkg_id).await {
-                        Ok(Some(lkg_record)) => {
-                            if let Err(e) = self.process_manager.spawn(&lkg_record) {
-                                tracing::warn!(error = %e, "failed to spawn LKG rollback");
-                            } else {
-                                tracing::info!(capability_id = %cap_id, lkg = %lkg_id, "rolled back to LKG version");
-                            }
-                        }
-                        _ => {
-                            tracing::debug!(lkg = %lkg_id, "LKG version not found in DB");
-                        }
+    /// Check capabilities the self-critic has graded since the last tick for regressions.
+    async fn check_graded_capabilities(&mut self) {
+        let mut graded = Vec::new();
+        while let Ok(cap_id) = self.graded_rx.try_recv() {
+            if !graded.contains(&cap_id) {
+                graded.push(cap_id);
+            }
+        }
+        for cap_id in graded {
+            self.check_regression(cap_id).await;
+        }
+    }
+
+    /// Quarantine (and so roll back) a capability whose installed version fails
+    /// noticeably more often than the confirmed version before it.
+    async fn check_regression(&mut self, cap_id: uuid::Uuid) {
+        let Some(pool) = self.pool.clone() else { return };
+        let Ok(Some(record)) = capability_db::fetch_by_id(&pool, cap_id).await else { return };
+        let Some(current_id) = record.current_version else { return };
+        if record.state != crate::types::CapabilityState::Confirmed
+            && record.state != crate::types::CapabilityState::ActiveCandidate
+        {
+            return;
+        }
+        let history = match versions::list(&pool, cap_id).await {
+            Ok(history) => history,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to load capability versions");
+                return;
+            }
+        };
+        let Some(current) = history.iter().find(|v| v.id == current_id) else { return };
+        let Some(previous) = versions::rollback_target(&history, Some(current_id)) else { return };
+        if !versions::regressed(current, previous) {
+            return;
+        }
+        let reason = format!(
+            "score regression: {} of {} calls to v{} failed",
+            current.fail_count, current.usage_count, current.version
+        );
+        tracing::warn!(capability = %record.name, reason = %reason, "capability version regressed");
+        self.quarantine_capability(cap_id, &reason).await;
+    }
+
+    /// Replace a failing build with the previous confirmed version and restart it.
+    /// Returns false when there is no version to fall back to.
+    async fn rollback_capability(&mut self, cap_id: uuid::Uuid, reason: &str) -> bool {
+        let Some(pool) = self.pool.clone() else { return false };
+        let Ok(Some(record)) = capability_db::fetch_by_id(&pool, cap_id).await else { return false };
+        let history = match versions::list(&pool, cap_id).await {
+            Ok(history) => history,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to load capability versions");
+                return false;
+            }
+        };
+        let Some(target) = versions::rollback_target(&history, record.current_version) else { return false };
+        let failed = record
+            .current_version
+            .and_then(|id| history.iter().find(|v| v.id == id))
+            .map(|v| format!("v{}", v.version))
+            .unwrap_or_else(|| "current build".into());
+
+        if let Err(e) = versions::restore(&pool, target).await {
+            tracing::warn!(error = %e, "failed to restore LKG version");
+            return false;
+        }
+        if record.state != crate::types::CapabilityState::Confirmed
+            && let Err(e) = capability_db::update_state(&pool, cap_id, crate::types::CapabilityState::Confirmed).await
+        {
+            tracing::warn!(error = %e, "failed to mark rolled-back capability confirmed");
+        }
+
+        self.process_manager.kill(cap_id);
+        match capability_db::fetch_by_id(&pool, cap_id).await {
+            Ok(Some(restored)) => {
+                match self.process_manager.spawn(&restored) {
+                    Ok(()) => {
+                        tracing::info!(capability = %record.name, version = target.version, "rolled back to LKG version")
                     }
+                    Err(e) => tracing::warn!(error = %e, "failed to spawn LKG rollback"),
                 }
-                _ => {}
+                self.capabilities.register(cap_id, restored.manifest.keywords.clone());
             }
+            _ => tracing::debug!(capability_id = %cap_id, "rolled-back capability not found in DB"),
+        }
+
+        let evt = narrative::new_event(
+            NarrativeEventType::CapabilityQuarantined,
+            format!("capability {} {failed} quarantined ({reason}), rolled back to v{}", record.name, target.version),
+            0.5,
+        );
+        let _ = narrative::record(&pool, &evt).await;
+        true
+    }
+
+    /// Start an MCP server and mount its tools; failures leave it unmounted.
+    async fn start_mcp_server(&mut self, name: &str) {
+        match self.mcp.start(name).await {
+            Ok(tools) => {
+                let mounted = self.builtin_registry.mount_mcp(name, tools);
+                tracing::info!(server = name, tools = mounted, "MCP tools mounted");
+            }
+            Err(e) => tracing::warn!(server = name, error = %e, "failed to start MCP server"),
+        }
+    }
+
+    /// Handle a crashed MCP server (or a failed restart) like a crashed capability
+    /// process: unmount its tools and schedule a restart with backoff, or give up
+    /// after `MAX_QUARANTINE_COUNT` crashes.
+    async fn handle_mcp_crash(&mut self, name: &str, reason: &str) {
+        self.builtin_registry.unmount_mcp(name);
+        let count = self.mcp.record_crash(name);
+        let retire = lifecycle::should_retire(count);
+
+        if let Some(pool) = &self.pool {
+            let evt = if retire {
+                narrative::new_event(
+                    NarrativeEventType::CapabilityLost,
+                    format!("MCP server {name} retired after {count} crashes"),
+                    0.7,
+                )
+            } else {
+                narrative::new_event(
+                    NarrativeEventType::CapabilityQuarantined,
+                    format!("MCP server {name} crashed ({reason}), restarting"),
+                    0.5,
+                )
+            };
+            let _ = narrative::record(pool, &evt).await;
+        }
+
+        if retire {
+            tracing::info!(server = name, crashes = count, "MCP server retired after repeated crashes");
+        } else {
+            self.mcp.schedule_restart(name);
         }
     }
 
@@ -1190,19 +2211,37 @@ impl Runtime {
             return;
         }
 
-        let Some(pool) = &self.pool else { return };
+        let Some(pool) = self.pool.clone() else { return };
+        let record = match capability_db::fetch_by_id(&pool, cap_id).await {
+            Ok(Some(record)) => record,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to fetch candidate");
+                return;
+            }
+        };
+        if record.state != crate::types::CapabilityState::ActiveCandidate {
+            self.process_manager.set_state(cap_id, record.state);
+            return;
+        }
 
         if let Err(e) =
-            capability_db::update_state(pool, cap_id, crate::types::CapabilityState::Confirmed)
+            capability_db::update_state(&pool, cap_id, crate::types::CapabilityState::Confirmed)
                 .await
         {
             tracing::warn!(error = %e, "failed to confirm candidate");
             return;
         }
+        self.process_manager.set_state(cap_id, crate::types::CapabilityState::Confirmed);
 
-        // Set current version as LKG
-        if let Err(e) = capability_db::update_lkg(pool, cap_id, cap_id).await {
-            tracing::warn!(error = %e, "failed to update LKG after confirmation");
+        // Point LKG at the confirmed version
+        match versions::ensure_current(&pool, &record).await {
+            Ok(version_id) => {
+                if let Err(e) = versions::confirm(&pool, cap_id, version_id).await {
+                    tracing::warn!(error = %e, "failed to update LKG after confirmation");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to record confirmed version"),
         }
 
         tracing::info!(capability_id = %cap_id, "active candidate confirmed after observation period");
@@ -1210,9 +2249,230 @@ impl Runtime {
         // Narrative: capability gained
         let evt = narrative::new_event(
             NarrativeEventType::CapabilityGained,
-            format!("capability {cap_id} confirmed after observation period"),
+            format!("capability {} confirmed after observation period", record.name),
             0.8,
         );
-        let _ = narrative::record(pool, &evt).await;
+        let _ = narrative::record(&pool, &evt).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::db::tests::test_pool;
+    use crate::capability::self_test::tests::{record_for, replying, script};
+    use crate::types::{CapabilityState, RouteTarget, SalienceScore};
+    use std::path::Path;
+
+    fn runtime(pool: Option<sqlx::PgPool>) -> (Runtime, OutputReceiver) {
+        let (runtime, _events, output) = Runtime::new(Arc::new(IrisCfg::default()), pool, None, None);
+        (runtime, output)
+    }
+
+    fn dialogue(content: &str) -> GatedEvent {
+        GatedEvent {
+            event: SensoryEvent::external(content),
+            salience: SalienceScore::compute(0.6, 0.4, 0.3, 0.5, 0.82),
+            route: RouteTarget::TextDialogue,
+        }
+    }
+
+    /// Confirmed weather capability that declares `NetworkRead` but reports `NetworkWrite`.
+    fn overreaching(dir: &Path) -> CapabilityRecord {
+        let bin = script(dir, &replying(r#"{"temp":21}"#, r#"["NetworkWrite"]"#));
+        let mut record = record_for(&bin, vec![Permission::NetworkRead], Vec::new());
+        record.name = format!("weather_{}", &record.id.simple().to_string()[..8]);
+        record.state = CapabilityState::Confirmed;
+        record.manifest.keywords = vec!["weather".into()];
+        record
+    }
+
+    #[tokio::test]
+    async fn undeclared_side_effects_stop_the_capability_and_its_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = overreaching(dir.path());
+        let (mut rt, mut output) = runtime(None);
+        rt.capabilities.register(record.id, record.manifest.keywords.clone());
+        let event = dialogue("what's the weather in Paris?");
+        assert_eq!(rt.capabilities.evaluate(&event).and_then(|d| d.capability_id), Some(record.id));
+
+        rt.invoke_process_capability(&event, &record, &[]).await;
+
+        let reply = output.try_recv().unwrap().content;
+        assert!(reply.contains("NetworkWrite"), "{reply}");
+        assert!(!rt.process_manager.is_running(record.id));
+        let next = rt.capabilities.evaluate(&event).unwrap();
+        assert_eq!(next.action, ReflexAction::DirectLlmFallback);
+    }
+
+    #[tokio::test]
+    async fn critic_scores_the_routed_capability() {
+        let Some(pool) = test_pool().await else { return };
+        let dir = tempfile::tempdir().unwrap();
+        let bin = script(dir.path(), &replying(r#"{"temp":21}"#, r#"["NetworkRead"]"#));
+        let mut record = record_for(&bin, vec![Permission::NetworkRead], Vec::new());
+        record.name = format!("forecast_{}", &record.id.simple().to_string()[..8]);
+        record.state = CapabilityState::Confirmed;
+        record.manifest.keywords = vec!["forecast".into()];
+        capability_db::insert(&pool, &record).await.unwrap();
+        capability_db::init_score(&pool, record.id).await.unwrap();
+        let cfg = IrisCfg { self_critic_enabled: true, ..IrisCfg::default() };
+        let (mut rt, _events, _output) = Runtime::new(Arc::new(cfg), Some(pool.clone()), None, None);
+        rt.capabilities.register(record.id, record.manifest.keywords.clone());
+
+        rt.process_event(&dialogue("forecast for tomorrow?")).await;
+
+        // The critic runs in the background; its graded outcome is the only one counted
+        let mut usage = 0;
+        for _ in 0..100 {
+            usage = capability_db::fetch_score(&pool, record.id).await.unwrap().unwrap().usage_count;
+            if usage > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(usage, 1);
+        let graded: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM turn_evaluation WHERE capability_id = $1")
+            .bind(record.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(graded.0, 1);
+        rt.process_manager.kill(record.id);
+    }
+
+    /// Capability on a failing v2 one call short of counting as a regression,
+    /// with a confirmed v1 to fall back to. Returns (record, v1 id).
+    async fn regressing(pool: &sqlx::PgPool, dir: &Path) -> (CapabilityRecord, uuid::Uuid) {
+        let v1_dir = dir.join("v1");
+        std::fs::create_dir(&v1_dir).unwrap();
+        let v1_bin = script(&v1_dir, &replying(r#"{"temp":21}"#, "[]"));
+        let v2_bin = script(
+            dir,
+            r#"while read -r line; do
+id=$(printf '%s' "$line" | sed 's/.*"id":"\([^"]*\)".*/\1/')
+printf '{"id":"%s","result":null,"error":"upstream down","metrics":null,"side_effects":[]}\n' "$id"
+done"#,
+        );
+        let mut record = record_for(&v1_bin, Vec::new(), Vec::new());
+        record.name = format!("climate_{}", &record.id.simple().to_string()[..8]);
+        record.state = CapabilityState::ActiveCandidate;
+        record.manifest.keywords = vec!["climate".into()];
+        capability_db::insert(pool, &record).await.unwrap();
+        capability_db::init_score(pool, record.id).await.unwrap();
+
+        let v1 = versions::insert(pool, record.id, &record.binary_path, "", &record.manifest, "v1").await.unwrap();
+        versions::confirm(pool, record.id, v1.id).await.unwrap();
+        let manifest = crate::types::CapabilityManifest { binary_path: v2_bin.display().to_string(), ..record.manifest.clone() };
+        let v2 = versions::insert(pool, record.id, &manifest.binary_path, "", &manifest, "v2").await.unwrap();
+        versions::activate(pool, &v2).await.unwrap();
+        sqlx::query("UPDATE capability_version SET usage_count = 20, success_count = 19, fail_count = 1 WHERE id = $1")
+            .bind(v1.id)
+            .execute(pool)
+            .await
+            .unwrap();
+        let failures = versions::MIN_REGRESSION_SAMPLES - 1;
+        sqlx::query("UPDATE capability_version SET usage_count = $2, fail_count = $2 WHERE id = $1")
+            .bind(v2.id)
+            .bind(failures)
+            .execute(pool)
+            .await
+            .unwrap();
+        let record = capability_db::fetch_by_id(pool, record.id).await.unwrap().unwrap();
+        (record, v1.id)
+    }
+
+    async fn assert_rolled_back(pool: &sqlx::PgPool, rt: &Runtime, cap_id: uuid::Uuid, v1: uuid::Uuid) {
+        let stored = capability_db::fetch_by_id(pool, cap_id).await.unwrap().unwrap();
+        assert_eq!(stored.current_version, Some(v1));
+        assert_eq!(stored.lkg_version, Some(v1));
+        assert_eq!(stored.state, CapabilityState::Confirmed);
+        assert_eq!(rt.process_manager.running_version(cap_id), Some(Some(v1)));
+    }
+
+    #[tokio::test]
+    async fn regressing_version_rolls_back_after_its_outcome_is_recorded() {
+        let Some(pool) = test_pool().await else { return };
+        let dir = tempfile::tempdir().unwrap();
+        let (record, v1) = regressing(&pool, dir.path()).await;
+        let (mut rt, _output) = runtime(Some(pool.clone()));
+        rt.capabilities.register(record.id, record.manifest.keywords.clone());
+
+        rt.process_event(&dialogue("climate in Oslo?")).await;
+
+        assert_rolled_back(&pool, &rt, record.id, v1).await;
+        rt.process_manager.kill(record.id);
+    }
+
+    #[tokio::test]
+    async fn regressing_version_rolls_back_after_the_critic_grades_it() {
+        let Some(pool) = test_pool().await else { return };
+        let dir = tempfile::tempdir().unwrap();
+        let (record, v1) = regressing(&pool, dir.path()).await;
+        let cfg = IrisCfg { self_critic_enabled: true, ..IrisCfg::default() };
+        let (mut rt, _events, _output) = Runtime::new(Arc::new(cfg), Some(pool.clone()), None, None);
+        rt.capabilities.register(record.id, record.manifest.keywords.clone());
+
+        rt.process_event(&dialogue("climate in Oslo?")).await;
+        // The critic reports back once the graded outcome is recorded
+        for _ in 0..250 {
+            if !rt.graded_rx.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        rt.check_graded_capabilities().await;
+
+        assert_rolled_back(&pool, &rt, record.id, v1).await;
+        rt.process_manager.kill(record.id);
+    }
+
+    #[tokio::test]
+    async fn finished_codegen_routes_and_starts_the_installed_capability() {
+        let Some(pool) = test_pool().await else { return };
+        let dir = tempfile::tempdir().unwrap();
+        let bin = script(dir.path(), &replying(r#"{"rate":1.1}"#, "[]"));
+        let mut record = record_for(&bin, Vec::new(), Vec::new());
+        record.name = format!("exchange_{}", &record.id.simple().to_string()[..8]);
+        record.state = CapabilityState::ActiveCandidate;
+        record.manifest.keywords = vec![format!("exchange{}", &record.id.simple().to_string()[..8])];
+        capability_db::insert(&pool, &record).await.unwrap();
+        let (mut rt, _output) = runtime(Some(pool.clone()));
+        let event = dialogue(&format!("{} rate for EUR?", record.manifest.keywords[0]));
+        assert_eq!(rt.capabilities.evaluate(&event).unwrap().action, ReflexAction::DirectLlmFallback);
+
+        let (done, run) = tokio::sync::oneshot::channel();
+        rt.codegen.push(run);
+        let result = crate::codegen::repair_loop::RepairResult {
+            source_code: String::new(),
+            success: true,
+            iterations: 1,
+            last_error: None,
+        };
+        done.send(Ok(result)).unwrap();
+        rt.collect_codegen().await;
+
+        assert_eq!(rt.capabilities.evaluate(&event).and_then(|d| d.capability_id), Some(record.id));
+        assert!(rt.process_manager.is_running(record.id));
+        rt.process_manager.kill(record.id);
+    }
+
+    #[tokio::test]
+    async fn routed_capability_with_undeclared_side_effects_is_quarantined() {
+        let Some(pool) = test_pool().await else { return };
+        let dir = tempfile::tempdir().unwrap();
+        let record = overreaching(dir.path());
+        capability_db::insert(&pool, &record).await.unwrap();
+        let (mut rt, mut output) = runtime(Some(pool.clone()));
+        rt.capabilities.register(record.id, record.manifest.keywords.clone());
+
+        rt.process_event(&dialogue("what's the weather in Paris?")).await;
+
+        let reply = output.try_recv().unwrap().content;
+        assert!(reply.contains("NetworkWrite"), "{reply}");
+        let stored = capability_db::fetch_by_id(&pool, record.id).await.unwrap().unwrap();
+        assert_eq!(stored.state, CapabilityState::Quarantined);
+        assert_eq!(stored.quarantine_count, 1);
+        assert!(!rt.process_manager.is_running(record.id));
     }
 }
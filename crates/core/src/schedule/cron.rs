@@ -0,0 +1,224 @@
+//! Recurrence rules: five-field cron (`min hour day-of-month month day-of-week`,
+//! with `*`, lists, ranges and steps), the `@hourly`/`@daily`/`@weekly`/`@monthly`
+//! shorthands, and fixed intervals such as `@every 30m`. Cron fields are
+//! evaluated in local time.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
+
+/// Shortest interval accepted by `@every`.
+const MIN_INTERVAL_SECS: i64 = 60;
+/// Longest interval accepted by `@every` (366 days).
+const MAX_INTERVAL_SECS: i64 = 366 * 86400;
+/// How far ahead to look for the next match (covers Feb 29 rules).
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    Cron(CronSpec),
+    Every(Duration),
+}
+
+/// Allowed values per cron field, as bitmasks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSpec {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Both day fields restricted: cron matches either of them.
+    day_or: bool,
+}
+
+impl Recurrence {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let expanded = match spec {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => spec,
+        };
+        if let Some(interval) = expanded.strip_prefix("@every") {
+            return parse_interval(interval.trim()).map(Self::Every);
+        }
+        CronSpec::parse(expanded).map(Self::Cron)
+    }
+
+    /// First occurrence strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Every(interval) => after.checked_add_signed(*interval),
+            Self::Cron(spec) => spec.next_after(after),
+        }
+    }
+}
+
+fn parse_interval(text: &str) -> Result<Duration, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+    let n: i64 = num.parse().map_err(|_| format!("invalid interval '{text}', e.g. @every 30m"))?;
+    let unit_secs = match unit.trim() {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        other => return Err(format!("unknown interval unit '{other}' (use s, m, h or d)")),
+    };
+    let secs = n.checked_mul(unit_secs).filter(|s| *s <= MAX_INTERVAL_SECS);
+    let Some(secs) = secs else {
+        return Err("interval must be at most 366 days".into());
+    };
+    if secs < MIN_INTERVAL_SECS {
+        return Err(format!("interval must be at least {MIN_INTERVAL_SECS} seconds"));
+    }
+    Duration::try_seconds(secs).ok_or_else(|| format!("invalid interval '{text}'"))
+}
+
+impl CronSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [min, hour, dom, month, dow] = fields.as_slice() else {
+            return Err(format!(
+                "cron needs 5 fields (minute hour day-of-month month day-of-week), got {}",
+                fields.len()
+            ));
+        };
+        // Sunday may be written as 7.
+        let weekdays = parse_field(dow, 0, 7, "day-of-week")?;
+        let weekdays = (weekdays | (weekdays >> 7)) & 0x7f;
+        Ok(Self {
+            minutes: parse_field(min, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")? as u32,
+            days: parse_field(dom, 1, 31, "day-of-month")? as u32,
+            months: parse_field(month, 1, 12, "month")? as u16,
+            weekdays: weekdays as u8,
+            day_or: *dom != "*" && *dow != "*",
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let dom = self.days & (1 << date.day()) != 0;
+        let dow = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.day_or { dom || dow } else { dom && dow }
+    }
+
+    /// Next matching local minute strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&Local) + Duration::minutes(1);
+        let start_date = start.date_naive();
+        for offset in 0..MAX_SEARCH_DAYS {
+            let date = start_date + Duration::days(offset);
+            if !self.day_matches(date) {
+                continue;
+            }
+            for hour in 0..24u32 {
+                if self.hours & (1 << hour) == 0 {
+                    continue;
+                }
+                for minute in 0..60u32 {
+                    if self.minutes & (1 << minute) == 0 {
+                        continue;
+                    }
+                    let Some(naive) = date.and_hms_opt(hour, minute, 0) else {
+                        continue;
+                    };
+                    // Skipped by a DST jump: no such local time.
+                    let Some(local) = Local.from_local_datetime(&naive).earliest() else {
+                        continue;
+                    };
+                    if local.with_second(0)? >= start.with_second(0)? {
+                        return Some(local.with_timezone(&Utc));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Parse one cron field into a bitmask of allowed values.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(|| format!("invalid step in {name} field '{part}'"))?),
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, name)?, parse_value(b, name)?)
+        } else {
+            let v = parse_value(range, name)?;
+            // `5/15` means from 5 to the end in steps of 15.
+            (v, if part.contains('/') { max } else { v })
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err(format!("{name} field '{part}' is outside {min}-{max}"));
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(text: &str, name: &str) -> Result<u32, String> {
+    text.parse().map_err(|_| format!("invalid {name} value '{text}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).earliest().unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_fields_and_shorthands() {
+        assert!(Recurrence::parse("*/15 9-17 * * 1-5").is_ok());
+        assert!(Recurrence::parse("@daily").is_ok());
+        assert!(Recurrence::parse("0 0 * *").unwrap_err().contains("5 fields"));
+        assert!(Recurrence::parse("61 * * * *").unwrap_err().contains("outside"));
+        assert!(Recurrence::parse("@every 10s").unwrap_err().contains("at least"));
+        assert_eq!(
+            Recurrence::parse("@every 2h").unwrap(),
+            Recurrence::Every(Duration::hours(2))
+        );
+    }
+
+    #[test]
+    fn huge_intervals_are_rejected_without_overflow() {
+        assert!(Recurrence::parse("@every 100000000d").unwrap_err().contains("at most 366 days"));
+        assert!(Recurrence::parse("@every 9223372036854775807s").unwrap_err().contains("at most"));
+        assert!(Recurrence::parse("@every 367d").is_err());
+        let yearly = Recurrence::parse("@every 366d").unwrap();
+        assert!(yearly.next_after(Utc::now()).is_some());
+        assert_eq!(Recurrence::Every(Duration::days(366)).next_after(DateTime::<Utc>::MAX_UTC), None);
+    }
+
+    #[test]
+    fn next_occurrence_in_local_time() {
+        let weekday_9am = Recurrence::parse("0 9 * * 1-5").unwrap();
+        // 2026-10-16 is a Friday; the next weekday 09:00 after Friday 10:00 is Monday.
+        assert_eq!(weekday_9am.next_after(local(2026, 10, 16, 10, 0)), Some(local(2026, 10, 19, 9, 0)));
+        assert_eq!(weekday_9am.next_after(local(2026, 10, 16, 8, 59)), Some(local(2026, 10, 16, 9, 0)));
+
+        let quarter = Recurrence::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarter.next_after(local(2026, 10, 16, 10, 0)), Some(local(2026, 10, 16, 10, 15)));
+
+        // Day-of-month and day-of-week together match either one.
+        let either = Recurrence::parse("0 0 13 * 5").unwrap();
+        assert_eq!(either.next_after(local(2026, 10, 10, 0, 0)), Some(local(2026, 10, 13, 0, 0)));
+
+        let leap = Recurrence::parse("0 12 29 2 *").unwrap();
+        assert_eq!(leap.next_after(local(2026, 1, 1, 0, 0)), Some(local(2028, 2, 29, 12, 0)));
+    }
+}
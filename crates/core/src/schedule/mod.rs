@@ -0,0 +1,3 @@
+pub mod cron;
+pub mod store;
+pub mod timer;
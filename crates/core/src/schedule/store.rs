@@ -0,0 +1,205 @@
+//! `schedule` table access.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::cron::Recurrence;
+
+/// What happens when a schedule fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleKind {
+    /// Tell the user the message directly.
+    Reminder,
+    /// Inject the message as an internal event for iris to act on.
+    Task,
+}
+
+impl ScheduleKind {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Self::Reminder => "reminder",
+            Self::Task => "task",
+        }
+    }
+
+    pub fn from_db(s: &str) -> Option<Self> {
+        match s {
+            "reminder" => Some(Self::Reminder),
+            "task" => Some(Self::Task),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub id: Uuid,
+    pub kind: ScheduleKind,
+    pub message: String,
+    pub next_run: DateTime<Utc>,
+    /// Recurrence rule; `None` for one-shot schedules.
+    pub recurrence: Option<String>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Schedule {
+    /// Next run after firing at `now`, or `None` when the schedule is finished.
+    /// Missed occurrences of a recurring schedule are skipped, not replayed.
+    pub fn following_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let rule = Recurrence::parse(self.recurrence.as_deref()?).ok()?;
+        let mut next = rule.next_after(self.next_run)?;
+        if next <= now {
+            next = rule.next_after(now)?;
+        }
+        Some(next)
+    }
+
+    /// Short id shown in `/schedules` and accepted by `/schedules cancel`.
+    pub fn short_id(&self) -> String {
+        self.id.simple().to_string()[..8].to_string()
+    }
+}
+
+pub async fn create(pool: &PgPool, schedule: &Schedule) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO schedule (id, kind, message, next_run, recurrence, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(schedule.id)
+    .bind(schedule.kind.as_db_str())
+    .bind(&schedule.message)
+    .bind(schedule.next_run)
+    .bind(&schedule.recurrence)
+    .bind(schedule.created_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Active schedules, soonest first.
+pub async fn list_active(pool: &PgPool) -> Result<Vec<Schedule>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ScheduleRow>(
+        "SELECT id, kind, message, next_run, recurrence, last_run, created_at \
+         FROM schedule WHERE status = 'active' ORDER BY next_run",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().filter_map(ScheduleRow::into_schedule).collect())
+}
+
+/// Active schedules due at `now`, including ones missed while iris was down.
+pub async fn due(pool: &PgPool, now: DateTime<Utc>) -> Result<Vec<Schedule>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ScheduleRow>(
+        "SELECT id, kind, message, next_run, recurrence, last_run, created_at \
+         FROM schedule WHERE status = 'active' AND next_run <= $1 ORDER BY next_run",
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().filter_map(ScheduleRow::into_schedule).collect())
+}
+
+/// Record a firing: advance a recurring schedule or mark a one-shot done.
+pub async fn mark_fired(
+    pool: &PgPool,
+    id: Uuid,
+    fired_at: DateTime<Utc>,
+    next_run: Option<DateTime<Utc>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE schedule SET last_run = $2, next_run = COALESCE($3, next_run), \
+         status = CASE WHEN $3 IS NULL THEN 'done' ELSE status END WHERE id = $1",
+    )
+    .bind(id)
+    .bind(fired_at)
+    .bind(next_run)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Cancel active schedules whose id starts with `prefix` (hex, no dashes).
+/// Refuses ambiguous prefixes; returns the cancelled schedule.
+pub async fn cancel(pool: &PgPool, prefix: &str) -> Result<Option<Schedule>, String> {
+    let prefix = prefix.trim().replace('-', "").to_lowercase();
+    if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let matches: Vec<Schedule> = list_active(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|s| s.id.simple().to_string().starts_with(&prefix))
+        .collect();
+    let [schedule] = matches.as_slice() else {
+        return if matches.is_empty() {
+            Ok(None)
+        } else {
+            Err(format!("id prefix {prefix} matches {} schedules", matches.len()))
+        };
+    };
+    sqlx::query("UPDATE schedule SET status = 'cancelled' WHERE id = $1")
+        .bind(schedule.id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(schedule.clone()))
+}
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    id: Uuid,
+    kind: String,
+    message: String,
+    next_run: DateTime<Utc>,
+    recurrence: Option<String>,
+    last_run: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl ScheduleRow {
+    fn into_schedule(self) -> Option<Schedule> {
+        Some(Schedule {
+            id: self.id,
+            kind: ScheduleKind::from_db(&self.kind)?,
+            message: self.message,
+            next_run: self.next_run,
+            recurrence: self.recurrence,
+            last_run: self.last_run,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn schedule(next_run: DateTime<Utc>, recurrence: Option<&str>) -> Schedule {
+        Schedule {
+            id: Uuid::new_v4(),
+            kind: ScheduleKind::Reminder,
+            message: "stand up".into(),
+            next_run,
+            recurrence: recurrence.map(String::from),
+            last_run: None,
+            created_at: next_run,
+        }
+    }
+
+    #[test]
+    fn one_shot_finishes_and_missed_recurrences_collapse() {
+        let now = Utc::now();
+        assert_eq!(schedule(now, None).following_run(now), None);
+
+        let hourly = schedule(now - Duration::hours(5), Some("@every 1h"));
+        // Five runs were missed; the next one is an hour from now, not a backlog.
+        assert_eq!(hourly.following_run(now), Some(now + Duration::hours(1)));
+
+        let on_time = schedule(now, Some("@every 1h"));
+        assert_eq!(on_time.following_run(now), Some(now + Duration::hours(1)));
+    }
+}
@@ -0,0 +1,72 @@
+//! Background task that fires due schedules.
+//!
+//! Fired schedules go to the runtime over a channel; the runtime turns reminders
+//! into proactive output and tasks into internal events. Schedules that came due
+//! while iris was not running are fired on the first poll after boot.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::store::{self, Schedule};
+
+/// A schedule that just fired.
+#[derive(Debug, Clone)]
+pub struct Fired {
+    pub schedule: Schedule,
+    pub fired_at: DateTime<Utc>,
+}
+
+impl Fired {
+    /// Whole minutes between the scheduled time and the firing, if noticeably late.
+    pub fn late_minutes(&self) -> Option<i64> {
+        let late = (self.fired_at - self.schedule.next_run).num_minutes();
+        (late >= 2).then_some(late)
+    }
+}
+
+/// Fire every due schedule once and advance or finish it.
+pub async fn fire_due(pool: &PgPool, now: DateTime<Utc>) -> Result<Vec<Fired>, sqlx::Error> {
+    let mut fired = Vec::new();
+    for schedule in store::due(pool, now).await? {
+        let next = schedule.following_run(now);
+        store::mark_fired(pool, schedule.id, now, next).await?;
+        fired.push(Fired { schedule, fired_at: now });
+    }
+    Ok(fired)
+}
+
+/// Spawn the schedule poller.
+pub fn spawn(
+    pool: PgPool,
+    fired_tx: mpsc::Sender<Fired>,
+    poll_secs: u64,
+    cancel: CancellationToken,
+) {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(poll_secs.max(1));
+        loop {
+            match fire_due(&pool, Utc::now()).await {
+                Ok(fired) => {
+                    for f in fired {
+                        tracing::info!(id = %f.schedule.id, kind = f.schedule.kind.as_db_str(), "schedule fired");
+                        if fired_tx.send(f).await.is_err() {
+                            tracing::warn!("schedule: runtime channel closed");
+                            return;
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "schedule poll failed"),
+            }
+
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    tracing::info!("schedule task shutting down");
+                    return;
+                }
+                _ = tokio::time::sleep(interval) => {}
+            }
+        }
+    });
+}
@@ -1,14 +1,25 @@
 use crate::config::IrisCfg;
-use crate::types::{SensoryEvent, GatedEvent, RouteTarget, EventSource};
+use crate::types::{SensoryEvent, GatedEvent, PerceptFeature, RouteTarget, EventSource};
+use uuid::Uuid;
 use super::salience;
 
 /// Sensory gating: scores events and filters below noise_floor.
 /// Returns gated events that passed the filter, with route targets assigned.
 pub fn gate(events: Vec<SensoryEvent>, cfg: &IrisCfg) -> Vec<GatedEvent> {
+    gate_with_percepts(events, cfg, |_| None)
+}
+
+/// Like `gate`, but scores salience with a per-event percept when one is available.
+pub fn gate_with_percepts<'a>(
+    events: Vec<SensoryEvent>,
+    cfg: &IrisCfg,
+    percept: impl Fn(&Uuid) -> Option<&'a PerceptFeature>,
+) -> Vec<GatedEvent> {
     events
         .into_iter()
         .filter_map(|event| {
-            let score = salience::score(&event, cfg.urgent_bypass);
+            let score =
+                salience::score_with_percept(&event, percept(&event.id), cfg.urgent_bypass);
 
             // Below noise floor → discard
             if score.score < cfg.noise_floor {
@@ -1,16 +1,43 @@
-use crate::types::{EventSource, SalienceScore, SensoryEvent};
+use crate::types::{EventSource, PerceptFeature, PerceptSource, SalienceScore, SensoryEvent};
 
 /// Rule-based salience scorer (v1).
-/// In v2 this will incorporate LLM-based feature extraction.
 pub fn score(event: &SensoryEvent, urgent_bypass_threshold: f32) -> SalienceScore {
+    score_with_percept(event, None, urgent_bypass_threshold)
+}
+
+/// Salience scorer that lets LLM perception override urgency and task relevance.
+/// Rule-sourced percepts are ignored — the keyword heuristics here already cover them.
+pub fn score_with_percept(
+    event: &SensoryEvent,
+    percept: Option<&PerceptFeature>,
+    urgent_bypass_threshold: f32,
+) -> SalienceScore {
     let novelty = estimate_novelty(event);
-    let urgency = estimate_urgency(event);
     let complexity = estimate_complexity(event);
-    let task_relevance = estimate_task_relevance(event);
+    let (urgency, task_relevance) = match percept {
+        Some(p) if p.source == PerceptSource::Llm => (
+            p.urgency.max(p.threat),
+            intent_relevance(p, estimate_task_relevance(event)),
+        ),
+        _ => (estimate_urgency(event), estimate_task_relevance(event)),
+    };
 
     SalienceScore::compute(novelty, urgency, complexity, task_relevance, urgent_bypass_threshold)
 }
 
+/// Blend an intent-specific relevance with the heuristic baseline by intent confidence.
+fn intent_relevance(percept: &PerceptFeature, baseline: f32) -> f32 {
+    let target = match percept.intent_tag.as_str() {
+        "command" | "request" => 0.8,
+        "question" => 0.65,
+        "statement" => 0.45,
+        "feedback" => 0.35,
+        _ => baseline,
+    };
+    let c = percept.intent_confidence;
+    (target * c + baseline * (1.0 - c)).clamp(0.0, 1.0)
+}
+
 /// Heuristic novelty: external events are more novel than internal.
 fn estimate_novelty(event: &SensoryEvent) -> f32 {
     let base = match event.source {
@@ -72,6 +99,37 @@ mod tests {
         assert!(s_urgent.urgency > s_normal.urgency);
     }
 
+    fn llm_percept(intent: &str, urgency: f32) -> PerceptFeature {
+        PerceptFeature {
+            threat: 0.0,
+            complexity_raw: 0.1,
+            intent_tag: intent.into(),
+            intent_confidence: 1.0,
+            urgency,
+            language: "en".into(),
+            source: PerceptSource::Llm,
+        }
+    }
+
+    #[test]
+    fn llm_percept_drives_urgency_and_relevance() {
+        let event = SensoryEvent::external("the database is down");
+        let rules = score(&event, 0.82);
+        let perceived = score_with_percept(&event, Some(&llm_percept("command", 0.95)), 0.82);
+        assert!(perceived.urgency > rules.urgency);
+        assert!(perceived.task_relevance > rules.task_relevance);
+        assert!(perceived.is_urgent_bypass);
+    }
+
+    #[test]
+    fn rule_percept_keeps_heuristics() {
+        let event = SensoryEvent::external("hello");
+        let mut percept = llm_percept("command", 0.95);
+        percept.source = PerceptSource::Rules;
+        let s = score_with_percept(&event, Some(&percept), 0.82);
+        assert_eq!(s.urgency, score(&event, 0.82).urgency);
+    }
+
     #[test]
     fn urgent_bypass_triggers() {
         let event = SensoryEvent::external("critical error crash panic emergency");
@@ -80,6 +80,19 @@ pub struct PerceptFeature {
     pub complexity_raw: f32,
     pub intent_tag: String,
     pub intent_confidence: f32,
+    pub urgency: f32,
+    /// Language code of the content (e.g. "en", "zh"), "und" when unknown.
+    pub language: String,
+    pub source: PerceptSource,
+}
+
+/// Which perception stage produced a `PerceptFeature`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PerceptSource {
+    /// Keyword rules only (no LLM call, or LLM timed out / failed).
+    Rules,
+    /// Structured classification from the lite model.
+    Llm,
 }
 
 /// Route target after thalamic routing.
@@ -223,6 +236,8 @@ pub struct Knowledge {
     pub embedding: Option<Vec<u8>>,
     pub source_episode_ids: Vec<Uuid>,
     pub created_at: DateTime<Utc>,
+    /// Saved explicitly (`remember`) rather than by consolidation.
+    pub pinned: bool,
 }
 
 // ── Capability types ────────────────────────────────────────────
@@ -275,6 +290,34 @@ pub enum Permission {
     SystemInfo,
 }
 
+impl Permission {
+    pub const ALL: [Self; 6] = [
+        Self::FileRead,
+        Self::FileWrite,
+        Self::NetworkRead,
+        Self::NetworkWrite,
+        Self::ProcessSpawn,
+        Self::SystemInfo,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FileRead => "FileRead",
+            Self::FileWrite => "FileWrite",
+            Self::NetworkRead => "NetworkRead",
+            Self::NetworkWrite => "NetworkWrite",
+            Self::ProcessSpawn => "ProcessSpawn",
+            Self::SystemInfo => "SystemInfo",
+        }
+    }
+
+    /// Parse `FileWrite`, `file_write` or `file-write`, ignoring case.
+    pub fn parse(s: &str) -> Option<Self> {
+        let key: String = s.chars().filter(|c| *c != '_' && *c != '-').collect::<String>().to_lowercase();
+        Self::ALL.into_iter().find(|p| p.as_str().to_lowercase() == key)
+    }
+}
+
 /// Capability manifest — metadata describing a capability.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapabilityManifest {
@@ -283,6 +326,27 @@ pub struct CapabilityManifest {
     pub permissions: Vec<Permission>,
     pub resource_limits: serde_json::Value,
     pub keywords: Vec<String>,
+    /// Cases run by the self-test harness before promotion to active candidate.
+    #[serde(default)]
+    pub self_tests: Vec<SelfTestCase>,
+}
+
+/// An example request and the response it must produce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfTestCase {
+    pub name: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+    /// Example result: every key must be present with a value of the same JSON type.
+    #[serde(default)]
+    pub expect_shape: Option<serde_json::Value>,
+    /// JSON Schema the result must validate against.
+    #[serde(default)]
+    pub expect_schema: Option<serde_json::Value>,
+    /// The case passes only if the capability answers with an error.
+    #[serde(default)]
+    pub expect_error: bool,
 }
 
 /// A capability record as stored in the DB.
@@ -294,11 +358,32 @@ pub struct CapabilityRecord {
     pub manifest: CapabilityManifest,
     pub state: CapabilityState,
     pub lkg_version: Option<Uuid>,
+    /// The `capability_version` row whose binary is installed.
+    pub current_version: Option<Uuid>,
     pub quarantine_count: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// One build of a capability, as stored in `capability_version`.
+#[derive(Debug, Clone)]
+pub struct CapabilityVersion {
+    pub id: Uuid,
+    pub capability_id: Uuid,
+    pub version: i32,
+    pub binary_path: String,
+    pub source: String,
+    pub manifest: CapabilityManifest,
+    /// SHA-256 of the binary, hex.
+    pub binary_hash: String,
+    /// Set when the version survived the candidate observation period.
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub usage_count: i64,
+    pub success_count: i64,
+    pub fail_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
 /// IPC request sent to a capability subprocess.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapabilityRequest {
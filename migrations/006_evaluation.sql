@@ -0,0 +1,28 @@
+-- self-critic grades per dialogue turn
+CREATE TABLE IF NOT EXISTS turn_evaluation (
+    id                  UUID PRIMARY KEY,
+    event_id            UUID NOT NULL,
+    user_input          TEXT NOT NULL,
+    response            TEXT NOT NULL,
+    tools_used          TEXT[] NOT NULL DEFAULT '{}',
+    capability_id       UUID,
+    tool_consistency    REAL NOT NULL,
+    answered            REAL NOT NULL,
+    hallucination       REAL NOT NULL,
+    overall             REAL NOT NULL,
+    critic              TEXT NOT NULL CHECK (critic IN ('llm','rules')),
+    notes               TEXT,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_turn_evaluation_created ON turn_evaluation (created_at DESC);
+
+-- per-tool outcome scores (builtins have no capability row)
+CREATE TABLE IF NOT EXISTS tool_score (
+    tool_name       TEXT PRIMARY KEY,
+    usage_count     BIGINT NOT NULL DEFAULT 0,
+    success_count   BIGINT NOT NULL DEFAULT 0,
+    fail_count      BIGINT NOT NULL DEFAULT 0,
+    grade_sum       DOUBLE PRECISION NOT NULL DEFAULT 0,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- host allow/deny rules for network builtins (fetch_url)
+CREATE TABLE IF NOT EXISTS host_rule (
+    host        TEXT PRIMARY KEY,
+    rule        TEXT NOT NULL CHECK (rule IN ('allow','deny')),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+-- knowledge saved explicitly via the remember builtin is pinned: never pruned, ranked first in recall
+ALTER TABLE knowledge ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX IF NOT EXISTS idx_knowledge_pinned ON knowledge (pinned) WHERE pinned;
@@ -0,0 +1,12 @@
+-- timers, reminders and recurring internal events (schedule_reminder builtin, /schedules)
+CREATE TABLE IF NOT EXISTS schedule (
+    id          UUID PRIMARY KEY,
+    kind        TEXT NOT NULL CHECK (kind IN ('reminder','task')),
+    message     TEXT NOT NULL,
+    next_run    TIMESTAMPTZ NOT NULL,
+    recurrence  TEXT,
+    status      TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active','done','cancelled')),
+    last_run    TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX IF NOT EXISTS idx_schedule_due ON schedule (next_run) WHERE status = 'active';
@@ -0,0 +1,9 @@
+-- per-capability permission grants: allow adds a permission the manifest did not
+-- declare, deny revokes a declared one. Keyed by name so grants survive new versions.
+CREATE TABLE IF NOT EXISTS capability_grant (
+    capability  TEXT NOT NULL,
+    permission  TEXT NOT NULL,
+    grant_kind  TEXT NOT NULL CHECK (grant_kind IN ('allow','deny')),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (capability, permission)
+);
@@ -0,0 +1,14 @@
+-- self-test results: one row per case per run, written before a staged
+-- capability is promoted to active_candidate or quarantined
+CREATE TABLE IF NOT EXISTS capability_self_test (
+    id              UUID PRIMARY KEY,
+    run_id          UUID NOT NULL,
+    capability_id   UUID NOT NULL REFERENCES capability(id),
+    case_name       TEXT NOT NULL,
+    passed          BOOLEAN NOT NULL,
+    error           TEXT,
+    elapsed_ms      BIGINT NOT NULL DEFAULT 0,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_capability_self_test_capability ON capability_self_test (capability_id, created_at DESC);
@@ -0,0 +1,23 @@
+-- capability builds: one row per binary so the LKG pointer names a real version
+-- that can be restored, with per-version usage counters for history
+CREATE TABLE IF NOT EXISTS capability_version (
+    id              UUID PRIMARY KEY,
+    capability_id   UUID NOT NULL REFERENCES capability(id),
+    version         INT NOT NULL,
+    binary_path     TEXT NOT NULL,
+    source          TEXT NOT NULL DEFAULT '',
+    manifest        JSONB NOT NULL,
+    binary_hash     TEXT NOT NULL,
+    confirmed_at    TIMESTAMPTZ,
+    usage_count     BIGINT NOT NULL DEFAULT 0,
+    success_count   BIGINT NOT NULL DEFAULT 0,
+    fail_count      BIGINT NOT NULL DEFAULT 0,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (capability_id, version)
+);
+
+ALTER TABLE capability ADD COLUMN IF NOT EXISTS current_version UUID REFERENCES capability_version(id);
+
+-- lkg_version used to hold the capability's own id; drop pointers that are not versions
+UPDATE capability SET lkg_version = NULL
+WHERE lkg_version IS NOT NULL AND lkg_version NOT IN (SELECT id FROM capability_version);